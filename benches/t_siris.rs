@@ -5,10 +5,29 @@ use ark_ff::UniformRand;
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use std::ops::Mul;
 use std::time::Duration;
-use t_siris::keygen::keygen;
+use t_siris::attributes::Attributes;
+use t_siris::credential::Credential;
+use t_siris::keygen::PreparedVerificationKey;
 use t_siris::protocol::{UserProtocol, VerifierProtocol};
+use t_siris::rand::rngs::StdRng;
+use t_siris::rand::{CryptoRng, Rng, SeedableRng};
 use t_siris::shamir::{generate_shares, reconstruct_secret};
-use t_siris::signer::Signer;
+use t_siris::system::System;
+
+/// Dealer keygen for one benchmark configuration
+fn setup_system(
+    n_participants: usize,
+    threshold: usize,
+    l_attributes: usize,
+    rng: &mut (impl Rng + CryptoRng),
+) -> System<Bls12_381> {
+    System::builder()
+        .threshold(threshold)
+        .signers(n_participants)
+        .attributes(l_attributes)
+        .build(rng)
+        .expect("Failed to set up system")
+}
 
 /// Benchmark function for threshold PS protocol
 fn benchmark_t_siris(c: &mut Criterion) {
@@ -47,9 +66,9 @@ fn benchmark_t_siris(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Setup for this specific configuration
-            let mut setup_rng = ark_std::test_rng();
-            let (ck, _, _) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let mut setup_rng = StdRng::seed_from_u64(0);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             let s1_shared_secret = Fr::rand(&mut setup_rng);
             let shares =
@@ -59,19 +78,21 @@ fn benchmark_t_siris(c: &mut Criterion) {
             group.bench_function(BenchmarkId::new("obtain_master", id_suffix), |b| {
                 b.iter(|| {
                     // Fresh RNG for each iteration
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
 
                     // model the benchmark for creating the shared secret, this is currently not implemented inside the commitment but here for bench
                     let _ = reconstruct_secret(&shares[0..threshold], threshold);
 
                     // Create attributes specific to this benchmark iteration
-                    let attributes: Vec<Fr> = (0..l_attributes)
-                        .map(|_| Fr::rand(&mut bench_rng))
-                        .collect();
+                    let attributes = Attributes::random(ck, &mut bench_rng);
 
                     // Benchmark the complete request_credential operation
-                    UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut bench_rng)
-                        .expect("Failed to create credential request")
+                    UserProtocol::request_credential_with_attributes(
+                        ck.clone(),
+                        attributes,
+                        &mut bench_rng,
+                    )
+                    .expect("Failed to create credential request")
                 })
             });
         }
@@ -89,32 +110,29 @@ fn benchmark_t_siris(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Complete setup outside the benchmark
-            let mut setup_rng = ark_std::test_rng();
+            let mut setup_rng = StdRng::seed_from_u64(0);
 
             // Setup keys
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create credential request
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
             let (credential, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Benchmark the complete issuance process (share generation + verification + aggregation)
             group.bench_function(BenchmarkId::new("issue_master", id_suffix), |b| {
                 b.iter(|| {
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
 
                     // 1. Generate signature shares from threshold signers
                     let signature_shares = signers
@@ -135,20 +153,21 @@ fn benchmark_t_siris(c: &mut Criterion) {
 
                     // 2. Verify signature shares
                     let verified_shares = UserProtocol::verify_signature_shares(
-                        &ck,
-                        &ts_keys.vk_shares,
+                        ck,
+                        system.vk_shares(),
                         &credential_request,
                         &signature_shares,
                         threshold,
+                        &mut bench_rng,
                     )
                     .expect("Failed to verify signature shares");
 
                     // 3. Aggregate shares
                     let blindings = credential.get_blinding_factors();
                     UserProtocol::aggregate_shares(
-                        &ck,
+                        &system.vk,
                         &verified_shares,
-                        &blindings,
+                        blindings,
                         threshold,
                         &credential_request.h,
                     )
@@ -169,32 +188,29 @@ fn benchmark_t_siris(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Complete setup outside the benchmark
-            let mut setup_rng = ark_std::test_rng();
+            let mut setup_rng = StdRng::seed_from_u64(0);
 
             // Setup keys
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create credential request
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
             let (credential, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Benchmark the complete issuance process (share generation + verification + aggregation)
             group.bench_function(BenchmarkId::new("issue_master_no_zkp", id_suffix), |b| {
                 b.iter(|| {
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
 
                     // 1. Generate signature shares from threshold signers
                     let verified_shares = signers
@@ -216,7 +232,7 @@ fn benchmark_t_siris(c: &mut Criterion) {
                     // // 2. Verify signature shares
                     // let verified_shares = UserProtocol::verify_signature_shares(
                     //     &ck,
-                    //     &ts_keys.vk_shares,
+                    //     system.vk_shares(),
                     //     &credential_request,
                     //     &signature_shares,
                     //     threshold,
@@ -226,9 +242,9 @@ fn benchmark_t_siris(c: &mut Criterion) {
                     // 3. Aggregate shares
                     let blindings = credential.get_blinding_factors();
                     UserProtocol::aggregate_shares(
-                        &ck,
+                        &system.vk,
                         &verified_shares,
-                        &blindings,
+                        blindings,
                         threshold,
                         &credential_request.h,
                     )
@@ -249,25 +265,22 @@ fn benchmark_t_siris(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Setup
-            let mut setup_rng = ark_std::test_rng();
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let mut setup_rng = StdRng::seed_from_u64(0);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create and issue a complete master credential
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
             let (mut master_credential, master_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Generate signature shares
             let signature_shares = UserProtocol::collect_signature_shares(
@@ -280,35 +293,36 @@ fn benchmark_t_siris(c: &mut Criterion) {
 
             // Process shares
             let verified_shares = UserProtocol::verify_signature_shares(
-                &ck,
-                &ts_keys.vk_shares,
+                ck,
+                system.vk_shares(),
                 &master_request,
                 &signature_shares,
                 threshold,
+                &mut setup_rng,
             )
             .expect("Failed to verify signature shares");
 
             // Aggregate shares
             let blindings = master_credential.get_blinding_factors();
             let threshold_signature = UserProtocol::aggregate_shares(
-                &ck,
+                &system.vk,
                 &verified_shares,
-                &blindings,
+                blindings,
                 threshold,
                 &master_request.h,
             )
             .expect("Failed to aggregate signature shares");
 
             // Attach signature to create complete master credential
-            master_credential.attach_signature(threshold_signature);
+            master_credential.attach_signature(threshold_signature, &system.vk);
 
             // Benchmark ObtainContext
             group.bench_function(BenchmarkId::new("obtain_context", id_suffix), |b| {
                 b.iter(|| {
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
 
                     // 1. Show master credential (rerandomization + proof)
-                    let (master_sig, _, _, _) =
+                    let master_presentation =
                         UserProtocol::show(&master_credential, &mut bench_rng)
                             .expect("Failed to show master credential");
 
@@ -321,18 +335,16 @@ fn benchmark_t_siris(c: &mut Criterion) {
                     let nullifier = g.mul(inv).into_affine();
 
                     // 3. Create context credential request
-                    let context_attrs: Vec<Fr> = (0..l_attributes)
-                        .map(|_| Fr::rand(&mut bench_rng))
-                        .collect();
+                    let context_attrs = Attributes::random(ck, &mut bench_rng);
 
-                    let context_request = UserProtocol::request_credential(
+                    let context_request = UserProtocol::request_credential_with_attributes(
                         ck.clone(),
-                        Some(&context_attrs),
+                        context_attrs,
                         &mut bench_rng,
                     )
                     .expect("Failed to create context credential request");
 
-                    (master_sig, nullifier, context_request)
+                    (master_presentation, nullifier, context_request)
                 })
             });
         }
@@ -350,25 +362,23 @@ fn benchmark_t_siris(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Setup
-            let mut setup_rng = ark_std::test_rng();
-            let (ck, vk, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let mut setup_rng = StdRng::seed_from_u64(0);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
+            let vk = &system.vk;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create master credential
-            let master_attrs: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let master_attrs = Attributes::random(ck, &mut setup_rng);
             let (mut master_credential, master_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&master_attrs), &mut setup_rng)
-                    .expect("Failed to create master credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    master_attrs,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create master credential request");
 
             // Issue master credential
             let signature_shares = UserProtocol::collect_signature_shares(
@@ -380,37 +390,39 @@ fn benchmark_t_siris(c: &mut Criterion) {
             .expect("Failed to collect master signature shares");
 
             let verified_shares = UserProtocol::verify_signature_shares(
-                &ck,
-                &ts_keys.vk_shares,
+                ck,
+                system.vk_shares(),
                 &master_request,
                 &signature_shares,
                 threshold,
+                &mut setup_rng,
             )
             .expect("Failed to verify master signature shares");
 
             let master_signature = UserProtocol::aggregate_shares(
-                &ck,
+                &system.vk,
                 &verified_shares,
-                &master_credential.get_blinding_factors(),
+                master_credential.get_blinding_factors(),
                 threshold,
                 &master_request.h,
             )
             .expect("Failed to aggregate master signature shares");
 
-            master_credential.attach_signature(master_signature);
+            master_credential.attach_signature(master_signature, &system.vk);
 
             // Show master credential
-            let (master_sig, master_cm, master_cm_tilde, master_proof) =
-                UserProtocol::show(&master_credential, &mut setup_rng)
-                    .expect("Failed to show master credential");
+            let master_presentation = UserProtocol::show(&master_credential, &mut setup_rng)
+                .expect("Failed to show master credential");
 
             // Create context credential request
-            let context_attrs: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let context_attrs = Attributes::random(ck, &mut setup_rng);
             let (context_credential, context_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&context_attrs), &mut setup_rng)
-                    .expect("Failed to create context credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    context_attrs,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create context credential request");
 
             // Generate synthetic nullifier
             let sk = Fr::rand(&mut setup_rng);
@@ -423,18 +435,12 @@ fn benchmark_t_siris(c: &mut Criterion) {
             // Benchmark IssueContext
             group.bench_function(BenchmarkId::new("issue_context", id_suffix), |b| {
                 b.iter(|| {
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
 
                     // 1. Verify master credential
-                    let master_valid = VerifierProtocol::verify(
-                        &ck,
-                        &vk,
-                        &master_cm,
-                        &master_cm_tilde,
-                        &master_sig,
-                        &master_proof,
-                    )
-                    .expect("Failed to verify master credential");
+                    let master_valid =
+                        VerifierProtocol::verify(ck, vk, &master_presentation, &mut bench_rng)
+                            .expect("Failed to verify master credential");
                     assert!(master_valid, "Master credential verification failed");
 
                     // 2. Verify nullifier (synthetic benchmark)
@@ -461,20 +467,21 @@ fn benchmark_t_siris(c: &mut Criterion) {
 
                     // 4. Verify signature shares
                     let verified_shares = UserProtocol::verify_signature_shares(
-                        &ck,
-                        &ts_keys.vk_shares,
+                        ck,
+                        system.vk_shares(),
                         &context_request,
                         &sig_shares,
                         threshold,
+                        &mut bench_rng,
                     )
                     .expect("Failed to verify signature shares");
 
                     // 5. Aggregate shares
                     let blindings = context_credential.get_blinding_factors();
                     UserProtocol::aggregate_shares(
-                        &ck,
+                        &system.vk,
                         &verified_shares,
-                        &blindings,
+                        blindings,
                         threshold,
                         &context_request.h,
                     )
@@ -495,25 +502,23 @@ fn benchmark_t_siris(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Setup
-            let mut setup_rng = ark_std::test_rng();
-            let (ck, vk, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let mut setup_rng = StdRng::seed_from_u64(0);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
+            let vk = &system.vk;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create master credential
-            let master_attrs: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let master_attrs = Attributes::random(ck, &mut setup_rng);
             let (mut master_credential, master_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&master_attrs), &mut setup_rng)
-                    .expect("Failed to create master credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    master_attrs,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create master credential request");
 
             // Issue master credential
             let verified_shares = UserProtocol::collect_signature_shares(
@@ -525,28 +530,29 @@ fn benchmark_t_siris(c: &mut Criterion) {
             .expect("Failed to collect master signature shares");
 
             let master_signature = UserProtocol::aggregate_shares(
-                &ck,
+                &system.vk,
                 &verified_shares,
-                &master_credential.get_blinding_factors(),
+                master_credential.get_blinding_factors(),
                 threshold,
                 &master_request.h,
             )
             .expect("Failed to aggregate master signature shares");
 
-            master_credential.attach_signature(master_signature);
+            master_credential.attach_signature(master_signature, &system.vk);
 
             // Show master credential
-            let (master_sig, master_cm, master_cm_tilde, master_proof) =
-                UserProtocol::show(&master_credential, &mut setup_rng)
-                    .expect("Failed to show master credential");
+            let master_presentation = UserProtocol::show(&master_credential, &mut setup_rng)
+                .expect("Failed to show master credential");
 
             // Create context credential request
-            let context_attrs: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let context_attrs = Attributes::random(ck, &mut setup_rng);
             let (context_credential, context_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&context_attrs), &mut setup_rng)
-                    .expect("Failed to create context credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    context_attrs,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create context credential request");
 
             // Generate synthetic nullifier
             let sk = Fr::rand(&mut setup_rng);
@@ -559,18 +565,12 @@ fn benchmark_t_siris(c: &mut Criterion) {
             // Benchmark IssueContext
             group.bench_function(BenchmarkId::new("issue_context_no_zkp", id_suffix), |b| {
                 b.iter(|| {
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
 
                     // 1. Verify master credential
-                    let master_valid = VerifierProtocol::verify(
-                        &ck,
-                        &vk,
-                        &master_cm,
-                        &master_cm_tilde,
-                        &master_sig,
-                        &master_proof,
-                    )
-                    .expect("Failed to verify master credential");
+                    let master_valid =
+                        VerifierProtocol::verify(ck, vk, &master_presentation, &mut bench_rng)
+                            .expect("Failed to verify master credential");
                     assert!(master_valid, "Master credential verification failed");
 
                     // 2. Verify nullifier (synthetic benchmark)
@@ -598,7 +598,7 @@ fn benchmark_t_siris(c: &mut Criterion) {
                     // // 4. Verify signature shares
                     // let verified_shares = UserProtocol::verify_signature_shares(
                     //     &ck,
-                    //     &ts_keys.vk_shares,
+                    //     system.vk_shares(),
                     //     &context_request,
                     //     &sig_shares,
                     //     threshold,
@@ -608,9 +608,9 @@ fn benchmark_t_siris(c: &mut Criterion) {
                     // 5. Aggregate shares
                     let blindings = context_credential.get_blinding_factors();
                     UserProtocol::aggregate_shares(
-                        &ck,
+                        &system.vk,
                         &verified_shares,
-                        &blindings,
+                        blindings,
                         threshold,
                         &context_request.h,
                     )
@@ -631,26 +631,23 @@ fn benchmark_t_siris(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Setup - create one complete credential
-            let mut setup_rng = ark_std::test_rng();
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let mut setup_rng = StdRng::seed_from_u64(0);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create and issue a credential
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
 
             let (mut credential, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Get signature shares
             let signature_shares = UserProtocol::collect_signature_shares(
@@ -663,30 +660,31 @@ fn benchmark_t_siris(c: &mut Criterion) {
 
             // Verify and aggregate shares
             let verified_shares = UserProtocol::verify_signature_shares(
-                &ck,
-                &ts_keys.vk_shares,
+                ck,
+                system.vk_shares(),
                 &credential_request,
                 &signature_shares,
                 threshold,
+                &mut setup_rng,
             )
             .expect("Failed to verify signature shares");
 
             let threshold_signature = UserProtocol::aggregate_shares(
-                &ck,
+                &system.vk,
                 &verified_shares,
-                &credential.get_blinding_factors(),
+                credential.get_blinding_factors(),
                 threshold,
                 &credential_request.h,
             )
             .expect("Failed to aggregate signature shares");
 
             // Attach signature to credential
-            credential.attach_signature(threshold_signature);
+            credential.attach_signature(threshold_signature, &system.vk);
 
             // Benchmark the Show operation
             group.bench_function(BenchmarkId::new("show", id_suffix), |b| {
                 b.iter(|| {
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
                     UserProtocol::show(&credential, &mut bench_rng)
                 })
             });
@@ -705,26 +703,24 @@ fn benchmark_t_siris(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Setup - create one complete credential
-            let mut setup_rng = ark_std::test_rng();
-            let (ck, vk, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let mut setup_rng = StdRng::seed_from_u64(0);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
+            let vk = &system.vk;
 
             // Create and issue a credential
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
 
             let (mut credential, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Create signers and issue credential
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Get signature shares
             let signature_shares = UserProtocol::collect_signature_shares(
@@ -737,41 +733,47 @@ fn benchmark_t_siris(c: &mut Criterion) {
 
             // Verify and aggregate shares
             let verified_shares = UserProtocol::verify_signature_shares(
-                &ck,
-                &ts_keys.vk_shares,
+                ck,
+                system.vk_shares(),
                 &credential_request,
                 &signature_shares,
                 threshold,
+                &mut setup_rng,
             )
             .expect("Failed to verify signature shares");
 
             let threshold_signature = UserProtocol::aggregate_shares(
-                &ck,
+                &system.vk,
                 &verified_shares,
-                &credential.get_blinding_factors(),
+                credential.get_blinding_factors(),
                 threshold,
                 &credential_request.h,
             )
             .expect("Failed to aggregate signature shares");
 
             // Attach signature to credential
-            credential.attach_signature(threshold_signature);
+            credential.attach_signature(threshold_signature, &system.vk);
 
             // Create a presentation to verify
-            let (test_sig, test_cm, test_cm_tilde, test_proof) =
-                UserProtocol::show(&credential, &mut setup_rng)
-                    .expect("Failed to generate presentation");
+            let test_presentation = UserProtocol::show(&credential, &mut setup_rng)
+                .expect("Failed to generate presentation");
 
             // Benchmark just the verification
-            group.bench_function(BenchmarkId::new("verify", id_suffix), |b| {
+            group.bench_function(BenchmarkId::new("verify", &id_suffix), |b| {
+                b.iter(|| {
+                    VerifierProtocol::verify(ck, vk, &test_presentation, &mut setup_rng)
+                        .expect("Failed to verify credential")
+                })
+            });
+
+            let prepared_vk = PreparedVerificationKey::new(ck, vk);
+            group.bench_function(BenchmarkId::new("verify_prepared", id_suffix), |b| {
                 b.iter(|| {
-                    VerifierProtocol::verify(
-                        &ck,
-                        &vk,
-                        &test_cm,
-                        &test_cm_tilde,
-                        &test_sig,
-                        &test_proof,
+                    VerifierProtocol::verify_prepared(
+                        ck,
+                        &prepared_vk,
+                        &test_presentation,
+                        &mut setup_rng,
                     )
                     .expect("Failed to verify credential")
                 })
@@ -782,9 +784,77 @@ fn benchmark_t_siris(c: &mut Criterion) {
     }
 }
 
+/// Per-commitment proofs against one aggregated proof for the credential request
+fn benchmark_aggregated_request(c: &mut Criterion) {
+    let (n_participants, threshold, l_attributes) = (4, 3, 128);
+    let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
+
+    let mut setup_rng = StdRng::seed_from_u64(0);
+    let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+    let signer = system.signer(1).expect("Signer 1 exists");
+    let per_m_request = Credential::new(system.ck.clone(), None, &mut setup_rng)
+        .expect("Failed to create credential")
+        .compute_commitments_per_m(&mut setup_rng)
+        .expect("Failed to compute commitments");
+    let aggregated_request = Credential::new(system.ck.clone(), None, &mut setup_rng)
+        .expect("Failed to create credential")
+        .prove_all(&mut setup_rng)
+        .expect("Failed to compute commitments");
+    println!(
+        "request size at l={}: per-commitment proofs {} bytes, aggregated proof {} bytes",
+        l_attributes,
+        per_m_request.serialized_size(),
+        aggregated_request.serialized_size()
+    );
+
+    let mut group = c.benchmark_group("t_siris_aggregated_request");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(25));
+
+    group.bench_function(BenchmarkId::new("prove_per_m", &id_suffix), |b| {
+        let mut credential = Credential::new(system.ck.clone(), None, &mut setup_rng)
+            .expect("Failed to create credential");
+        b.iter(|| {
+            credential
+                .compute_commitments_per_m(&mut StdRng::seed_from_u64(0))
+                .expect("Failed to compute commitments")
+        })
+    });
+    group.bench_function(BenchmarkId::new("prove_all", &id_suffix), |b| {
+        let mut credential = Credential::new(system.ck.clone(), None, &mut setup_rng)
+            .expect("Failed to create credential");
+        b.iter(|| {
+            credential
+                .prove_all(&mut StdRng::seed_from_u64(0))
+                .expect("Failed to compute commitments")
+        })
+    });
+    group.bench_function(BenchmarkId::new("sign_share_per_m", &id_suffix), |b| {
+        b.iter(|| {
+            signer
+                .sign_share(
+                    &per_m_request.commitments,
+                    &per_m_request.proofs,
+                    &per_m_request.h,
+                    &mut StdRng::seed_from_u64(0),
+                )
+                .expect("Failed to sign share")
+        })
+    });
+    group.bench_function(BenchmarkId::new("sign_share_aggregated", &id_suffix), |b| {
+        b.iter(|| {
+            signer
+                .sign_share_aggregated(&aggregated_request, &mut StdRng::seed_from_u64(0))
+                .expect("Failed to sign share")
+        })
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default();
-    targets = benchmark_t_siris
+    targets = benchmark_t_siris, benchmark_aggregated_request
 );
 criterion_main!(benches);
@@ -1,13 +1,30 @@
-use ark_bls12_381::{Bls12_381, Fr};
-use ark_ff::UniformRand;
+use ark_bls12_381::Bls12_381;
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use std::time::Duration;
+use t_siris::attributes::Attributes;
 use t_siris::credential::Credential;
 use t_siris::credential::CredentialState;
-use t_siris::keygen::keygen;
 use t_siris::protocol::{UserProtocol, VerifierProtocol};
+use t_siris::rand::rngs::StdRng;
+use t_siris::rand::{CryptoRng, Rng, SeedableRng};
 use t_siris::signature::PartialSignature;
-use t_siris::signer::Signer;
+use t_siris::system::System;
+use t_siris::user::User;
+
+/// Dealer keygen for one benchmark configuration
+fn setup_system(
+    n_participants: usize,
+    threshold: usize,
+    l_attributes: usize,
+    rng: &mut (impl Rng + CryptoRng),
+) -> System<Bls12_381> {
+    System::builder()
+        .threshold(threshold)
+        .signers(n_participants)
+        .attributes(l_attributes)
+        .build(rng)
+        .expect("Failed to set up system")
+}
 
 /// Benchmark function for threshold PS protocol
 fn benchmark_t_utt(c: &mut Criterion) {
@@ -47,23 +64,22 @@ fn benchmark_t_utt(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Setup for this specific configuration
-            let mut setup_rng = ark_std::test_rng();
-            let (ck, _, _) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let mut setup_rng = StdRng::seed_from_u64(0);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create attributes specific to this configuration
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
 
             // Create credential for this configuration
-            let mut credential = Credential::new(ck.clone(), Some(&attributes), &mut setup_rng);
+            let mut credential =
+                Credential::from_attributes(ck.clone(), attributes, &mut setup_rng);
 
             // Only benchmark the compute_commitments_per_m function
             group.bench_function(BenchmarkId::new("token_request", id_suffix), |b| {
                 b.iter(|| {
                     // Need a fresh RNG for each iteration to ensure randomness
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
 
                     // Reset credential state for each iteration
                     credential.state = CredentialState::Initialized;
@@ -89,27 +105,23 @@ fn benchmark_t_utt(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Complete setup outside the benchmark
-            let mut setup_rng = ark_std::test_rng();
+            let mut setup_rng = StdRng::seed_from_u64(0);
 
             // Setup keys
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create credential request
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
-            let (_, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+            let attributes = Attributes::random(ck, &mut setup_rng);
+            let (_, credential_request) = UserProtocol::request_credential_with_attributes(
+                ck.clone(),
+                attributes,
+                &mut setup_rng,
+            )
+            .expect("Failed to create credential request");
 
             // Benchmark just the signing operation
             group.bench_function(BenchmarkId::new("t_issue", id_suffix), |b| {
@@ -149,27 +161,23 @@ fn benchmark_t_utt(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Complete setup outside the benchmark
-            let mut setup_rng = ark_std::test_rng();
+            let mut setup_rng = StdRng::seed_from_u64(0);
 
             // Setup keys
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create credential request
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
-            let (_, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+            let attributes = Attributes::random(ck, &mut setup_rng);
+            let (_, credential_request) = UserProtocol::request_credential_with_attributes(
+                ck.clone(),
+                attributes,
+                &mut setup_rng,
+            )
+            .expect("Failed to create credential request");
 
             // Benchmark just the signing operation
             group.bench_function(BenchmarkId::new("t_issue_no_verify", id_suffix), |b| {
@@ -210,27 +218,24 @@ fn benchmark_t_utt(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Complete setup outside the benchmark
-            let mut setup_rng = ark_std::test_rng();
+            let mut setup_rng = StdRng::seed_from_u64(0);
 
             // Setup keys
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create credential and request
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
             let (credential, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Generate signature shares
             let signature_shares: Vec<(usize, PartialSignature<Bls12_381>)> = signers
@@ -250,66 +255,94 @@ fn benchmark_t_utt(c: &mut Criterion) {
                 .collect();
 
             // Benchmark user verification and aggregation
-            group.bench_function(BenchmarkId::new("aggregate_with_verify", id_suffix), |b| {
+            group.bench_function(BenchmarkId::new("aggregate_with_verify", &id_suffix), |b| {
                 b.iter(|| {
                     // Verify signature shares
                     let verified_shares = UserProtocol::verify_signature_shares(
-                        &ck,
-                        &ts_keys.vk_shares,
+                        ck,
+                        system.vk_shares(),
                         &credential_request,
                         &signature_shares,
                         threshold,
+                        &mut setup_rng,
                     )
                     .expect("Failed to verify signature shares");
 
                     // Aggregate shares
                     let blindings = credential.get_blinding_factors();
                     UserProtocol::aggregate_shares(
-                        &ck,
+                        &system.vk,
                         &verified_shares,
-                        &blindings,
+                        blindings,
                         threshold,
                         &credential_request.h,
                     )
                 })
             });
+
+            // The same share checks with the commitment proofs re-verified for every
+            // share, as before they were verified once per request
+            group.bench_function(
+                BenchmarkId::new("verify_shares_reverify_proofs", id_suffix),
+                |b| {
+                    b.iter(|| {
+                        signature_shares.iter().all(|(i, share)| {
+                            let vk_share = system
+                                .vk_shares()
+                                .iter()
+                                .find(|vk| vk.index() == *i)
+                                .expect("Verification key share exists");
+                            User::verify_signature_share(
+                                ck,
+                                vk_share,
+                                &credential_request.commitments,
+                                &credential_request.proofs,
+                                share,
+                                &mut setup_rng,
+                            )
+                            .expect("Failed to verify signature share")
+                        })
+                    })
+                },
+            );
         }
 
         group.finish();
     }
 
-    // aggregate_no_verify benchmarks
+    // aggregate_no_verify benchmarks, with larger committees since aggregation grows
+    // with the threshold
     {
         let mut group = c.benchmark_group("t_utt");
         group
             .sample_size(100)
             .measurement_time(Duration::from_secs(20));
 
-        for &(n_participants, threshold, l_attributes) in &configs {
+        let aggregate_configs = configs
+            .iter()
+            .chain(&[(128, 65, 4), (128, 65, 16), (128, 65, 32)]);
+        for &(n_participants, threshold, l_attributes) in aggregate_configs {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Complete setup outside the benchmark
-            let mut setup_rng = ark_std::test_rng();
+            let mut setup_rng = StdRng::seed_from_u64(0);
 
             // Setup keys
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create credential and request
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
             let (credential, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Generate signature shares
             let signature_shares: Vec<(usize, PartialSignature<Bls12_381>)> = signers
@@ -330,11 +363,12 @@ fn benchmark_t_utt(c: &mut Criterion) {
 
             // Verify signature shares
             let verified_shares = UserProtocol::verify_signature_shares(
-                &ck,
-                &ts_keys.vk_shares,
+                ck,
+                system.vk_shares(),
                 &credential_request,
                 &signature_shares,
                 threshold,
+                &mut setup_rng,
             )
             .expect("Failed to verify signature shares");
 
@@ -344,9 +378,9 @@ fn benchmark_t_utt(c: &mut Criterion) {
                     // Aggregate shares
                     let blindings = credential.get_blinding_factors();
                     UserProtocol::aggregate_shares(
-                        &ck,
+                        &system.vk,
                         &verified_shares,
-                        &blindings,
+                        blindings,
                         threshold,
                         &credential_request.h,
                     )
@@ -367,27 +401,24 @@ fn benchmark_t_utt(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Complete setup outside the benchmark
-            let mut setup_rng = ark_std::test_rng();
+            let mut setup_rng = StdRng::seed_from_u64(0);
 
             // Setup keys
-            let (ck, _, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create credential and request
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
             let (mut credential, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Generate signature shares
             let signature_shares: Vec<(usize, PartialSignature<Bls12_381>)> = signers
@@ -408,32 +439,33 @@ fn benchmark_t_utt(c: &mut Criterion) {
 
             // Verify signature shares
             let verified_shares = UserProtocol::verify_signature_shares(
-                &ck,
-                &ts_keys.vk_shares,
+                ck,
+                system.vk_shares(),
                 &credential_request,
                 &signature_shares,
                 threshold,
+                &mut setup_rng,
             )
             .expect("Failed to verify signature shares");
 
             // Aggregate shares
             let blindings = credential.get_blinding_factors();
             let threshold_signature = UserProtocol::aggregate_shares(
-                &ck,
+                &system.vk,
                 &verified_shares,
-                &blindings,
+                blindings,
                 threshold,
                 &credential_request.h,
             )
             .expect("Failed to aggregate signature shares");
 
             // Attach signature to credential
-            credential.attach_signature(threshold_signature);
+            credential.attach_signature(threshold_signature, &system.vk);
 
             // Now benchmark only the show/prove function
             group.bench_function(BenchmarkId::new("prove", id_suffix), |b| {
                 b.iter(|| {
-                    let mut bench_rng = ark_std::test_rng();
+                    let mut bench_rng = StdRng::seed_from_u64(0);
                     // Only benchmark the show function which generates the presentation
                     UserProtocol::show(&credential, &mut bench_rng)
                 })
@@ -454,27 +486,25 @@ fn benchmark_t_utt(c: &mut Criterion) {
             let id_suffix = format!("N{}_t{}_n{}", n_participants, threshold, l_attributes);
 
             // Complete setup outside the benchmark
-            let mut setup_rng = ark_std::test_rng();
+            let mut setup_rng = StdRng::seed_from_u64(0);
 
             // Setup keys and parameters
-            let (ck, vk, ts_keys) =
-                keygen::<Bls12_381>(threshold, n_participants, l_attributes, &mut setup_rng);
+            let system = setup_system(n_participants, threshold, l_attributes, &mut setup_rng);
+            let ck = &system.ck;
+            let vk = &system.vk;
 
             // Create signers
-            let signers: Vec<_> = ts_keys
-                .sk_shares
-                .iter()
-                .zip(ts_keys.vk_shares.iter())
-                .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-                .collect();
+            let signers = system.signers();
 
             // Create credential and request
-            let attributes: Vec<Fr> = (0..l_attributes)
-                .map(|_| Fr::rand(&mut setup_rng))
-                .collect();
+            let attributes = Attributes::random(ck, &mut setup_rng);
             let (mut credential, credential_request) =
-                UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut setup_rng)
-                    .expect("Failed to create credential request");
+                UserProtocol::request_credential_with_attributes(
+                    ck.clone(),
+                    attributes,
+                    &mut setup_rng,
+                )
+                .expect("Failed to create credential request");
 
             // Generate signature shares
             let signature_shares = UserProtocol::collect_signature_shares(
@@ -487,42 +517,35 @@ fn benchmark_t_utt(c: &mut Criterion) {
 
             // Process signature shares
             let verified_shares = UserProtocol::verify_signature_shares(
-                &ck,
-                &ts_keys.vk_shares,
+                ck,
+                system.vk_shares(),
                 &credential_request,
                 &signature_shares,
                 threshold,
+                &mut setup_rng,
             )
             .expect("Failed to verify signature shares");
 
             // Aggregate shares
             let blindings = credential.get_blinding_factors();
             let threshold_signature = UserProtocol::aggregate_shares(
-                &ck,
+                &system.vk,
                 &verified_shares,
-                &blindings,
+                blindings,
                 threshold,
                 &credential_request.h,
             )
             .expect("Failed to aggregate signature shares");
 
             // Attach signature to credential
-            credential.attach_signature(threshold_signature);
+            credential.attach_signature(threshold_signature, &system.vk);
 
             // Optional: Verify once that our setup is working
-            let (test_sig, test_cm, test_cm_tilde, test_proof) =
-                UserProtocol::show(&credential, &mut setup_rng)
-                    .expect("Failed to generate presentation");
-
-            let test_result = VerifierProtocol::verify(
-                &ck,
-                &vk,
-                &test_cm,
-                &test_cm_tilde,
-                &test_sig,
-                &test_proof,
-            )
-            .expect("Failed to verify credential");
+            let test_presentation = UserProtocol::show(&credential, &mut setup_rng)
+                .expect("Failed to generate presentation");
+
+            let test_result = VerifierProtocol::verify(ck, vk, &test_presentation, &mut setup_rng)
+                .expect("Failed to verify credential");
 
             assert!(
                 test_result,
@@ -534,19 +557,17 @@ fn benchmark_t_utt(c: &mut Criterion) {
                 b.iter_with_setup(
                     // Setup generates a fresh presentation each time
                     || {
-                        let mut rng = ark_std::test_rng();
+                        let mut rng = StdRng::seed_from_u64(0);
                         UserProtocol::show(&credential, &mut rng)
                             .expect("Failed to generate presentation")
                     },
                     // Use the fresh presentation for verification
-                    |(randomized_sig, commitment, commitment_tilde, proof)| {
+                    |presentation| {
                         VerifierProtocol::verify(
-                            &ck,
-                            &vk,
-                            &commitment,
-                            &commitment_tilde,
-                            &randomized_sig,
-                            &proof,
+                            ck,
+                            vk,
+                            &presentation,
+                            &mut StdRng::seed_from_u64(0),
                         )
                         .expect("Failed to verify credential")
                     },
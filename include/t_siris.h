@@ -0,0 +1,65 @@
+#ifndef T_SIRIS_H
+#define T_SIRIS_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Status codes returned by every FFI function
+typedef enum TSirisStatus {
+  TSirisStatus_Ok = 0,
+  TSirisStatus_NullPointer = 1,
+  TSirisStatus_InvalidEncoding = 2,
+  TSirisStatus_InvalidKeyMaterial = 3,
+  TSirisStatus_BufferTooSmall = 4,
+  TSirisStatus_ProofVerificationFailed = 5,
+  TSirisStatus_CommitmentConsistencyFailed = 6,
+  TSirisStatus_InvalidShare = 7,
+  TSirisStatus_SigningFailed = 8,
+  TSirisStatus_Panic = 9,
+} TSirisStatus;
+
+// Opaque signer handle owning its key material
+typedef struct TSirisSigner TSirisSigner;
+
+// Create a signer from serialized key material.
+//
+// On success `*out_signer` holds a new signer that must be released with
+// `t_siris_signer_free`, on failure it is left untouched.
+//
+// # Safety
+// Each byte pointer must be valid for reads of its length and `out_signer`
+// must be valid for a pointer write.
+enum TSirisStatus t_siris_signer_new(const uint8_t *ck_bytes,
+                                     uintptr_t ck_len,
+                                     const uint8_t *sk_share_bytes,
+                                     uintptr_t sk_share_len,
+                                     const uint8_t *vk_share_bytes,
+                                     uintptr_t vk_share_len,
+                                     struct TSirisSigner **out_signer);
+
+// Verify a serialized `CredentialCommitments` request and sign a share of it.
+//
+// The serialized `PartialSignature` is written to `out_buf` and its length to `*out_len`.
+//
+// # Safety
+// `signer` must come from `t_siris_signer_new` and not have been freed,
+// `request_bytes` must be valid for reads of `request_len` bytes, `out_buf` valid
+// for writes of `out_cap` bytes and `out_len` valid for a write.
+enum TSirisStatus t_siris_sign_share(const struct TSirisSigner *signer,
+                                     const uint8_t *request_bytes,
+                                     uintptr_t request_len,
+                                     uint8_t *out_buf,
+                                     uintptr_t out_cap,
+                                     uintptr_t *out_len);
+
+// Release a signer created by `t_siris_signer_new`. Passing null is a no-op.
+//
+// # Safety
+// `signer` must be null or come from `t_siris_signer_new`, and must not be used afterwards.
+void t_siris_signer_free(struct TSirisSigner *signer);
+
+#endif /* T_SIRIS_H */
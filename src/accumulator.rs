@@ -0,0 +1,527 @@
+//! Revocation through a universal accumulator over the *revoked* handles.
+//!
+//! `revocation` accumulates the handles that are still valid, so every issuance changes
+//! the published value. Here the issuer accumulates the revoked handles instead,
+//! `V = P^{f(alpha)}` with `f(X) = (X + s) prod_{y revoked} (X + y)` and a secret `s`
+//! that keeps `f` from being constant, and publishes `V` along with
+//! `alpha_tilde = g_tilde^alpha`. A handle `x` is not accumulated exactly when
+//! `d = f(-x) != 0`, its non-membership witness is `(C, d)` with
+//! `C = P^{(f(alpha) - d)/(alpha + x)}`, so `e(C, alpha_tilde g_tilde^x) e(P, g_tilde)^d = e(V, g_tilde)`.
+//!
+//! On show the holder randomizes `C' = C^rho`, sends `C_bar = C'^alpha` and `Z = P^{d rho}`,
+//! checked by `e(C', alpha_tilde) = e(C_bar, g_tilde)` and `Z != 1`, and proves knowledge
+//! of `(x, rho, d rho)` in `C_bar Z = V^rho C'^{-x}` and `Z = P^{d rho}`. As in
+//! `revocation`, the response for `x` is the opening proof's response for the handle
+//! attribute, which ties the handle to the credential.
+//!
+//! Witnesses go stale whenever `V` changes. The holder applies every published change
+//! in order with `update_on_addition` and `update_on_removal`; once the holder's own
+//! handle is added `d` becomes zero and no witness is left.
+use crate::commitment::CommitmentProof;
+use crate::errors::{CommitmentError, RevocationError, SignatureError};
+use crate::keygen::VerificationKey;
+use crate::pairing::PairingCheck;
+use crate::presentation::Presentation;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::{DomainSeparator, Transcript};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::ops::Mul;
+use ark_std::rand::{CryptoRng, Rng};
+use ark_std::{One, Zero};
+#[cfg(feature = "signing")]
+use {
+    crate::ct::{ct_inverse, rand_nonzero},
+    ark_ff::UniformRand,
+    ark_std::collections::BTreeSet,
+};
+
+/// The accumulator's public key `(P, g_tilde, g_tilde^alpha)`
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct UniversalAccumulatorPublicKey<E: Pairing> {
+    pub base: E::G1Affine,
+    pub g_tilde: E::G2Affine,
+    pub alpha_tilde: E::G2Affine,
+}
+
+/// The issuer's accumulator over the revoked handles
+#[cfg(feature = "signing")]
+pub struct UniversalAccumulator<E: Pairing> {
+    alpha: E::ScalarField,
+    /// The root `-s` of `f` that is never revoked
+    seed: E::ScalarField,
+    public_key: UniversalAccumulatorPublicKey<E>,
+    value: E::G1Affine,
+    members: BTreeSet<E::ScalarField>,
+}
+
+#[cfg(feature = "signing")]
+impl<E: Pairing> UniversalAccumulator<E> {
+    /// An accumulator with nothing revoked, `V = P^{alpha + s}`, with fresh secrets
+    pub fn new(rng: &mut (impl Rng + CryptoRng)) -> Self {
+        let alpha = rand_nonzero(rng);
+        let seed = E::ScalarField::rand(rng);
+        let base = E::G1Affine::generator();
+        let g_tilde = E::G2Affine::generator();
+        Self {
+            alpha,
+            seed,
+            public_key: UniversalAccumulatorPublicKey {
+                base,
+                g_tilde,
+                alpha_tilde: g_tilde.mul(alpha).into_affine(),
+            },
+            value: base.mul(alpha + seed).into_affine(),
+            members: BTreeSet::new(),
+        }
+    }
+
+    pub fn public_key(&self) -> &UniversalAccumulatorPublicKey<E> {
+        &self.public_key
+    }
+
+    /// The current value `V`, to be published after every change
+    pub fn value(&self) -> E::G1Affine {
+        self.value
+    }
+
+    /// Whether `handle` is revoked
+    pub fn is_member(&self, handle: &E::ScalarField) -> bool {
+        self.members.contains(handle)
+    }
+
+    /// `1/(alpha + handle)`, failing for the one handle that has no inverse
+    fn inverse(&self, handle: &E::ScalarField) -> Result<E::ScalarField, RevocationError> {
+        let (inverse, is_zero) = ct_inverse(&(self.alpha + handle));
+        if bool::from(is_zero) {
+            return Err(RevocationError::InvalidHandle);
+        }
+        Ok(inverse)
+    }
+
+    /// Revoke `handle` and return the new value. Holders of the other handles update
+    /// their witnesses with `NonMembershipWitness::update_on_addition`
+    pub fn add(&mut self, handle: E::ScalarField) -> Result<E::G1Affine, RevocationError> {
+        if self.is_member(&handle) {
+            return Err(RevocationError::AlreadyMember);
+        }
+        self.inverse(&handle)?;
+        self.value = self.value.mul(self.alpha + handle).into_affine();
+        self.members.insert(handle);
+        Ok(self.value)
+    }
+
+    /// Reinstate `handle` and return the new value. Holders of the other handles update
+    /// their witnesses with `NonMembershipWitness::update_on_removal`
+    pub fn remove(&mut self, handle: &E::ScalarField) -> Result<E::G1Affine, RevocationError> {
+        if !self.is_member(handle) {
+            return Err(RevocationError::NotMember);
+        }
+        self.value = self.value.mul(self.inverse(handle)?).into_affine();
+        self.members.remove(handle);
+        Ok(self.value)
+    }
+
+    /// The non-membership witness for `handle` against the current value. Fails with
+    /// `Revoked` for an accumulated handle
+    pub fn witness(
+        &self,
+        handle: &E::ScalarField,
+    ) -> Result<NonMembershipWitness<E>, RevocationError> {
+        if self.is_member(handle) {
+            return Err(RevocationError::Revoked);
+        }
+        // d = f(-x) = (s - x) prod_y (y - x)
+        let d = self
+            .members
+            .iter()
+            .fold(self.seed - handle, |acc, member| acc * (*member - handle));
+        if d.is_zero() {
+            return Err(RevocationError::InvalidHandle);
+        }
+        let witness = (self.value.into_group() - self.public_key.base.mul(d))
+            .mul(self.inverse(handle)?)
+            .into_affine();
+        Ok(NonMembershipWitness {
+            witness,
+            d,
+            value: self.value,
+        })
+    }
+}
+
+/// A holder's witness `(C, d)` that a handle is not accumulated in `value`
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct NonMembershipWitness<E: Pairing> {
+    pub witness: E::G1Affine,
+    pub d: E::ScalarField,
+    pub value: E::G1Affine,
+}
+
+impl<E: Pairing> NonMembershipWitness<E> {
+    /// Whether `handle` is not accumulated in `self.value`
+    pub fn verify(
+        &self,
+        public_key: &UniversalAccumulatorPublicKey<E>,
+        handle: &E::ScalarField,
+    ) -> bool {
+        if self.d.is_zero() {
+            return false;
+        }
+        let shifted = (public_key.alpha_tilde + public_key.g_tilde.mul(*handle)).into_affine();
+        let lhs = E::pairing(self.witness, shifted)
+            + E::pairing(public_key.base.mul(self.d), public_key.g_tilde);
+        lhs == E::pairing(self.value, public_key.g_tilde)
+    }
+
+    /// Move the witness for `handle` to `new_value` after `added` was revoked:
+    /// `C' = V C^{added - handle}`, `d' = d (added - handle)`. Fails with `Revoked` when
+    /// `added` is `handle` itself
+    pub fn update_on_addition(
+        &mut self,
+        handle: &E::ScalarField,
+        added: &E::ScalarField,
+        new_value: E::G1Affine,
+    ) -> Result<(), RevocationError> {
+        let delta = *added - handle;
+        if delta.is_zero() {
+            return Err(RevocationError::Revoked);
+        }
+        self.witness = (self.witness.mul(delta) + self.value).into_affine();
+        self.d *= delta;
+        self.value = new_value;
+        Ok(())
+    }
+
+    /// Move the witness for `handle` to `new_value` after `removed` was reinstated:
+    /// `C' = (C / V')^{1/(removed - handle)}`, `d' = d / (removed - handle)`
+    pub fn update_on_removal(
+        &mut self,
+        handle: &E::ScalarField,
+        removed: &E::ScalarField,
+        new_value: E::G1Affine,
+    ) -> Result<(), RevocationError> {
+        let inverse = (*removed - handle)
+            .inverse()
+            .ok_or(RevocationError::InvalidHandle)?;
+        self.witness = (self.witness.into_group() - new_value)
+            .mul(inverse)
+            .into_affine();
+        self.d *= inverse;
+        self.value = new_value;
+        Ok(())
+    }
+}
+
+/// Proof that a hidden attribute `x` is not accumulated in `V`, see the module
+/// documentation for the statement
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct NonMembershipProof<E: Pairing> {
+    /// `C' = C^rho`
+    pub witness: E::G1Affine,
+    /// `C_bar = C'^alpha = V^rho C'^{-x} P^{-d rho}`
+    pub witness_bar: E::G1Affine,
+    /// `Z = P^{d rho}`
+    pub d_commitment: E::G1Affine,
+    /// `V^{t_rho} C'^{-t_x}`, with `t_x` the opening proof's blinding for the attribute
+    pub announcement: E::G1Affine,
+    /// `P^{t_d}`
+    pub d_announcement: E::G1Affine,
+    /// `t_rho + c rho`
+    pub response_rho: E::ScalarField,
+    /// `t_d + c d rho`
+    pub response_d: E::ScalarField,
+}
+
+/// What the holder keeps between committing and answering the challenge
+#[cfg(feature = "signing")]
+pub(crate) struct NonMembershipProverState<F> {
+    rho: F,
+    d_rho: F,
+    t_rho: F,
+    t_d: F,
+}
+
+impl<E: Pairing> NonMembershipProof<E> {
+    /// Randomize `witness` for the attribute `x` and commit with the opening proof's
+    /// `blinding` for it. The responses are set by `respond` once the challenge is known
+    #[cfg(feature = "signing")]
+    pub(crate) fn commit(
+        public_key: &UniversalAccumulatorPublicKey<E>,
+        witness: &NonMembershipWitness<E>,
+        x: &E::ScalarField,
+        blinding: &E::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Self, NonMembershipProverState<E::ScalarField>), RevocationError> {
+        if witness.d.is_zero() || witness.witness.is_zero() {
+            return Err(RevocationError::Revoked);
+        }
+        let rho = rand_nonzero::<E::ScalarField, _>(rng);
+        let d_rho = witness.d * rho;
+        let t_rho = E::ScalarField::rand(rng);
+        let t_d = E::ScalarField::rand(rng);
+        let randomized = witness.witness.mul(rho).into_affine();
+        let d_commitment = public_key.base.mul(d_rho).into_affine();
+        let witness_bar =
+            (witness.value.mul(rho) - randomized.mul(*x) - d_commitment).into_affine();
+        let announcement = (witness.value.mul(t_rho) - randomized.mul(*blinding)).into_affine();
+        let proof = Self {
+            witness: randomized,
+            witness_bar,
+            d_commitment,
+            announcement,
+            d_announcement: public_key.base.mul(t_d).into_affine(),
+            response_rho: E::ScalarField::zero(),
+            response_d: E::ScalarField::zero(),
+        };
+        let state = NonMembershipProverState {
+            rho,
+            d_rho,
+            t_rho,
+            t_d,
+        };
+        Ok((proof, state))
+    }
+
+    #[cfg(feature = "signing")]
+    pub(crate) fn respond(
+        &mut self,
+        state: NonMembershipProverState<E::ScalarField>,
+        challenge: &E::ScalarField,
+    ) {
+        self.response_rho = state.t_rho + *challenge * state.rho;
+        self.response_d = state.t_d + *challenge * state.d_rho;
+    }
+
+    /// Check the proof against the accumulator `value` and `attribute_response`, the
+    /// opening proof's response for the attribute, under the shared `challenge`
+    pub(crate) fn verify(
+        &self,
+        public_key: &UniversalAccumulatorPublicKey<E>,
+        value: &E::G1Affine,
+        attribute_response: &E::ScalarField,
+        challenge: &E::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> bool {
+        if self.witness.is_zero() || self.d_commitment.is_zero() {
+            return false;
+        }
+        // V^{z_rho} C'^{-z_x} = T (C_bar Z)^c
+        let lhs = value.mul(self.response_rho) - self.witness.mul(*attribute_response);
+        let rhs = (self.witness_bar + self.d_commitment).mul(*challenge) + self.announcement;
+        // P^{z_d} = T_d Z^c
+        let d_lhs = public_key.base.mul(self.response_d);
+        let d_rhs = self.d_commitment.mul(*challenge) + self.d_announcement;
+        // e(C', alpha_tilde) = e(C_bar, g_tilde)
+        let neg_witness_bar = (-self.witness_bar.into_group()).into_affine();
+        let check = PairingCheck::<E>::rand(
+            rng,
+            &[
+                (&self.witness, &public_key.alpha_tilde),
+                (&neg_witness_bar, &public_key.g_tilde),
+            ],
+            &E::TargetField::one(),
+        );
+        lhs == rhs && d_lhs == d_rhs && check.verify()
+    }
+}
+
+/// A presentation with a proof that the attribute at `handle_index` is not
+/// accumulated, i.e. not revoked
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct NonMembershipPresentation<E: Pairing> {
+    pub presentation: Presentation<E>,
+    pub handle_index: usize,
+    pub proof: NonMembershipProof<E>,
+}
+
+/// Joint challenge over the randomized credential, the accumulator value, the
+/// randomized witness and the announcements of both proofs
+pub(crate) fn non_membership_show_challenge<E: Pairing>(
+    domain: &DomainSeparator,
+    presentation: &Presentation<E>,
+    value: &E::G1Affine,
+    handle_index: usize,
+    proof: &NonMembershipProof<E>,
+    schnorr_commitment: &E::G1Affine,
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris show with non-membership");
+    transcript.append(b"signature", &presentation.signature);
+    transcript.append(b"cm", &presentation.commitment);
+    transcript.append(b"cm_tilde", &presentation.commitment_tilde);
+    transcript.append(b"accumulator", value);
+    transcript.append(b"handle_index", &(handle_index as u64));
+    transcript.append(b"witness", &proof.witness);
+    transcript.append(b"witness_bar", &proof.witness_bar);
+    transcript.append(b"d_commitment", &proof.d_commitment);
+    transcript.append(b"schnorr_commitment", schnorr_commitment);
+    transcript.append(b"announcement", &proof.announcement);
+    transcript.append(b"d_announcement", &proof.d_announcement);
+    transcript.challenge_scalar(b"challenge")
+}
+
+impl<E: Pairing> NonMembershipPresentation<E> {
+    /// Verify the credential and that its handle is not accumulated in `value`, the
+    /// accumulator value the verifier currently trusts
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        public_key: &UniversalAccumulatorPublicKey<E>,
+        value: &E::G1Affine,
+        domain: &DomainSeparator,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&self.presentation.proof[..])?;
+        let challenge = non_membership_show_challenge(
+            domain,
+            &self.presentation,
+            value,
+            self.handle_index,
+            &self.proof,
+            &proof.schnorr_commitment,
+        );
+        if proof.challenge != challenge || self.handle_index >= ck.num_attributes() {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        let handle_response = proof
+            .responses
+            .get(ck.attribute_position(self.handle_index))
+            .ok_or(CommitmentError::InvalidProof)?;
+        if !self
+            .proof
+            .verify(public_key, value, handle_response, &challenge, rng)
+        {
+            return Err(SignatureError::ProofError(
+                "non-membership proof verification failed".to_string(),
+            ));
+        }
+
+        self.presentation.verify(ck, vk, rng)
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::protocol::{UserProtocol, VerifierProtocol};
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr};
+
+    #[test]
+    fn test_revoked_credential_loses_non_membership() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let mut accumulator = UniversalAccumulator::<Bls12_381>::new(&mut rng);
+        let public_key = accumulator.public_key().clone();
+
+        // Three credentials carrying their handle as attribute 1
+        let handles: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let credentials: Vec<_> = handles
+            .iter()
+            .map(|handle| {
+                let attributes = [Fr::rand(&mut rng), *handle, Fr::rand(&mut rng)];
+                let user = system.user();
+                let (mut credential, request) = user
+                    .request_credential(Some(&attributes), &mut rng)
+                    .unwrap();
+                let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+                user.complete_credential(&mut credential, &request, &shares, &mut rng)
+                    .unwrap();
+                credential
+            })
+            .collect();
+        let mut witnesses: Vec<_> = handles
+            .iter()
+            .map(|handle| accumulator.witness(handle).unwrap())
+            .collect();
+        let domain = credentials[0].domain().clone();
+        let verify = |shown: &NonMembershipPresentation<Bls12_381>, value: &_, rng: &mut _| {
+            VerifierProtocol::verify_non_membership(
+                &system.ck,
+                &system.vk,
+                &public_key,
+                &domain,
+                shown,
+                value,
+                rng,
+            )
+        };
+
+        for (credential, witness) in credentials.iter().zip(&witnesses) {
+            assert!(witness.verify(&public_key, &credential.get_messages()[1]));
+            let shown =
+                UserProtocol::prove_non_membership(credential, 1, &public_key, witness, &mut rng)
+                    .unwrap();
+            assert!(verify(&shown, &accumulator.value(), &mut rng).unwrap());
+        }
+        // Another attribute isn't the handle the witness is for
+        let wrong_index = UserProtocol::prove_non_membership(
+            &credentials[0],
+            0,
+            &public_key,
+            &witnesses[0],
+            &mut rng,
+        )
+        .unwrap();
+        assert!(verify(&wrong_index, &accumulator.value(), &mut rng).is_err());
+
+        // Revoke the first credential
+        let value = accumulator.add(handles[0]).unwrap();
+        assert!(matches!(
+            accumulator.witness(&handles[0]),
+            Err(RevocationError::Revoked)
+        ));
+        let stale = UserProtocol::prove_non_membership(
+            &credentials[0],
+            1,
+            &public_key,
+            &witnesses[0],
+            &mut rng,
+        )
+        .unwrap();
+        assert!(verify(&stale, &value, &mut rng).is_err());
+        assert!(matches!(
+            witnesses[0].update_on_addition(&handles[0], &handles[0], value),
+            Err(RevocationError::Revoked)
+        ));
+
+        // The others follow the change and still show
+        for i in 1..3 {
+            witnesses[i]
+                .update_on_addition(&handles[i], &handles[0], value)
+                .unwrap();
+            assert_eq!(witnesses[i], accumulator.witness(&handles[i]).unwrap());
+            let shown = UserProtocol::prove_non_membership(
+                &credentials[i],
+                1,
+                &public_key,
+                &witnesses[i],
+                &mut rng,
+            )
+            .unwrap();
+            assert!(verify(&shown, &value, &mut rng).unwrap());
+        }
+
+        // Reinstating the handle gives back a valid witness
+        let value = accumulator.remove(&handles[0]).unwrap();
+        witnesses[1]
+            .update_on_removal(&handles[1], &handles[0], value)
+            .unwrap();
+        assert_eq!(witnesses[1], accumulator.witness(&handles[1]).unwrap());
+        let fresh = accumulator.witness(&handles[0]).unwrap();
+        let shown =
+            UserProtocol::prove_non_membership(&credentials[0], 1, &public_key, &fresh, &mut rng)
+                .unwrap();
+        assert!(verify(&shown, &value, &mut rng).unwrap());
+    }
+}
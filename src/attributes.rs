@@ -0,0 +1,119 @@
+//! Attribute vectors checked against a commitment key once, when they are built.
+//!
+//! `Attributes::new` fails unless there is exactly one value per attribute base of the
+//! key, so the credential and request code taking `Attributes` has no length to check.
+//! The slice based entry points build an `Attributes` first and report the same error.
+use crate::errors::CommitmentError;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use ark_ec::pairing::Pairing;
+use ark_ff::UniformRand;
+use ark_std::ops::Deref;
+use ark_std::rand::{CryptoRng, Rng};
+
+/// One attribute per base of the key the vector was built for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attributes<E: Pairing> {
+    values: Vec<E::ScalarField>,
+}
+
+impl<E: Pairing> Attributes<E> {
+    /// `values` for `ck`, failing with `TooManyMessages` unless there is one per base
+    pub fn new(
+        values: Vec<E::ScalarField>,
+        ck: &SymmetricCommitmentKey<E>,
+    ) -> Result<Self, CommitmentError> {
+        let bases = Self::capacity(ck);
+        if values.len() != bases {
+            return Err(CommitmentError::TooManyMessages {
+                messages: values.len(),
+                bases,
+            });
+        }
+        Ok(Self { values })
+    }
+
+    /// Uniformly random attributes for `ck`
+    pub fn random(ck: &SymmetricCommitmentKey<E>, rng: &mut (impl Rng + CryptoRng)) -> Self {
+        Self {
+            values: (0..Self::capacity(ck))
+                .map(|_| E::ScalarField::rand(rng))
+                .collect(),
+        }
+    }
+
+    /// Number of attributes `ck` signs, bases in both groups
+    pub(crate) fn capacity(ck: &SymmetricCommitmentKey<E>) -> usize {
+        ck.ck.len().min(ck.ck_tilde.len())
+    }
+
+    pub fn as_slice(&self) -> &[E::ScalarField] {
+        &self.values
+    }
+
+    pub fn iter(&self) -> ark_std::slice::Iter<'_, E::ScalarField> {
+        self.values.iter()
+    }
+
+    pub fn into_vec(mut self) -> Vec<E::ScalarField> {
+        ark_std::mem::take(&mut self.values)
+    }
+}
+
+impl<E: Pairing> Deref for Attributes<E> {
+    type Target = [E::ScalarField];
+
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+impl<E: Pairing> AsRef<[E::ScalarField]> for Attributes<E> {
+    fn as_ref(&self) -> &[E::ScalarField] {
+        &self.values
+    }
+}
+
+impl<'a, E: Pairing> IntoIterator for &'a Attributes<E> {
+    type Item = &'a E::ScalarField;
+    type IntoIter = ark_std::slice::Iter<'a, E::ScalarField>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: Pairing> Drop for Attributes<E> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr};
+
+    #[test]
+    fn test_attributes_match_key_length() {
+        let mut rng = test_rng();
+        let y_values: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&y_values, &mut rng);
+
+        let values: Vec<Fr> = (1..=3u64).map(Fr::from).collect();
+        let attributes = Attributes::new(values.clone(), &ck).unwrap();
+        assert_eq!(attributes.len(), 3);
+        assert_eq!(attributes[1], Fr::from(2u64));
+        assert_eq!(attributes.iter().copied().collect::<Vec<_>>(), values);
+        assert_eq!(attributes.clone().into_vec(), values);
+        assert_eq!(Attributes::random(&ck, &mut rng).len(), 3);
+
+        for len in [2, 4] {
+            assert!(matches!(
+                Attributes::new(vec![Fr::from(1u64); len], &ck),
+                Err(CommitmentError::TooManyMessages { messages, bases: 3 }) if messages == len
+            ));
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! The credential types instantiated over BLS12-381.
+pub use ark_bls12_381::{Bls12_381 as Curve, Fr, G1Affine, G2Affine};
+
+pub type SymmetricCommitmentKey = crate::symmetric_commitment::SymmetricCommitmentKey<Curve>;
+pub type VerificationKey = crate::keygen::VerificationKey<Curve>;
+pub type VerificationKeyShare = crate::keygen::VerificationKeyShare<Curve>;
+pub type KeygenTranscript = crate::keygen::KeygenTranscript<Curve>;
+pub type WeightedVerificationKeyShare = crate::keygen::WeightedVerificationKeyShare<Curve>;
+pub type PartialSignature = crate::signature::PartialSignature<Curve>;
+pub type ThresholdSignature = crate::signature::ThresholdSignature<Curve>;
+pub type Presentation = crate::presentation::Presentation<Curve>;
+pub type NullifierPresentation = crate::presentation::NullifierPresentation<Curve>;
+pub type IssuanceResponse = crate::messages::IssuanceResponse<Curve>;
+pub type PresentationMessage = crate::messages::PresentationMessage<Curve>;
+pub type NullifierVrf = crate::nullifier::DYPFPrivVRF<G1Affine>;
+pub type NullifierSecretKey = crate::nullifier::DYPFPrivSecretKey<Fr>;
+
+#[cfg(feature = "signing")]
+pub type Credential = crate::credential::Credential<Curve>;
+#[cfg(feature = "signing")]
+pub type CredentialCommitments = crate::credential::CredentialCommitments<Curve>;
+#[cfg(feature = "signing")]
+pub type AggregatedCredentialCommitments =
+    crate::credential::AggregatedCredentialCommitments<Curve>;
+#[cfg(feature = "signing")]
+pub type IssuanceRequest = crate::messages::IssuanceRequest<Curve>;
+#[cfg(feature = "signing")]
+pub type Signer<'a> = crate::signer::Signer<'a, Curve>;
+#[cfg(feature = "signing")]
+pub type System = crate::system::System<Curve>;
+#[cfg(feature = "signing")]
+pub type SecretKeyShare = crate::keygen::SecretKeyShare<Curve>;
+#[cfg(feature = "signing")]
+pub type ThresholdKeys = crate::keygen::ThresholdKeys<Curve>;
+#[cfg(feature = "signing")]
+pub type WeightedThresholdKeys = crate::keygen::WeightedThresholdKeys<Curve>;
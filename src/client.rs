@@ -0,0 +1,258 @@
+//! Signer access for share collection over a network.
+//!
+//! `SignerClient` abstracts a remote signer so that `UserProtocol::collect_signature_shares_async`
+//! can fan a credential request out to every signer at once. `LocalSignerClient` adapts an
+//! in-process `Signer` to the same interface.
+use crate::credential::CredentialCommitments;
+use crate::errors::SignatureError;
+use crate::signature::PartialSignature;
+use crate::signer::Signer;
+use ark_ec::pairing::Pairing;
+use ark_std::future::Future;
+use ark_std::rand::{rngs::StdRng, CryptoRng, Rng, SeedableRng};
+use std::cell::RefCell;
+
+/// A signer reachable asynchronously, typically over a network
+pub trait SignerClient<E: Pairing> {
+    /// Ask the signer to verify `request` and return its signature share
+    fn sign_share(
+        &self,
+        request: &CredentialCommitments<E>,
+    ) -> impl Future<Output = Result<PartialSignature<E>, SignatureError>>;
+}
+
+/// Wraps an in-process `Signer` as a `SignerClient`
+pub struct LocalSignerClient<'a, E: Pairing> {
+    signer: Signer<'a, E>,
+    rng: RefCell<StdRng>,
+}
+
+impl<'a, E: Pairing> LocalSignerClient<'a, E> {
+    /// Create a client whose proof verification randomness is seeded from `rng`
+    pub fn new(signer: Signer<'a, E>, rng: &mut (impl Rng + CryptoRng)) -> Self {
+        Self {
+            signer,
+            rng: RefCell::new(StdRng::from_seed(rng.gen())),
+        }
+    }
+}
+
+impl<'a, E: Pairing> SignerClient<E> for LocalSignerClient<'a, E> {
+    async fn sign_share(
+        &self,
+        request: &CredentialCommitments<E>,
+    ) -> Result<PartialSignature<E>, SignatureError> {
+        self.signer.sign_share(
+            &request.commitments,
+            &request.proofs,
+            &request.h,
+            &mut *self.rng.borrow_mut(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment::CommitmentProof;
+    use crate::errors::CommitmentError;
+    use crate::keygen::keygen;
+    use crate::protocol::UserProtocol;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_ec::CurveGroup;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use std::time::{Duration, Instant};
+
+    /// A local signer behind an artificial network delay that may fail, or answer with
+    /// a well-formed share that doesn't verify
+    struct DelayedClient<'a> {
+        inner: LocalSignerClient<'a, Bls12_381>,
+        delay: Duration,
+        fail: bool,
+        forge: bool,
+    }
+
+    impl<'a> SignerClient<Bls12_381> for DelayedClient<'a> {
+        async fn sign_share(
+            &self,
+            request: &CredentialCommitments<Bls12_381>,
+        ) -> Result<PartialSignature<Bls12_381>, SignatureError> {
+            tokio::time::sleep(self.delay).await;
+            if self.fail {
+                return Err(SignatureError::InvalidShare(
+                    self.inner.signer.sk_share.index,
+                ));
+            }
+            let mut share = self.inner.sign_share(request).await?;
+            if self.forge {
+                share.sigma = (share.sigma + share.h).into_affine();
+            }
+            Ok(share)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_returns_at_threshold() {
+        let mut rng = test_rng();
+        let threshold = 2;
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(threshold, 4, 3, &mut rng);
+        let (_, request) = UserProtocol::request_credential(ck.clone(), None, &mut rng).unwrap();
+
+        // Signer 1 fails fast, signers 2 and 3 answer quickly, signer 4 straggles
+        let delays_ms = [0, 10, 20, 60_000];
+        let clients: Vec<_> = ts_keys
+            .sk_shares
+            .iter()
+            .zip(ts_keys.vk_shares.iter())
+            .zip(delays_ms)
+            .enumerate()
+            .map(|(i, ((sk, vk), delay))| DelayedClient {
+                inner: LocalSignerClient::new(Signer::new(&ck, sk, vk), &mut rng),
+                delay: Duration::from_millis(delay),
+                fail: i == 0,
+                forge: false,
+            })
+            .collect();
+
+        let start = Instant::now();
+        let shares = UserProtocol::collect_signature_shares_async(
+            &ck,
+            &ts_keys.vk_shares,
+            &clients,
+            &request,
+            threshold,
+            &mut rng,
+        )
+        .await
+        .expect("two healthy signers should answer");
+        assert!(start.elapsed() < Duration::from_secs(30));
+
+        let mut indices: Vec<_> = shares.iter().map(|(i, _)| *i).collect();
+        indices.sort();
+        assert_eq!(indices, vec![2, 3]);
+
+        let verified = UserProtocol::verify_signature_shares(
+            &ck,
+            &ts_keys.vk_shares,
+            &request,
+            &shares,
+            threshold,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(verified.len(), threshold);
+    }
+
+    #[tokio::test]
+    async fn test_collect_insufficient_shares() {
+        let mut rng = test_rng();
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(2, 3, 3, &mut rng);
+        let (_, request) = UserProtocol::request_credential(ck.clone(), None, &mut rng).unwrap();
+
+        let clients: Vec<_> = ts_keys
+            .sk_shares
+            .iter()
+            .zip(ts_keys.vk_shares.iter())
+            .enumerate()
+            .map(|(i, (sk, vk))| DelayedClient {
+                inner: LocalSignerClient::new(Signer::new(&ck, sk, vk), &mut rng),
+                delay: Duration::ZERO,
+                fail: i != 0,
+                forge: false,
+            })
+            .collect();
+
+        let result = UserProtocol::collect_signature_shares_async(
+            &ck,
+            &ts_keys.vk_shares,
+            &clients,
+            &request,
+            2,
+            &mut rng,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(SignatureError::InsufficientShares { needed: 2, got: 1 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_collect_skips_invalid_shares() {
+        let mut rng = test_rng();
+        let threshold = 2;
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(threshold, 3, 3, &mut rng);
+        let (_, request) = UserProtocol::request_credential(ck.clone(), None, &mut rng).unwrap();
+
+        // Signer 1 answers first with a share over the right h that doesn't verify
+        let delays_ms = [0, 10, 20];
+        let clients: Vec<_> = ts_keys
+            .sk_shares
+            .iter()
+            .zip(ts_keys.vk_shares.iter())
+            .zip(delays_ms)
+            .enumerate()
+            .map(|(i, ((sk, vk), delay))| DelayedClient {
+                inner: LocalSignerClient::new(Signer::new(&ck, sk, vk), &mut rng),
+                delay: Duration::from_millis(delay),
+                fail: false,
+                forge: i == 0,
+            })
+            .collect();
+
+        let shares = UserProtocol::collect_signature_shares_async(
+            &ck,
+            &ts_keys.vk_shares,
+            &clients,
+            &request,
+            threshold,
+            &mut rng,
+        )
+        .await
+        .unwrap();
+        let mut indices: Vec<_> = shares.iter().map(|(i, _)| *i).collect();
+        indices.sort();
+        assert_eq!(indices, vec![2, 3]);
+
+        // Without its share only one valid share remains
+        let result = UserProtocol::collect_signature_shares_async(
+            &ck,
+            &ts_keys.vk_shares,
+            &clients[..2],
+            &request,
+            threshold,
+            &mut rng,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(SignatureError::InsufficientShares { needed: 2, got: 1 })
+        ));
+
+        // A proof that doesn't verify fails the request before any share is looked at
+        let mut bad_request = request.clone();
+        let mut proof =
+            CommitmentProof::<Bls12_381>::deserialize_compressed(&bad_request.proofs[0][..])
+                .unwrap();
+        proof.responses[0] += Fr::from(1u64);
+        bad_request.proofs[0].clear();
+        proof
+            .serialize_compressed(&mut bad_request.proofs[0])
+            .unwrap();
+        assert!(matches!(
+            UserProtocol::collect_signature_shares_async(
+                &ck,
+                &ts_keys.vk_shares,
+                &clients,
+                &bad_request,
+                threshold,
+                &mut rng,
+            )
+            .await,
+            Err(SignatureError::CommitmentError(
+                CommitmentError::BatchVerifyError
+            ))
+        ));
+    }
+}
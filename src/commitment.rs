@@ -1,11 +1,16 @@
+use crate::ct::rand_batching_scalar;
 use crate::errors::CommitmentError;
 use crate::schnorr::SchnorrProtocol;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::{DomainSeparator, Transcript};
 use ark_ec::pairing::Pairing;
-use ark_ec::{CurveGroup, VariableBaseMSM};
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
 use ark_ff::UniformRand;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Valid, Validate};
 use ark_std::ops::Mul;
-use ark_std::rand::Rng;
+use ark_std::rand::{CryptoRng, Rng};
+use ark_std::Zero;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, CanonicalDeserialize, CanonicalSerialize)]
 pub struct Commitment<E: Pairing> {
@@ -23,13 +28,48 @@ pub struct CommitmentProof<E: Pairing> {
     pub responses: Vec<E::ScalarField>,
 }
 
+impl<E: Pairing> CommitmentProof<E> {
+    /// See `SchnorrProtocol::is_well_formed`
+    pub fn is_well_formed(&self) -> bool {
+        SchnorrProtocol::is_well_formed(
+            &self.bases,
+            &self.commitment,
+            &self.schnorr_commitment,
+            &self.responses,
+            &self.challenge,
+        )
+    }
+
+    /// `Commitment::verify_against` on an already deserialized proof
+    pub fn verify_against(
+        &self,
+        expected_bases: &[E::G1Affine],
+        expected_commitment: &E::G1Affine,
+    ) -> Result<bool, CommitmentError> {
+        if self.commitment != *expected_commitment || self.bases != expected_bases {
+            return Ok(false);
+        }
+        if !self.is_well_formed() {
+            return Err(CommitmentError::InvalidProof);
+        }
+
+        Ok(SchnorrProtocol::verify_schnorr(
+            &self.bases,
+            &self.commitment,
+            &self.schnorr_commitment,
+            &self.responses,
+            &self.challenge,
+        ))
+    }
+}
+
 impl<E: Pairing> Commitment<E> {
     pub fn new(
         h: &E::G1Affine,
         g: &E::G1Affine,
         m: &E::ScalarField,
         r_opt: Option<E::ScalarField>,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Self {
         let r = match r_opt {
             Some(r_value) => r_value,
@@ -47,14 +87,14 @@ impl<E: Pairing> Commitment<E> {
         }
     }
 
-    pub fn prove(self, rng: &mut impl Rng) -> Result<Vec<u8>, CommitmentError> {
-        let schnorr_commitment = SchnorrProtocol::commit(&self.bases, rng);
+    pub fn prove(self, rng: &mut (impl Rng + CryptoRng)) -> Result<Vec<u8>, CommitmentError> {
+        let (state, announcement) = SchnorrProtocol::commit(&self.bases, rng);
         let challenge = E::ScalarField::rand(rng);
-        let responses = SchnorrProtocol::prove(&schnorr_commitment, &self.exponents, &challenge);
+        let responses = SchnorrProtocol::prove(state, &self.exponents, &challenge);
         let proof: CommitmentProof<E> = CommitmentProof {
             bases: self.bases.clone(),
             commitment: self.cm,
-            schnorr_commitment: schnorr_commitment.commited_blindings,
+            schnorr_commitment: announcement.0,
             challenge,
             responses: responses.0,
         };
@@ -68,6 +108,9 @@ impl<E: Pairing> Commitment<E> {
     pub fn verify(serialized_proof: &[u8]) -> Result<bool, CommitmentError> {
         let proof: CommitmentProof<E> =
             CanonicalDeserialize::deserialize_compressed(serialized_proof)?;
+        if !proof.is_well_formed() {
+            return Err(CommitmentError::InvalidProof);
+        }
 
         // Verify using Schnorr protocol
         let is_valid = SchnorrProtocol::verify_schnorr(
@@ -80,75 +123,649 @@ impl<E: Pairing> Commitment<E> {
 
         Ok(is_valid)
     }
+
+    /// Verify a serialized proof for `expected_commitment` over `expected_bases`.
+    /// Unlike `verify`, the bases and commitment embedded in the proof are not trusted:
+    /// a proof carrying any others is rejected before the Schnorr check runs
+    pub fn verify_against(
+        serialized_proof: &[u8],
+        expected_bases: &[E::G1Affine],
+        expected_commitment: &E::G1Affine,
+    ) -> Result<bool, CommitmentError> {
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(serialized_proof)?;
+        proof.verify_against(expected_bases, expected_commitment)
+    }
+}
+
+/// One proof of opening for all of a request's commitments `cm_k = h^{m_k} g^{r_k}`:
+/// an announcement per commitment, a single Fiat-Shamir challenge over all of them and
+/// a response pair per commitment. The bases and the commitments aren't repeated, the
+/// verifier already has them
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregateCommitmentProof<E: Pairing> {
+    pub announcements: Vec<E::G1Affine>,
+    pub responses_m: Vec<E::ScalarField>,
+    pub responses_r: Vec<E::ScalarField>,
+}
+
+impl<E: Pairing> AggregateCommitmentProof<E> {
+    fn challenge(
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        commitments: &[E::G1Affine],
+        announcements: &[E::G1Affine],
+    ) -> E::ScalarField {
+        let mut transcript = Transcript::with_domain(domain, b"t-siris aggregate opening");
+        transcript.append(b"h", h);
+        transcript.append(b"g", g);
+        transcript.append(b"commitments", commitments);
+        transcript.append(b"announcements", announcements);
+        transcript.challenge_scalar(b"challenge")
+    }
+
+    /// Prove knowledge of `messages` and `blindings` opening each of `commitments`
+    pub fn prove(
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        commitments: &[E::G1Affine],
+        messages: &[E::ScalarField],
+        blindings: &[E::ScalarField],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Self, CommitmentError> {
+        if commitments.is_empty()
+            || messages.len() != commitments.len()
+            || blindings.len() != commitments.len()
+        {
+            return Err(CommitmentError::InvalidComputeCommitment);
+        }
+
+        let (states, announcements): (Vec<_>, Vec<_>) = commitments
+            .iter()
+            .map(|_| SchnorrProtocol::commit(&[*h, *g], rng))
+            .map(|(state, announcement)| (state, announcement.0))
+            .unzip();
+        let challenge = Self::challenge(domain, h, g, commitments, &announcements);
+
+        let (responses_m, responses_r) = states
+            .into_iter()
+            .zip(messages.iter().zip(blindings))
+            .map(|(state, (m, r))| {
+                let responses = SchnorrProtocol::prove(state, &[*m, *r], &challenge).0;
+                (responses[0], responses[1])
+            })
+            .unzip();
+        Ok(Self {
+            announcements,
+            responses_m,
+            responses_r,
+        })
+    }
+
+    /// Check `h^{z_m,k} g^{z_r,k} = T_k cm_k^c` for every k, batched into one MSM with
+    /// random weights
+    pub fn verify(
+        &self,
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        commitments: &[E::G1Affine],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, CommitmentError> {
+        let l = commitments.len();
+        if l == 0
+            || self.announcements.len() != l
+            || self.responses_m.len() != l
+            || self.responses_r.len() != l
+        {
+            return Err(CommitmentError::InvalidProof);
+        }
+        // Same rule as `SchnorrProtocol::is_well_formed`
+        if self
+            .announcements
+            .iter()
+            .zip(commitments)
+            .any(|(t, cm)| t.is_zero() && !cm.is_zero())
+        {
+            return Err(CommitmentError::InvalidProof);
+        }
+        let challenge = Self::challenge(domain, h, g, commitments, &self.announcements);
+
+        let weights: Vec<E::ScalarField> = (0..l).map(|_| rand_batching_scalar(rng)).collect();
+        let mut bases = Vec::with_capacity(2 + 2 * l);
+        let mut scalars = Vec::with_capacity(2 + 2 * l);
+        bases.extend([*h, *g]);
+        scalars.push(
+            weights
+                .iter()
+                .zip(&self.responses_m)
+                .map(|(w, z)| *w * z)
+                .sum(),
+        );
+        scalars.push(
+            weights
+                .iter()
+                .zip(&self.responses_r)
+                .map(|(w, z)| *w * z)
+                .sum(),
+        );
+        for ((t, cm), w) in self.announcements.iter().zip(commitments).zip(&weights) {
+            bases.push(*t);
+            scalars.push(-*w);
+            bases.push(*cm);
+            scalars.push(-(*w * challenge));
+        }
+        Ok(E::G1::msm_unchecked(&bases, &scalars).is_zero())
+    }
+}
+
+/// Proof that a request's commitments `cm_k = h^{m_k} g^{r_k}` and a symmetric
+/// commitment `cm = g^r \prod_k ck_k^{m_k}` open to the same messages `m_k`. The message
+/// nonces are shared between the per-attribute announcements and the symmetric one, so
+/// each `z_{m,k}` is sent once in `responses` and answers both equations
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ConsistencyProof<E: Pairing> {
+    /// The symmetric commitment the request is bound to
+    pub cm: E::G1Affine,
+    /// `T_k` over `[h, g]`, one per commitment
+    pub announcements: Vec<E::G1Affine>,
+    /// `T` over `ck || g`
+    pub announcement: E::G1Affine,
+    /// `z_{m,1}, ..., z_{m,L}, z_r` for `cm`, in the key's base order
+    pub responses: Vec<E::ScalarField>,
+    /// `z_{r,k}` for each `cm_k`
+    pub responses_r: Vec<E::ScalarField>,
+}
+
+impl<E: Pairing> ConsistencyProof<E> {
+    fn challenge(
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        sym_bases: &[E::G1Affine],
+        commitments: &[E::G1Affine],
+        cm: &E::G1Affine,
+        announcements: &[E::G1Affine],
+        announcement: &E::G1Affine,
+    ) -> E::ScalarField {
+        let mut transcript = Transcript::with_domain(domain, b"t-siris commitment consistency");
+        transcript.append(b"h", h);
+        transcript.append(b"bases", sym_bases);
+        transcript.append(b"commitments", commitments);
+        transcript.append(b"cm", cm);
+        transcript.append(b"announcements", announcements);
+        transcript.append(b"announcement", announcement);
+        transcript.challenge_scalar(b"challenge")
+    }
+
+    /// Prove that `commitments` open to `messages` under `blindings` and that
+    /// `cm = g^r \prod_k ck_k^{m_k}` for the same messages
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove(
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        ck: &SymmetricCommitmentKey<E>,
+        commitments: &[E::G1Affine],
+        messages: &[E::ScalarField],
+        blindings: &[E::ScalarField],
+        cm: &E::G1Affine,
+        r: &E::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Self, CommitmentError> {
+        let l = commitments.len();
+        if l == 0 || messages.len() != l || blindings.len() != l || ck.num_attributes() != l {
+            return Err(CommitmentError::InvalidComputeCommitment);
+        }
+
+        let (sym_bases, _) = ck.get_bases();
+        let message_nonces: Vec<E::ScalarField> =
+            (0..l).map(|_| E::ScalarField::rand(rng)).collect();
+        let (states, announcements): (Vec<_>, Vec<_>) = message_nonces
+            .iter()
+            .map(|nonce| SchnorrProtocol::commit_with_shared(&[*h, ck.g], &[(0, *nonce)], rng))
+            .map(|(state, announcement)| (state, announcement.0))
+            .unzip();
+        let shared: Vec<_> = message_nonces
+            .into_iter()
+            .enumerate()
+            .map(|(k, nonce)| (ck.attribute_position(k), nonce))
+            .collect();
+        let (sym_state, announcement) =
+            SchnorrProtocol::commit_with_shared(&sym_bases, &shared, rng);
+        let challenge = Self::challenge(
+            domain,
+            h,
+            &sym_bases,
+            commitments,
+            cm,
+            &announcements,
+            &announcement.0,
+        );
+
+        let responses_r = states
+            .into_iter()
+            .zip(messages.iter().zip(blindings))
+            .map(|(state, (m, r_k))| SchnorrProtocol::prove(state, &[*m, *r_k], &challenge).0[1])
+            .collect();
+        let sym_witnesses = ck.order_exponents(messages, r);
+        let responses = SchnorrProtocol::prove(sym_state, &sym_witnesses, &challenge).0;
+        Ok(Self {
+            cm: *cm,
+            announcements,
+            announcement: announcement.0,
+            responses,
+            responses_r,
+        })
+    }
+
+    /// Check `h^{z_{m,k}} g^{z_{r,k}} = T_k cm_k^c` for every k and
+    /// `g^{z_r} \prod_k ck_k^{z_{m,k}} = T cm^c`
+    pub fn verify(
+        &self,
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        ck: &SymmetricCommitmentKey<E>,
+        commitments: &[E::G1Affine],
+    ) -> Result<bool, CommitmentError> {
+        let l = commitments.len();
+        if l == 0
+            || ck.num_attributes() != l
+            || self.announcements.len() != l
+            || self.responses_r.len() != l
+            || self.responses.len() != l + 1
+        {
+            return Err(CommitmentError::InvalidProof);
+        }
+
+        let (sym_bases, _) = ck.get_bases();
+        let challenge = Self::challenge(
+            domain,
+            h,
+            &sym_bases,
+            commitments,
+            &self.cm,
+            &self.announcements,
+            &self.announcement,
+        );
+        let per_attribute = (0..l).all(|k| {
+            SchnorrProtocol::verify_schnorr(
+                &[*h, ck.g],
+                &commitments[k],
+                &self.announcements[k],
+                &[
+                    self.responses[ck.attribute_position(k)],
+                    self.responses_r[k],
+                ],
+                &challenge,
+            )
+        });
+        Ok(per_attribute
+            && SchnorrProtocol::verify_schnorr(
+                &sym_bases,
+                &self.cm,
+                &self.announcement,
+                &self.responses,
+                &challenge,
+            ))
+    }
+}
+
+/// Proof that the request commitments `cm_k = h^{m_k} g^{r_k}` at the `carried` indices
+/// open to the same messages as the symmetric commitment `cm = g^r \prod_k ck_k^{m_k}` of
+/// another credential. Like `ConsistencyProof`, the message nonces are shared between
+/// both announcements and each `z_{m,k}` is sent once, in `responses`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CarryOverProof<E: Pairing> {
+    /// `T_k` over `[h, g]`, one per carried index
+    pub announcements: Vec<E::G1Affine>,
+    /// `T` over `ck || g`
+    pub announcement: E::G1Affine,
+    /// `z_{m,1}, ..., z_{m,L}, z_r` for `cm`, in the key's base order
+    pub responses: Vec<E::ScalarField>,
+    /// `z_{r,k}` for each carried `cm_k`
+    pub responses_r: Vec<E::ScalarField>,
+}
+
+impl<E: Pairing> CarryOverProof<E> {
+    #[allow(clippy::too_many_arguments)]
+    fn challenge(
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        sym_bases: &[E::G1Affine],
+        commitments: &[E::G1Affine],
+        carried: &[usize],
+        cm: &E::G1Affine,
+        announcements: &[E::G1Affine],
+        announcement: &E::G1Affine,
+    ) -> E::ScalarField {
+        let mut transcript = Transcript::with_domain(domain, b"t-siris carry over");
+        transcript.append(b"h", h);
+        transcript.append(b"g", g);
+        transcript.append(b"bases", sym_bases);
+        transcript.append(b"commitments", commitments);
+        let carried: Vec<u64> = carried.iter().map(|k| *k as u64).collect();
+        transcript.append(b"carried", &carried);
+        transcript.append(b"cm", cm);
+        transcript.append(b"announcements", announcements);
+        transcript.append(b"announcement", announcement);
+        transcript.challenge_scalar(b"challenge")
+    }
+
+    /// Prove that `commitments[k]` opens to `messages[k]` under `blindings[k]` for every
+    /// carried `k`, and that `cm = g^r \prod_k ck_k^{m_k}` for all of `messages`
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove(
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        commitments: &[E::G1Affine],
+        blindings: &[E::ScalarField],
+        carried: &[usize],
+        ck: &SymmetricCommitmentKey<E>,
+        cm: &E::G1Affine,
+        messages: &[E::ScalarField],
+        r: &E::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Self, CommitmentError> {
+        let l = commitments.len();
+        if messages.len() != l
+            || blindings.len() != l
+            || ck.num_attributes() != l
+            || carried.iter().any(|k| *k >= l)
+        {
+            return Err(CommitmentError::InvalidComputeCommitment);
+        }
+
+        let (sym_bases, _) = ck.get_bases();
+        let message_nonces: Vec<E::ScalarField> =
+            carried.iter().map(|_| E::ScalarField::rand(rng)).collect();
+        let (states, announcements): (Vec<_>, Vec<_>) = message_nonces
+            .iter()
+            .map(|nonce| SchnorrProtocol::commit_with_shared(&[*h, *g], &[(0, *nonce)], rng))
+            .map(|(state, announcement)| (state, announcement.0))
+            .unzip();
+        let shared: Vec<_> = carried
+            .iter()
+            .map(|k| ck.attribute_position(*k))
+            .zip(message_nonces)
+            .collect();
+        let (sym_state, announcement) =
+            SchnorrProtocol::commit_with_shared(&sym_bases, &shared, rng);
+        let challenge = Self::challenge(
+            domain,
+            h,
+            g,
+            &sym_bases,
+            commitments,
+            carried,
+            cm,
+            &announcements,
+            &announcement.0,
+        );
+
+        let responses_r = states
+            .into_iter()
+            .zip(carried)
+            .map(|(state, k)| {
+                SchnorrProtocol::prove(state, &[messages[*k], blindings[*k]], &challenge).0[1]
+            })
+            .collect();
+        let sym_witnesses = ck.order_exponents(messages, r);
+        let responses = SchnorrProtocol::prove(sym_state, &sym_witnesses, &challenge).0;
+        Ok(Self {
+            announcements,
+            announcement: announcement.0,
+            responses,
+            responses_r,
+        })
+    }
+
+    /// Check `h^{z_{m,k}} g^{z_{r,k}} = T_k cm_k^c` for every carried k and
+    /// `g^{z_r} \prod_k ck_k^{z_{m,k}} = T cm^c`
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify(
+        &self,
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        commitments: &[E::G1Affine],
+        carried: &[usize],
+        ck: &SymmetricCommitmentKey<E>,
+        cm: &E::G1Affine,
+    ) -> Result<bool, CommitmentError> {
+        let l = commitments.len();
+        if ck.num_attributes() != l
+            || self.announcements.len() != carried.len()
+            || self.responses_r.len() != carried.len()
+            || self.responses.len() != l + 1
+            || carried.iter().any(|k| *k >= l)
+        {
+            return Err(CommitmentError::InvalidProof);
+        }
+
+        let (sym_bases, _) = ck.get_bases();
+        let challenge = Self::challenge(
+            domain,
+            h,
+            g,
+            &sym_bases,
+            commitments,
+            carried,
+            cm,
+            &self.announcements,
+            &self.announcement,
+        );
+        let per_attribute = carried.iter().enumerate().all(|(i, k)| {
+            SchnorrProtocol::verify_schnorr(
+                &[*h, *g],
+                &commitments[*k],
+                &self.announcements[i],
+                &[
+                    self.responses[ck.attribute_position(*k)],
+                    self.responses_r[i],
+                ],
+                &challenge,
+            )
+        });
+        Ok(per_attribute
+            && SchnorrProtocol::verify_schnorr(
+                &sym_bases,
+                cm,
+                &self.announcement,
+                &self.responses,
+                &challenge,
+            ))
+    }
+}
+
+/// The attribute a request bound to the holder key `pk` carries at index 0, a hash of
+/// `pk` into the scalar field
+pub fn key_attribute<E: Pairing>(domain: &DomainSeparator, pk: &E::G1Affine) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris key attribute");
+    transcript.append(b"pk", pk);
+    transcript.challenge_scalar(b"attribute")
+}
+
+/// Proof that a request's first commitment `cm_0 = h^{m_0} g^{r_0}` opens to the public
+/// value `key_attribute(pk)`, with a proof of knowledge of `sk` for `pk = g^sk`. Both
+/// answer one challenge over the whole request, which makes the proof a signature on
+/// the request under `pk`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeyBindingProof<E: Pairing> {
+    /// The holder's public key
+    pub pk: E::G1Affine,
+    /// `g^{t_sk}`
+    pub announcement_sk: E::G1Affine,
+    /// `g^{t_r}` for `cm_0 h^{-m_0} = g^{r_0}`
+    pub announcement_r: E::G1Affine,
+    pub response_sk: E::ScalarField,
+    pub response_r: E::ScalarField,
+}
+
+impl<E: Pairing> KeyBindingProof<E> {
+    fn challenge(
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        commitments: &[E::G1Affine],
+        pk: &E::G1Affine,
+        announcement_sk: &E::G1Affine,
+        announcement_r: &E::G1Affine,
+    ) -> E::ScalarField {
+        let mut transcript = Transcript::with_domain(domain, b"t-siris key binding");
+        transcript.append(b"h", h);
+        transcript.append(b"g", g);
+        transcript.append(b"commitments", commitments);
+        transcript.append(b"pk", pk);
+        transcript.append(b"announcement_sk", announcement_sk);
+        transcript.append(b"announcement_r", announcement_r);
+        transcript.challenge_scalar(b"challenge")
+    }
+
+    /// Prove knowledge of `sk` and of the blinding `r_0` of `commitments[0]`, which has
+    /// to commit to `key_attribute(g^sk)` for the proof to verify
+    pub fn prove(
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        commitments: &[E::G1Affine],
+        sk: &E::ScalarField,
+        r_0: &E::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Self, CommitmentError> {
+        if commitments.is_empty() {
+            return Err(CommitmentError::InvalidComputeCommitment);
+        }
+        let pk = g.mul(sk).into_affine();
+        let (state_sk, announcement_sk) = SchnorrProtocol::commit(&[*g], rng);
+        let (state_r, announcement_r) = SchnorrProtocol::commit(&[*g], rng);
+        let challenge = Self::challenge(
+            domain,
+            h,
+            g,
+            commitments,
+            &pk,
+            &announcement_sk.0,
+            &announcement_r.0,
+        );
+        Ok(Self {
+            pk,
+            announcement_sk: announcement_sk.0,
+            announcement_r: announcement_r.0,
+            response_sk: SchnorrProtocol::prove(state_sk, &[*sk], &challenge).0[0],
+            response_r: SchnorrProtocol::prove(state_r, &[*r_0], &challenge).0[0],
+        })
+    }
+
+    /// Check `g^{z_sk} = T_sk pk^c` and `g^{z_r} = T_r (cm_0 h^{-key_attribute(pk)})^c`
+    pub fn verify(
+        &self,
+        domain: &DomainSeparator,
+        h: &E::G1Affine,
+        g: &E::G1Affine,
+        commitments: &[E::G1Affine],
+    ) -> Result<bool, CommitmentError> {
+        let cm_0 = commitments.first().ok_or(CommitmentError::InvalidProof)?;
+        let challenge = Self::challenge(
+            domain,
+            h,
+            g,
+            commitments,
+            &self.pk,
+            &self.announcement_sk,
+            &self.announcement_r,
+        );
+        let unblinded =
+            (cm_0.into_group() - h.mul(key_attribute::<E>(domain, &self.pk))).into_affine();
+        Ok(SchnorrProtocol::verify_schnorr(
+            &[*g],
+            &self.pk,
+            &self.announcement_sk,
+            &[self.response_sk],
+            &challenge,
+        ) && SchnorrProtocol::verify_schnorr(
+            &[*g],
+            &unblinded,
+            &self.announcement_r,
+            &[self.response_r],
+            &challenge,
+        ))
+    }
 }
 
 pub fn batch_verify<E: Pairing>(
     serialized_proofs: &[Vec<u8>],
-    rng: &mut impl Rng,
+    rng: &mut (impl Rng + CryptoRng),
 ) -> Result<bool, CommitmentError> {
     if serialized_proofs.is_empty() {
         return Ok(true); // No proofs to verify
     }
 
-    // Step 1: Deserialize all proofs
+    // Step 1: Deserialize all proofs. Their points are checked below, the bases shared
+    // by the proofs only once
     let mut deserialized_proofs = Vec::with_capacity(serialized_proofs.len());
 
     for proof_bytes in serialized_proofs {
-        match CommitmentProof::<E>::deserialize_compressed(&proof_bytes[..]) {
+        match CommitmentProof::<E>::deserialize_with_mode(
+            &proof_bytes[..],
+            Compress::Yes,
+            Validate::No,
+        ) {
+            // A short response vector would otherwise index out of bounds below
+            Ok(proof) if !proof.is_well_formed() => return Err(CommitmentError::InvalidProof),
             Ok(proof) => deserialized_proofs.push(proof),
             Err(e) => return Err(CommitmentError::SerializationError(e)),
         }
     }
 
     // Step 2: Perform batch verification using random linear combination
-    // Generate a random scalar for each proof
+    // Generate a 128-bit batching scalar for each proof
     let random_scalars: Vec<E::ScalarField> = (0..deserialized_proofs.len())
-        .map(|_| E::ScalarField::rand(rng))
+        .map(|_| rand_batching_scalar(rng))
         .collect();
 
-    // For each proof, compute LHS = g^(r + e*m) and RHS = T * C^e
-    let mut all_bases = Vec::new();
-    let mut all_scalars = Vec::new();
-
-    // Calculate combined LHS
-    for (i, proof) in deserialized_proofs.iter().enumerate() {
-        // Add this proof's bases and responses to the combined MSM operation
-        // We scale by the random scalar for this proof
-        for (base_idx, base) in proof.bases.iter().enumerate() {
-            all_bases.push(*base);
-            all_scalars.push(proof.responses[base_idx] * random_scalars[i]);
+    // \sum_i r_i (\sum_k z_{i,k} B_{i,k} - T_i - e_i C_i) = 0 as one MSM. The proofs of a
+    // request share their bases, so the responses are summed per distinct base and the
+    // MSM has two points per proof plus a few bases, whatever the number of attributes
+    let mut bases: Vec<E::G1Affine> = Vec::with_capacity(2 * deserialized_proofs.len() + 2);
+    let mut scalars: Vec<E::ScalarField> = Vec::with_capacity(bases.capacity());
+    let mut base_positions: HashMap<E::G1Affine, usize> = HashMap::new();
+    for (proof, r_i) in deserialized_proofs.iter().zip(&random_scalars) {
+        for (base, response) in proof.bases.iter().zip(&proof.responses) {
+            let position = match base_positions.get(base) {
+                Some(position) => *position,
+                None => {
+                    base.check()?;
+                    bases.push(*base);
+                    scalars.push(E::ScalarField::zero());
+                    base_positions.insert(*base, bases.len() - 1);
+                    bases.len() - 1
+                }
+            };
+            scalars[position] += *response * r_i;
         }
     }
-
-    // Calculate LHS using a single multi-scalar multiplication
-    let lhs = E::G1::msm_unchecked(&all_bases, &all_scalars).into_affine();
-
-    // Optimize RHS calculation with a single MSM operation
-    let mut rhs_bases = Vec::with_capacity(deserialized_proofs.len() * 2);
-    let mut rhs_scalars = Vec::with_capacity(deserialized_proofs.len() * 2);
-
-    for (i, proof) in deserialized_proofs.iter().enumerate() {
-        // Add T (schnorr_commitment) with random scalar
-        rhs_bases.push(proof.schnorr_commitment);
-        rhs_scalars.push(random_scalars[i]);
-
-        // Add C^e (commitment * challenge) with random scalar
-        rhs_bases.push(proof.commitment);
-        rhs_scalars.push(random_scalars[i] * proof.challenge);
+    for (proof, r_i) in deserialized_proofs.iter().zip(&random_scalars) {
+        proof.schnorr_commitment.check()?;
+        proof.commitment.check()?;
+        bases.push(proof.schnorr_commitment);
+        scalars.push(-*r_i);
+        bases.push(proof.commitment);
+        scalars.push(-(*r_i * proof.challenge));
     }
 
-    // Calculate RHS using a single efficient MSM operation
-    let rhs = E::G1::msm_unchecked(&rhs_bases, &rhs_scalars).into_affine();
-
-    // Check if LHS == RHS
-    Ok(lhs == rhs)
+    Ok(E::G1::msm_unchecked(&bases, &scalars).is_zero())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+    use ark_ec::AffineRepr;
     use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use ark_std::Zero;
 
     #[test]
     fn test_basic_commitment_and_proof() {
@@ -182,4 +799,211 @@ mod tests {
 
         assert!(is_valid, "Proof verification failed");
     }
+
+    #[test]
+    fn test_verify_against_rejects_other_bases() {
+        let mut rng = StdRng::seed_from_u64(12345);
+        let h = G1Affine::rand(&mut rng);
+        let g = G1Affine::rand(&mut rng);
+        let other_h = G1Affine::rand(&mut rng);
+        let m = Fr::rand(&mut rng);
+
+        let commitment = Commitment::<Bls12_381>::new(&h, &g, &m, None, &mut rng);
+        let cm = commitment.cm;
+        let proof = commitment.prove(&mut rng).unwrap();
+        assert!(Commitment::<Bls12_381>::verify_against(&proof, &[h, g], &cm).unwrap());
+
+        // Valid over its own bases, but those aren't the expected ones
+        let forged = Commitment::<Bls12_381>::new(&other_h, &g, &m, None, &mut rng);
+        let forged_cm = forged.cm;
+        let forged_proof = forged.prove(&mut rng).unwrap();
+        assert!(Commitment::<Bls12_381>::verify(&forged_proof).unwrap());
+        assert!(
+            !Commitment::<Bls12_381>::verify_against(&forged_proof, &[h, g], &forged_cm).unwrap()
+        );
+
+        // Right bases, wrong commitment
+        assert!(!Commitment::<Bls12_381>::verify_against(&proof, &[h, g], &forged_cm).unwrap());
+    }
+
+    #[test]
+    fn test_malformed_proofs_rejected() {
+        let mut rng = StdRng::seed_from_u64(12345);
+        let h = G1Affine::rand(&mut rng);
+        let g = G1Affine::rand(&mut rng);
+        let commitment = Commitment::<Bls12_381>::new(&h, &g, &Fr::rand(&mut rng), None, &mut rng);
+        let bytes = commitment.prove(&mut rng).unwrap();
+        let proof: CommitmentProof<Bls12_381> =
+            CanonicalDeserialize::deserialize_compressed(&bytes[..]).unwrap();
+
+        let encode = |proof: &CommitmentProof<Bls12_381>| {
+            let mut bytes = Vec::new();
+            proof.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        };
+        let mut truncated = proof.clone();
+        truncated.responses.pop();
+        let mut zero_challenge = proof.clone();
+        zero_challenge.challenge = Fr::zero();
+        let mut identity_announcement = proof.clone();
+        identity_announcement.schnorr_commitment = G1Affine::zero();
+
+        for malformed in [truncated, zero_challenge, identity_announcement] {
+            let malformed = encode(&malformed);
+            assert!(matches!(
+                Commitment::<Bls12_381>::verify(&malformed),
+                Err(CommitmentError::InvalidProof)
+            ));
+            assert!(matches!(
+                Commitment::<Bls12_381>::verify_against(
+                    &malformed,
+                    &proof.bases,
+                    &proof.commitment
+                ),
+                Err(CommitmentError::InvalidProof)
+            ));
+            assert!(matches!(
+                batch_verify::<Bls12_381>(&[bytes.clone(), malformed], &mut rng),
+                Err(CommitmentError::InvalidProof)
+            ));
+        }
+        assert!(batch_verify::<Bls12_381>(&[bytes], &mut rng).unwrap());
+    }
+
+    #[test]
+    fn test_batch_verify_catches_single_bad_proof() {
+        let mut rng = StdRng::seed_from_u64(12345);
+        let h = G1Affine::rand(&mut rng);
+        let g = G1Affine::rand(&mut rng);
+        let mut proofs: Vec<Vec<u8>> = (0..8)
+            .map(|_| {
+                Commitment::<Bls12_381>::new(&h, &g, &Fr::rand(&mut rng), None, &mut rng)
+                    .prove(&mut rng)
+                    .unwrap()
+            })
+            .collect();
+        assert!(batch_verify::<Bls12_381>(&proofs, &mut rng).unwrap());
+
+        // One well-formed proof with a response off by one, among seven valid ones
+        let mut bad: CommitmentProof<Bls12_381> =
+            CanonicalDeserialize::deserialize_compressed(&proofs[5][..]).unwrap();
+        bad.responses[0] += Fr::from(1u64);
+        proofs[5].clear();
+        bad.serialize_compressed(&mut proofs[5]).unwrap();
+        for seed in 0..256 {
+            let mut verifier_rng = StdRng::seed_from_u64(seed);
+            assert!(!batch_verify::<Bls12_381>(&proofs, &mut verifier_rng).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_batch_verify_matches_individual_verdicts() {
+        let mut rng = StdRng::seed_from_u64(12345);
+        let h = G1Affine::rand(&mut rng);
+        let g = G1Affine::rand(&mut rng);
+        let prove = |h: &G1Affine, rng: &mut StdRng| {
+            Commitment::<Bls12_381>::new(h, &g, &Fr::rand(rng), None, rng)
+                .prove(rng)
+                .unwrap()
+        };
+        let tamper = |bytes: &[u8], edit: &dyn Fn(&mut CommitmentProof<Bls12_381>)| {
+            let mut proof: CommitmentProof<Bls12_381> =
+                CanonicalDeserialize::deserialize_compressed(bytes).unwrap();
+            edit(&mut proof);
+            let mut tampered = Vec::new();
+            proof.serialize_compressed(&mut tampered).unwrap();
+            tampered
+        };
+
+        // Proofs over the shared (h, g) and over bases of their own
+        let shared: Vec<Vec<u8>> = (0..6).map(|_| prove(&h, &mut rng)).collect();
+        let own: Vec<Vec<u8>> = (0..3)
+            .map(|_| prove(&G1Affine::rand(&mut rng), &mut rng))
+            .collect();
+        let other_commitment = CommitmentProof::<Bls12_381>::deserialize_compressed(&shared[1][..])
+            .unwrap()
+            .commitment;
+        let mut batches = vec![
+            shared.clone(),
+            own.clone(),
+            shared.iter().chain(&own).cloned().collect::<Vec<_>>(),
+        ];
+        let mut bad_response = shared.clone();
+        bad_response[2] = tamper(&shared[2], &|p| p.responses[1] += Fr::from(1u64));
+        let mut swapped = shared.clone();
+        swapped[3] = tamper(&shared[3], &|p| p.commitment = other_commitment);
+        let mut moved_base = own.clone();
+        moved_base[0] = tamper(&own[0], &|p| p.bases[0] = h);
+        // Two wrong proofs whose errors would cancel if they shared a coefficient
+        let mut cancelling = shared.clone();
+        cancelling[0] = tamper(&shared[0], &|p| p.responses[0] += Fr::from(1u64));
+        cancelling[1] = tamper(&shared[1], &|p| p.responses[0] -= Fr::from(1u64));
+        batches.extend([bad_response, swapped, moved_base, cancelling]);
+
+        for batch in &batches {
+            let expected = batch
+                .iter()
+                .all(|proof| Commitment::<Bls12_381>::verify(proof).unwrap());
+            assert_eq!(
+                batch_verify::<Bls12_381>(batch, &mut rng).unwrap(),
+                expected
+            );
+        }
+        assert!(batches[..3]
+            .iter()
+            .all(|batch| batch_verify::<Bls12_381>(batch, &mut rng).unwrap()));
+    }
+
+    #[test]
+    fn test_aggregate_proof() {
+        let mut rng = StdRng::seed_from_u64(12345);
+        let h = G1Affine::rand(&mut rng);
+        let g = G1Affine::rand(&mut rng);
+        let messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let blindings: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let commitments: Vec<G1Affine> = messages
+            .iter()
+            .zip(&blindings)
+            .map(|(m, r)| (h * m + g * r).into_affine())
+            .collect();
+
+        let domain = DomainSeparator::default();
+        let proof = AggregateCommitmentProof::<Bls12_381>::prove(
+            &domain,
+            &h,
+            &g,
+            &commitments,
+            &messages,
+            &blindings,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(proof
+            .verify(&domain, &h, &g, &commitments, &mut rng)
+            .unwrap());
+
+        // Reordered commitments change the challenge and the pairing with announcements
+        let mut swapped = commitments.clone();
+        swapped.swap(0, 1);
+        assert!(!proof.verify(&domain, &h, &g, &swapped, &mut rng).unwrap());
+
+        // A response for the wrong opening
+        let mut wrong = proof.clone();
+        wrong.responses_m[2] += Fr::from(1u64);
+        assert!(!wrong
+            .verify(&domain, &h, &g, &commitments, &mut rng)
+            .unwrap());
+
+        // A proof made for another application
+        let other = DomainSeparator::new(b"myapp-v1".as_slice());
+        assert!(!proof
+            .verify(&other, &h, &g, &commitments, &mut rng)
+            .unwrap());
+
+        // One commitment short
+        assert!(matches!(
+            proof.verify(&domain, &h, &g, &commitments[..3], &mut rng),
+            Err(CommitmentError::InvalidProof)
+        ));
+    }
 }
@@ -1,15 +1,49 @@
-use crate::commitment::Commitment;
-use crate::errors::{CommitmentError, CredentialError};
+use crate::accumulator::{
+    non_membership_show_challenge, NonMembershipPresentation, NonMembershipProof,
+    NonMembershipWitness, UniversalAccumulatorPublicKey,
+};
+use crate::attributes::Attributes;
+use crate::commitment::{
+    key_attribute, AggregateCommitmentProof, CarryOverProof, Commitment, CommitmentProof,
+    ConsistencyProof, KeyBindingProof,
+};
+use crate::ct::rand_nonzero;
+use crate::display::HexPrefix;
+use crate::errors::{
+    CommitmentError, CredentialError, PolicyError, RevocationError, SetMembershipError,
+};
+use crate::keygen::VerificationKey;
+use crate::nullifier::{DYPFPrivSecretKey, DYPFPrivVRF, DYPFPrivVRFWitness};
+use crate::policy::{policy_show_challenge, PolicyPresentation, PresentationPolicy};
+use crate::presentation::{
+    multi_show_challenge, nonce_show_challenge, nullifier_input, nullifier_show_challenge,
+    possession_challenge, signed_message_hash, signed_show_challenge, MultiPresentation,
+    NullifierPresentation, OpenedCredential, PossessionProof, Presentation, SignedPresentation,
+};
+use crate::revocation::{
+    revocation_show_challenge, AttributeSignatureProof, MembershipWitness, RevocationPresentation,
+};
+use crate::schnorr::{SchnorrProtocol, SchnorrProverState};
+use crate::set_membership::{set_membership_show_challenge, SetMembershipPresentation, SetParams};
 use crate::signature::ThresholdSignature;
-use crate::symmetric_commitment::{SymmetricCommitment, SymmetricCommitmentKey};
+use crate::symmetric_commitment::{
+    g1_commit, g2_commit, SymmetricCommitment, SymmetricCommitmentKey,
+};
+use crate::transcript::DomainSeparator;
+use crate::wallet::{key_id, KeyId};
 use ark_ec::pairing::Pairing;
 use ark_ec::AffineRepr;
 use ark_ec::CurveGroup;
 use ark_ff::UniformRand;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::fmt;
 use ark_std::ops::Mul;
-use ark_std::rand::Rng;
+use ark_std::rand::{CryptoRng, Rng};
 use ark_std::Zero;
-use std::iter;
+use std::borrow::Cow;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum CredentialState {
@@ -18,66 +52,444 @@ pub enum CredentialState {
     Signed,      // Has valid signature
     Randomized,  // Has been shown/randomized
 }
+
+impl CredentialState {
+    const ALL: [CredentialState; 4] = [
+        CredentialState::Initialized,
+        CredentialState::Committed,
+        CredentialState::Signed,
+        CredentialState::Randomized,
+    ];
+
+    fn tag(&self) -> u8 {
+        match self {
+            CredentialState::Initialized => 0,
+            CredentialState::Committed => 1,
+            CredentialState::Signed => 2,
+            CredentialState::Randomized => 3,
+        }
+    }
+}
+
+/// Encoded as a single tag byte
+impl CanonicalSerialize for CredentialState {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.tag().serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.tag().serialized_size(compress)
+    }
+}
+
+impl Valid for CredentialState {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for CredentialState {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let tag = u8::deserialize_with_mode(reader, compress, validate)?;
+        Self::ALL
+            .get(tag as usize)
+            .cloned()
+            .ok_or(SerializationError::InvalidData)
+    }
+}
+/// Application data stored with a credential, kept out of every proof
+#[derive(Clone, Debug, Default, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CredentialMetadata {
+    /// Identifier of the attribute schema, e.g. a URL
+    pub schema_id: Option<String>,
+    /// Issuance time, in the application's unit
+    pub issued_at: Option<u64>,
+    pub tags: Vec<String>,
+    /// Free-form data that fits none of the fields above
+    pub note: Option<String>,
+}
+
+/// Metadata with only the free-form note set
+impl From<String> for CredentialMetadata {
+    fn from(note: String) -> Self {
+        Self {
+            note: Some(note),
+            ..Self::default()
+        }
+    }
+}
+
+/// Output of `show`: the randomized signature, randomized commitments in G1 and G2,
+/// and the serialized opening proof
+#[deprecated(note = "use `Presentation`, returned by `show`")]
+pub type ShowOutput<E> = (
+    ThresholdSignature<E>,
+    <E as Pairing>::G1Affine,
+    <E as Pairing>::G2Affine,
+    Vec<u8>,
+);
+
 /// Commitment to a single message with its proof
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct CredentialCommitments<E: Pairing> {
     pub h: E::G1Affine,
     pub commitments: Vec<E::G1Affine>,
     pub proofs: Vec<Vec<u8>>,
+    /// Binds the commitments to the credential's symmetric commitment, checked by
+    /// signers that require it
+    pub consistency: Option<ConsistencyProof<E>>,
+    /// Binds attribute 0 to a key the holder controls, checked by signers that require it
+    pub key_binding: Option<KeyBindingProof<E>>,
+}
+
+/// A request for a fresh credential that carries over the hidden attributes of a shown
+/// one. The attributes at `changed` take new values, the proof ties every other
+/// commitment of `request` to the old credential's randomized commitment
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ReissueRequest<E: Pairing> {
+    /// The old credential, shown under its own keys
+    pub presentation: Presentation<E>,
+    pub request: CredentialCommitments<E>,
+    /// Indices of the attributes that change, strictly increasing
+    pub changed: Vec<usize>,
+    pub proof: CarryOverProof<E>,
+}
+
+impl<E: Pairing> ReissueRequest<E> {
+    /// Indices of the attributes carried over from the old credential
+    pub fn carried(&self) -> Result<Vec<usize>, CommitmentError> {
+        let changed: Vec<_> = self.changed.iter().map(|k| (*k, ())).collect();
+        private_indices(self.request.commitments.len(), &changed)
+    }
+}
+
+/// Commitments to every attribute with a single `AggregateCommitmentProof`, the compact
+/// alternative to `CredentialCommitments` produced by `Credential::prove_all`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregatedCredentialCommitments<E: Pairing> {
+    pub h: E::G1Affine,
+    pub commitments: Vec<E::G1Affine>,
+    pub proof: AggregateCommitmentProof<E>,
+}
+
+impl<E: Pairing> AggregatedCredentialCommitments<E> {
+    /// Length of the compressed encoding in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+}
+
+impl<E: Pairing> CredentialCommitments<E> {
+    /// Length of the compressed encoding in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+
+    /// The commitment to attribute `k` and its serialized opening proof
+    pub fn attribute(&self, k: usize) -> Option<(&E::G1Affine, &[u8])> {
+        Some((self.commitments.get(k)?, self.proofs.get(k)?.as_slice()))
+    }
+
+    /// `(k, commitment, proof)` for every attribute that has both, in index order
+    pub fn attributes(&self) -> impl Iterator<Item = (usize, &E::G1Affine, &[u8])> {
+        self.commitments
+            .iter()
+            .zip(&self.proofs)
+            .enumerate()
+            .map(|(k, (commitment, proof))| (k, commitment, proof.as_slice()))
+    }
+
+    /// The full per-attribute commitments for a request that only commits to the private
+    /// attributes, with `h^m` standing in for each issuer-set `(index, m)`
+    pub fn with_public_attributes(
+        &self,
+        public_attributes: &[(usize, E::ScalarField)],
+        num_attributes: usize,
+    ) -> Result<Self, CommitmentError> {
+        Ok(Self {
+            h: self.h,
+            commitments: expand_commitments::<E>(
+                &self.h,
+                &self.commitments,
+                public_attributes,
+                num_attributes,
+            )?,
+            proofs: self.proofs.clone(),
+            // The proof covers the private commitments only
+            consistency: None,
+            key_binding: None,
+        })
+    }
+}
+
+/// Indices of the attributes the user commits to once the issuer sets `public_attributes`
+pub(crate) fn private_indices<F>(
+    num_attributes: usize,
+    public_attributes: &[(usize, F)],
+) -> Result<Vec<usize>, CommitmentError> {
+    if public_attributes
+        .windows(2)
+        .any(|pair| pair[0].0 >= pair[1].0)
+    {
+        return Err(CommitmentError::InvalidPublicAttributes(
+            "indices must be strictly increasing".to_string(),
+        ));
+    }
+    if let Some((index, _)) = public_attributes
+        .iter()
+        .find(|(index, _)| *index >= num_attributes)
+    {
+        return Err(CommitmentError::InvalidPublicAttributes(format!(
+            "index {index} out of range for {num_attributes} attributes"
+        )));
+    }
+    Ok((0..num_attributes)
+        .filter(|k| !public_attributes.iter().any(|(index, _)| index == k))
+        .collect())
+}
+
+/// Interleave the private commitments with `h^m` for each public attribute
+pub(crate) fn expand_commitments<E: Pairing>(
+    h: &E::G1Affine,
+    commitments: &[E::G1Affine],
+    public_attributes: &[(usize, E::ScalarField)],
+    num_attributes: usize,
+) -> Result<Vec<E::G1Affine>, CommitmentError> {
+    let private = private_indices(num_attributes, public_attributes)?;
+    if commitments.len() != private.len() {
+        return Err(CommitmentError::InvalidPublicAttributes(format!(
+            "expected {} private commitments, got {}",
+            private.len(),
+            commitments.len()
+        )));
+    }
+
+    let mut full = vec![E::G1Affine::zero(); num_attributes];
+    for (index, m) in public_attributes {
+        full[*index] = h.mul(m).into_affine();
+    }
+    for (index, commitment) in private.into_iter().zip(commitments) {
+        full[index] = *commitment;
+    }
+    Ok(full)
 }
 
+/// Redraw until the point isn't the identity, an identity `h` makes every share degenerate
+fn non_identity<G: AffineRepr>(mut draw: impl FnMut() -> G) -> G {
+    loop {
+        let point = draw();
+        if !point.is_zero() {
+            return point;
+        }
+    }
+}
+
+/// Serializable so that a holder can store it, the encoding includes the attributes
+/// and blindings and has to be kept as secret as the credential itself
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct Credential<E: Pairing> {
     pub ck: SymmetricCommitmentKey<E>,
-    pub cm: SymmetricCommitment<E>,
+    /// `CM.Com([m_1, ..., m_L], 0)` over the issuing committee's `vk.show_key(ck)`,
+    /// only needed to show. Left empty until the signature is attached, a request
+    /// commits to the attributes one by one
+    pub cm: Option<SymmetricCommitment<E>>,
     messages: Vec<E::ScalarField>,
     pub blindings: Vec<E::ScalarField>, //public for testing
     h: E::G1Affine,
     sig: Option<ThresholdSignature<E>>,
     pub context: E::ScalarField, // context for the credential like an id
     pub state: CredentialState,
-    pub metadata: Option<String>, // testing for benchmarking
+    pub metadata: CredentialMetadata,
+    domain: DomainSeparator,
+}
+
+/// State, attribute count and fingerprints of the context and `h`. The attributes,
+/// blindings, commitment and signature are left out
+impl<E: Pairing> fmt::Display for Credential<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Credential(state={:?}, attributes={}, context={}, h={}, signed={})",
+            self.state,
+            self.messages.len(),
+            HexPrefix(&self.context),
+            HexPrefix(&self.h),
+            self.sig.is_some()
+        )
+    }
+}
+
+/// Clears the attributes, the blindings and the commitment opening
+/// The randomized commitment a show proves an opening of. Only the points and the
+/// blinding, the prover takes the attributes from the credential, so nothing here
+/// copies them or the key
+struct ShownCommitment<E: Pairing> {
+    cm: E::G1Affine,
+    cm_tilde: E::G2Affine,
+    r: E::ScalarField,
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: Pairing> Drop for ShownCommitment<E> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.r);
+    }
 }
 
+#[cfg(feature = "zeroize")]
+impl<E: Pairing> zeroize::Zeroize for Credential<E> {
+    fn zeroize(&mut self) {
+        self.messages.zeroize();
+        self.blindings.zeroize();
+        if let Some(cm) = &mut self.cm {
+            cm.messages.zeroize();
+            cm.r.zeroize();
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: Pairing> Drop for Credential<E> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: Pairing> zeroize::ZeroizeOnDrop for Credential<E> {}
+
 impl<E: Pairing> Credential<E> {
+    /// `from_attributes` for raw `messages`, checked with `Attributes::new`. Random
+    /// attributes are drawn if `None`
     pub fn new(
         ck: SymmetricCommitmentKey<E>,
         messages: Option<&[E::ScalarField]>,
-        rng: &mut impl Rng,
-    ) -> Self {
-        let num_messages = ck.ck.len();
-        // Generate random messages if none are provided
-        let messages = match messages {
-            Some(msgs) => msgs.to_vec(),
-            None => iter::repeat_with(|| E::ScalarField::rand(rng))
-                .take(num_messages)
-                .collect(),
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Self, CredentialError> {
+        let attributes = match messages {
+            Some(msgs) => {
+                Attributes::new(msgs.to_vec(), &ck).map_err(CredentialError::InvalidAttributes)?
+            }
+            None => Attributes::random(&ck, rng),
         };
-        // gen h
-        let h = E::G1Affine::rand(rng);
-        // gen cm
-        let cm = SymmetricCommitment::<E>::new(&ck, &messages, &E::ScalarField::zero());
+        Ok(Self::from_attributes(ck, attributes, rng))
+    }
+
+    /// A fresh credential over `attributes`, which must have been built for `ck` or a key
+    /// of the same length
+    ///
+    /// # Panics
+    /// If `attributes` don't have one value per attribute base of `ck`
+    pub fn from_attributes(
+        ck: SymmetricCommitmentKey<E>,
+        attributes: Attributes<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Self {
+        assert_eq!(
+            attributes.len(),
+            Attributes::capacity(&ck),
+            "attributes built for another key"
+        );
+        // gen h, never the identity
+        let h = non_identity::<E::G1Affine>(|| E::G1Affine::rand(rng));
 
         Self {
             ck,
-            cm,
-            messages,
+            cm: None,
+            messages: attributes.into_vec(),
             blindings: Vec::new(),
             h,
             sig: None,
             context: E::ScalarField::rand(rng),
             state: CredentialState::Initialized,
-            metadata: None,
+            metadata: CredentialMetadata::default(),
+            domain: DomainSeparator::default(),
         }
     }
 
+    /// Make the Fiat-Shamir proofs of this credential under `domain` instead of the
+    /// default, signers and verifiers have to use the same one
+    pub fn with_domain(mut self, domain: DomainSeparator) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    pub fn domain(&self) -> &DomainSeparator {
+        &self.domain
+    }
+
+    /// Replace the attributes. The commitment is dropped, to be recomputed once a new
+    /// signature is attached, as are any blindings or signature over the old attributes,
+    /// so the credential starts over
     pub fn set_attributes(&mut self, messages: Vec<E::ScalarField>) {
         self.messages = messages;
+        self.cm = None;
+        self.blindings.clear();
+        self.sig = None;
+        self.state = CredentialState::Initialized;
     }
 
     // set the symmetric commitment, at the start it will be CM.Com([m_1, ..., m_L], 0)
     pub fn set_symmetric_commitment(&mut self) {
-        let zero = E::ScalarField::zero();
-        let cm = SymmetricCommitment::<E>::new(&self.ck, &self.messages, &zero);
-        self.cm = cm;
+        self.cm = Some(self.symmetric_commitment().into_owned());
+    }
+
+    /// `cm`, or the commitment it will hold if it hasn't been computed yet
+    fn symmetric_commitment(&self) -> Cow<'_, SymmetricCommitment<E>> {
+        match &self.cm {
+            Some(cm) => Cow::Borrowed(cm),
+            None => {
+                let zero = E::ScalarField::zero();
+                Cow::Owned(SymmetricCommitment::new_g1_only(
+                    &self.ck,
+                    &self.messages,
+                    &zero,
+                ))
+            }
+        }
+    }
+
+    /// Check that `cm`, when computed, commits to the current attributes and that the
+    /// state matches the blindings and signature held
+    pub fn check_invariants(&self) -> bool {
+        let cm_matches = self.cm.as_ref().is_none_or(|cm| {
+            cm.messages == self.messages
+                && cm.cm == g1_commit(&cm.ck, &self.messages, &cm.r)
+                && cm.cm_tilde() == g2_commit(&cm.ck, &self.messages, &cm.r)
+        });
+        let state_matches = match self.state {
+            CredentialState::Initialized => self.sig.is_none(),
+            CredentialState::Committed => {
+                self.blindings.len() == self.messages.len() && self.sig.is_none()
+            }
+            CredentialState::Signed | CredentialState::Randomized => {
+                self.blindings.len() == self.messages.len() && self.sig.is_some()
+            }
+        };
+        cm_matches && state_matches
+    }
+
+    /// `L`, the number of attributes
+    pub fn num_attributes(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Whether a signature is attached, as `signed` in the `Display` output
+    pub fn is_signed(&self) -> bool {
+        self.sig.is_some()
+    }
+
+    /// The `KeyId` of the commitment key the credential is issued under
+    pub fn key_id(&self) -> KeyId {
+        key_id(&self.ck)
     }
 
     pub fn get_messages(&self) -> &Vec<E::ScalarField> {
@@ -92,67 +504,38 @@ impl<E: Pairing> Credential<E> {
     // adds ~25% efficiency over standard version
     pub fn compute_commitments_per_m(
         &mut self,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<CredentialCommitments<E>, CommitmentError> {
-        if self.messages.is_empty() {
-            return Err(CommitmentError::InvalidComputeCommitment);
-        }
-
-        let num_messages = self.messages.len();
+        let commitments = self.commit_all(rng)?;
+        let num_messages = commitments.len();
+        let blindings = &self.blindings;
 
         // Pre-allocate vectors with capacity
-        let mut commitments = Vec::with_capacity(num_messages);
         let mut commitment_proofs = Vec::with_capacity(num_messages);
-        let mut blindings = Vec::with_capacity(num_messages);
-
-        // Generate all randomness at once for better entropy management
-        for _ in 0..num_messages {
-            blindings.push(E::ScalarField::rand(rng));
-        }
-
-        // Store the blindings for future signature operations
-        self.blindings = blindings.clone();
-        self.state = CredentialState::Committed;
-
-        // Use a modified batch method to compute all commitments efficiently
-        // This is optimized for the specific case of computing h*m + g*r for each message
-
-        // First, convert all the points that need to be computed into projective form for efficiency
-        let h_projective = self.h.into_group();
-        let g_projective = self.ck.g.into_group();
-
-        // Prepare temporary storage for all projective points
-        let mut projective_commitments = Vec::with_capacity(num_messages);
-
-        // Compute commitments in projective form (more efficient for arithmetic)
-        for i in 0..num_messages {
-            let h_m = h_projective.mul(self.messages[i]);
-            let g_r = g_projective.mul(blindings[i]);
-            projective_commitments.push(h_m + g_r);
-        }
-
-        // Batch normalize all commitments at once (converting from projective to affine coordinates)
-        // This is much more efficient than converting one by one
-        commitments = E::G1::normalize_batch(&projective_commitments);
 
         // Generate proofs for each commitment (can be parallelized with Rayon)
         #[cfg(feature = "parallel")]
         {
-            use rand::thread_rng;
+            use ark_std::rand::{rngs::StdRng, SeedableRng};
             use rayon::prelude::*;
 
-            let proof_results: Vec<Result<Vec<u8>, CommitmentError>> = (0..num_messages)
-                .into_par_iter()
-                .map(|i| {
-                    let current_cm = Commitment::<E> {
-                        bases: vec![self.h, self.ck.g],
-                        exponents: vec![self.messages[i], blindings[i]],
-                        cm: commitments[i],
-                    };
-                    // Use a thread-local RNG instead of sharing the mutable reference
-                    current_cm.prove(&mut thread_rng())
-                })
-                .collect();
+            // Seed one RNG per proof from the caller's RNG, so the proofs only depend on it
+            // and not on which side of the cut-over this runs
+            let seeds: Vec<[u8; 32]> = (0..num_messages).map(|_| rng.gen()).collect();
+            let prove = |(i, seed): (usize, [u8; 32])| {
+                let current_cm = Commitment::<E> {
+                    bases: vec![self.h, self.ck.g],
+                    exponents: vec![self.messages[i], blindings[i]],
+                    cm: commitments[i],
+                };
+                current_cm.prove(&mut StdRng::from_seed(seed))
+            };
+            let proof_results: Vec<Result<Vec<u8>, CommitmentError>> =
+                if crate::parallel::use_rayon(num_messages) {
+                    seeds.into_par_iter().enumerate().map(prove).collect()
+                } else {
+                    seeds.into_iter().enumerate().map(prove).collect()
+                };
 
             for result in proof_results {
                 match result {
@@ -166,7 +549,7 @@ impl<E: Pairing> Credential<E> {
         #[cfg(not(feature = "parallel"))]
         {
             for i in 0..num_messages {
-                let current_cm = Commitment {
+                let current_cm = Commitment::<E> {
                     bases: vec![self.h, self.ck.g],
                     exponents: vec![self.messages[i], blindings[i]],
                     cm: commitments[i],
@@ -183,16 +566,162 @@ impl<E: Pairing> Credential<E> {
             h: self.h,
             commitments,
             proofs: commitment_proofs,
+            consistency: None,
+            key_binding: None,
+        })
+    }
+
+    /// `compute_commitments_per_m` with a `ConsistencyProof` binding the commitments to
+    /// the symmetric commitment used at show time. The proof carries that commitment
+    /// under a fresh blinding, so the request doesn't reveal `\prod_k ck_k^{m_k}`
+    pub fn compute_commitments_with_consistency(
+        &mut self,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<CredentialCommitments<E>, CommitmentError> {
+        let mut request = self.compute_commitments_per_m(rng)?;
+        let sym_cm = self.symmetric_commitment().randomize(&rand_nonzero(rng))?;
+        request.consistency = Some(ConsistencyProof::prove(
+            &self.domain,
+            &self.h,
+            &self.ck,
+            &request.commitments,
+            &self.messages,
+            &self.blindings,
+            &sym_cm.cm,
+            &sym_cm.r,
+            rng,
+        )?);
+        Ok(request)
+    }
+
+    /// `compute_commitments_per_m` with a `KeyBindingProof` that attribute 0 is
+    /// `key_attribute(pk)` for the holder key `pk = g^sk`, for signers that only
+    /// issue one credential per key
+    pub fn compute_commitments_with_key_binding(
+        &mut self,
+        sk: &E::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<CredentialCommitments<E>, CommitmentError> {
+        let pk = self.ck.g.mul(sk).into_affine();
+        if self.messages.first() != Some(&key_attribute::<E>(&self.domain, &pk)) {
+            return Err(CommitmentError::InvalidPublicAttributes(
+                "attribute 0 is not the key attribute".to_string(),
+            ));
+        }
+        let mut request = self.compute_commitments_per_m(rng)?;
+        request.key_binding = Some(KeyBindingProof::prove(
+            &self.domain,
+            &self.h,
+            &self.ck.g,
+            &request.commitments,
+            sk,
+            &self.blindings[0],
+            rng,
+        )?);
+        Ok(request)
+    }
+
+    /// Like `compute_commitments_per_m`, with one `AggregateCommitmentProof` for all the
+    /// commitments instead of a proof per commitment
+    pub fn prove_all(
+        &mut self,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<AggregatedCredentialCommitments<E>, CommitmentError> {
+        let commitments = self.commit_all(rng)?;
+        let proof = AggregateCommitmentProof::prove(
+            &self.domain,
+            &self.h,
+            &self.ck.g,
+            &commitments,
+            &self.messages,
+            &self.blindings,
+            rng,
+        )?;
+        Ok(AggregatedCredentialCommitments {
+            h: self.h,
+            commitments,
+            proof,
         })
     }
 
+    /// Draw fresh blindings and compute `cm_k = h^{m_k} g^{r_k}` for every attribute
+    fn commit_all(
+        &mut self,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Vec<E::G1Affine>, CommitmentError> {
+        if self.messages.is_empty() {
+            return Err(CommitmentError::InvalidComputeCommitment);
+        }
+
+        let num_messages = self.messages.len();
+        let mut blindings = Vec::with_capacity(num_messages);
+
+        // Generate all randomness at once for better entropy management
+        for _ in 0..num_messages {
+            blindings.push(E::ScalarField::rand(rng));
+        }
+
+        // Store the blindings for future signature operations
+        self.blindings = blindings.clone();
+        self.state = CredentialState::Committed;
+
+        // Use a modified batch method to compute all commitments efficiently
+        // This is optimized for the specific case of computing h*m + g*r for each message
+
+        // First, convert all the points that need to be computed into projective form for efficiency
+        let h_projective = self.h.into_group();
+        let g_projective = self.ck.g.into_group();
+
+        // Prepare temporary storage for all projective points
+        let mut projective_commitments = Vec::with_capacity(num_messages);
+
+        // Compute commitments in projective form (more efficient for arithmetic)
+        for (m, r) in self.messages.iter().zip(blindings.iter()) {
+            let h_m = h_projective.mul(m);
+            let g_r = g_projective.mul(r);
+            projective_commitments.push(h_m + g_r);
+        }
+
+        // Batch normalize all commitments at once (converting from projective to affine coordinates)
+        // This is much more efficient than converting one by one
+        Ok(E::G1::normalize_batch(&projective_commitments))
+    }
+
+    /// `compute_commitments_per_m` for a credential whose attributes at `public_indices`
+    /// are set by the issuer. Their blindings are zero, so the aggregated signature is
+    /// unblinded as usual, and their commitments are left out of the request
+    pub fn compute_commitments_partial(
+        &mut self,
+        public_indices: &[usize],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<CredentialCommitments<E>, CommitmentError> {
+        let mut request = self.compute_commitments_per_m(rng)?;
+        for (k, blinding) in self.blindings.iter_mut().enumerate() {
+            if public_indices.contains(&k) {
+                *blinding = E::ScalarField::zero();
+            }
+        }
+
+        let (commitments, proofs) = request
+            .commitments
+            .into_iter()
+            .zip(request.proofs)
+            .enumerate()
+            .filter(|(k, _)| !public_indices.contains(k))
+            .map(|(_, pair)| pair)
+            .unzip();
+        request.commitments = commitments;
+        request.proofs = proofs;
+        Ok(request)
+    }
+
     // commit to each message attribute individually for threshold sig
     //  h_1^m_1 g_1^r_1 * h_2^m_2 g_2^r_2
     //  m_1, ..., m_L
     //  r_1, ..., r_L
     pub fn compute_commitments_per_m_old(
         &mut self,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<CredentialCommitments<E>, CommitmentError> {
         if self.messages.is_empty() {
             return Err(CommitmentError::InvalidComputeCommitment);
@@ -226,19 +755,29 @@ impl<E: Pairing> Credential<E> {
             h: self.h,
             commitments,
             proofs: commitment_proofs,
+            consistency: None,
+            key_binding: None,
         })
     }
 
-    pub fn attach_signature(&mut self, sig: ThresholdSignature<E>) {
+    /// Attach the aggregate signature of the committee with key `vk`, computing the
+    /// symmetric commitment showing it needs over `vk.show_key(ck)`
+    pub fn attach_signature(&mut self, sig: ThresholdSignature<E>, vk: &VerificationKey<E>) {
+        let zero = E::ScalarField::zero();
+        self.cm = Some(SymmetricCommitment::new_g1_only(
+            &vk.show_key(&self.ck),
+            &self.messages,
+            &zero,
+        ));
         self.state = CredentialState::Signed;
         self.sig = Some(sig);
     }
 
-    /// this is the anonymous credential `show` protocol. generates proof for commitment
-    pub fn show(
+    /// Randomize the signature and the symmetric commitment with the same r_delta
+    fn randomize_for_show(
         &self,
-        rng: &mut impl Rng,
-    ) -> Result<(ThresholdSignature<E>, E::G1Affine, E::G2Affine, Vec<u8>), CredentialError> {
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(ThresholdSignature<E>, ShownCommitment<E>), CredentialError> {
         // Check signature exists
         if self.state != CredentialState::Signed {
             return Err(CredentialError::InvalidState(
@@ -248,23 +787,1120 @@ impl<E: Pairing> Credential<E> {
 
         let sig = self.sig.as_ref().unwrap();
         // Randomize signature
-        let (randomized_sig, r_delta) = sig.randomize(rng);
+        let (randomized_sig, r_delta) = sig
+            .randomize(rng)
+            .map_err(|e| CredentialError::RandomizationFailed(e.to_string()))?;
+
+        // Randomize commitment, the first show fills in the cached cm_tilde
+        let cm = self.symmetric_commitment();
+        let (randomized_cm, randomized_cm_tilde) = cm
+            .randomized_points(&r_delta)
+            .map_err(|e| CredentialError::RandomizationFailed(e.to_string()))?;
+        Ok((
+            randomized_sig,
+            ShownCommitment {
+                cm: randomized_cm,
+                cm_tilde: randomized_cm_tilde,
+                r: cm.r + r_delta,
+            },
+        ))
+    }
 
-        // Randomize commitment
-        let sym_cm = self.cm.clone();
-        let rand_sym_cm = sym_cm.randomize(&r_delta);
+    /// The key the credential is shown under, `cm`'s key once a signature is attached
+    fn show_key(&self) -> &SymmetricCommitmentKey<E> {
+        self.cm.as_ref().map_or(&self.ck, |cm| &cm.ck)
+    }
+
+    /// The attributes and the randomized blinding of `shown`, in the key's base order
+    fn shown_exponents(&self, shown: &ShownCommitment<E>) -> Vec<E::ScalarField> {
+        self.ck.order_exponents(&self.messages, &shown.r)
+    }
+
+    /// The opening proof for the randomized commitment `shown` answering `challenge`,
+    /// serialized as in a `Presentation`
+    fn opening_proof_for_challenge(
+        &self,
+        shown: &ShownCommitment<E>,
+        bases: Vec<E::G1Affine>,
+        state: SchnorrProverState<E::G1Affine>,
+        schnorr_commitment: E::G1Affine,
+        challenge: E::ScalarField,
+    ) -> Result<Vec<u8>, CredentialError> {
+        let responses = SchnorrProtocol::prove(state, &self.shown_exponents(shown), &challenge);
+        let opening_proof = CommitmentProof::<E> {
+            commitment: shown.cm,
+            schnorr_commitment,
+            bases,
+            challenge,
+            responses: responses.0,
+        };
+        let mut proof = Vec::new();
+        opening_proof
+            .serialize_compressed(&mut proof)
+            .map_err(|e| CredentialError::ProofGenerationFailed(e.into()))?;
+        Ok(proof)
+    }
+
+    /// The opening proof of `SymmetricCommitment::prove` for `shown`, with a random
+    /// challenge
+    fn opening_proof(
+        &self,
+        shown: &ShownCommitment<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Vec<u8>, CredentialError> {
+        let bases = self.show_key().get_bases().0;
+        let (state, announcement) = SchnorrProtocol::commit(&bases, rng);
+        let challenge = E::ScalarField::rand(rng);
+        self.opening_proof_for_challenge(shown, bases, state, announcement.0, challenge)
+    }
+
+    /// this is the anonymous credential `show` protocol. generates proof for commitment
+    pub fn show(
+        &self,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Presentation<E>, CredentialError> {
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
 
         // Generate proof
-        let proof = rand_sym_cm
-            .clone()
-            .prove(rng)
-            .map_err(CredentialError::ProofGenerationFailed)?;
-        Ok((randomized_sig, rand_sym_cm.cm, rand_sym_cm.cm_tilde, proof))
+        let proof = self.opening_proof(&shown, rng)?;
+        Ok(Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof,
+            nonce: None,
+        })
+    }
+
+    /// `show` for a verifier issued `nonce`: the opening proof answers a Fiat-Shamir
+    /// challenge over the presentation and the nonce, which the presentation carries.
+    /// It passes `verify_with_nonce` for that nonce only
+    pub fn show_with_nonce(
+        &self,
+        nonce: [u8; 32],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Presentation<E>, CredentialError> {
+        self.show_disclosing(nonce, &[], rng)
+            .map(|(presentation, _)| presentation)
+    }
+
+    /// `show_with_nonce` disclosing the attributes at `indices`, strictly increasing.
+    /// Their blindings in the opening proof are zero, so each response is `c m` for the
+    /// disclosed `m`, and the challenge covers the disclosed values. Returns the
+    /// presentation and the disclosed `(index, attribute)` pairs, which pass
+    /// `verify_disclosed` with it
+    #[allow(clippy::type_complexity)]
+    pub fn show_disclosing(
+        &self,
+        nonce: [u8; 32],
+        indices: &[usize],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Presentation<E>, Vec<(usize, E::ScalarField)>), CredentialError> {
+        let checked: Vec<(usize, ())> = indices.iter().map(|index| (*index, ())).collect();
+        private_indices(self.messages.len(), &checked)
+            .map_err(CredentialError::InvalidAttributes)?;
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+
+        // Disclosed attributes get a zero blinding, so their responses are c m
+        let bases = self.show_key().get_bases().0;
+        let mut blindings: Vec<E::ScalarField> = (0..bases.len())
+            .map(|_| E::ScalarField::rand(rng))
+            .collect();
+        for index in indices {
+            blindings[self.ck.attribute_position(*index)] = E::ScalarField::zero();
+        }
+        let (schnorr_state, announcement) =
+            SchnorrProtocol::commit_with_prepared_blindings(&bases, &blindings);
+
+        let mut presentation = Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof: Vec::new(),
+            nonce: Some(nonce),
+        };
+        let disclosed: Vec<(usize, E::ScalarField)> = indices
+            .iter()
+            .map(|index| (*index, self.messages[*index]))
+            .collect();
+        let challenge = nonce_show_challenge(
+            &self.domain,
+            &presentation,
+            &nonce,
+            &disclosed,
+            &announcement.0,
+        );
+        presentation.proof = self.opening_proof_for_challenge(
+            &shown,
+            bases,
+            schnorr_state,
+            announcement.0,
+            challenge,
+        )?;
+        Ok((presentation, disclosed))
+    }
+
+    /// Open the randomized credential in full instead of proving the opening, for uses
+    /// that disclose every attribute. Cheaper than `show` for prover and verifier, and
+    /// the randomized signature still can't be linked to issuance or other shows except
+    /// through the attributes. See `OpenedCredential::verify`
+    pub fn open_all(
+        &self,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<OpenedCredential<E>, CredentialError> {
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+        Ok(OpenedCredential {
+            signature: randomized_sig,
+            messages: self.messages.clone(),
+            blinding: shown.r,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+        })
     }
 
-    // Helper methods for multi-credential management
-    pub fn with_metadata(mut self, metadata: String) -> Self {
-        self.metadata = Some(metadata);
+    /// A new credential under `ck` with the attributes of this one, except for the
+    /// `(index, value)` pairs in `changed`, and the request for it. The request shows
+    /// this credential and proves that the unchanged attributes are carried over
+    /// without revealing them. `ck` may be a rotated key with the same attribute count
+    pub fn request_reissue(
+        &self,
+        ck: SymmetricCommitmentKey<E>,
+        changed: &[(usize, E::ScalarField)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Credential<E>, ReissueRequest<E>), CredentialError> {
+        let l = self.messages.len();
+        if ck.num_attributes() != l {
+            return Err(CredentialError::InvalidAttributes(
+                CommitmentError::TooManyMessages {
+                    messages: l,
+                    bases: ck.num_attributes(),
+                },
+            ));
+        }
+        let carried = private_indices(l, changed)?;
+        let mut messages = self.messages.clone();
+        for (index, value) in changed {
+            messages[*index] = *value;
+        }
+        let mut credential =
+            Credential::new(ck, Some(&messages), rng)?.with_domain(self.domain.clone());
+        let request = credential.compute_commitments_per_m(rng)?;
+
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+        let proof = CarryOverProof::prove(
+            &self.domain,
+            &request.h,
+            &credential.ck.g,
+            &request.commitments,
+            &credential.blindings,
+            &carried,
+            self.show_key(),
+            &shown.cm,
+            &self.messages,
+            &shown.r,
+            rng,
+        )?;
+        let presentation = Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof: self.opening_proof(&shown, rng)?,
+            nonce: None,
+        };
+        Ok((
+            credential,
+            ReissueRequest {
+                presentation,
+                request,
+                changed: changed.iter().map(|(index, _)| *index).collect(),
+                proof,
+            },
+        ))
+    }
+
+    /// Show several credentials at once with one Fiat-Shamir challenge for all their
+    /// opening proofs, under the domain of the first credential
+    pub fn show_many(
+        credentials: &[&Credential<E>],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<MultiPresentation<E>, CredentialError> {
+        let first = credentials.first().ok_or(CredentialError::InvalidState(
+            "No credentials to show".to_string(),
+        ))?;
+        let mut randomized = Vec::with_capacity(credentials.len());
+        for credential in credentials {
+            randomized.push(credential.randomize_for_show(rng)?);
+        }
+
+        let bases: Vec<_> = credentials
+            .iter()
+            .map(|credential| credential.show_key().get_bases().0)
+            .collect();
+        let (states, announcements): (Vec<_>, Vec<_>) = bases
+            .iter()
+            .map(|bases| SchnorrProtocol::commit(bases, rng))
+            .map(|(state, announcement)| (state, announcement.0))
+            .unzip();
+        let (signatures, shown_cms): (Vec<_>, Vec<_>) = randomized.into_iter().unzip();
+        let commitments: Vec<_> = shown_cms.iter().map(|cm| cm.cm).collect();
+        let commitments_tilde: Vec<_> = shown_cms.iter().map(|cm| cm.cm_tilde).collect();
+        let challenge = multi_show_challenge(
+            &first.domain,
+            &bases,
+            &signatures,
+            &commitments,
+            &commitments_tilde,
+            &announcements,
+        );
+
+        let responses = states
+            .into_iter()
+            .zip(credentials.iter().zip(&shown_cms))
+            .map(|(state, (credential, cm))| {
+                SchnorrProtocol::prove(state, &credential.shown_exponents(cm), &challenge).0
+            })
+            .collect();
+        Ok(MultiPresentation {
+            signatures,
+            commitments,
+            commitments_tilde,
+            announcements,
+            responses,
+        })
+    }
+
+    /// `show` bound to the nullifier `y = g^(1/(sk + context))` for the holder's VRF key `sk`.
+    /// One transcript absorbs the randomized credential, the nullifier and the announcements
+    /// of the opening proof and the VRF proof, and its challenge is used by both
+    pub fn show_with_nullifier(
+        &self,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        sk: &DYPFPrivSecretKey<E::ScalarField>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<NullifierPresentation<E>, CredentialError> {
+        let randomized = self.randomize_for_show(rng)?;
+
+        // VRF statement: commitments to sk and the context, and y
+        let (input, cm_x) = vrf.commit_to_input(&self.context, rng);
+        let witness = DYPFPrivVRFWitness {
+            sk: sk.sk,
+            r_sk: sk.r_sk,
+            x: input.x,
+            r_x: input.r_x,
+        };
+        self.show_with_vrf_witness(vrf, randomized, &witness, cm_x, None, rng)
+    }
+
+    /// `show` bound to the nullifier `y = g^(1/(m + x))`, with the hidden attribute `m`
+    /// at `key_index` as the VRF key and `x = nullifier_input(context_input)`. The VRF
+    /// proof and the opening proof share the blinding for `m`, which lets the verifier
+    /// check with `NullifierPresentation::verify_binding` that the nullifier belongs to
+    /// the shown credential and the given context
+    pub fn show_with_bound_nullifier(
+        &self,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        key_index: usize,
+        context_input: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<NullifierPresentation<E>, CredentialError> {
+        let sk = *self.messages.get(key_index).ok_or_else(|| {
+            CredentialError::NullifierFailed(format!("no attribute at index {key_index}"))
+        })?;
+        let randomized = self.randomize_for_show(rng)?;
+
+        // x is public, its commitment carries no blinding
+        let x = nullifier_input::<E>(&self.domain, context_input);
+        let witness = DYPFPrivVRFWitness {
+            sk,
+            r_sk: E::ScalarField::rand(rng),
+            x,
+            r_x: E::ScalarField::zero(),
+        };
+        let cm_x = vrf.pp.g2.mul(x).into_affine();
+        self.show_with_vrf_witness(vrf, randomized, &witness, cm_x, Some(key_index), rng)
+    }
+
+    /// The nullifier presentation for `witness` on an already randomized credential. With
+    /// `key_index` the opening proof's blinding at that index is reused for `sk`
+    fn show_with_vrf_witness(
+        &self,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        (randomized_sig, shown): (ThresholdSignature<E>, ShownCommitment<E>),
+        witness: &DYPFPrivVRFWitness<E::ScalarField>,
+        cm_x: E::G1Affine,
+        key_index: Option<usize>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<NullifierPresentation<E>, CredentialError> {
+        let cm_sk = (vrf.pp.g1.mul(witness.sk) + vrf.pp.g.mul(witness.r_sk)).into_affine();
+        let output = vrf
+            .evaluate(witness)
+            .map_err(|e| CredentialError::NullifierFailed(e.to_string()))?;
+
+        // Announcements of both sigma protocols
+        let bases = self.show_key().get_bases().0;
+        let (schnorr_state, announcement, vrf_commitment) = match key_index {
+            Some(index) => {
+                let a_sk = E::ScalarField::rand(rng);
+                let (state, announcement) = SchnorrProtocol::commit_with_shared(
+                    &bases,
+                    &[(self.ck.attribute_position(index), a_sk)],
+                    rng,
+                );
+                let vrf_commitment = vrf.commit_proof_with_sk_blinding(&output, a_sk, rng);
+                (state, announcement, vrf_commitment)
+            }
+            None => {
+                let (state, announcement) = SchnorrProtocol::commit(&bases, rng);
+                let vrf_commitment = vrf.commit_proof(&output, rng);
+                (state, announcement, vrf_commitment)
+            }
+        };
+
+        let mut presentation = Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof: Vec::new(),
+            nonce: None,
+        };
+        let challenge = nullifier_show_challenge(
+            &self.domain,
+            &presentation,
+            &output.y,
+            &cm_sk,
+            &cm_x,
+            &announcement.0,
+            [&vrf_commitment.t1, &vrf_commitment.t2, &vrf_commitment.ty],
+        );
+
+        presentation.proof = self.opening_proof_for_challenge(
+            &shown,
+            bases,
+            schnorr_state,
+            announcement.0,
+            challenge,
+        )?;
+
+        Ok(NullifierPresentation {
+            presentation,
+            nullifier: output.y,
+            cm_sk,
+            cm_x,
+            vrf_proof: vrf.respond(&vrf_commitment, witness, &challenge),
+        })
+    }
+
+    /// `show` as a signature of knowledge on `message`: the opening proof answers a
+    /// Fiat-Shamir challenge over the presentation and the message's hash, so the
+    /// presentation doesn't verify for any other message
+    pub fn show_and_sign(
+        &self,
+        message: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<SignedPresentation<E>, CredentialError> {
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+        let bases = self.show_key().get_bases().0;
+        let (schnorr_state, announcement) = SchnorrProtocol::commit(&bases, rng);
+
+        let mut presentation = Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof: Vec::new(),
+            nonce: None,
+        };
+        let message_hash = signed_message_hash(&self.domain, message);
+        let challenge =
+            signed_show_challenge(&self.domain, &presentation, &message_hash, &announcement.0);
+        presentation.proof = self.opening_proof_for_challenge(
+            &shown,
+            bases,
+            schnorr_state,
+            announcement.0,
+            challenge,
+        )?;
+        Ok(SignedPresentation {
+            presentation,
+            message_hash,
+        })
+    }
+
+    /// `show` with a proof that the attribute at `handle_index` is accumulated in
+    /// `witness.value`, i.e. not revoked. The opening proof's blinding for that attribute
+    /// is reused in the membership proof and both answer one challenge
+    pub fn show_with_revocation(
+        &self,
+        handle_index: usize,
+        witness: &MembershipWitness<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<RevocationPresentation<E>, CredentialError> {
+        let handle = *self
+            .messages
+            .get(handle_index)
+            .ok_or(RevocationError::InvalidHandleIndex(handle_index))?;
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+
+        let bases = self.show_key().get_bases().0;
+        let blindings: Vec<E::ScalarField> = (0..bases.len())
+            .map(|_| E::ScalarField::rand(rng))
+            .collect();
+        let (schnorr_state, schnorr_commitment) =
+            SchnorrProtocol::commit_with_prepared_blindings(&bases, &blindings);
+
+        let (mut proof, rho, t_rho) = AttributeSignatureProof::commit(
+            &witness.witness,
+            &witness.value,
+            &handle,
+            &blindings[self.ck.attribute_position(handle_index)],
+            rng,
+        );
+
+        let mut presentation = Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof: Vec::new(),
+            nonce: None,
+        };
+        let challenge = revocation_show_challenge(
+            &self.domain,
+            &presentation,
+            &witness.value,
+            handle_index,
+            &proof.witness,
+            &proof.witness_bar,
+            &schnorr_commitment.0,
+            &proof.announcement,
+        );
+        presentation.proof = self.opening_proof_for_challenge(
+            &shown,
+            bases,
+            schnorr_state,
+            schnorr_commitment.0,
+            challenge,
+        )?;
+        proof.response = t_rho + challenge * rho;
+
+        Ok(RevocationPresentation {
+            presentation,
+            handle_index,
+            proof,
+        })
+    }
+
+    /// `show` with a proof that the attribute at `handle_index` is not accumulated in
+    /// `witness.value`, the issuer's universal accumulator over revoked handles. As in
+    /// `show_with_revocation`, the opening proof's blinding for that attribute is reused
+    /// and both proofs answer one challenge
+    pub fn prove_non_membership(
+        &self,
+        handle_index: usize,
+        public_key: &UniversalAccumulatorPublicKey<E>,
+        witness: &NonMembershipWitness<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<NonMembershipPresentation<E>, CredentialError> {
+        let handle = *self
+            .messages
+            .get(handle_index)
+            .ok_or(RevocationError::InvalidHandleIndex(handle_index))?;
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+
+        let bases = self.show_key().get_bases().0;
+        let blindings: Vec<E::ScalarField> = (0..bases.len())
+            .map(|_| E::ScalarField::rand(rng))
+            .collect();
+        let (schnorr_state, schnorr_commitment) =
+            SchnorrProtocol::commit_with_prepared_blindings(&bases, &blindings);
+
+        let (mut proof, state) = NonMembershipProof::commit(
+            public_key,
+            witness,
+            &handle,
+            &blindings[self.ck.attribute_position(handle_index)],
+            rng,
+        )?;
+
+        let mut presentation = Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof: Vec::new(),
+            nonce: None,
+        };
+        let challenge = non_membership_show_challenge(
+            &self.domain,
+            &presentation,
+            &witness.value,
+            handle_index,
+            &proof,
+            &schnorr_commitment.0,
+        );
+        presentation.proof = self.opening_proof_for_challenge(
+            &shown,
+            bases,
+            schnorr_state,
+            schnorr_commitment.0,
+            challenge,
+        )?;
+        proof.respond(state, &challenge);
+
+        Ok(NonMembershipPresentation {
+            presentation,
+            handle_index,
+            proof,
+        })
+    }
+
+    /// Show the credential with a proof that the attribute at `attr_index` is one of
+    /// the values signed in `params`. Fails with `NotInSet` for any other value
+    pub fn show_with_membership(
+        &self,
+        attr_index: usize,
+        params: &SetParams<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<SetMembershipPresentation<E>, CredentialError> {
+        let value = self
+            .messages
+            .get(attr_index)
+            .ok_or(SetMembershipError::InvalidAttributeIndex(attr_index))?;
+        let signature = params
+            .signature(value)
+            .ok_or(SetMembershipError::NotInSet)?;
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+
+        let bases = self.show_key().get_bases().0;
+        let blindings: Vec<E::ScalarField> = (0..bases.len())
+            .map(|_| E::ScalarField::rand(rng))
+            .collect();
+        let (schnorr_state, schnorr_commitment) =
+            SchnorrProtocol::commit_with_prepared_blindings(&bases, &blindings);
+        let (mut proof, rho, t_rho) = AttributeSignatureProof::commit(
+            signature,
+            &params.base,
+            value,
+            &blindings[self.ck.attribute_position(attr_index)],
+            rng,
+        );
+
+        let mut presentation = Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof: Vec::new(),
+            nonce: None,
+        };
+        let challenge = set_membership_show_challenge(
+            &self.domain,
+            &presentation,
+            params,
+            attr_index,
+            &proof.witness,
+            &proof.witness_bar,
+            &schnorr_commitment.0,
+            &proof.announcement,
+        );
+        presentation.proof = self.opening_proof_for_challenge(
+            &shown,
+            bases,
+            schnorr_state,
+            schnorr_commitment.0,
+            challenge,
+        )?;
+        proof.response = t_rho + challenge * rho;
+
+        Ok(SetMembershipPresentation {
+            presentation,
+            attribute_index: attr_index,
+            proof,
+        })
+    }
+
+    /// Answer a verifier's `policy`: disclose the attributes it names, prove each set
+    /// requirement and bind everything to the policy's nonce and context
+    pub fn present(
+        &self,
+        policy: &PresentationPolicy<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<PolicyPresentation<E>, CredentialError> {
+        if self.domain != policy.domain {
+            return Err(PolicyError::DomainMismatch.into());
+        }
+        let indices = policy.disclosed_indices();
+        let checked = indices
+            .iter()
+            .chain(policy.membership.iter().map(|(index, _)| index));
+        for index in checked {
+            if *index >= self.messages.len() {
+                return Err(PolicyError::InvalidAttributeIndex(*index).into());
+            }
+        }
+        let signatures = policy
+            .membership
+            .iter()
+            .map(|(index, params)| {
+                params
+                    .signature(&self.messages[*index])
+                    .ok_or(SetMembershipError::NotInSet)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+
+        // Disclosed attributes get a zero blinding, so their responses are c m
+        let bases = self.show_key().get_bases().0;
+        let mut blindings: Vec<E::ScalarField> = (0..bases.len())
+            .map(|_| E::ScalarField::rand(rng))
+            .collect();
+        for index in &indices {
+            blindings[self.ck.attribute_position(*index)] = E::ScalarField::zero();
+        }
+        let (schnorr_state, schnorr_commitment) =
+            SchnorrProtocol::commit_with_prepared_blindings(&bases, &blindings);
+        let mut membership = Vec::with_capacity(signatures.len());
+        let mut randomizers = Vec::with_capacity(signatures.len());
+        for ((index, params), signature) in policy.membership.iter().zip(signatures) {
+            let (proof, rho, t_rho) = AttributeSignatureProof::commit(
+                signature,
+                &params.base,
+                &self.messages[*index],
+                &blindings[self.ck.attribute_position(*index)],
+                rng,
+            );
+            membership.push(proof);
+            randomizers.push((rho, t_rho));
+        }
+
+        let mut presentation = Presentation {
+            signature: randomized_sig,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof: Vec::new(),
+            nonce: None,
+        };
+        let disclosed: Vec<(usize, E::ScalarField)> = indices
+            .iter()
+            .map(|index| (*index, self.messages[*index]))
+            .collect();
+        let challenge = policy_show_challenge(
+            policy,
+            &presentation,
+            &disclosed,
+            &membership,
+            &schnorr_commitment.0,
+        );
+        presentation.proof = self.opening_proof_for_challenge(
+            &shown,
+            bases,
+            schnorr_state,
+            schnorr_commitment.0,
+            challenge,
+        )?;
+        for (proof, (rho, t_rho)) in membership.iter_mut().zip(randomizers) {
+            proof.response = t_rho + challenge * rho;
+        }
+
+        Ok(PolicyPresentation {
+            presentation,
+            disclosed,
+            membership,
+        })
+    }
+
+    /// Prove possession of a signature on the committed attributes without revealing
+    /// it, bound to `challenge_bytes` such as a login nonce. See `PossessionProof`
+    pub fn prove_possession(
+        &self,
+        challenge_bytes: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<PossessionProof<E>, CredentialError> {
+        let (randomized_sig, shown) = self.randomize_for_show(rng)?;
+
+        // Announcements of the opening proof and of the proof for sigma
+        let bases = self.show_key().get_bases().0;
+        let (schnorr_state, schnorr_commitment) = SchnorrProtocol::commit(&bases, rng);
+        let t = E::G1::rand(rng);
+        let announcement = E::pairing(t, self.ck.g_tilde).0;
+
+        let challenge = possession_challenge::<E>(
+            &self.domain,
+            challenge_bytes,
+            &randomized_sig.h,
+            &shown.cm,
+            &shown.cm_tilde,
+            &schnorr_commitment.0,
+            &announcement,
+        );
+        let proof = self.opening_proof_for_challenge(
+            &shown,
+            bases,
+            schnorr_state,
+            schnorr_commitment.0,
+            challenge,
+        )?;
+
+        Ok(PossessionProof {
+            h: randomized_sig.h,
+            commitment: shown.cm,
+            commitment_tilde: shown.cm_tilde,
+            proof,
+            announcement,
+            response: (t + randomized_sig.sigma.mul(challenge)).into_affine(),
+        })
+    }
+
+    /// `show` returning the presentation as a tuple
+    #[deprecated(note = "use `show`, which returns a `Presentation`")]
+    #[allow(deprecated)]
+    pub fn show_tuple(
+        &self,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<ShowOutput<E>, CredentialError> {
+        self.show(rng)
+            .map(|p| (p.signature, p.commitment, p.commitment_tilde, p.proof))
+    }
+
+    /// Attach application metadata, a `String` is stored as the note
+    pub fn with_metadata(mut self, metadata: impl Into<CredentialMetadata>) -> Self {
+        self.metadata = metadata.into();
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::SignatureError;
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_display_redacts_attributes() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let shown = credential.to_string();
+        assert!(shown.starts_with("Credential(state=Signed, attributes=3, context=0x"));
+
+        let hex = |m: &Fr| {
+            let mut bytes = Vec::new();
+            m.serialize_compressed(&mut bytes).unwrap();
+            bytes.iter().map(|b| format!("{b:02x}")).collect::<String>()
+        };
+        for secret in credential
+            .get_messages()
+            .iter()
+            .chain(&credential.blindings)
+        {
+            assert!(!shown.contains(&secret.to_string()));
+            assert!(!shown.contains(&hex(secret)));
+            assert!(!shown.contains(&hex(secret)[..8]));
+        }
+
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        let signature = presentation.signature.to_string();
+        assert!(signature.starts_with("ThresholdSignature(h=0x"));
+        assert!(signature.len() < 64);
+    }
+
+    #[test]
+    fn test_credential_getters() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(4)
+            .build(&mut rng)
+            .unwrap();
+        let user = system.user();
+        let (mut credential, request) = user.request_credential(None, &mut rng).unwrap();
+        assert_eq!(credential.num_attributes(), 4);
+        assert!(!credential.is_signed());
+        assert_eq!(credential.key_id(), crate::wallet::key_id(&system.ck));
+
+        let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+        user.complete_credential(&mut credential, &request, &shares, &mut rng)
+            .unwrap();
+        assert!(credential.is_signed());
+
+        let other = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(4)
+            .build(&mut rng)
+            .unwrap();
+        let other_credential = other.user().obtain_credential(None, &mut rng).unwrap();
+        assert_ne!(other_credential.key_id(), credential.key_id());
+    }
+
+    #[test]
+    fn test_attribute_commitments_by_index() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let (_, request) = system.user().request_credential(None, &mut rng).unwrap();
+
+        let attributes: Vec<_> = request.attributes().collect();
+        assert_eq!(attributes.len(), 3);
+        for (k, commitment, proof) in attributes {
+            assert_eq!(request.attribute(k), Some((commitment, proof)));
+            assert_eq!(*commitment, request.commitments[k]);
+            assert!(Commitment::<Bls12_381>::verify(proof).unwrap());
+        }
+        assert!(request.attribute(3).is_none());
+    }
+
+    #[test]
+    fn test_metadata_survives_serialization() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let metadata = CredentialMetadata {
+            schema_id: Some("https://example.org/schemas/member/v2".to_string()),
+            issued_at: Some(1_700_000_000),
+            tags: vec!["member".to_string(), "eu".to_string()],
+            note: None,
+        };
+        let credential = system
+            .user()
+            .obtain_credential(None, &mut rng)
+            .unwrap()
+            .with_metadata(metadata.clone());
+
+        let mut bytes = Vec::new();
+        credential.serialize_compressed(&mut bytes).unwrap();
+        let loaded = Credential::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(loaded.metadata, metadata);
+        let presentation = system.user().show(&loaded, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // A plain string is kept as the note
+        let noted = loaded.with_metadata("bench run 3".to_string());
+        assert_eq!(noted.metadata.note.as_deref(), Some("bench run 3"));
+        assert!(noted.metadata.tags.is_empty());
+    }
+
+    #[test]
+    fn test_non_identity_redraws() {
+        let mut rng = test_rng();
+        let point = G1Affine::rand(&mut rng);
+        let mut draws = vec![point, G1Affine::zero()];
+        assert_eq!(non_identity(|| draws.pop().unwrap()), point);
+        assert!(draws.is_empty());
+    }
+
+    #[test]
+    fn test_set_attributes_keeps_invariants() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let mut credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        assert!(credential.check_invariants());
+
+        // New attributes invalidate the signature instead of leaving a stale commitment
+        let attributes: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        credential.set_attributes(attributes.clone());
+        assert!(credential.check_invariants());
+        assert_eq!(credential.state, CredentialState::Initialized);
+        assert!(credential.cm.is_none());
+        assert!(matches!(
+            credential.show(&mut rng),
+            Err(CredentialError::InvalidState(_))
+        ));
+
+        // A commitment to other messages is caught
+        let other: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        credential.cm = Some(SymmetricCommitment::new(&system.ck, &other, &Fr::zero()));
+        assert!(!credential.check_invariants());
+    }
+
+    #[test]
+    fn test_symmetric_commitment_deferred_to_signing() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let user = system.user();
+        let (mut credential, request) = user.request_credential(None, &mut rng).unwrap();
+        // The request path never needs the symmetric commitment
+        assert!(credential.cm.is_none());
+        assert!(credential.check_invariants());
+
+        let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+        user.complete_credential(&mut credential, &request, &shares, &mut rng)
+            .unwrap();
+        let cm = credential.cm.as_ref().unwrap();
+        assert_eq!(
+            cm.cm,
+            g1_commit(
+                &system.vk.show_key(&system.ck),
+                credential.get_messages(),
+                &cm.r
+            )
+        );
+        assert!(credential.check_invariants());
+        let presentation = credential.show(&mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // Changing the attributes of a signed credential leaves nothing to show
+        let (sig, blindings) = (
+            credential.sig.clone().unwrap(),
+            credential.blindings.clone(),
+        );
+        let reversed = credential.get_messages().iter().rev().copied().collect();
+        credential.set_attributes(reversed);
+        assert!(credential.cm.is_none());
+        assert!(matches!(
+            credential.show(&mut rng),
+            Err(CredentialError::InvalidState(_))
+        ));
+        // A signature attached over other attributes doesn't verify
+        credential.blindings = blindings;
+        credential.attach_signature(sig, &system.vk);
+        assert!(credential.check_invariants());
+        let presentation = credential.show(&mut rng).unwrap();
+        assert!(!matches!(
+            system.verifier().verify(&presentation, &mut rng),
+            Ok(true)
+        ));
+    }
+
+    #[test]
+    fn test_commitment_proofs_deterministic_for_seed() {
+        let ck = System::<Bls12_381>::builder()
+            .threshold(1)
+            .signers(1)
+            .attributes(4)
+            .build(&mut test_rng())
+            .unwrap()
+            .ck;
+        let request_for_seed = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut credential = Credential::new(ck.clone(), None, &mut rng).unwrap();
+            credential.compute_commitments_per_m(&mut rng).unwrap()
+        };
+
+        // Same seed, same proofs, however the proofs are spread across threads
+        let first = request_for_seed(7);
+        let second = request_for_seed(7);
+        assert_eq!(first.commitments, second.commitments);
+        assert_eq!(first.proofs, second.proofs);
+        assert_ne!(first.proofs, request_for_seed(8).proofs);
+    }
+
+    #[test]
+    fn test_show_carries_no_attribute_material() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(4)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let presentation = credential.show(&mut StdRng::seed_from_u64(3)).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // Same proof as randomizing and proving the full symmetric commitment
+        let mut reference_rng = StdRng::seed_from_u64(3);
+        let (_, r_delta) = credential
+            .sig
+            .as_ref()
+            .unwrap()
+            .randomize(&mut reference_rng)
+            .unwrap();
+        let randomized = credential.cm.as_ref().unwrap().randomize(&r_delta).unwrap();
+        assert_eq!(
+            (presentation.commitment, presentation.commitment_tilde),
+            (randomized.cm, randomized.cm_tilde())
+        );
+        assert_eq!(
+            presentation.proof,
+            randomized.clone().prove(&mut reference_rng).unwrap()
+        );
+
+        // Points, the proof, whose scalars are the challenge and responses, and the tag
+        // of the absent nonce only
+        assert_eq!(presentation.nonce, None);
+        assert_eq!(
+            presentation.compressed_size(),
+            presentation.signature.compressed_size()
+                + presentation.commitment.compressed_size()
+                + presentation.commitment_tilde.compressed_size()
+                + presentation.proof.compressed_size()
+                + presentation.nonce.compressed_size()
+        );
+        let proof =
+            CommitmentProof::<Bls12_381>::deserialize_compressed(&presentation.proof[..]).unwrap();
+        assert_eq!(proof.responses.len(), credential.get_messages().len() + 1);
+        for secret in credential.get_messages().iter().chain([&randomized.r]) {
+            assert!(proof.responses.iter().all(|z| z != secret));
+            assert_ne!(&proof.challenge, secret);
+        }
+    }
+
+    #[test]
+    fn test_prove_possession() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let mut credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let domain = credential.domain().clone();
+        let nonce = b"login nonce 42";
+
+        let proof = credential.prove_possession(nonce, &mut rng).unwrap();
+        assert!(proof
+            .verify(&system.ck, &system.vk, &domain, nonce, &mut rng)
+            .unwrap());
+        // sigma itself is never sent
+        let sigma = credential.sig.as_ref().unwrap().sigma;
+        assert_ne!(proof.response, sigma);
+
+        // The proof is bound to the challenge bytes
+        assert!(matches!(
+            proof.verify(&system.ck, &system.vk, &domain, b"other nonce", &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::ProofVerificationFailed
+            ))
+        ));
+
+        // Without a valid signature the holder can't answer the challenge
+        let sig = credential.sig.clone().unwrap();
+        credential.attach_signature(
+            ThresholdSignature {
+                h: sig.h,
+                sigma: G1Affine::rand(&mut rng),
+            },
+            &system.vk,
+        );
+        let proof = credential.prove_possession(nonce, &mut rng).unwrap();
+        assert!(matches!(
+            proof.verify(&system.ck, &system.vk, &domain, nonce, &mut rng),
+            Err(SignatureError::SignatureVerificationFailed)
+        ));
+    }
+}
@@ -0,0 +1,220 @@
+//! Constant-time helpers for secret scalars, and the audit of the secret paths.
+//!
+//! Reviewed functions and their status:
+//!
+//! - `Signer::sign_share`: the only branch is on the public attribute count. The
+//!   multiplications by `x_i` and `y_k_i` go through arkworks' group scalar
+//!   multiplication, which is not constant-time.
+//! - `SchnorrProtocol::prove`: field additions and multiplications only, no branches.
+//! - `ThresholdSignature::randomize_with_factors`: the group scalar multiplications by
+//!   the randomizers carry the arkworks caveat above. The zero `u_delta` rejection
+//!   branches on `ct_is_zero`, after the randomized signature is computed.
+//! - `DYPFPrivVRF::evaluate`: `1/(sk + x)` used to take the early-exit extended Euclid
+//!   inverse, it now always runs `ct_inverse`. Only the public failure for `sk + x = 0`
+//!   branches, after the inverse is computed.
+//! - `PairingCheck::rand` coefficients (`rand_batching_scalar`): the rejection loop for
+//!   a zero draw is replaced by `ct_select`. The coefficient is verifier randomness, but
+//!   it scales every pairing of the check.
+//! - `ThresholdSignature::randomize`: `u_delta` and `r_delta` come from `rand_nonzero`
+//!   as well, so they are never zero and their sampling doesn't loop.
+//! - `PairingCheck::merge` (`mul_if_not_one`): the comparisons of Miller loop outputs
+//!   with one are gone, both sides are always multiplied.
+//! - `PairingCheck::combine_all`: draws its coefficients with `rand_batching_scalar`
+//!   like `rand`, so the zero check in `merge_randomized` never fires on them.
+//! - `Accumulator::add`, `witness` and `revoke`: `1/(alpha + h)` for the accumulator
+//!   secret `alpha` runs `ct_inverse`, the failure for `alpha + h = 0` branches after it.
+//! - `SetParams::new`: `1/(x + v)` for the set key's secret `x` runs `ct_inverse` too,
+//!   with the same late branch for `x + v = 0`.
+//! - `compute_lagrange_coefficient`: branches and inverts only on the public signer
+//!   indices, left as is.
+//!
+//! Field arithmetic in arkworks runs in constant time for a given field, group scalar
+//! multiplication and `Field::inverse` do not. Nothing here makes the crate constant-time
+//! end to end; it only removes the branches this crate itself takes on secret values.
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_std::rand::Rng;
+use subtle::{Choice, ConstantTimeEq};
+
+/// Whether `x` is zero, compared over its canonical encoding without early exit
+pub(crate) fn ct_is_zero<F: PrimeField>(x: &F) -> Choice {
+    let bytes = x.into_bigint().to_bytes_le();
+    bytes.ct_eq(&vec![0u8; bytes.len()])
+}
+
+/// `a` if `choice` is unset and `b` if it is set, with the same operations either way
+pub(crate) fn ct_select<F: Field>(a: &F, b: &F, choice: Choice) -> F {
+    *a + (*b - *a) * F::from(choice.unwrap_u8() as u64)
+}
+
+/// `x^(p - 2)` through a fixed public exponent, which is `1/x` for nonzero `x`. The
+/// returned choice is set when `x` is zero and the result is then zero too
+pub(crate) fn ct_inverse<F: PrimeField>(x: &F) -> (F, Choice) {
+    let mut exponent = F::MODULUS;
+    exponent.sub_with_borrow(&F::BigInt::from(2u64));
+    (x.pow(exponent), ct_is_zero(x))
+}
+
+/// A nonzero scalar. A zero draw becomes one instead of being redrawn, so the time
+/// taken doesn't depend on the value and a single draw is always enough
+pub(crate) fn rand_nonzero<F: PrimeField, R: Rng>(rng: &mut R) -> F {
+    let x = F::rand(rng);
+    ct_select(&x, &F::one(), ct_is_zero(&x))
+}
+
+/// A nonzero coefficient of at most 128 bits for a random linear combination in batch
+/// verification. A batch with a false statement passes with probability about
+/// `2^{-128}`, the same as with a full width scalar, while the scalar multiplications and
+/// exponentiations by it take half as long. It is a batching randomizer only and too
+/// short to blind or hide anything, secrets and blindings use `F::rand` or `rand_nonzero`
+pub(crate) fn rand_batching_scalar<F: PrimeField, R: Rng>(rng: &mut R) -> F {
+    let x = F::from(rng.gen::<u128>());
+    ct_select(&x, &F::one(), ct_is_zero(&x))
+}
+
+#[cfg(test)]
+mod tests {
+    //! Each replacement checked against the branching code it replaced
+    use super::*;
+    use crate::nullifier::{DYPFPrivVRF, DYPFPrivVRFWitness};
+    use crate::pairing::PairingCheck;
+    use crate::schnorr::SchnorrProtocol;
+    use crate::signature::ThresholdSignature;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fq12, Fr, G1Affine, G1Projective};
+    use ark_ec::pairing::Pairing;
+    use ark_ec::CurveGroup;
+    use ark_std::{One, UniformRand, Zero};
+
+    #[test]
+    fn test_ct_helpers_match_branching_versions() {
+        let mut rng = test_rng();
+        for x in (0..16)
+            .map(|_| Fr::rand(&mut rng))
+            .chain([Fr::zero(), Fr::one()])
+        {
+            assert_eq!(bool::from(ct_is_zero(&x)), x.is_zero());
+            let (inverse, is_zero) = ct_inverse(&x);
+            assert_eq!(bool::from(is_zero), x.is_zero());
+            assert_eq!(inverse, x.inverse().unwrap_or_else(Fr::zero));
+
+            // rand_nonzero: a zero draw used to be redrawn, it is now mapped to one
+            let coeff = ct_select(&x, &Fr::one(), ct_is_zero(&x));
+            assert!(!coeff.is_zero());
+            if !x.is_zero() {
+                assert_eq!(coeff, x);
+            }
+        }
+    }
+
+    /// Always yields zero bytes, so every `Fr::rand` draw is zero
+    struct ZeroRng;
+
+    impl ark_std::rand::RngCore for ZeroRng {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            0
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), ark_std::rand::Error> {
+            dest.fill(0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rand_nonzero_terminates_on_zero_draws() {
+        assert!(Fr::rand(&mut ZeroRng).is_zero());
+        assert_eq!(rand_nonzero::<Fr, _>(&mut ZeroRng), Fr::one());
+
+        let mut rng = test_rng();
+        assert!((0..64).all(|_| !rand_nonzero::<Fr, _>(&mut rng).is_zero()));
+    }
+
+    #[test]
+    fn test_batching_scalar_is_short_and_nonzero() {
+        assert_eq!(rand_batching_scalar::<Fr, _>(&mut ZeroRng), Fr::one());
+
+        let mut rng = test_rng();
+        for _ in 0..64 {
+            let x = rand_batching_scalar::<Fr, _>(&mut rng);
+            assert!(!x.is_zero());
+            assert!(x.into_bigint().num_bits() <= 128);
+        }
+    }
+
+    #[test]
+    fn test_merge_matches_mul_if_not_one() {
+        let mut rng = test_rng();
+        let g1 = G1Projective::rand(&mut rng).into_affine();
+        let g2 = <Bls12_381 as Pairing>::G2::rand(&mut rng).into_affine();
+        let out = Bls12_381::pairing(g1, g2).0;
+        let valid = PairingCheck::<Bls12_381>::rand(&mut rng, &[(&g1, &g2)], &out);
+        let wrong = PairingCheck::<Bls12_381>::rand(&mut rng, &[(&g1, &g2)], &Fq12::one());
+
+        // Merging always multiplies, which agrees with skipping a factor of one
+        for (first, second, expected) in [
+            (PairingCheck::new(), valid, true),
+            (valid, PairingCheck::new(), true),
+            (valid, valid, true),
+            (PairingCheck::new(), wrong, false),
+            (valid, wrong, false),
+        ] {
+            let mut merged = first;
+            merged.merge(&second);
+            assert_eq!(merged.verify(), expected);
+        }
+    }
+
+    #[test]
+    fn test_secret_paths_unchanged() {
+        let mut rng = test_rng();
+
+        // DYPFPrivVRF::evaluate: y = g^(1/(sk + x)) as with the branching inverse
+        let vrf = DYPFPrivVRF::<G1Affine>::new(&mut rng);
+        let (sk, _) = vrf.generate_keys(&mut rng);
+        let x = Fr::rand(&mut rng);
+        let (input, _) = vrf.commit_to_input(&x, &mut rng);
+        let witness = DYPFPrivVRFWitness {
+            sk: sk.sk,
+            r_sk: sk.r_sk,
+            x: input.x,
+            r_x: input.r_x,
+        };
+        let expected = (vrf.pp.g * (sk.sk + x).inverse().unwrap()).into_affine();
+        assert_eq!(vrf.evaluate(&witness).unwrap().y, expected);
+        let zero_witness = DYPFPrivVRFWitness {
+            x: -sk.sk,
+            ..witness
+        };
+        assert!(vrf.evaluate(&zero_witness).is_err());
+
+        // SchnorrProtocol::prove: z_i = t_i + e * m_i
+        let bases: Vec<G1Affine> = (0..3)
+            .map(|_| G1Projective::rand(&mut rng).into_affine())
+            .collect();
+        let witnesses: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let blindings: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let (state, _) = SchnorrProtocol::commit_with_prepared_blindings(&bases, &blindings);
+        let challenge = Fr::rand(&mut rng);
+        let responses = SchnorrProtocol::prove(state, &witnesses, &challenge);
+        for ((z, t), m) in responses.0.iter().zip(&blindings).zip(&witnesses) {
+            assert_eq!(*z, *t + challenge * m);
+        }
+
+        // ThresholdSignature::randomize_with_factors: (h^u, (sigma h^r)^u)
+        let h = G1Projective::rand(&mut rng).into_affine();
+        let sigma = G1Projective::rand(&mut rng).into_affine();
+        let signature = ThresholdSignature::<Bls12_381> { h, sigma };
+        let (u, r) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+        let randomized = signature.randomize_with_factors(&u, &r).unwrap();
+        assert_eq!(randomized.h, (h * u).into_affine());
+        assert_eq!(randomized.sigma, ((h * r + sigma) * u).into_affine());
+    }
+}
@@ -0,0 +1,24 @@
+//! Helpers for the `Display` implementations, which print short fingerprints of public
+//! values so logs can tell objects apart without dumping coordinates.
+use ark_serialize::CanonicalSerialize;
+use ark_std::fmt;
+
+/// Number of bytes of the compressed encoding shown in a fingerprint
+const PREFIX_BYTES: usize = 4;
+
+/// The first bytes of the compressed encoding of `value` in hex, e.g. `0x8a3f12ab…`
+pub(crate) struct HexPrefix<'a, T: CanonicalSerialize>(pub(crate) &'a T);
+
+impl<T: CanonicalSerialize> fmt::Display for HexPrefix<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bytes = Vec::with_capacity(self.0.compressed_size());
+        self.0
+            .serialize_compressed(&mut bytes)
+            .map_err(|_| fmt::Error)?;
+        write!(f, "0x")?;
+        for byte in bytes.iter().take(PREFIX_BYTES) {
+            write!(f, "{byte:02x}")?;
+        }
+        write!(f, "…")
+    }
+}
@@ -0,0 +1,428 @@
+//! Self-describing presentation documents for verifiers that don't speak arkworks.
+//!
+//! A `PresentationDocument` lays a `Presentation` out field by field: group and field
+//! elements are base64url (no padding) of their compressed encoding, the opening proof
+//! is split into its announcement, challenge and responses and the bases are left out,
+//! the verifier takes them from the issuer's show key. The document derives serde, so the
+//! same value goes to JSON, CBOR or any other serde format.
+//!
+//! The presentation must come from `show_disclosing` for the envelope's nonce and
+//! disclosed indices, under the domain named by its context. Verifying the document
+//! checks every disclosed value against the opening proof.
+//!
+//! ```ignore
+//! let (presentation, disclosed) = credential.show_disclosing(nonce, &[1], &mut rng)?;
+//! let document = presentation.to_document(&envelope)?;
+//! let json = serde_json::to_string(&document)?;
+//! let (presentation, envelope) = Presentation::from_document(&serde_json::from_str(&json)?, &ck, &vk)?;
+//! ```
+use crate::commitment::CommitmentProof;
+use crate::errors::MessageError;
+use crate::keygen::VerificationKey;
+use crate::messages::validate_point;
+use crate::presentation::Presentation;
+use crate::signature::ThresholdSignature;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::DomainSeparator;
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+/// Version written by `to_document`, the only one `from_document` accepts
+pub const DOCUMENT_VERSION: u8 = 1;
+
+/// A presentation and its envelope as base64url strings, see the module docs
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresentationDocument {
+    pub version: u8,
+    pub key_id: String,
+    pub context: String,
+    pub nonce: String,
+    pub sigma: String,
+    pub h: String,
+    pub cm: String,
+    pub cm_tilde: String,
+    pub proof: ProofDocument,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disclosed: Vec<DisclosedAttribute>,
+}
+
+/// The opening proof of `cm` without its bases
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProofDocument {
+    pub announcement: String,
+    pub challenge: String,
+    /// One response per base of the commitment key, blinding base included
+    pub responses: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DisclosedAttribute {
+    pub index: usize,
+    pub value: String,
+}
+
+/// What a document carries besides the presentation: the committee key it's for, the
+/// holder's domain, the verifier's nonce and the disclosed `(index, attribute)` pairs,
+/// all of which the presentation must be shown for with `show_disclosing`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PresentationEnvelope<E: Pairing> {
+    pub key_id: Vec<u8>,
+    /// Bytes of the `DomainSeparator` the holder's proofs are made under
+    pub context: Vec<u8>,
+    /// Becomes the decoded presentation's `nonce`
+    pub nonce: [u8; 32],
+    /// Strictly increasing by index
+    pub disclosed: Vec<(usize, E::ScalarField)>,
+}
+
+impl<E: Pairing> PresentationEnvelope<E> {
+    /// The domain named by `context`
+    pub fn domain(&self) -> DomainSeparator {
+        DomainSeparator::new(self.context.clone())
+    }
+}
+
+fn encode<T: CanonicalSerialize>(value: &T) -> Result<String, MessageError> {
+    let mut bytes = Vec::with_capacity(value.compressed_size());
+    value.serialize_compressed(&mut bytes)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn decode_bytes(field: &'static str, encoded: &str) -> Result<Vec<u8>, MessageError> {
+    URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|_| MessageError::InvalidEncoding(field))
+}
+
+/// Decode a value that must take up all of `encoded`
+fn decode<T: CanonicalDeserialize>(field: &'static str, encoded: &str) -> Result<T, MessageError> {
+    let bytes = decode_bytes(field, encoded)?;
+    let mut reader = &bytes[..];
+    // Deserializing rejects non-canonical scalars and points off the subgroup
+    let value = T::deserialize_compressed(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(MessageError::InvalidLength {
+            field,
+            expected: bytes.len() - reader.len(),
+            got: bytes.len(),
+        });
+    }
+    Ok(value)
+}
+
+fn decode_point<G: AffineRepr>(field: &'static str, encoded: &str) -> Result<G, MessageError> {
+    let point: G = decode(field, encoded)?;
+    validate_point(&point, field)?;
+    Ok(point)
+}
+
+impl<E: Pairing> Presentation<E> {
+    /// This presentation in `envelope` as a `PresentationDocument`. Fails with
+    /// `NonceMismatch` unless the presentation was shown for the envelope's nonce
+    pub fn to_document(
+        &self,
+        envelope: &PresentationEnvelope<E>,
+    ) -> Result<PresentationDocument, MessageError> {
+        if self.nonce != Some(envelope.nonce) {
+            return Err(MessageError::NonceMismatch);
+        }
+        let proof = CommitmentProof::<E>::deserialize_compressed(&self.proof[..])?;
+        Ok(PresentationDocument {
+            version: DOCUMENT_VERSION,
+            key_id: URL_SAFE_NO_PAD.encode(&envelope.key_id),
+            context: URL_SAFE_NO_PAD.encode(&envelope.context),
+            nonce: URL_SAFE_NO_PAD.encode(envelope.nonce),
+            sigma: encode(&self.signature.sigma)?,
+            h: encode(&self.signature.h)?,
+            cm: encode(&self.commitment)?,
+            cm_tilde: encode(&self.commitment_tilde)?,
+            proof: ProofDocument {
+                announcement: encode(&proof.schnorr_commitment)?,
+                challenge: encode(&proof.challenge)?,
+                responses: proof
+                    .responses
+                    .iter()
+                    .map(encode)
+                    .collect::<Result<_, _>>()?,
+            },
+            disclosed: envelope
+                .disclosed
+                .iter()
+                .map(|(index, value)| {
+                    Ok(DisclosedAttribute {
+                        index: *index,
+                        value: encode(value)?,
+                    })
+                })
+                .collect::<Result<_, MessageError>>()?,
+        })
+    }
+
+    /// Decode and validate `document` for a verifier holding `ck` and `vk`: the version,
+    /// every encoding, every point, the number of responses against the bases of
+    /// `vk.show_key(ck)` and the order and range of the disclosed indices. The proof, the disclosed attributes and
+    /// the signature are checked by `verify_disclosed` under the envelope's domain, nonce
+    /// and disclosures, which `VerifierProtocol::verify` does for a document
+    pub fn from_document(
+        document: &PresentationDocument,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+    ) -> Result<(Self, PresentationEnvelope<E>), MessageError> {
+        if document.version != DOCUMENT_VERSION {
+            return Err(MessageError::UnsupportedVersion(document.version));
+        }
+
+        let nonce = decode_bytes("nonce", &document.nonce)?;
+        let nonce: [u8; 32] =
+            nonce
+                .as_slice()
+                .try_into()
+                .map_err(|_| MessageError::InvalidLength {
+                    field: "nonce",
+                    expected: 32,
+                    got: nonce.len(),
+                })?;
+
+        let attributes = ck.num_attributes();
+        let mut disclosed = Vec::with_capacity(document.disclosed.len());
+        for attribute in &document.disclosed {
+            if attribute.index >= attributes {
+                return Err(MessageError::DisclosedIndexOutOfRange {
+                    index: attribute.index,
+                    attributes,
+                });
+            }
+            disclosed.push((attribute.index, decode("disclosed", &attribute.value)?));
+        }
+        if disclosed.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(MessageError::UnorderedDisclosure);
+        }
+
+        let bases = vk.show_key(ck).get_bases().0;
+        if document.proof.responses.len() != bases.len() {
+            return Err(MessageError::ResponseCountMismatch {
+                expected: bases.len(),
+                got: document.proof.responses.len(),
+            });
+        }
+        let commitment = decode_point("cm", &document.cm)?;
+        let proof = CommitmentProof::<E> {
+            commitment,
+            schnorr_commitment: decode_point("announcement", &document.proof.announcement)?,
+            bases,
+            challenge: decode("challenge", &document.proof.challenge)?,
+            responses: document
+                .proof
+                .responses
+                .iter()
+                .map(|response| decode("responses", response))
+                .collect::<Result<_, _>>()?,
+        };
+        let mut proof_bytes = Vec::with_capacity(proof.compressed_size());
+        proof.serialize_compressed(&mut proof_bytes)?;
+
+        let presentation = Presentation {
+            signature: ThresholdSignature {
+                h: decode_point("h", &document.h)?,
+                sigma: decode_point("sigma", &document.sigma)?,
+            },
+            commitment,
+            commitment_tilde: decode_point("cm_tilde", &document.cm_tilde)?,
+            proof: proof_bytes,
+            nonce: Some(nonce),
+        };
+        let envelope = PresentationEnvelope {
+            key_id: decode_bytes("key_id", &document.key_id)?,
+            context: decode_bytes("context", &document.context)?,
+            nonce,
+            disclosed,
+        };
+        Ok((presentation, envelope))
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::keygen::keygen;
+    use crate::protocol::VerifierProtocol;
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_std::rand::rngs::StdRng;
+
+    /// Stable test vector: attributes `[7, 42, 9]` issued under
+    /// `keygen(2, 5, 3, test_rng())`, shown in the `verifier` domain disclosing the second
+    const FIXTURE: &str = include_str!("../fixtures/presentation_document.json");
+
+    fn envelope() -> PresentationEnvelope<Bls12_381> {
+        PresentationEnvelope {
+            key_id: b"committee-1".to_vec(),
+            context: b"verifier".to_vec(),
+            nonce: [7u8; 32],
+            disclosed: vec![(1, Fr::from(42u64))],
+        }
+    }
+
+    fn shown_document() -> (System<Bls12_381>, PresentationDocument) {
+        let mut rng = test_rng();
+        let (ck, vk, ts_keys) = keygen::<Bls12_381>(2, 5, 3, &mut rng);
+        let system = System::from_key_material(ck, vk, ts_keys).unwrap();
+        let user = system.user();
+        let attributes = [7u64, 42, 9].map(Fr::from);
+        let (mut credential, request) = user
+            .request_credential(Some(&attributes), &mut rng)
+            .unwrap();
+        let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+        user.complete_credential(&mut credential, &request, &shares, &mut rng)
+            .unwrap();
+        let envelope = envelope();
+        let credential = credential.with_domain(envelope.domain());
+        let (presentation, disclosed) = credential
+            .show_disclosing(envelope.nonce, &[1], &mut rng)
+            .unwrap();
+        assert_eq!(disclosed, envelope.disclosed);
+        let document = presentation.to_document(&envelope).unwrap();
+        (system, document)
+    }
+
+    #[test]
+    fn test_document_round_trips_through_json_and_cbor() {
+        let mut rng = test_rng();
+        let (system, document) = shown_document();
+
+        let json = serde_json::to_string(&document).unwrap();
+        let from_json: PresentationDocument = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json, document);
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&document, &mut cbor).unwrap();
+        let from_cbor: PresentationDocument = ciborium::from_reader(&cbor[..]).unwrap();
+        assert_eq!(from_cbor, document);
+
+        let (presentation, decoded) =
+            Presentation::from_document(&from_cbor, &system.ck, &system.vk).unwrap();
+        assert_eq!(decoded, envelope());
+        assert_eq!(presentation.to_document(&decoded).unwrap(), document);
+        assert!(VerifierProtocol::verify(&system.ck, &system.vk, &presentation, &mut rng).unwrap());
+        assert!(VerifierProtocol::verify(&system.ck, &system.vk, &from_json, &mut rng).unwrap());
+    }
+
+    #[test]
+    fn test_fixture_is_stable() {
+        let mut rng = test_rng();
+        let (system, document) = shown_document();
+        let fixture: PresentationDocument = serde_json::from_str(FIXTURE).unwrap();
+        assert_eq!(fixture, document);
+        assert!(VerifierProtocol::verify(&system.ck, &system.vk, &fixture, &mut rng).unwrap());
+    }
+
+    #[test]
+    fn test_tampered_document_rejected() {
+        let mut rng = test_rng();
+        let (system, document) = shown_document();
+        let verify = |document: &PresentationDocument, rng: &mut StdRng| {
+            VerifierProtocol::verify(&system.ck, &system.vk, document, rng)
+        };
+        assert!(verify(&document, &mut rng).unwrap());
+
+        // Another value for the disclosed attribute
+        let mut bad = document.clone();
+        bad.disclosed[0].value = encode(&Fr::from(43u64)).unwrap();
+        assert!(verify(&bad, &mut rng).is_err());
+
+        // A disclosure the proof wasn't made for, or one left out
+        let mut bad = document.clone();
+        bad.disclosed.insert(
+            0,
+            DisclosedAttribute {
+                index: 0,
+                value: encode(&Fr::from(7u64)).unwrap(),
+            },
+        );
+        assert!(verify(&bad, &mut rng).is_err());
+        let mut bad = document.clone();
+        bad.disclosed.clear();
+        assert!(verify(&bad, &mut rng).is_err());
+
+        // Another nonce or domain
+        let mut bad = document.clone();
+        bad.nonce = URL_SAFE_NO_PAD.encode([8u8; 32]);
+        assert!(verify(&bad, &mut rng).is_err());
+        let mut bad = document.clone();
+        bad.context = URL_SAFE_NO_PAD.encode(b"other verifier");
+        assert!(verify(&bad, &mut rng).is_err());
+
+        // A presentation that isn't bound to the nonce doesn't become a document
+        let (presentation, envelope) =
+            Presentation::from_document(&document, &system.ck, &system.vk).unwrap();
+        let mut unbound = presentation.clone();
+        unbound.nonce = None;
+        assert!(matches!(
+            unbound.to_document(&envelope),
+            Err(MessageError::NonceMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_document_validation_failures() {
+        let (system, document) = shown_document();
+        let from = |document: &PresentationDocument| {
+            Presentation::<Bls12_381>::from_document(document, &system.ck, &system.vk).map(|_| ())
+        };
+
+        let mut bad = document.clone();
+        bad.version = 2;
+        assert!(matches!(
+            from(&bad),
+            Err(MessageError::UnsupportedVersion(2))
+        ));
+
+        let mut bad = document.clone();
+        bad.sigma.push('=');
+        assert!(matches!(
+            from(&bad),
+            Err(MessageError::InvalidEncoding("sigma"))
+        ));
+
+        let mut bad = document.clone();
+        bad.nonce = URL_SAFE_NO_PAD.encode([7u8; 31]);
+        assert!(matches!(
+            from(&bad),
+            Err(MessageError::InvalidLength { field: "nonce", .. })
+        ));
+
+        let mut bad = document.clone();
+        bad.h = encode(&<Bls12_381 as Pairing>::G1Affine::zero()).unwrap();
+        assert!(matches!(
+            from(&bad),
+            Err(MessageError::IdentityElement("h"))
+        ));
+
+        let mut bad = document.clone();
+        bad.proof.responses.pop();
+        assert!(matches!(
+            from(&bad),
+            Err(MessageError::ResponseCountMismatch { .. })
+        ));
+
+        let mut bad = document.clone();
+        bad.disclosed[0].index = 3;
+        assert!(matches!(
+            from(&bad),
+            Err(MessageError::DisclosedIndexOutOfRange { index: 3, .. })
+        ));
+
+        let json = serde_json::to_string(&document)
+            .unwrap()
+            .replacen('{', "{\"extra\":1,", 1);
+        assert!(serde_json::from_str::<PresentationDocument>(&json).is_err());
+    }
+}
@@ -21,6 +21,15 @@ pub enum CommitmentError {
 
     #[error("Batch Proof verification failed")]
     BatchVerifyError,
+
+    #[error("Invalid public attributes: {0}")]
+    InvalidPublicAttributes(String),
+
+    #[error("Zero randomizer r_delta")]
+    ZeroRandomizer,
+
+    #[error("{messages} messages for a key with {bases} bases")]
+    TooManyMessages { messages: usize, bases: usize },
 }
 
 /// Errors that can occur during signature operations
@@ -38,6 +47,18 @@ pub enum SignatureError {
     #[error("Duplicate signature share from party {0}")]
     DuplicateShare(usize),
 
+    #[error("Party index 0 is the shared secret's point, parties start at 1")]
+    ZeroPartyIndex,
+
+    #[error("No verification key share for signer {0}")]
+    MissingVerificationKeyShare(usize),
+
+    #[error("More than one verification key share for signer {0}")]
+    DuplicateVerificationKeyShare(usize),
+
+    #[error("Share listed under signer {index} is from party {party_index}")]
+    PartyIndexMismatch { index: usize, party_index: usize },
+
     #[error("Threshold requirement not met")]
     ThresholdNotMet,
 
@@ -58,6 +79,27 @@ pub enum SignatureError {
 
     #[error("Invalid credential state: {0}")]
     InvalidState(String),
+
+    #[error("Invalid message: {0}")]
+    InvalidMessage(#[from] MessageError),
+
+    #[error("Issuance request with identity h")]
+    IdentityH,
+
+    #[error("Signature with identity h or sigma")]
+    IdentitySignature,
+
+    #[error("Zero randomizer u_delta or r_delta")]
+    ZeroRandomizer,
+
+    #[error("Request carries no consistency proof")]
+    MissingConsistencyProof,
+
+    #[error("Request carries no key binding proof")]
+    MissingKeyBinding,
+
+    #[error("Key binding check failed")]
+    KeyBindingFailed,
 }
 
 /// Errors that can occur during protocol operations
@@ -76,6 +118,15 @@ pub enum ProtocolError {
 
     #[error("User error: {0}")]
     UserError(String),
+
+    #[error("Credential error: {0}")]
+    CredentialError(#[from] CredentialError),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] SerializationError),
+
+    #[error("Invalid system parameters: {0}")]
+    InvalidParameters(String),
 }
 
 #[derive(Error, Debug)]
@@ -88,4 +139,152 @@ pub enum CredentialError {
     RandomizationFailed(String),
     #[error("Invalid credential state: {0}")]
     InvalidState(String),
+    #[error("Nullifier evaluation failed: {0}")]
+    NullifierFailed(String),
+    #[error("A credential needs at least one attribute")]
+    NoAttributes,
+    #[error("Invalid attributes: {0}")]
+    InvalidAttributes(CommitmentError),
+    #[error("No credential with id {0}")]
+    UnknownCredential(u64),
+    #[error("Revocation error: {0}")]
+    Revocation(#[from] RevocationError),
+    #[error("Set membership error: {0}")]
+    SetMembership(#[from] SetMembershipError),
+    #[error("Policy error: {0}")]
+    Policy(#[from] PolicyError),
+}
+
+/// Errors raised by the revocation accumulator and its witnesses
+#[derive(Error, Debug)]
+pub enum RevocationError {
+    #[error("Handle is already accumulated")]
+    AlreadyMember,
+    #[error("Handle is not accumulated")]
+    NotMember,
+    #[error("Handle can't be accumulated")]
+    InvalidHandle,
+    #[error("Handle has been revoked")]
+    Revoked,
+    #[error("No attribute at handle index {0}")]
+    InvalidHandleIndex(usize),
+}
+
+/// Errors raised when building set membership parameters or proofs
+#[derive(Error, Debug)]
+pub enum SetMembershipError {
+    #[error("Value appears twice in the set")]
+    DuplicateValue,
+    #[error("Value can't be signed")]
+    InvalidValue,
+    #[error("Attribute is not in the set")]
+    NotInSet,
+    #[error("No attribute at index {0}")]
+    InvalidAttributeIndex(usize),
+}
+
+/// The requirement of a `PresentationPolicy` that a presentation doesn't meet, or why
+/// the holder can't meet it
+#[derive(Error, Debug)]
+pub enum PolicyError {
+    #[error("Attribute {0} is not disclosed as required")]
+    MissingDisclosure(usize),
+    #[error("Attribute {0} is not shown to be in the required set")]
+    NotInSet(usize),
+    #[error("Credential has expired")]
+    Expired,
+    #[error("Presentation was made for another nonce, context or domain")]
+    NotBound,
+    #[error("Presentation doesn't have the policy's shape")]
+    Malformed,
+    #[error("Credential proofs are made under another domain than the policy's")]
+    DomainMismatch,
+    #[error("No attribute at index {0}")]
+    InvalidAttributeIndex(usize),
+    #[error("Credential verification failed: {0}")]
+    Signature(#[from] SignatureError),
+}
+
+/// Why key material fails `ThresholdKeys::validate`
+#[derive(Error, Debug)]
+pub enum KeyGenError {
+    #[error("Invalid point: {0}")]
+    InvalidPoint(#[from] MessageError),
+    #[error("Threshold {t} is not between 1 and {n}")]
+    InvalidThreshold { t: usize, n: usize },
+    #[error("Key material doesn't have the expected number of shares or attributes")]
+    ShareCount,
+    #[error("Key shares at position {0} don't have party index {0} + 1")]
+    IndexMismatch(usize),
+    #[error("Verification key share of party {0} doesn't match the keygen commitments")]
+    ShareMismatch(usize),
+}
+
+/// Why `reconstruct_secret_checked` rejected a set of shares
+#[derive(Error, Debug)]
+pub enum ShamirError {
+    #[error("Threshold must be positive")]
+    ZeroThreshold,
+    #[error("Got {shares} shares, need at least {threshold}")]
+    NotEnoughShares { shares: usize, threshold: usize },
+    #[error("Share at party index 0")]
+    ZeroIndex,
+    #[error("More than one share for party {0}")]
+    DuplicateIndex(usize),
+    #[error("Share of party {0} is not on the polynomial of the other shares")]
+    Inconsistent(usize),
+}
+
+/// The step of `self_test` that failed
+#[derive(Error, Debug)]
+pub enum SelfTestError {
+    #[error("Setup failed: {0}")]
+    Setup(#[from] ProtocolError),
+    #[error("Show failed: {0}")]
+    Show(#[from] CredentialError),
+    #[error("Serialization round trip failed: {0}")]
+    Serialization(#[from] SerializationError),
+    #[error("Valid presentation was rejected")]
+    Rejected,
+    #[error("Tampered presentation was accepted")]
+    Accepted,
+}
+
+/// Errors raised when converting protocol messages into internal types
+#[derive(Error, Debug)]
+pub enum MessageError {
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] SerializationError),
+    #[error("Request carries no commitments")]
+    EmptyRequest,
+    #[error("Got {commitments} commitments but {proofs} proofs")]
+    LengthMismatch { commitments: usize, proofs: usize },
+    #[error("Proof {0} is not for commitment {0}")]
+    ProofCommitmentMismatch(usize),
+    #[error("Identity element in field {0}")]
+    IdentityElement(&'static str),
+    #[error("Point in field {0} is not in the prime order subgroup")]
+    InvalidPoint(&'static str),
+    #[error("Invalid party index {0}")]
+    InvalidPartyIndex(usize),
+    #[error("Disclosed attribute indices must be strictly increasing")]
+    UnorderedDisclosure,
+    #[error("Presentation carries an empty proof")]
+    EmptyProof,
+    #[error("Field {0} is not canonical base64url")]
+    InvalidEncoding(&'static str),
+    #[error("Field {field} decodes to {got} bytes, expected {expected}")]
+    InvalidLength {
+        field: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Unsupported presentation document version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Proof carries {got} responses, the key needs {expected}")]
+    ResponseCountMismatch { expected: usize, got: usize },
+    #[error("Disclosed attribute index {index} is out of range for {attributes} attributes")]
+    DisclosedIndexOutOfRange { index: usize, attributes: usize },
+    #[error("Presentation was not shown for the envelope's nonce")]
+    NonceMismatch,
 }
@@ -0,0 +1,312 @@
+//! Minimal C ABI for running a single signer behind a process boundary.
+//!
+//! Every input and output uses the compressed `CanonicalSerialize` encoding over
+//! BLS12-381: `SymmetricCommitmentKey`, `SecretKeyShare`, `VerificationKeyShare`,
+//! `CredentialCommitments` in and `PartialSignature` out.
+//!
+//! Memory ownership:
+//! - `t_siris_signer_new` allocates a signer owned by the caller, which must be
+//!   released exactly once with `t_siris_signer_free`.
+//! - Input buffers are only borrowed for the duration of a call and never retained.
+//! - `t_siris_sign_share` writes into a caller-allocated buffer. If it is too small,
+//!   nothing is written, `out_len` receives the required size and
+//!   `TSirisStatus::BufferTooSmall` is returned.
+//!
+//! No panic crosses the boundary, any panic is caught and reported as `TSirisStatus::Panic`.
+use crate::credential::CredentialCommitments;
+use crate::errors::SignatureError;
+use crate::keygen::{SecretKeyShare, VerificationKeyShare};
+use crate::signer::Signer;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use ark_bls12_381::Bls12_381;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+
+/// Status codes returned by every FFI function
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TSirisStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidEncoding = 2,
+    InvalidKeyMaterial = 3,
+    BufferTooSmall = 4,
+    ProofVerificationFailed = 5,
+    CommitmentConsistencyFailed = 6,
+    InvalidShare = 7,
+    SigningFailed = 8,
+    Panic = 9,
+}
+
+impl From<&SignatureError> for TSirisStatus {
+    fn from(err: &SignatureError) -> Self {
+        match err {
+            SignatureError::SerializationError(_) | SignatureError::InvalidMessage(_) => {
+                TSirisStatus::InvalidEncoding
+            }
+            SignatureError::CommitmentError(_) => TSirisStatus::ProofVerificationFailed,
+            SignatureError::CommitmentConsistencyFailed => {
+                TSirisStatus::CommitmentConsistencyFailed
+            }
+            SignatureError::InvalidShare(_) | SignatureError::DuplicateShare(_) => {
+                TSirisStatus::InvalidShare
+            }
+            _ => TSirisStatus::SigningFailed,
+        }
+    }
+}
+
+/// Opaque signer handle owning its key material
+pub struct TSirisSigner {
+    ck: SymmetricCommitmentKey<Bls12_381>,
+    sk_share: SecretKeyShare<Bls12_381>,
+    vk_share: VerificationKeyShare<Bls12_381>,
+}
+
+/// Borrow `len` bytes from `ptr`, rejecting null pointers
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], TSirisStatus> {
+    if ptr.is_null() {
+        return Err(TSirisStatus::NullPointer);
+    }
+    Ok(slice::from_raw_parts(ptr, len))
+}
+
+fn decode<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, TSirisStatus> {
+    T::deserialize_compressed(bytes).map_err(|_| TSirisStatus::InvalidEncoding)
+}
+
+fn guarded(f: impl FnOnce() -> Result<(), TSirisStatus>) -> TSirisStatus {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => TSirisStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => TSirisStatus::Panic,
+    }
+}
+
+/// Create a signer from serialized key material.
+///
+/// On success `*out_signer` holds a new signer that must be released with
+/// `t_siris_signer_free`, on failure it is left untouched.
+///
+/// # Safety
+/// Each byte pointer must be valid for reads of its length and `out_signer`
+/// must be valid for a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn t_siris_signer_new(
+    ck_bytes: *const u8,
+    ck_len: usize,
+    sk_share_bytes: *const u8,
+    sk_share_len: usize,
+    vk_share_bytes: *const u8,
+    vk_share_len: usize,
+    out_signer: *mut *mut TSirisSigner,
+) -> TSirisStatus {
+    guarded(|| {
+        if out_signer.is_null() {
+            return Err(TSirisStatus::NullPointer);
+        }
+        let ck: SymmetricCommitmentKey<Bls12_381> = decode(input(ck_bytes, ck_len)?)?;
+        let sk_share: SecretKeyShare<Bls12_381> = decode(input(sk_share_bytes, sk_share_len)?)?;
+        let vk_share: VerificationKeyShare<Bls12_381> =
+            decode(input(vk_share_bytes, vk_share_len)?)?;
+
+        // The shares must belong to the same party and match the key's attribute count
+        let l = ck.num_attributes();
+        if sk_share.index != vk_share.index
+            || sk_share.index == 0
+            || sk_share.y_shares.len() != l
+            || vk_share.g_tilde_y_shares.len() != l
+        {
+            return Err(TSirisStatus::InvalidKeyMaterial);
+        }
+
+        let signer = Box::new(TSirisSigner {
+            ck,
+            sk_share,
+            vk_share,
+        });
+        *out_signer = Box::into_raw(signer);
+        Ok(())
+    })
+}
+
+/// Verify a serialized `CredentialCommitments` request and sign a share of it.
+///
+/// The serialized `PartialSignature` is written to `out_buf` and its length to `*out_len`.
+///
+/// # Safety
+/// `signer` must come from `t_siris_signer_new` and not have been freed,
+/// `request_bytes` must be valid for reads of `request_len` bytes, `out_buf` valid
+/// for writes of `out_cap` bytes and `out_len` valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn t_siris_sign_share(
+    signer: *const TSirisSigner,
+    request_bytes: *const u8,
+    request_len: usize,
+    out_buf: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> TSirisStatus {
+    guarded(|| {
+        if signer.is_null() || out_buf.is_null() || out_len.is_null() {
+            return Err(TSirisStatus::NullPointer);
+        }
+        let signer = &*signer;
+        let request: CredentialCommitments<Bls12_381> = decode(input(request_bytes, request_len)?)?;
+
+        let mut seed = [0u8; 32];
+        getrandom::getrandom(&mut seed).map_err(|_| TSirisStatus::SigningFailed)?;
+        let mut rng = StdRng::from_seed(seed);
+        let share = Signer::new(&signer.ck, &signer.sk_share, &signer.vk_share)
+            .sign_share(&request.commitments, &request.proofs, &request.h, &mut rng)
+            .map_err(|e| TSirisStatus::from(&e))?;
+
+        let size = share.compressed_size();
+        *out_len = size;
+        if size > out_cap {
+            return Err(TSirisStatus::BufferTooSmall);
+        }
+        let out = slice::from_raw_parts_mut(out_buf, size);
+        share
+            .serialize_compressed(out)
+            .map_err(|_| TSirisStatus::InvalidEncoding)
+    })
+}
+
+/// Release a signer created by `t_siris_signer_new`. Passing null is a no-op.
+///
+/// # Safety
+/// `signer` must be null or come from `t_siris_signer_new`, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn t_siris_signer_free(signer: *mut TSirisSigner) {
+    if !signer.is_null() {
+        drop(Box::from_raw(signer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::keygen;
+    use crate::protocol::UserProtocol;
+    use crate::signature::{PartialSignature, ThresholdSignature};
+    use crate::test_rng;
+    use ark_std::ptr;
+
+    fn to_bytes<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        value.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_ffi_sign_share_round_trip() {
+        let mut rng = test_rng();
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(2, 3, 3, &mut rng);
+        let (_, request) = UserProtocol::request_credential(ck.clone(), None, &mut rng).unwrap();
+
+        let ck_bytes = to_bytes(&ck);
+        let sk_bytes = to_bytes(&ts_keys.sk_shares[0]);
+        let vk_bytes = to_bytes(&ts_keys.vk_shares[0]);
+        let request_bytes = to_bytes(&request);
+
+        unsafe {
+            let mut signer: *mut TSirisSigner = ptr::null_mut();
+            let status = t_siris_signer_new(
+                ck_bytes.as_ptr(),
+                ck_bytes.len(),
+                sk_bytes.as_ptr(),
+                sk_bytes.len(),
+                vk_bytes.as_ptr(),
+                vk_bytes.len(),
+                &mut signer,
+            );
+            assert_eq!(status, TSirisStatus::Ok);
+            assert!(!signer.is_null());
+
+            // A too small buffer reports the required size
+            let mut out_len = 0usize;
+            let mut small = [0u8; 4];
+            let status = t_siris_sign_share(
+                signer,
+                request_bytes.as_ptr(),
+                request_bytes.len(),
+                small.as_mut_ptr(),
+                small.len(),
+                &mut out_len,
+            );
+            assert_eq!(status, TSirisStatus::BufferTooSmall);
+
+            let mut out = vec![0u8; out_len];
+            let status = t_siris_sign_share(
+                signer,
+                request_bytes.as_ptr(),
+                request_bytes.len(),
+                out.as_mut_ptr(),
+                out.len(),
+                &mut out_len,
+            );
+            assert_eq!(status, TSirisStatus::Ok);
+
+            let share = PartialSignature::<Bls12_381>::deserialize_compressed(&out[..out_len])
+                .expect("share should deserialize");
+            assert_eq!(share.party_index, ts_keys.vk_shares()[0].index());
+            assert!(ThresholdSignature::verify_share(
+                &ck,
+                &ts_keys.vk_shares[0],
+                &request.commitments,
+                &share,
+            ));
+
+            // Malformed requests are reported, not panicked on
+            let status = t_siris_sign_share(
+                signer,
+                request_bytes.as_ptr(),
+                request_bytes.len() / 2,
+                out.as_mut_ptr(),
+                out.len(),
+                &mut out_len,
+            );
+            assert_eq!(status, TSirisStatus::InvalidEncoding);
+
+            t_siris_signer_free(signer);
+        }
+    }
+
+    #[test]
+    fn test_ffi_rejects_mismatched_key_material() {
+        let mut rng = test_rng();
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(2, 3, 3, &mut rng);
+        let ck_bytes = to_bytes(&ck);
+        let sk_bytes = to_bytes(&ts_keys.sk_shares[0]);
+        let vk_bytes = to_bytes(&ts_keys.vk_shares[1]);
+
+        unsafe {
+            let mut signer: *mut TSirisSigner = ptr::null_mut();
+            let status = t_siris_signer_new(
+                ck_bytes.as_ptr(),
+                ck_bytes.len(),
+                sk_bytes.as_ptr(),
+                sk_bytes.len(),
+                vk_bytes.as_ptr(),
+                vk_bytes.len(),
+                &mut signer,
+            );
+            assert_eq!(status, TSirisStatus::InvalidKeyMaterial);
+            assert!(signer.is_null());
+
+            let status = t_siris_signer_new(
+                ptr::null(),
+                0,
+                sk_bytes.as_ptr(),
+                sk_bytes.len(),
+                vk_bytes.as_ptr(),
+                vk_bytes.len(),
+                &mut signer,
+            );
+            assert_eq!(status, TSirisStatus::NullPointer);
+        }
+    }
+}
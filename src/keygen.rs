@@ -1,103 +1,634 @@
-use crate::shamir::generate_shares;
+use crate::errors::CommitmentError;
+use crate::pairing::PairingCheck;
+use crate::schnorr::SchnorrProtocol;
 use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::Transcript;
 use ark_ec::pairing::Pairing;
-use ark_ec::CurveGroup;
-use ark_ff::UniformRand;
-use ark_std::ops::Mul;
-use ark_std::rand::Rng;
+use ark_ec::{CurveGroup, VariableBaseMSM};
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::One;
+#[cfg(feature = "signing")]
+use {
+    crate::errors::KeyGenError,
+    crate::messages::validate_point,
+    crate::shamir::generate_shares_with_commitments,
+    ark_ff::UniformRand,
+    ark_std::ops::Mul,
+    ark_std::ops::Range,
+    ark_std::rand::{CryptoRng, Rng},
+};
 
-#[derive(Clone)]
+#[cfg(feature = "signing")]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SecretKeyShare<E: Pairing> {
     pub index: usize,
     pub x_share: E::ScalarField,
     pub y_shares: Vec<E::ScalarField>,
 }
-#[derive(Clone)]
+
+#[cfg(all(feature = "signing", feature = "zeroize"))]
+impl<E: Pairing> zeroize::Zeroize for SecretKeyShare<E> {
+    fn zeroize(&mut self) {
+        self.x_share.zeroize();
+        self.y_shares.zeroize();
+    }
+}
+
+#[cfg(all(feature = "signing", feature = "zeroize"))]
+impl<E: Pairing> Drop for SecretKeyShare<E> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(all(feature = "signing", feature = "zeroize"))]
+impl<E: Pairing> zeroize::ZeroizeOnDrop for SecretKeyShare<E> {}
+#[cfg(feature = "signing")]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct ThresholdKeys<E: Pairing> {
-    pub t: usize,
-    pub n: usize,
-    pub l: usize,
-    pub sk_shares: Vec<SecretKeyShare<E>>,
-    pub vk_shares: Vec<VerificationKeyShare<E>>,
+    pub(crate) t: usize,
+    pub(crate) n: usize,
+    pub(crate) l: usize,
+    /// Ordered by party index, `1..=n`
+    pub(crate) sk_shares: Vec<SecretKeyShare<E>>,
+    /// In the order of `sk_shares`
+    pub(crate) vk_shares: Vec<VerificationKeyShare<E>>,
+    /// The dealer's proof that `vk` is built from the shared secrets
+    pub proof: KeygenProof<E>,
+    /// Feldman commitments to the sharing polynomials, published with `vk`
+    pub transcript: KeygenTranscript<E>,
+}
+
+#[cfg(feature = "signing")]
+impl<E: Pairing> ThresholdKeys<E> {
+    /// `t`, the number of shares needed to sign
+    pub fn threshold(&self) -> usize {
+        self.t
+    }
+
+    /// `n`, the committee size
+    pub fn participants(&self) -> usize {
+        self.n
+    }
+
+    /// `L`, the number of attributes the keys sign
+    pub fn num_attributes(&self) -> usize {
+        self.l
+    }
+
+    /// Every party's verification key share, ordered by party index
+    pub fn vk_shares(&self) -> &[VerificationKeyShare<E>] {
+        &self.vk_shares
+    }
+
+    /// The key shares of the party with index `index`, counting from 1
+    pub fn share_for(
+        &self,
+        index: usize,
+    ) -> Option<(&SecretKeyShare<E>, &VerificationKeyShare<E>)> {
+        // Indices run 1 to n in order, see `validate`
+        let position = index.checked_sub(1)?;
+        let sk_share = self.sk_shares.get(position)?;
+        let vk_share = self.vk_shares.get(position)?;
+        (sk_share.index == index).then_some((sk_share, vk_share))
+    }
+
+    /// Check key material from an untrusted source before use: every point of `ck`,
+    /// the verification key shares and the keygen commitments is a non-identity
+    /// element of the prime order subgroup, the share and attribute counts agree, the
+    /// shares carry the party indices 1 to `n` in order and every verification key
+    /// share is the one the commitments give for its index
+    pub fn validate(&self, ck: &SymmetricCommitmentKey<E>) -> Result<(), KeyGenError> {
+        let (t, n, l) = (self.t, self.n, self.l);
+        if t == 0 || t > n {
+            return Err(KeyGenError::InvalidThreshold { t, n });
+        }
+        if ck.num_attributes() != l
+            || ck.ck_tilde.len() != l
+            || self.sk_shares.len() != n
+            || self.vk_shares.len() != n
+            || self.transcript.x_commitments.len() != t
+            || self.transcript.y_commitments.len() != l
+            || self.transcript.y_commitments.iter().any(|c| c.len() != t)
+        {
+            return Err(KeyGenError::ShareCount);
+        }
+
+        validate_point(&ck.g, "g")?;
+        validate_point(&ck.g_tilde, "g_tilde")?;
+        for base in &ck.ck {
+            validate_point(base, "ck")?;
+        }
+        for base in &ck.ck_tilde {
+            validate_point(base, "ck_tilde")?;
+        }
+        for commitment in self
+            .transcript
+            .y_commitments
+            .iter()
+            .flatten()
+            .chain(&self.transcript.x_commitments)
+        {
+            validate_point(commitment, "keygen commitment")?;
+        }
+
+        for (position, (sk_share, vk_share)) in
+            self.sk_shares.iter().zip(&self.vk_shares).enumerate()
+        {
+            if sk_share.index != position + 1 || vk_share.index != position + 1 {
+                return Err(KeyGenError::IndexMismatch(position));
+            }
+            if sk_share.y_shares.len() != l || vk_share.g_tilde_y_shares.len() != l {
+                return Err(KeyGenError::ShareCount);
+            }
+            validate_point(&vk_share.g_tilde_x_share, "g_tilde_x_share")?;
+            for point in &vk_share.g_tilde_y_shares {
+                validate_point(point, "g_tilde_y_share")?;
+            }
+            let derived =
+                VerificationKeyShare::derive_from_commitments(&self.transcript, vk_share.index);
+            if derived.g_tilde_x_share != vk_share.g_tilde_x_share
+                || derived.g_tilde_y_shares != vk_share.g_tilde_y_shares
+            {
+                return Err(KeyGenError::ShareMismatch(vk_share.index));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keys of a weighted committee. Signer `s` holds `weights[s]` consecutive Shamir
+/// points of `keys`, and `keys.t` points, from any mix of signers, are needed to sign
+#[cfg(feature = "signing")]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct WeightedThresholdKeys<E: Pairing> {
+    pub weights: Vec<usize>,
+    /// The shares of all `weights.iter().sum()` points, in point order
+    pub keys: ThresholdKeys<E>,
+}
+
+#[cfg(feature = "signing")]
+impl<E: Pairing> WeightedThresholdKeys<E> {
+    pub fn num_signers(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Positions in `keys.sk_shares` and `keys.vk_shares` of the points of signer `s`,
+    /// counting signers from 0. The party indices are one higher
+    pub fn points(&self, s: usize) -> Option<Range<usize>> {
+        let weight = *self.weights.get(s)?;
+        let start = self.weights[..s].iter().sum();
+        Some(start..start + weight)
+    }
+
+    pub fn sk_shares(&self, s: usize) -> Option<&[SecretKeyShare<E>]> {
+        self.points(s).map(|points| &self.keys.sk_shares[points])
+    }
+
+    pub fn vk_share(&self, s: usize) -> Option<WeightedVerificationKeyShare<E>> {
+        let points = self.points(s)?;
+        Some(WeightedVerificationKeyShare {
+            signer: s,
+            shares: self.keys.vk_shares[points].to_vec(),
+        })
+    }
 }
 
-#[derive(Clone)]
+/// The verification key of one weighted signer, a sub-share per point it holds
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct WeightedVerificationKeyShare<E: Pairing> {
+    /// The signer, counting from 0
+    pub signer: usize,
+    pub shares: Vec<VerificationKeyShare<E>>,
+}
+
+impl<E: Pairing> WeightedVerificationKeyShare<E> {
+    pub fn weight(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// Party indices of the signer's points
+    pub fn indices(&self) -> Vec<usize> {
+        self.shares.iter().map(|share| share.index).collect()
+    }
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct VerificationKey<E: Pairing> {
     pub g_tilde_x: E::G2Affine,
+    /// The committee's own `g^{y_k}`, divided out of the aggregated signature with the
+    /// request's blindings
+    pub g_y: Vec<E::G1Affine>,
+    /// The committee's own `g_tilde^{y_k}`
+    pub g_tilde_y: Vec<E::G2Affine>,
+    /// The committee's threshold parameters, if the issuer publishes them
+    pub params: Option<ThresholdParams>,
+}
+
+impl<E: Pairing> VerificationKey<E> {
+    pub fn num_attributes(&self) -> usize {
+        self.g_tilde_y.len()
+    }
+
+    /// The key a credential signed by this committee is shown under: the generators
+    /// and base order of the shared `ck` with the committee's `g^{y_k}` and
+    /// `g_tilde^{y_k}` as attribute bases, so the shown `cm_tilde` pairs against this
+    /// key and no other
+    pub fn show_key(&self, ck: &SymmetricCommitmentKey<E>) -> SymmetricCommitmentKey<E> {
+        SymmetricCommitmentKey::from_bases(
+            ck.g,
+            self.g_y.clone(),
+            ck.g_tilde,
+            self.g_tilde_y.clone(),
+        )
+        .with_base_order(ck.base_order())
+    }
+
+    /// Whether the key is for `expected`. A key that doesn't carry its parameters
+    /// can't be checked and never matches
+    pub fn has_params(&self, expected: ThresholdParams) -> bool {
+        self.params == Some(expected)
+    }
+}
+
+/// The fixed G2 points of signature verification, the commitment key's `g_tilde` and
+/// `g_tilde^x`, with their Miller loop line coefficients computed once, and the bases
+/// of `vk.show_key(ck)` opening proofs are checked against. Build it once per key for
+/// `ThresholdSignature::verify_prepared`
+#[derive(Clone, Debug)]
+pub struct PreparedVerificationKey<E: Pairing> {
+    pub g_tilde: E::G2Prepared,
+    pub g_tilde_x: E::G2Prepared,
+    pub bases: Vec<E::G1Affine>,
+}
+
+impl<E: Pairing> PreparedVerificationKey<E> {
+    pub fn new(ck: &SymmetricCommitmentKey<E>, vk: &VerificationKey<E>) -> Self {
+        Self {
+            g_tilde: ck.g_tilde.into(),
+            g_tilde_x: vk.g_tilde_x.into(),
+            bases: vk.show_key(ck).get_bases().0,
+        }
+    }
+}
+
+/// `t` of `n` signers issue a credential
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ThresholdParams {
+    pub t: usize,
+    pub n: usize,
 }
 
-#[derive(Clone)]
+/// Public parameters that several committees can key under: the generators and
+/// independent attribute bases `g^{b_k}`, `g_tilde^{b_k}`, whose exponents are
+/// discarded at setup and so aren't any committee's `y_k`. Every committee under the
+/// CRS has `commitment_key` as its commitment key, a holder requests from any of them
+/// with the same key and shows under the issuing committee's `VerificationKey::show_key`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Crs<E: Pairing> {
+    pub g: E::G1Affine,
+    pub g_tilde: E::G2Affine,
+    pub bases: Vec<E::G1Affine>,
+    pub bases_tilde: Vec<E::G2Affine>,
+}
+
+impl<E: Pairing> Crs<E> {
+    pub fn num_attributes(&self) -> usize {
+        self.bases.len()
+    }
+
+    /// The commitment key shared by the committees under the CRS
+    pub fn commitment_key(&self) -> SymmetricCommitmentKey<E> {
+        SymmetricCommitmentKey::from_bases(
+            self.g,
+            self.bases.clone(),
+            self.g_tilde,
+            self.bases_tilde.clone(),
+        )
+    }
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct VerificationKeyShare<E: Pairing> {
-    pub index: usize,
+    pub(crate) index: usize,
     pub g_tilde_x_share: E::G2Affine,
     pub g_tilde_y_shares: Vec<E::G2Affine>,
 }
 
+/// Feldman commitments, over `g_tilde`, to the coefficients of the polynomials the
+/// dealer shared `x` and every `y_k` with, constant term first. The constant terms are
+/// `vk.g_tilde_x` and `vk.g_tilde_y`, and evaluating the commitments at a party index
+/// gives that party's `VerificationKeyShare` without trusting the dealer's copy
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeygenTranscript<E: Pairing> {
+    pub x_commitments: Vec<E::G2Affine>,
+    /// One commitment vector per attribute
+    pub y_commitments: Vec<Vec<E::G2Affine>>,
+}
+
+impl<E: Pairing> KeygenTranscript<E> {
+    /// Whether the constant terms are the committee key `vk`
+    pub fn matches(&self, vk: &VerificationKey<E>) -> bool {
+        self.x_commitments.first() == Some(&vk.g_tilde_x)
+            && self.y_commitments.len() == vk.g_tilde_y.len()
+            && self
+                .y_commitments
+                .iter()
+                .zip(&vk.g_tilde_y)
+                .all(|(commitments, g_tilde_y_k)| commitments.first() == Some(g_tilde_y_k))
+    }
+}
+
+/// Evaluate `commitments`, Feldman commitments to a polynomial's coefficients, at
+/// `index`, giving `base^{f(index)}`
+fn evaluate_commitments<G: CurveGroup>(commitments: &[G::Affine], index: usize) -> G {
+    let x = G::ScalarField::from(index as u64);
+    // Horner's method, in the exponent
+    commitments
+        .iter()
+        .rev()
+        .fold(G::zero(), |acc, c_j| acc * x + c_j)
+}
+
+impl<E: Pairing> VerificationKeyShare<E> {
+    /// `g_tilde^{x_i}` and every `g_tilde^{y_{k,i}}` for `sk_share`, normalized in one
+    /// batch
+    #[cfg(feature = "signing")]
+    pub(crate) fn from_secret_share(g_tilde: &E::G2Affine, sk_share: &SecretKeyShare<E>) -> Self {
+        let mut points: Vec<E::G2> = Vec::with_capacity(sk_share.y_shares.len() + 1);
+        points.push(g_tilde.mul(sk_share.x_share));
+        points.extend(sk_share.y_shares.iter().map(|y_share| g_tilde.mul(y_share)));
+        let mut points = E::G2::normalize_batch(&points);
+        let g_tilde_y_shares = points.split_off(1);
+        Self {
+            index: sk_share.index,
+            g_tilde_x_share: points[0],
+            g_tilde_y_shares,
+        }
+    }
+
+    /// Party index of the share, counting from 1
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The verification key share of party `index`, `g_tilde^{x_i}` and every
+    /// `g_tilde^{y_{k,i}}`, evaluated from the public commitments
+    pub fn derive_from_commitments(transcript: &KeygenTranscript<E>, index: usize) -> Self {
+        let g_tilde_y_shares: Vec<E::G2> = transcript
+            .y_commitments
+            .iter()
+            .map(|commitments| evaluate_commitments::<E::G2>(commitments, index))
+            .collect();
+        Self {
+            index,
+            g_tilde_x_share: evaluate_commitments::<E::G2>(&transcript.x_commitments, index)
+                .into_affine(),
+            g_tilde_y_shares: E::G2::normalize_batch(&g_tilde_y_shares),
+        }
+    }
+}
+
+/// The dealer's proof that the verification key is internally consistent: Schnorr
+/// proofs of knowledge of `x` for `g_tilde_x` and of each `y_k` for `g_y[k]` under one
+/// Fiat-Shamir challenge, and a single pairing check that every `g_tilde_y[k]` has the
+/// exponent of `g_y[k]`. Anyone holding `ck` and `vk` can verify it
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeygenProof<E: Pairing> {
+    /// `g_tilde^{rho_x}`
+    pub announcement_x: E::G2Affine,
+    /// `g^{rho_k}`, one per `g_y[k]`
+    pub announcements_y: Vec<E::G1Affine>,
+    /// `rho_x + c x`
+    pub response_x: E::ScalarField,
+    /// `rho_k + c y_k`
+    pub responses_y: Vec<E::ScalarField>,
+}
+
+impl<E: Pairing> KeygenProof<E> {
+    fn transcript(
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        announcement_x: &E::G2Affine,
+        announcements_y: &[E::G1Affine],
+    ) -> Transcript {
+        let mut transcript = Transcript::new(b"t-siris keygen");
+        transcript.append(b"ck", ck);
+        transcript.append(b"vk", vk);
+        transcript.append(b"announcement_x", announcement_x);
+        transcript.append(b"announcements_y", announcements_y);
+        transcript
+    }
+
+    /// Prove knowledge of `x` and `y_values` behind `vk`, over the generators of `ck`
+    #[cfg(feature = "signing")]
+    pub(crate) fn prove(
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        x: &E::ScalarField,
+        y_values: &[E::ScalarField],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Self {
+        let (state_x, announcement_x) = SchnorrProtocol::commit(&[ck.g_tilde], rng);
+        let (states_y, announcements_y): (Vec<_>, Vec<_>) = y_values
+            .iter()
+            .map(|_| SchnorrProtocol::commit(&[ck.g], rng))
+            .map(|(state, announcement)| (state, announcement.0))
+            .unzip();
+        let challenge = Self::transcript(ck, vk, &announcement_x.0, &announcements_y)
+            .challenge_scalar(b"challenge");
+
+        let response_x = SchnorrProtocol::prove(state_x, &[*x], &challenge).0[0];
+        let responses_y = states_y
+            .into_iter()
+            .zip(y_values)
+            .map(|(state, y_k)| SchnorrProtocol::prove(state, &[*y_k], &challenge).0[0])
+            .collect();
+        Self {
+            announcement_x: announcement_x.0,
+            announcements_y,
+            response_x,
+            responses_y,
+        }
+    }
+
+    /// Check the Schnorr proofs for `vk.g_tilde_x` and each `vk.g_y[k]`, then
+    /// `e(\prod_k g_y[k]^{s^k}, g_tilde) = e(g, \prod_k g_tilde_y[k]^{s^k})` for a
+    /// scalar `s` drawn from the transcript
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+    ) -> Result<bool, CommitmentError> {
+        let l = ck.num_attributes();
+        if l == 0
+            || vk.g_y.len() != l
+            || vk.g_tilde_y.len() != l
+            || self.announcements_y.len() != l
+            || self.responses_y.len() != l
+        {
+            return Err(CommitmentError::InvalidProof);
+        }
+
+        let mut transcript = Self::transcript(ck, vk, &self.announcement_x, &self.announcements_y);
+        let challenge = transcript.challenge_scalar(b"challenge");
+        let knows_x = SchnorrProtocol::verify_schnorr(
+            &[ck.g_tilde],
+            &vk.g_tilde_x,
+            &self.announcement_x,
+            &[self.response_x],
+            &challenge,
+        );
+        let knows_y = (0..l).all(|k| {
+            SchnorrProtocol::verify_schnorr(
+                &[ck.g],
+                &vk.g_y[k],
+                &self.announcements_y[k],
+                &[self.responses_y[k]],
+                &challenge,
+            )
+        });
+        if !knows_x || !knows_y {
+            return Ok(false);
+        }
+
+        // Batch the L cross-group equalities with powers of a transcript scalar
+        let s: E::ScalarField = transcript.challenge_scalar(b"cross-group");
+        let powers: Vec<E::ScalarField> = (1..=l as u64).map(|k| s.pow([k])).collect();
+        let combined = E::G1::msm_unchecked(&vk.g_y, &powers).into_affine();
+        let combined_tilde = E::G2::msm_unchecked(&vk.g_tilde_y, &powers).into_affine();
+        let check = PairingCheck::<E>::from_pairs(
+            &[(&combined, &ck.g_tilde), (ck.neg_g(), &combined_tilde)],
+            &E::TargetField::one(),
+        );
+        Ok(check.verify())
+    }
+}
+
+/// Draw the generators and `l` attribute bases in each group. The exponents of the
+/// bases are dropped once they are computed
+#[cfg(feature = "signing")]
+pub fn setup_crs<E: Pairing>(l: usize, rng: &mut (impl Rng + CryptoRng)) -> Crs<E> {
+    let g = E::G1Affine::rand(rng);
+    let g_tilde = E::G2Affine::rand(rng);
+    #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+    let mut exponents: Vec<E::ScalarField> = (0..l).map(|_| E::ScalarField::rand(rng)).collect();
+    let ck = SymmetricCommitmentKey::<E>::derive(g, g_tilde, &exponents);
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut exponents);
+    Crs {
+        g,
+        g_tilde,
+        bases: ck.ck,
+        bases_tilde: ck.ck_tilde,
+    }
+}
+
+/// Keygen for a single committee with a fresh CRS of its own
+#[cfg(feature = "signing")]
 pub fn keygen<E: Pairing>(
     t: usize,
     n: usize,
     l: usize,
-    rng: &mut impl Rng,
+    rng: &mut (impl Rng + CryptoRng),
+) -> (
+    SymmetricCommitmentKey<E>,
+    VerificationKey<E>,
+    ThresholdKeys<E>,
+) {
+    let crs = setup_crs(l, rng);
+    keygen_under_crs(&crs, t, n, rng)
+}
+
+/// Deal a `t`-of-`n` committee key for the `crs.num_attributes()` attributes. The
+/// returned commitment key is `crs.commitment_key()`, the same for every committee
+/// under the CRS, the committee's `g^{y_k}` and `g_tilde^{y_k}` are in the verification
+/// key
+#[cfg(feature = "signing")]
+pub fn keygen_under_crs<E: Pairing>(
+    crs: &Crs<E>,
+    t: usize,
+    n: usize,
+    rng: &mut (impl Rng + CryptoRng),
 ) -> (
     SymmetricCommitmentKey<E>,
     VerificationKey<E>,
     ThresholdKeys<E>,
 ) {
+    let l = crs.num_attributes();
     // 1. generate x and xshares
-    let x = E::ScalarField::rand(rng);
-    let x_shares = generate_shares(&x, t, n, rng);
+    #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+    let mut x = E::ScalarField::rand(rng);
+    #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+    let (mut x_shares, x_commitments) =
+        generate_shares_with_commitments::<E::G2, _>(&x, t, n, &crs.g_tilde, rng);
 
     // generate y values [y1,..,yL]
     let mut y_values = Vec::with_capacity(l);
     // [[y1_1,...,y1_L]_1,...,[yL_1,...,yL_L]_k]
     let mut y_shares_by_k = Vec::with_capacity(l);
+    let mut y_commitments = Vec::with_capacity(l);
 
     // gen l x t degree poly's
     for _ in 0..l {
         let y_k = E::ScalarField::rand(rng);
         y_values.push(y_k);
-        y_shares_by_k.push(generate_shares(&y_k, t, n, rng));
+        let (y_shares_k, y_commitments_k) =
+            generate_shares_with_commitments::<E::G2, _>(&y_k, t, n, &crs.g_tilde, rng);
+        y_shares_by_k.push(y_shares_k);
+        y_commitments.push(y_commitments_k);
     }
 
-    let ck: SymmetricCommitmentKey<E> = SymmetricCommitmentKey::new(&y_values, rng);
+    let ck = crs.commitment_key();
+    let committee = SymmetricCommitmentKey::<E>::derive(crs.g, crs.g_tilde, &y_values);
 
     let g_tilde_x = ck.g_tilde.mul(x).into_affine();
-    let vk: VerificationKey<E> = VerificationKey { g_tilde_x };
-
-    // exponentiate the shares for g1,g2 values of shares
-    let mut sk_shares = Vec::with_capacity(n);
-    let mut vk_shares = Vec::with_capacity(n);
-
-    for i in 0..n {
-        // looping from (1, x_1),...,(L, x_L)
-
-        let (idx, x_share_i) = x_shares[i];
-
-        let mut y_shares_i = Vec::with_capacity(l);
-        let mut g_tilde_y_shares_i = Vec::with_capacity(l);
-
-        // from [[y1_1,...,y1_L]_1,...,[yL_1,...,YL_L]_k]
-        // select from each y_L array for size [k] [y1_1,...,yL_1]_[k]
-        for k in 0..l {
-            let (_, y_share_k_i) = y_shares_by_k[k][i];
-            y_shares_i.push(y_share_k_i);
-            g_tilde_y_shares_i.push(ck.g_tilde.mul(y_share_k_i).into_affine());
-        }
+    let vk: VerificationKey<E> = VerificationKey {
+        g_tilde_x,
+        g_y: committee.ck,
+        g_tilde_y: committee.ck_tilde,
+        params: Some(ThresholdParams { t, n }),
+    };
+    let proof = KeygenProof::prove(&ck, &vk, &x, &y_values, rng);
 
-        let sk_share = SecretKeyShare {
-            index: idx,
-            x_share: x_share_i,
-            y_shares: y_shares_i,
-        };
+    // Each signer's shares [x_i, y1_i, ..., yL_i], selected from
+    // [[y1_1,...,y1_n],...,[yL_1,...,yL_n]]
+    let sk_shares: Vec<SecretKeyShare<E>> = (0..n)
+        .map(|i| {
+            let (idx, x_share) = x_shares[i];
+            debug_assert_eq!(idx, i + 1, "party indices start at 1");
+            SecretKeyShare {
+                index: idx,
+                x_share,
+                y_shares: y_shares_by_k
+                    .iter()
+                    .map(|y_shares_k| y_shares_k[i].1)
+                    .collect(),
+            }
+        })
+        .collect();
 
-        let vk_share = VerificationKeyShare {
-            index: idx,
-            g_tilde_x_share: ck.g_tilde.mul(x_share_i).into_affine(),
-            g_tilde_y_shares: g_tilde_y_shares_i,
-        };
+    // The (L + 1) n G2 exponentiations dominate keygen, they only depend on the shares
+    // so signers are spread over rayon's pool
+    #[cfg(feature = "parallel")]
+    use rayon::prelude::*;
+    #[cfg(feature = "parallel")]
+    let signers = sk_shares.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let signers = sk_shares.iter();
+    let vk_shares: Vec<VerificationKeyShare<E>> = signers
+        .map(|sk_share| VerificationKeyShare::from_secret_share(&ck.g_tilde, sk_share))
+        .collect();
 
-        sk_shares.push(sk_share);
-        vk_shares.push(vk_share);
+    // The dealer's secrets only live on in the shares
+    #[cfg(feature = "zeroize")]
+    {
+        use zeroize::Zeroize;
+        x.zeroize();
+        y_values.zeroize();
+        x_shares.zeroize();
+        y_shares_by_k.zeroize();
     }
 
     let ts_keys = ThresholdKeys {
@@ -106,19 +637,92 @@ pub fn keygen<E: Pairing>(
         l,
         sk_shares,
         vk_shares,
+        proof,
+        transcript: KeygenTranscript {
+            x_commitments,
+            y_commitments,
+        },
     };
 
     (ck, vk, ts_keys)
 }
 
-#[cfg(test)]
+/// Keygen for a weighted committee: signer `s` gets `weights[s]` consecutive points of
+/// a `threshold`-of-`weights.iter().sum()` sharing, so a signer of weight `w` counts as
+/// `w` signers towards `threshold`
+///
+/// # Panics
+/// If a weight is zero or `threshold` is zero or above the total weight, like `keygen`
+#[cfg(feature = "signing")]
+pub fn keygen_weighted<E: Pairing>(
+    weights: &[usize],
+    threshold: usize,
+    l: usize,
+    rng: &mut (impl Rng + CryptoRng),
+) -> (
+    SymmetricCommitmentKey<E>,
+    VerificationKey<E>,
+    WeightedThresholdKeys<E>,
+) {
+    assert!(
+        weights.iter().all(|&weight| weight > 0),
+        "every signer needs a positive weight"
+    );
+    let (ck, vk, keys) = keygen(threshold, weights.iter().sum(), l, rng);
+    let keys = WeightedThresholdKeys {
+        weights: weights.to_vec(),
+        keys,
+    };
+    (ck, vk, keys)
+}
+
+/// Test oracle: interpolate `x` and every `y_k` from the first `threshold` key shares
+#[cfg(all(test, feature = "signing"))]
+pub(crate) fn reconstruct_full_key<E: Pairing>(
+    ts_keys: &ThresholdKeys<E>,
+    threshold: usize,
+) -> (E::ScalarField, Vec<E::ScalarField>) {
+    use crate::shamir::reconstruct_secret;
+
+    let shares = &ts_keys.sk_shares[..threshold];
+    let x_shares: Vec<_> = shares.iter().map(|s| (s.index, s.x_share)).collect();
+    let y = (0..ts_keys.l)
+        .map(|k| {
+            let y_k_shares: Vec<_> = shares.iter().map(|s| (s.index, s.y_shares[k])).collect();
+            reconstruct_secret(&y_k_shares, threshold)
+        })
+        .collect();
+    (reconstruct_secret(&x_shares, threshold), y)
+}
+
+#[cfg(all(test, feature = "signing"))]
 mod tests {
     use super::*;
     use crate::shamir::reconstruct_secret;
+    use crate::test_rng;
     use ark_bls12_381::{Bls12_381, Fr};
     use ark_ec::pairing::Pairing;
     use ark_ec::CurveGroup;
-    use ark_std::test_rng;
+
+    #[test]
+    fn test_threshold_keys_getters() {
+        let mut rng = test_rng();
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(2, 4, 3, &mut rng);
+        assert_eq!(ts_keys.threshold(), 2);
+        assert_eq!(ts_keys.participants(), 4);
+        assert_eq!(ts_keys.num_attributes(), 3);
+        assert_eq!(ck.num_attributes(), 3);
+        assert_eq!(ts_keys.vk_shares().len(), 4);
+
+        for index in 1..=4 {
+            let (sk_share, vk_share) = ts_keys.share_for(index).unwrap();
+            assert_eq!(sk_share.index, index);
+            assert_eq!(vk_share.index(), index);
+            assert_eq!(ts_keys.vk_shares()[index - 1].index(), index);
+        }
+        assert!(ts_keys.share_for(0).is_none());
+        assert!(ts_keys.share_for(5).is_none());
+    }
 
     #[test]
     fn test_dist_keygen() {
@@ -174,10 +778,152 @@ mod tests {
             let reconstructed_y_k: Fr = reconstruct_secret(&y_k_shares_subset, threshold + 1);
             let computed_g_tilde_y_k = ck.g_tilde.mul(reconstructed_y_k).into_affine();
             assert_eq!(
-                computed_g_tilde_y_k, ck.ck_tilde[k],
+                computed_g_tilde_y_k, vk.g_tilde_y[k],
                 "Reconstructed y_{} verification failed",
                 k
             );
         }
     }
+
+    #[test]
+    fn test_keygen_proof() {
+        let mut rng = test_rng();
+        let (ck, vk, ts_keys) = keygen::<Bls12_381>(2, 3, 4, &mut rng);
+        assert!(ts_keys.proof.verify(&ck, &vk).unwrap());
+
+        // A g_tilde_y base with another exponent than its g_y counterpart
+        let mut tampered = vk.clone();
+        tampered.g_tilde_y[2] = (tampered.g_tilde_y[2] + ck.g_tilde).into_affine();
+        assert!(!ts_keys.proof.verify(&ck, &tampered).unwrap());
+
+        // Even a dealer proving for the tampered key with the real secrets fails the
+        // pairing check, the Schnorr proofs only cover g_y
+        let (x, y) = reconstruct_full_key(&ts_keys, 2);
+        let reproved = KeygenProof::prove(&ck, &tampered, &x, &y, &mut rng);
+        assert!(!reproved.verify(&ck, &tampered).unwrap());
+        assert!(KeygenProof::prove(&ck, &vk, &x, &y, &mut rng)
+            .verify(&ck, &vk)
+            .unwrap());
+
+        // Or a verification key the dealer can't open
+        let other = VerificationKey::<Bls12_381> {
+            g_tilde_x: (vk.g_tilde_x + ck.g_tilde).into_affine(),
+            ..vk.clone()
+        };
+        assert!(!ts_keys.proof.verify(&ck, &other).unwrap());
+
+        tampered.g_tilde_y.pop();
+        assert!(ts_keys.proof.verify(&ck, &tampered).is_err());
+    }
+
+    #[test]
+    fn test_vk_shares_derived_from_commitments() {
+        let mut rng = test_rng();
+        let (_, vk, ts_keys) = keygen::<Bls12_381>(3, 5, 4, &mut rng);
+        let transcript = &ts_keys.transcript;
+        assert!(transcript.matches(&vk));
+        assert_eq!(transcript.x_commitments.len(), 3);
+        for dealt in &ts_keys.vk_shares {
+            let derived = VerificationKeyShare::derive_from_commitments(transcript, dealt.index);
+            assert_eq!(derived.g_tilde_x_share, dealt.g_tilde_x_share);
+            assert_eq!(derived.g_tilde_y_shares, dealt.g_tilde_y_shares);
+        }
+        // Index 0 is the committee key itself
+        let at_zero = VerificationKeyShare::derive_from_commitments(transcript, 0);
+        assert_eq!(at_zero.g_tilde_x_share, vk.g_tilde_x);
+
+        let (_, other_vk, _) = keygen::<Bls12_381>(3, 5, 4, &mut rng);
+        assert!(!transcript.matches(&other_vk));
+    }
+
+    #[test]
+    fn test_validate_rejects_tampered_keys() {
+        use crate::errors::{KeyGenError, MessageError};
+        use ark_bls12_381::{Fq2, G2Affine};
+        use ark_serialize::{Compress, Validate};
+
+        let mut rng = test_rng();
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(2, 3, 2, &mut rng);
+        ts_keys.validate(&ck).unwrap();
+
+        // A G2 point on the curve but outside the prime order subgroup
+        let off_subgroup = (1u64..)
+            .find_map(|x| {
+                G2Affine::get_point_from_x_unchecked(Fq2::from(x), false)
+                    .filter(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+            })
+            .unwrap();
+        let mut tampered = ts_keys.clone();
+        tampered.vk_shares[1].g_tilde_y_shares[0] = off_subgroup;
+        let mut bytes = Vec::new();
+        tampered.serialize_uncompressed(&mut bytes).unwrap();
+        // Loaded without the checks of a validating deserialization
+        let loaded = ThresholdKeys::<Bls12_381>::deserialize_with_mode(
+            &bytes[..],
+            Compress::No,
+            Validate::No,
+        )
+        .unwrap();
+        assert!(matches!(
+            loaded.validate(&ck),
+            Err(KeyGenError::InvalidPoint(MessageError::InvalidPoint(
+                "g_tilde_y_share"
+            )))
+        ));
+
+        // A valid point, but not the share the commitments give
+        let mut swapped = ts_keys.clone();
+        swapped.vk_shares[2].g_tilde_x_share = ts_keys.vk_shares[0].g_tilde_x_share;
+        assert!(matches!(
+            swapped.validate(&ck),
+            Err(KeyGenError::ShareMismatch(3))
+        ));
+        let mut short = ts_keys.clone();
+        short.sk_shares.pop();
+        assert!(matches!(short.validate(&ck), Err(KeyGenError::ShareCount)));
+    }
+
+    // The dealer's x and y_k are zeroized after the shares and keys are derived
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_keys_consistent_with_zeroized_dealer_secrets() {
+        let mut rng = test_rng();
+        let (ck, vk, ts_keys) = keygen::<Bls12_381>(3, 5, 4, &mut rng);
+        let (x, y) = reconstruct_full_key(&ts_keys, 3);
+
+        assert_eq!(ck.g_tilde.mul(x).into_affine(), vk.g_tilde_x);
+        for (k, y_k) in y.iter().enumerate() {
+            assert_eq!(ck.g_tilde.mul(y_k).into_affine(), vk.g_tilde_y[k]);
+            assert_eq!(ck.g.mul(y_k).into_affine(), vk.g_y[k]);
+        }
+    }
+
+    #[test]
+    fn test_vk_shares_match_serial_exponentiation() {
+        use ark_serialize::CanonicalSerialize;
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+        let encode = |vk_shares: &[VerificationKeyShare<Bls12_381>]| {
+            let mut bytes = Vec::new();
+            vk_shares.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        };
+        let (ck, _, keys) = keygen::<Bls12_381>(3, 6, 4, &mut StdRng::seed_from_u64(7));
+        let (_, _, again) = keygen::<Bls12_381>(3, 6, 4, &mut StdRng::seed_from_u64(7));
+        assert_eq!(encode(&keys.vk_shares), encode(&again.vk_shares));
+
+        for (sk_share, vk_share) in keys.sk_shares.iter().zip(&keys.vk_shares) {
+            assert_eq!(vk_share.index(), sk_share.index);
+            assert_eq!(
+                vk_share.g_tilde_x_share,
+                ck.g_tilde.mul(sk_share.x_share).into_affine()
+            );
+            let serial: Vec<_> = sk_share
+                .y_shares
+                .iter()
+                .map(|y_share| ck.g_tilde.mul(y_share).into_affine())
+                .collect();
+            assert_eq!(vk_share.g_tilde_y_shares, serial);
+        }
+    }
 }
@@ -1,15 +1,94 @@
+pub mod accumulator;
+pub mod attributes;
+pub mod bls12_381;
+#[cfg(feature = "bn254")]
+pub mod bn254;
+#[cfg(feature = "async")]
+pub mod client;
 pub mod commitment;
+#[cfg(feature = "signing")]
 pub mod credential;
+mod ct;
+mod display;
+#[cfg(feature = "serde")]
+pub mod document;
 pub mod errors;
+#[cfg(all(feature = "ffi", feature = "signing"))]
+pub mod ffi;
 pub mod keygen;
+pub mod messages;
+#[cfg(feature = "signing")]
+pub mod misbehavior;
 pub mod nullifier;
 pub mod pairing;
+pub mod parallel;
+pub mod policy;
+pub mod presentation;
+#[cfg(feature = "profiling")]
+pub mod profiling;
 pub mod protocol;
+pub mod receipt;
+pub mod revocation;
 pub mod schnorr;
 pub mod schnorr_batch;
+#[cfg(feature = "zeroize")]
+pub mod secret;
+#[cfg(feature = "signing")]
+pub mod self_test;
+#[cfg(feature = "signing")]
+pub mod session;
+pub mod set_membership;
+#[cfg(feature = "signing")]
 pub mod shamir;
 pub mod signature;
+#[cfg(feature = "signing")]
 pub mod signer;
+pub mod sizes;
 pub mod symmetric_commitment;
-pub mod tests;
+#[cfg(feature = "signing")]
+pub mod system;
+#[cfg(all(test, feature = "signing"))]
+mod tests;
+pub mod transcript;
+#[cfg(feature = "signing")]
 pub mod user;
+#[cfg(feature = "signing")]
+pub mod wallet;
+
+/// The `rand` every public function is written against. Randomness always comes from
+/// the caller as `rng: &mut (impl Rng + CryptoRng)`, so any cryptographically secure
+/// generator over this `rand_core` works and no function draws from a global RNG
+///
+/// ```
+/// # #[cfg(feature = "signing")]
+/// # {
+/// use rand_chacha::ChaCha20Rng;
+/// use rand_core::OsRng;
+/// use t_siris::bls12_381::System;
+/// use t_siris::rand::rngs::StdRng;
+/// use t_siris::rand::SeedableRng;
+///
+/// let system = System::builder()
+///     .threshold(2)
+///     .signers(3)
+///     .attributes(2)
+///     .build(&mut OsRng)
+///     .unwrap();
+/// let user = system.user();
+/// user.request_credential(None, &mut StdRng::seed_from_u64(1)).unwrap();
+/// user.request_credential(None, &mut OsRng).unwrap();
+/// user.request_credential(None, &mut ChaCha20Rng::seed_from_u64(2)).unwrap();
+/// # }
+/// ```
+pub use ark_std::rand;
+
+/// `ark_std::test_rng` as the `StdRng` it wraps, which the `CryptoRng` bounds accept.
+/// Same seed, so fixtures generated with either stay valid
+#[cfg(test)]
+pub(crate) fn test_rng() -> rand::rngs::StdRng {
+    use rand::SeedableRng;
+    rand::rngs::StdRng::from_seed([
+        1, 0, 0, 0, 23, 0, 0, 0, 200, 1, 0, 0, 210, 30, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0,
+    ])
+}
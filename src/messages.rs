@@ -0,0 +1,400 @@
+//! Wire messages for the issuance and presentation flows.
+//!
+//! Every message has a canonical compressed encoding through `CanonicalSerialize`. With the
+//! `serde` feature the messages also implement serde, group and field elements are carried as
+//! their compressed bytes. The `TryFrom` conversions into the internal types only check the
+//! shape of a message, proofs and signatures are still checked by the signer and verifier.
+use crate::errors::MessageError;
+use crate::presentation::Presentation;
+use crate::signature::PartialSignature;
+#[cfg(feature = "signing")]
+use crate::{commitment::CommitmentProof, credential::CredentialCommitments};
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A holder's request for signature shares, sent to every signer
+#[cfg(feature = "signing")]
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(bound = ""))]
+pub struct IssuanceRequest<E: Pairing> {
+    /// Identifies the committee key the request is meant for
+    pub key_id: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "ark_bytes"))]
+    pub context: E::ScalarField,
+    #[cfg_attr(feature = "serde", serde(with = "ark_bytes"))]
+    pub commitments: CredentialCommitments<E>,
+}
+
+/// A single signer's answer to an `IssuanceRequest`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(bound = ""))]
+pub struct IssuanceResponse<E: Pairing> {
+    pub party_index: usize,
+    #[cfg_attr(feature = "serde", serde(with = "ark_bytes"))]
+    pub share: PartialSignature<E>,
+}
+
+/// A presentation as sent to a verifier
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(bound = ""))]
+pub struct PresentationMessage<E: Pairing> {
+    #[cfg_attr(feature = "serde", serde(with = "ark_bytes"))]
+    pub presentation: Presentation<E>,
+    /// Disclosed `(index, attribute)` pairs, strictly increasing by index. The opening
+    /// proof answers for each value, see `Presentation::verify_disclosed`
+    #[cfg_attr(feature = "serde", serde(with = "ark_bytes"))]
+    pub disclosed: Vec<(usize, E::ScalarField)>,
+    /// Verifier supplied freshness value, bound into the opening proof by
+    /// `present_message`
+    pub nonce: [u8; 32],
+}
+
+/// Check that an externally supplied point is on the curve, in the prime order subgroup
+/// and not the identity. Points that pass can go into `msm_unchecked`
+pub fn validate_point<G: AffineRepr>(point: &G, field: &'static str) -> Result<(), MessageError> {
+    if point.is_zero() {
+        return Err(MessageError::IdentityElement(field));
+    }
+    // The same curve and subgroup checks deserialization runs
+    point.check().map_err(|_| MessageError::InvalidPoint(field))
+}
+
+/// Validate the points of a credential request: `h`, every commitment and the
+/// commitments carried by each serialized opening proof. `proofs[k]` must be the
+/// proof for `commitments[k]`; batch verification alone would accept them in any order
+#[cfg(feature = "signing")]
+pub(crate) fn validate_commitment_points<E: Pairing>(
+    h: &E::G1Affine,
+    commitments: &[E::G1Affine],
+    proofs: &[Vec<u8>],
+) -> Result<(), MessageError> {
+    if commitments.len() != proofs.len() {
+        return Err(MessageError::LengthMismatch {
+            commitments: commitments.len(),
+            proofs: proofs.len(),
+        });
+    }
+    validate_point(h, "h")?;
+    for (k, (commitment, proof)) in commitments.iter().zip(proofs).enumerate() {
+        validate_point(commitment, "commitment")?;
+        // Deserializing checks curve and subgroup membership, which leaves the identity
+        let proof = CommitmentProof::<E>::deserialize_compressed(&proof[..])?;
+        validate_point(&proof.commitment, "proof commitment")?;
+        validate_point(&proof.schnorr_commitment, "schnorr_commitment")?;
+        if proof.commitment != *commitment {
+            return Err(MessageError::ProofCommitmentMismatch(k));
+        }
+    }
+    Ok(())
+}
+
+/// Validate the points of a signer's share
+pub(crate) fn validate_share_points<E: Pairing>(
+    share: &PartialSignature<E>,
+) -> Result<(), MessageError> {
+    validate_point(&share.h, "h")?;
+    validate_point(&share.sigma, "sigma")
+}
+
+#[cfg(feature = "signing")]
+impl<E: Pairing> IssuanceRequest<E> {
+    pub fn validate(&self) -> Result<(), MessageError> {
+        let request = &self.commitments;
+        if request.commitments.is_empty() {
+            return Err(MessageError::EmptyRequest);
+        }
+        validate_commitment_points::<E>(&request.h, &request.commitments, &request.proofs)
+    }
+}
+
+#[cfg(feature = "signing")]
+impl<E: Pairing> TryFrom<IssuanceRequest<E>> for CredentialCommitments<E> {
+    type Error = MessageError;
+
+    fn try_from(request: IssuanceRequest<E>) -> Result<Self, Self::Error> {
+        request.validate()?;
+        Ok(request.commitments)
+    }
+}
+
+impl<E: Pairing> IssuanceResponse<E> {
+    pub fn validate(&self) -> Result<(), MessageError> {
+        if self.party_index == 0 || self.party_index != self.share.party_index {
+            return Err(MessageError::InvalidPartyIndex(self.party_index));
+        }
+        validate_share_points(&self.share)
+    }
+}
+
+impl<E: Pairing> TryFrom<IssuanceResponse<E>> for (usize, PartialSignature<E>) {
+    type Error = MessageError;
+
+    fn try_from(response: IssuanceResponse<E>) -> Result<Self, Self::Error> {
+        response.validate()?;
+        Ok((response.party_index, response.share))
+    }
+}
+
+impl<E: Pairing> PresentationMessage<E> {
+    pub fn validate(&self) -> Result<(), MessageError> {
+        if self.disclosed.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(MessageError::UnorderedDisclosure);
+        }
+        if self.presentation.proof.is_empty() {
+            return Err(MessageError::EmptyProof);
+        }
+        if self.presentation.signature.h.is_zero() {
+            return Err(MessageError::IdentityElement("h"));
+        }
+        Ok(())
+    }
+}
+
+impl<E: Pairing> TryFrom<PresentationMessage<E>> for Presentation<E> {
+    type Error = MessageError;
+
+    fn try_from(message: PresentationMessage<E>) -> Result<Self, Self::Error> {
+        message.validate()?;
+        Ok(message.presentation)
+    }
+}
+
+/// serde adapter carrying any arkworks value as its compressed encoding
+#[cfg(feature = "serde")]
+mod ark_bytes {
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use serde::de::Error as _;
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: CanonicalSerialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut bytes = Vec::with_capacity(value.compressed_size());
+        value
+            .serialize_compressed(&mut bytes)
+            .map_err(S::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+
+    pub fn deserialize<'de, T: CanonicalDeserialize, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        T::deserialize_compressed(&bytes[..]).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::errors::SignatureError;
+    use crate::keygen::keygen;
+    use crate::protocol::{IssuerProtocol, UserProtocol, VerifierProtocol};
+    use crate::signer::Signer;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fq, Fr, G1Affine};
+
+    fn round_trip<T: CanonicalSerialize + CanonicalDeserialize>(value: &T) -> T {
+        let mut bytes = Vec::new();
+        value.serialize_compressed(&mut bytes).unwrap();
+        T::deserialize_compressed(&bytes[..]).unwrap()
+    }
+
+    #[test]
+    fn test_message_flow_round_trip() {
+        let mut rng = test_rng();
+        let threshold = 2;
+        let (ck, vk, ts_keys) = keygen::<Bls12_381>(threshold, 3, 3, &mut rng);
+
+        let (mut credential, request) =
+            UserProtocol::request_issuance(b"committee-1", ck.clone(), None, &mut rng).unwrap();
+        let request = round_trip(&request);
+        assert_eq!(request.key_id, b"committee-1");
+        assert_eq!(request.context, credential.context);
+
+        let responses: Vec<_> = ts_keys
+            .sk_shares
+            .iter()
+            .zip(ts_keys.vk_shares.iter())
+            .map(|(sk, vk)| {
+                let response = IssuerProtocol::respond_to_issuance(
+                    &Signer::new(&ck, sk, vk),
+                    &request,
+                    &mut rng,
+                )
+                .unwrap();
+                round_trip(&response)
+            })
+            .collect();
+
+        UserProtocol::complete_issuance(
+            &ck,
+            &vk,
+            &ts_keys.vk_shares,
+            &mut credential,
+            &request,
+            &responses,
+            threshold,
+            &mut rng,
+        )
+        .unwrap();
+
+        let domain = credential.domain().clone();
+        let message = round_trip(
+            &UserProtocol::present_message(&credential, [7u8; 32], &[1], &mut rng).unwrap(),
+        );
+        assert_eq!(message.nonce, [7u8; 32]);
+        assert_eq!(message.disclosed, vec![(1, credential.get_messages()[1])]);
+        assert!(VerifierProtocol::verify_message(&ck, &vk, &domain, &message, &mut rng).unwrap());
+
+        // A forged value, a hidden attribute passed off as disclosed, or another nonce
+        let mut forged = message.clone();
+        forged.disclosed[0].1 += Fr::from(1u64);
+        assert!(VerifierProtocol::verify_message(&ck, &vk, &domain, &forged, &mut rng).is_err());
+        let mut forged = message.clone();
+        forged.disclosed.push((2, credential.get_messages()[2]));
+        assert!(VerifierProtocol::verify_message(&ck, &vk, &domain, &forged, &mut rng).is_err());
+        let mut forged = message.clone();
+        forged.nonce = [8u8; 32];
+        assert!(VerifierProtocol::verify_message(&ck, &vk, &domain, &forged, &mut rng).is_err());
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&message).unwrap();
+            let decoded: PresentationMessage<Bls12_381> = serde_json::from_str(&json).unwrap();
+            assert!(
+                VerifierProtocol::verify_message(&ck, &vk, &domain, &decoded, &mut rng).unwrap()
+            );
+
+            let json = serde_json::to_string(&request).unwrap();
+            let decoded: IssuanceRequest<Bls12_381> = serde_json::from_str(&json).unwrap();
+            assert_eq!(
+                decoded.commitments.commitments,
+                request.commitments.commitments
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_validation_failures() {
+        let mut rng = test_rng();
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(2, 3, 3, &mut rng);
+        let (_, request) =
+            UserProtocol::request_issuance(b"committee-1", ck.clone(), None, &mut rng).unwrap();
+
+        let mut truncated = request.clone();
+        truncated.commitments.proofs.pop();
+        assert!(matches!(
+            CredentialCommitments::try_from(truncated),
+            Err(MessageError::LengthMismatch {
+                commitments: 3,
+                proofs: 2
+            })
+        ));
+
+        let signer = Signer::new(&ck, &ts_keys.sk_shares[0], &ts_keys.vk_shares[0]);
+        let mut response =
+            IssuerProtocol::respond_to_issuance(&signer, &request, &mut rng).unwrap();
+        response.party_index += 1;
+        assert!(matches!(
+            <(usize, PartialSignature<Bls12_381>)>::try_from(response),
+            Err(MessageError::InvalidPartyIndex(_))
+        ));
+
+        let signature = crate::signature::ThresholdSignature {
+            h: request.commitments.h,
+            sigma: request.commitments.h,
+        };
+        let message = PresentationMessage::<Bls12_381> {
+            presentation: Presentation {
+                signature,
+                commitment: ck.g,
+                commitment_tilde: ck.g_tilde,
+                proof: vec![1],
+                nonce: None,
+            },
+            disclosed: vec![(2, 1u64.into()), (1, 2u64.into())],
+            nonce: [0u8; 32],
+        };
+        assert!(matches!(
+            Presentation::try_from(message.clone()),
+            Err(MessageError::UnorderedDisclosure)
+        ));
+
+        let mut empty_proof = message;
+        empty_proof.disclosed.clear();
+        empty_proof.presentation.proof.clear();
+        assert!(matches!(
+            Presentation::try_from(empty_proof),
+            Err(MessageError::EmptyProof)
+        ));
+    }
+
+    /// A point on the G1 curve outside the prime order subgroup
+    fn point_outside_subgroup() -> G1Affine {
+        (1u64..)
+            .filter_map(|x| G1Affine::get_point_from_x_unchecked(Fq::from(x), true))
+            .find(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rejects_points_outside_subgroup() {
+        let mut rng = test_rng();
+        let (ck, _, ts_keys) = keygen::<Bls12_381>(2, 3, 3, &mut rng);
+        let signer = Signer::new(&ck, &ts_keys.sk_shares[0], &ts_keys.vk_shares[0]);
+        let (_, request) =
+            UserProtocol::request_issuance(b"committee-1", ck.clone(), None, &mut rng).unwrap();
+
+        let mut outside = request.clone();
+        outside.commitments.commitments[1] = point_outside_subgroup();
+        assert!(matches!(
+            outside.validate(),
+            Err(MessageError::InvalidPoint("commitment"))
+        ));
+        assert!(matches!(
+            IssuerProtocol::respond_to_issuance(&signer, &outside, &mut rng),
+            Err(SignatureError::InvalidMessage(MessageError::InvalidPoint(
+                "commitment"
+            )))
+        ));
+
+        // The signer checks raw inputs too, not only wrapped messages
+        let identity_h = G1Affine::zero();
+        assert!(matches!(
+            signer.sign_share(
+                &request.commitments.commitments,
+                &request.commitments.proofs,
+                &identity_h,
+                &mut rng
+            ),
+            Err(SignatureError::IdentityH)
+        ));
+
+        let mut response =
+            IssuerProtocol::respond_to_issuance(&signer, &request, &mut rng).unwrap();
+        response.share.sigma = point_outside_subgroup();
+        assert!(matches!(
+            response.validate(),
+            Err(MessageError::InvalidPoint("sigma"))
+        ));
+        assert!(matches!(
+            UserProtocol::verify_signature_shares(
+                &ck,
+                &ts_keys.vk_shares,
+                &request.commitments,
+                &[(response.party_index, response.share)],
+                1,
+                &mut rng,
+            ),
+            Err(SignatureError::InvalidMessage(MessageError::InvalidPoint(
+                "sigma"
+            )))
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+//! Evidence against signers that return invalid shares.
+//!
+//! A `MisbehaviorReport` carries the request's commitments and the share a signer
+//! returned for them. Anyone holding the signer's `VerificationKeyShare` can re-run the
+//! share equation and confirm the share is invalid for that request, so a report against
+//! a signer whose share was valid is rejected.
+//!
+//! Shares are not signed by their signer. The report proves that the share is invalid
+//! for the reported commitments; that the signer returned it for exactly that request
+//! is left to the channel the share came over, e.g. an authenticated connection or a
+//! signature over the request and the `IssuanceResponse`.
+use crate::keygen::VerificationKeyShare;
+use crate::pairing::PairingCheck;
+use crate::signature::PartialSignature;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::user::User;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::One;
+
+/// A share that failed verification and the request it was returned for
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MisbehaviorReport<E: Pairing> {
+    /// Party index of the accused signer
+    pub signer_index: usize,
+    /// Commitments of the request the share was meant to sign
+    pub commitments: Vec<E::G1Affine>,
+    pub share: PartialSignature<E>,
+}
+
+impl<E: Pairing> MisbehaviorReport<E> {
+    /// Whether the report holds up: it names the owner of `vk_share` and the share
+    /// fails the share equation under `vk_share` for the reported commitments. The
+    /// single equation is checked without randomization
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk_share: &VerificationKeyShare<E>,
+    ) -> bool {
+        if self.signer_index != vk_share.index || self.share.party_index != vk_share.index {
+            return false;
+        }
+        let pairs = User::share_pairs(ck, vk_share, &self.commitments, &self.share);
+        !PairingCheck::<E>::from_pairs(&pairs, &E::TargetField::one()).verify()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::Bls12_381;
+
+    #[test]
+    fn test_reports_only_hold_for_invalid_shares() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let (_, request) = system.user().request_credential(None, &mut rng).unwrap();
+        let mut shares: Vec<_> = system
+            .signers()
+            .iter()
+            .map(|signer| {
+                let share = signer.sign_request(&request, false, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        shares[2].1.sigma = shares[0].1.sigma;
+
+        let verified = User::process_signature_shares(
+            &system.ck,
+            system.vk_shares(),
+            &request.commitments,
+            &request.proofs,
+            &shares,
+            2,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(verified.failed(), vec![3]);
+        let report = &verified.reports[0];
+        assert!(report.verify(&system.ck, &system.vk_shares()[2]));
+        // Only the accused signer's key confirms it
+        assert!(!report.verify(&system.ck, &system.vk_shares()[1]));
+
+        let mut bytes = Vec::new();
+        report.serialize_compressed(&mut bytes).unwrap();
+        let loaded = MisbehaviorReport::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(loaded.verify(&system.ck, &system.vk_shares()[2]));
+
+        // A report fabricated around the honest share of signer 1
+        let fabricated = MisbehaviorReport {
+            signer_index: 1,
+            commitments: request.commitments.clone(),
+            share: shares[0].1.clone(),
+        };
+        assert!(!fabricated.verify(&system.ck, &system.vk_shares()[0]));
+    }
+}
@@ -19,10 +19,14 @@
  * - Binds the VRF output to specific committed values
  */
 
+use crate::ct::ct_inverse;
 use ark_ec::{AffineRepr, CurveGroup};
 use ark_ff::Field;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{rand::Rng, UniformRand, Zero};
+use ark_std::{
+    rand::{CryptoRng, Rng},
+    UniformRand, Zero,
+};
 use core::marker::PhantomData;
 
 /// Input to the Private Pairing-Free VRF
@@ -41,6 +45,17 @@ pub struct DYPFPrivVRFWitness<F> {
     pub r_x: F,  // Randomness for input commitment
 }
 
+impl<F: Field> DYPFPrivVRFWitness<F> {
+    /// The witness for `cm_x` rerandomized by `r_delta`, see
+    /// `DYPFPrivVRF::rerandomize_input_commitment`. `sk`, `x` and so `y` are unchanged
+    pub fn with_rerandomized_input(&self, r_delta: &F) -> Self {
+        Self {
+            r_x: self.r_x + r_delta,
+            ..self.clone()
+        }
+    }
+}
+
 /// Public key and commitments for the Private Pairing-Free VRF
 #[derive(Clone, Debug)]
 pub struct DYPFPrivPublicKey<G: AffineRepr> {
@@ -50,11 +65,29 @@ pub struct DYPFPrivPublicKey<G: AffineRepr> {
 
 /// Secret key for the Private Pairing-Free VRF
 #[derive(Clone, Debug)]
-pub struct DYPFPrivSecretKey<F> {
+pub struct DYPFPrivSecretKey<F: Field> {
     pub sk: F,   // Secret key
     pub r_sk: F, // Randomness used in commitment
 }
 
+#[cfg(feature = "zeroize")]
+impl<F: Field> zeroize::Zeroize for DYPFPrivSecretKey<F> {
+    fn zeroize(&mut self) {
+        self.sk.zeroize();
+        self.r_sk.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: Field> Drop for DYPFPrivSecretKey<F> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: Field> zeroize::ZeroizeOnDrop for DYPFPrivSecretKey<F> {}
+
 /// Output of the Private Pairing-Free VRF
 #[derive(Clone, Debug)]
 pub struct DYPFPrivVRFOutput<G: AffineRepr> {
@@ -74,6 +107,25 @@ pub struct DYPFPrivVRFProof<G: AffineRepr> {
     pub z_m: G::ScalarField,    // z_m = (a_sk + a_x) + c*(sk + x)
 }
 
+impl<G: AffineRepr> DYPFPrivVRFProof<G> {
+    /// Length of the compressed encoding in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+}
+
+/// Prover state between the announcement and the response of the Σ-protocol
+#[derive(Clone, Debug)]
+pub struct DYPFPrivVRFProofCommitment<G: AffineRepr> {
+    a_sk: G::ScalarField,
+    a_x: G::ScalarField,
+    a_r_sk: G::ScalarField,
+    a_r_x: G::ScalarField,
+    pub t1: G,
+    pub t2: G,
+    pub ty: G,
+}
+
 /// Public parameters for the Private Pairing-Free VRF
 pub struct DYPFPrivVRFPublicParams<G: AffineRepr> {
     pub g: G,  // Generator of the prime-order group
@@ -89,7 +141,7 @@ pub struct DYPFPrivVRF<G: AffineRepr> {
 
 impl<G: AffineRepr> DYPFPrivVRF<G> {
     /// Initialize a new P-DY-Priv VRF with random generators
-    pub fn new<R: Rng>(rng: &mut R) -> Self {
+    pub fn new<R: Rng + CryptoRng>(rng: &mut R) -> Self {
         let g = G::Group::rand(rng).into_affine();
         let g1 = G::Group::rand(rng).into_affine();
         let g2 = G::Group::rand(rng).into_affine();
@@ -110,7 +162,7 @@ impl<G: AffineRepr> DYPFPrivVRF<G> {
 
     /// Generate keys with commitments: VRF.Gen(1^λ) → (sk, pk, cm_sk)
     /// Sample sk ←$ Z_p*, r_sk ←$ Z_p*, compute pk = g^sk, cm_sk = g1^sk * g^r_sk
-    pub fn generate_keys<R: Rng>(
+    pub fn generate_keys<R: Rng + CryptoRng>(
         &self,
         rng: &mut R,
     ) -> (DYPFPrivSecretKey<G::ScalarField>, DYPFPrivPublicKey<G>) {
@@ -132,7 +184,7 @@ impl<G: AffineRepr> DYPFPrivVRF<G> {
     pub fn commit_to_input(
         &self,
         x: &G::ScalarField,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> (DYPFPrivVRFInput<G::ScalarField>, G) {
         let r_x = G::ScalarField::rand(rng);
 
@@ -142,14 +194,25 @@ impl<G: AffineRepr> DYPFPrivVRF<G> {
         (DYPFPrivVRFInput { x: *x, r_x }, cm_x)
     }
 
+    /// `cm_x * g^r_delta`, a fresh commitment to the same `x` under `r_x + r_delta`.
+    /// Prove against it with `DYPFPrivVRFWitness::with_rerandomized_input` and a fresh
+    /// `commit_proof`: only `z_r_x` depends on `r_x`, so adjusting an old proof would
+    /// reuse its announcements and link the two proofs
+    pub fn rerandomize_input_commitment(&self, cm_x: &G, r_delta: &G::ScalarField) -> G {
+        (cm_x.into_group() + self.pp.g.mul(*r_delta)).into_affine()
+    }
+
     /// Evaluate: VRF.Eval(sk, x) → y
     /// Compute y = g^(1/(sk+x)) ∈ G
     pub fn evaluate(
         &self,
         witness: &DYPFPrivVRFWitness<G::ScalarField>,
     ) -> Result<DYPFPrivVRFOutput<G>, &'static str> {
-        // Compute 1/(sk+x)
-        let exponent = (witness.sk + witness.x).inverse().ok_or("sk + x is zero")?;
+        // Compute 1/(sk+x) in constant time, only the public failure case branches
+        let (exponent, is_zero) = ct_inverse(&(witness.sk + witness.x));
+        if bool::from(is_zero) {
+            return Err("sk + x is zero");
+        }
 
         // Compute y = g^(1/(sk+x))
         let y = self.pp.g.mul(exponent).into_affine();
@@ -159,7 +222,7 @@ impl<G: AffineRepr> DYPFPrivVRF<G> {
 
     /// Prove: VRF.Prove(sk, x, r_sk, r_x, y) → π
     /// Generate proof π using the Σ-protocol from Protocol 3
-    pub fn prove<R: Rng>(
+    pub fn prove<R: Rng + CryptoRng>(
         &self,
         witness: &DYPFPrivVRFWitness<G::ScalarField>,
         output: &DYPFPrivVRFOutput<G>,
@@ -215,17 +278,37 @@ impl<G: AffineRepr> DYPFPrivVRF<G> {
         witness: &DYPFPrivVRFWitness<G::ScalarField>,
         output: &DYPFPrivVRFOutput<G>,
         challenge: &G::ScalarField,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> DYPFPrivVRFProof<G> {
+        let commitment = self.commit_proof(output, rng);
+        self.respond(&commitment, witness, challenge)
+    }
+
+    /// First move of the Σ-protocol: sample blindings and compute T₁, T₂, T_y.
+    /// Lets the announcements go into a transcript before the challenge is derived
+    pub fn commit_proof(
+        &self,
+        output: &DYPFPrivVRFOutput<G>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> DYPFPrivVRFProofCommitment<G> {
         // 1. Commitment phase: Sample random values
         let a_sk = G::ScalarField::rand(rng);
+        self.commit_proof_with_sk_blinding(output, a_sk, rng)
+    }
+
+    /// `commit_proof` with the given blinding for `sk`. A proof answering the same
+    /// challenge as an opening proof that used `a_sk` for an attribute has `z_sk` equal
+    /// to that attribute's response exactly when `sk` is the attribute
+    pub fn commit_proof_with_sk_blinding(
+        &self,
+        output: &DYPFPrivVRFOutput<G>,
+        a_sk: G::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> DYPFPrivVRFProofCommitment<G> {
         let a_x = G::ScalarField::rand(rng);
         let a_r_sk = G::ScalarField::rand(rng);
         let a_r_x = G::ScalarField::rand(rng);
 
-        // Compute a_sk + a_x for T_y
-        let a_sk_plus_a_x = a_sk + a_x;
-
         // Compute T₁ = g1^a_sk * g^a_r_sk
         let t1 = (self.pp.g1.mul(a_sk) + self.pp.g.mul(a_r_sk)).into_affine();
 
@@ -233,24 +316,41 @@ impl<G: AffineRepr> DYPFPrivVRF<G> {
         let t2 = (self.pp.g2.mul(a_x) + self.pp.g.mul(a_r_x)).into_affine();
 
         // Compute T_y = y^(a_sk + a_x)
-        let ty = output.y.mul(a_sk_plus_a_x).into_affine();
+        let ty = output.y.mul(a_sk + a_x).into_affine();
 
-        // Use provided challenge
+        DYPFPrivVRFProofCommitment {
+            a_sk,
+            a_x,
+            a_r_sk,
+            a_r_x,
+            t1,
+            t2,
+            ty,
+        }
+    }
+
+    /// Final move of the Σ-protocol: responses to `challenge`
+    pub fn respond(
+        &self,
+        commitment: &DYPFPrivVRFProofCommitment<G>,
+        witness: &DYPFPrivVRFWitness<G::ScalarField>,
+        challenge: &G::ScalarField,
+    ) -> DYPFPrivVRFProof<G> {
         let c = *challenge;
 
         // 3. Response phase: Compute z values
-        let z_sk = a_sk + (c * witness.sk);
-        let z_x = a_x + (c * witness.x);
-        let z_r_sk = a_r_sk + (c * witness.r_sk);
-        let z_r_x = a_r_x + (c * witness.r_x);
+        let z_sk = commitment.a_sk + (c * witness.sk);
+        let z_x = commitment.a_x + (c * witness.x);
+        let z_r_sk = commitment.a_r_sk + (c * witness.r_sk);
+        let z_r_x = commitment.a_r_x + (c * witness.r_x);
 
         // Compute z_m = (a_sk + a_x) + c * (sk + x)
-        let z_m = a_sk_plus_a_x + (c * (witness.sk + witness.x));
+        let z_m = (commitment.a_sk + commitment.a_x) + (c * (witness.sk + witness.x));
 
         DYPFPrivVRFProof {
-            t1,
-            t2,
-            ty,
+            t1: commitment.t1,
+            t2: commitment.t2,
+            ty: commitment.ty,
             z_sk,
             z_x,
             z_r_sk,
@@ -297,9 +397,9 @@ impl<G: AffineRepr> DYPFPrivVRF<G> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_rng;
     use ark_bls12_381::{Fr, G1Affine};
     use ark_std::ops::Mul;
-    use ark_std::test_rng;
 
     #[test]
     fn test_pdy_priv_vrf_complete_protocol() {
@@ -338,4 +438,48 @@ mod tests {
         let is_valid = vrf.verify(&pk, &output, &proof, &challenge);
         assert!(is_valid, "P-DY-Priv VRF verification failed");
     }
+
+    #[test]
+    fn test_rerandomized_input_commitment() {
+        let mut rng = test_rng();
+        let vrf = DYPFPrivVRF::<G1Affine>::new(&mut rng);
+        let (sk, _) = vrf.generate_keys(&mut rng);
+        let x = Fr::rand(&mut rng);
+        let (input, cm_x) = vrf.commit_to_input(&x, &mut rng);
+        let witness = DYPFPrivVRFWitness {
+            sk: sk.sk,
+            r_sk: sk.r_sk,
+            x: input.x,
+            r_x: input.r_x,
+        };
+        let cm_sk = (vrf.pp.g1.mul(sk.sk) + vrf.pp.g.mul(sk.r_sk)).into_affine();
+
+        let r_delta = Fr::rand(&mut rng);
+        let fresh_cm_x = vrf.rerandomize_input_commitment(&cm_x, &r_delta);
+        let fresh_witness = witness.with_rerandomized_input(&r_delta);
+        assert_ne!(fresh_cm_x, cm_x);
+        assert_eq!(
+            fresh_cm_x,
+            (vrf.pp.g2.mul(x) + vrf.pp.g.mul(fresh_witness.r_x)).into_affine()
+        );
+
+        // Same y, and a proof for each commitment
+        let output = vrf.evaluate(&witness).unwrap();
+        assert_eq!(vrf.evaluate(&fresh_witness).unwrap().y, output.y);
+        for (cm_x, witness) in [(cm_x, &witness), (fresh_cm_x, &fresh_witness)] {
+            let pk = DYPFPrivPublicKey { cm_sk, cm_x };
+            let challenge = Fr::rand(&mut rng);
+            let proof = vrf.prove_with_challenge(witness, &output, &challenge, &mut rng);
+            assert!(vrf.verify(&pk, &output, &proof, &challenge));
+        }
+
+        // The old witness doesn't open the new commitment
+        let pk = DYPFPrivPublicKey {
+            cm_sk,
+            cm_x: fresh_cm_x,
+        };
+        let challenge = Fr::rand(&mut rng);
+        let proof = vrf.prove_with_challenge(&witness, &output, &challenge, &mut rng);
+        assert!(!vrf.verify(&pk, &output, &proof, &challenge));
+    }
 }
@@ -1,16 +1,24 @@
+use crate::ct::rand_batching_scalar;
 use ark_ec::{
     pairing::{MillerLoopOutput, Pairing, PairingOutput},
-    CurveGroup,
+    AffineRepr, CurveGroup,
 };
 // {AffineCurve, PairingEngine, ProjectiveCurve};
 use ark_ff::{Field, PrimeField};
-use ark_std::test_rng;
-// use ark_std::{ops::Mul, rand::Rng,  sync::Mutex, One, UniformRand, Zero};
-use ark_std::{ops::Mul, rand::Rng, sync::Mutex, One, UniformRand, Zero};
+use ark_std::{
+    ops::Mul,
+    rand::{CryptoRng, Rng},
+    One, Zero,
+};
 // use itertools::Itertools;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use std::ops::MulAssign;
 
+/// Checks with at most this many pairs run sequentially, for the two-pair checks of
+/// signature verification the rayon overhead costs more than the Miller loops
+const SEQUENTIAL_PAIRS: usize = 4;
+
 // https://github.com/nikkolasg/snarkpack/blob/main/src/pairing_check.rs
 /// PairingCheck represents a check of the form e(A,B)e(C,D)... = T. Checks can
 /// be aggregated together using random linear combination. The efficiency comes
@@ -18,9 +26,9 @@ use std::ops::MulAssign;
 /// exponentiation when verifying if all checks are verified.
 /// It is a tuple:
 /// - a miller loop result that is to be multiplied by other miller loop results
-/// before going into a final exponentiation result
+///   before going into a final exponentiation result
 /// - a right side result which is already in the right subgroup Gt which is to
-/// be compared to the left side when "final_exponentiatiat"-ed
+///   be compared to the left side when "final_exponentiatiat"-ed
 #[derive(Debug, Copy, Clone)]
 pub struct PairingCheck<E: Pairing> {
     left: <E as Pairing>::TargetField,
@@ -30,6 +38,15 @@ pub struct PairingCheck<E: Pairing> {
     non_randomized: u8,
 }
 
+impl<E> Default for PairingCheck<E>
+where
+    E: Pairing,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<E> PairingCheck<E>
 where
     E: Pairing,
@@ -91,6 +108,21 @@ where
         Self::from_pair(product, right)
     }
 
+    /// The check `\prod_i e(a_i, b_i) = out` with a single multi Miller loop and no
+    /// coefficient. Like `from_products` it counts as non randomized until it is
+    /// merged with `merge_randomized` or `combine_all`
+    pub fn from_pairs(
+        it: &[(&E::G1Affine, &E::G2Affine)],
+        out: &<E as Pairing>::TargetField,
+    ) -> PairingCheck<E> {
+        if it.is_empty() {
+            return Self::new_invalid();
+        }
+        let miller_out =
+            E::multi_miller_loop(it.iter().map(|(a, _)| **a), it.iter().map(|(_, b)| **b));
+        Self::from_pair(miller_out.0, *out)
+    }
+
     /// returns a pairing tuple that is scaled by a random element.
     /// When aggregating pairing checks, this creates a random linear
     /// combination of all checks so that it is secure. Specifically
@@ -99,26 +131,35 @@ where
     /// e(rA,B)e(rC,D) ... = out^r <=>
     /// e(A,B)^r e(C,D)^r = out^r <=> e(g,h)^{abr + cdr} = out^r
     /// (e(g,h)^{ab + cd})^r = out^r
-    pub fn rand<'a, R: Rng + Send>(
-        rng: &Mutex<R>,
+    ///
+    /// The coefficient is drawn from the caller's `rng`, so no shared state or
+    /// locking is needed and the check works on targets without threads.
+    ///
+    /// An empty `it` gives an invalid check: the empty product is one, and a
+    /// comparison of one against `out` proves nothing about any pairing.
+    ///
+    /// Up to `SEQUENTIAL_PAIRS` pairs are scaled with one batch normalization and go
+    /// through a single multi Miller loop. Raising the Miller loop output to the
+    /// coefficient instead of scaling the G1 side is equivalent but slower.
+    pub fn rand<'a, R: Rng + CryptoRng>(
+        rng: &mut R,
         it: &[(&'a E::G1Affine, &'a E::G2Affine)],
         out: &'a <E as Pairing>::TargetField,
     ) -> PairingCheck<E> {
-        let coeff = rand_fr::<E, R>(&rng);
-        let miller_out = it
-            .into_par_iter()
-            .map(|(a, b)| {
-                let na = a.mul(coeff).into_affine();
-                (E::G1Prepared::from(na), E::G2Prepared::from(**b))
-            })
-            .map(|(a, b)| E::miller_loop(a, b))
-            .map(|res| res.0)
-            .product();
-        let mut outt = out.clone();
+        if it.is_empty() {
+            return Self::new_invalid();
+        }
+        let coeff = rand_batching_scalar::<E::ScalarField, R>(rng);
+        let miller_out = if it.len() <= SEQUENTIAL_PAIRS {
+            Self::miller_sequential(it, &coeff)
+        } else {
+            Self::miller_parallel(it, &coeff)
+        };
+        let mut outt = *out;
         if out != &<E as Pairing>::TargetField::one() {
             // we only need to make this expensive operation is the output is
             // not one since 1^r = 1
-            outt = outt.pow(&(coeff.into_bigint()));
+            outt = outt.pow(coeff.into_bigint());
         }
         PairingCheck {
             left: miller_out,
@@ -127,15 +168,101 @@ where
         }
     }
 
+    /// The checks `\prod_j e(a_j, b_j) = 1` of several `equations` in one multi Miller
+    /// loop over all their pairs. Every equation after the first has its G1 side scaled
+    /// by its own nonzero coefficient, which is enough to keep a failing equation from
+    /// being cancelled by another, so the first one isn't scaled at all. An empty
+    /// equation makes the check invalid, as in `rand`.
+    ///
+    /// With its first equation unscaled the result counts as non randomized, like
+    /// `from_pairs`, until it is merged with `merge_randomized` or `combine_all`
+    pub fn rand_equations<R: Rng + CryptoRng>(
+        rng: &mut R,
+        equations: &[&[(&E::G1Affine, &E::G2Affine)]],
+    ) -> PairingCheck<E> {
+        if equations.is_empty() || equations.iter().any(|pairs| pairs.is_empty()) {
+            return Self::new_invalid();
+        }
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        for (j, pairs) in equations.iter().enumerate() {
+            let coeff = (j > 0).then(|| rand_batching_scalar::<E::ScalarField, R>(rng));
+            for (a, b) in pairs.iter() {
+                left.push(match coeff {
+                    Some(coeff) => a.mul(coeff),
+                    None => a.into_group(),
+                });
+                right.push(**b);
+            }
+        }
+        let miller_out = E::multi_miller_loop(E::G1::normalize_batch(&left), right);
+        Self::from_pair(miller_out.0, <E as Pairing>::TargetField::one())
+    }
+
+    /// `\prod_i ML(coeff * a_i, b_i)` with one multi Miller loop
+    fn miller_sequential(
+        it: &[(&E::G1Affine, &E::G2Affine)],
+        coeff: &E::ScalarField,
+    ) -> <E as Pairing>::TargetField {
+        let scaled: Vec<E::G1> = it.iter().map(|(a, _)| a.mul(*coeff)).collect();
+        let scaled = E::G1::normalize_batch(&scaled);
+        E::multi_miller_loop(scaled, it.iter().map(|(_, b)| **b)).0
+    }
+
+    /// `miller_sequential` with a Miller loop per pair, spread over rayon's pool
+    fn miller_parallel(
+        it: &[(&E::G1Affine, &E::G2Affine)],
+        coeff: &E::ScalarField,
+    ) -> <E as Pairing>::TargetField {
+        #[cfg(feature = "parallel")]
+        let pairs = it.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let pairs = it.iter();
+        pairs
+            .map(|(a, b)| {
+                let na = a.mul(*coeff).into_affine();
+                (E::G1Prepared::from(na), E::G2Prepared::from(**b))
+            })
+            .map(|(a, b)| E::miller_loop(a, b))
+            .map(|res| res.0)
+            .product()
+    }
+
     /// takes another pairing tuple and combine both sides together. Note the checks are not
     /// randomized when merged, the checks must have been randomized before.
     pub fn merge(&mut self, p2: &PairingCheck<E>) {
-        mul_if_not_one::<E>(&mut self.left, &p2.left);
-        mul_if_not_one::<E>(&mut self.right, &p2.right);
+        // Always multiply, comparing Miller loop outputs with one would branch on them
+        self.left.mul_assign(&p2.left);
+        self.right.mul_assign(&p2.right);
         // A merged PairingCheck is only randomized if both of its contributors are.
         self.non_randomized += p2.non_randomized;
     }
 
+    /// Raise both sides of `other` to `coeff` and merge it. `other` then counts as
+    /// randomized, so any number of checks from `from_pairs` can be merged this way
+    /// as long as every one gets its own random coefficient. A zero coefficient would
+    /// drop `other` from the check, the merged check is then invalid
+    pub fn merge_randomized(&mut self, other: &PairingCheck<E>, coeff: E::ScalarField) {
+        let exponent = coeff.into_bigint();
+        self.left.mul_assign(&other.left.pow(exponent));
+        self.right.mul_assign(&other.right.pow(exponent));
+        // An invalid check stays invalid, randomizing it doesn't make it sound
+        if other.non_randomized > 1 || coeff.is_zero() {
+            self.non_randomized = self.non_randomized.saturating_add(2);
+        }
+    }
+
+    /// Merge `checks` with a fresh nonzero coefficient for each
+    pub fn combine_all<R: Rng + CryptoRng>(
+        checks: &[PairingCheck<E>],
+        rng: &mut R,
+    ) -> PairingCheck<E> {
+        checks.iter().fold(Self::new(), |mut acc, check| {
+            acc.merge_randomized(check, rand_batching_scalar::<E::ScalarField, R>(rng));
+            acc
+        })
+    }
+
     /// Returns false if there is more than 1 non-random check and otherwise
     /// returns true if
     /// $$
@@ -153,30 +280,6 @@ where
     }
 }
 
-fn rand_fr<E: Pairing, R: Rng + Send>(r: &Mutex<R>) -> E::ScalarField {
-    let rng: &mut R = &mut r.lock().unwrap();
-    loop {
-        let c = E::ScalarField::rand(rng);
-        if c != E::ScalarField::zero() {
-            return c;
-        }
-    }
-}
-fn mul_if_not_one<E: Pairing>(
-    left: &mut <E as Pairing>::TargetField,
-    right: &<E as Pairing>::TargetField,
-) {
-    let one = <E as Pairing>::TargetField::one();
-    if left == &one {
-        *left = right.clone();
-        return;
-    } else if right == &one {
-        // nothing to do here
-        return;
-    }
-    left.mul_assign(right);
-}
-
 /// Single-line verification for common equations of form: e(a,b)·e(c,d)... = target
 ///
 /// # Arguments
@@ -185,14 +288,17 @@ fn mul_if_not_one<E: Pairing>(
 ///
 /// # Returns
 /// * `bool` - True if the equation holds
+///
+/// Use `create_check` for equations that will be merged with others
 pub fn verify_pairing_equation<E: Pairing>(
     pairs: &[(&E::G1Affine, &E::G2Affine)],
     target: Option<&E::TargetField>,
 ) -> bool {
-    let mut rng = test_rng();
-    let target_value = target.cloned().unwrap_or_else(|| E::TargetField::one());
+    let target_value = target.cloned().unwrap_or_else(E::TargetField::one);
 
-    let check = PairingCheck::<E>::rand(&Mutex::new(rng), pairs, &target_value);
+    // A lone equation holds scaled by a nonzero coefficient exactly when it holds
+    // unscaled, so skip the scalar multiplications and the target exponentiation
+    let check = PairingCheck::<E>::from_pairs(pairs, &target_value);
 
     check.verify()
 }
@@ -203,33 +309,30 @@ pub fn verify_pairing_equation<E: Pairing>(
 pub fn create_check<E: Pairing>(
     pairs: &[(&E::G1Affine, &E::G2Affine)],
     target: Option<&E::TargetField>,
+    rng: &mut (impl Rng + CryptoRng),
 ) -> PairingCheck<E> {
-    let mut rng = test_rng();
-    let target_value = target.cloned().unwrap_or_else(|| E::TargetField::one());
+    let target_value = target.cloned().unwrap_or_else(E::TargetField::one);
 
-    PairingCheck::<E>::rand(&Mutex::new(rng), pairs, &target_value)
+    PairingCheck::<E>::rand(rng, pairs, &target_value)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use ark_bls12_381::{Bls12_381 as Bls12, G1Projective, G2Projective};
-    use ark_std::test_rng;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381 as Bls12, Fr, G1Projective, G2Projective};
     use ark_std::{rand::Rng, UniformRand};
 
-    fn gen_pairing_check<R: Rng + Send>(r: &mut R) -> PairingCheck<Bls12> {
+    fn gen_pairing_check<R: Rng + CryptoRng>(r: &mut R) -> PairingCheck<Bls12> {
         let g1r = G1Projective::rand(r);
         let g2r = G2Projective::rand(r);
 
         // expected output from g1r and g2r
         let exp = Bls12::pairing(g1r, g2r);
 
-        // Wrap the random number generator in a Mutex for safe data parallelism
-        let mr = Mutex::new(r);
-
         // the pairing lhs should equal the expected output
         let tuple =
-            PairingCheck::<Bls12>::rand(&mr, &[(&g1r.into_affine(), &g2r.into_affine())], &exp.0);
+            PairingCheck::<Bls12>::rand(r, &[(&g1r.into_affine(), &g2r.into_affine())], &exp.0);
 
         assert!(tuple.verify());
         tuple
@@ -251,4 +354,129 @@ mod test {
             });
         assert!(final_tuple.verify());
     }
+
+    #[test]
+    fn test_rand_matches_unrandomized_check() {
+        let mut rng = test_rng();
+        // Both sides of SEQUENTIAL_PAIRS
+        for n in [1, 2, SEQUENTIAL_PAIRS, SEQUENTIAL_PAIRS + 1, 7] {
+            let g1: Vec<_> = (0..n)
+                .map(|_| G1Projective::rand(&mut rng).into_affine())
+                .collect();
+            let g2: Vec<_> = (0..n)
+                .map(|_| G2Projective::rand(&mut rng).into_affine())
+                .collect();
+            let pairs: Vec<_> = g1.iter().zip(&g2).collect();
+            let valid = Bls12::multi_pairing(&g1, &g2).0;
+            let invalid = valid.double();
+
+            for out in [valid, invalid] {
+                let unrandomized = PairingCheck::<Bls12>::from_products(
+                    vec![Bls12::multi_miller_loop(&g1, &g2).0],
+                    out,
+                );
+                assert_eq!(
+                    PairingCheck::<Bls12>::rand(&mut rng, &pairs, &out).verify(),
+                    unrandomized.verify()
+                );
+                assert_eq!(unrandomized.verify(), out == valid);
+            }
+
+            // Both Miller loop strategies agree once exponentiated
+            let coeff = Fr::rand(&mut rng);
+            assert_eq!(
+                Bls12::final_exponentiation(MillerLoopOutput(
+                    PairingCheck::<Bls12>::miller_sequential(&pairs, &coeff)
+                )),
+                Bls12::final_exponentiation(MillerLoopOutput(
+                    PairingCheck::<Bls12>::miller_parallel(&pairs, &coeff)
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_randomized() {
+        let mut rng = test_rng();
+        let g1 = G1Projective::rand(&mut rng).into_affine();
+        let g2 = G2Projective::rand(&mut rng).into_affine();
+        let out = Bls12::pairing(g1, g2).0;
+        let valid = || PairingCheck::<Bls12>::from_pairs(&[(&g1, &g2)], &out);
+        let invalid = PairingCheck::<Bls12>::from_pairs(&[(&g1, &g2)], &out.double());
+
+        // Unrandomized checks can't be merged plainly more than once
+        let mut plain = valid();
+        plain.merge(&valid());
+        assert!(!plain.verify());
+
+        for coeffs in [
+            (Fr::one(), Fr::one()),
+            (Fr::rand(&mut rng), Fr::rand(&mut rng)),
+            (Fr::zero(), Fr::rand(&mut rng)),
+            (Fr::rand(&mut rng), Fr::zero()),
+        ] {
+            let mut mixed = PairingCheck::<Bls12>::new();
+            mixed.merge_randomized(&valid(), coeffs.0);
+            mixed.merge_randomized(&invalid, coeffs.1);
+            assert!(!mixed.verify());
+        }
+
+        let mut valids = PairingCheck::<Bls12>::new();
+        for _ in 0..3 {
+            valids.merge_randomized(&valid(), Fr::rand(&mut rng));
+        }
+        assert!(valids.verify());
+        assert!(PairingCheck::combine_all(&[valid(), valid(), valid()], &mut rng).verify());
+        assert!(!PairingCheck::combine_all(&[valid(), invalid, valid()], &mut rng).verify());
+        assert!(!PairingCheck::combine_all(
+            &[valid(), PairingCheck::<Bls12>::from_pairs(&[], &out)],
+            &mut rng
+        )
+        .verify());
+    }
+
+    #[test]
+    fn test_verify_pairing_equation_matches_randomized() {
+        let mut rng = test_rng();
+        for n in [1, 2, SEQUENTIAL_PAIRS + 1] {
+            let g1: Vec<_> = (0..n)
+                .map(|_| G1Projective::rand(&mut rng).into_affine())
+                .collect();
+            let g2: Vec<_> = (0..n)
+                .map(|_| G2Projective::rand(&mut rng).into_affine())
+                .collect();
+            let pairs: Vec<_> = g1.iter().zip(&g2).collect();
+            let valid = Bls12::multi_pairing(&g1, &g2).0;
+
+            for out in [valid, valid.double()] {
+                assert_eq!(
+                    verify_pairing_equation::<Bls12>(&pairs, Some(&out)),
+                    create_check::<Bls12>(&pairs, Some(&out), &mut rng).verify()
+                );
+                assert_eq!(
+                    verify_pairing_equation::<Bls12>(&pairs, Some(&out)),
+                    out == valid
+                );
+            }
+            // With the default target of one
+            let mut balanced = pairs.clone();
+            let neg: Vec<_> = g1.iter().map(|a| (-a.into_group()).into_affine()).collect();
+            balanced.extend(neg.iter().zip(&g2));
+            assert!(verify_pairing_equation::<Bls12>(&balanced, None));
+            assert!(!verify_pairing_equation::<Bls12>(&pairs, None));
+        }
+        assert!(!verify_pairing_equation::<Bls12>(&[], None));
+    }
+
+    #[test]
+    fn test_pairing_rand_empty_pairs_invalid() {
+        let mut rng = test_rng();
+        let one = <Bls12 as Pairing>::TargetField::one();
+        assert!(!PairingCheck::<Bls12>::rand(&mut rng, &[], &one).verify());
+
+        // An empty check also poisons any check it is merged into
+        let mut merged = gen_pairing_check(&mut rng);
+        merged.merge(&PairingCheck::<Bls12>::rand(&mut rng, &[], &one));
+        assert!(!merged.verify());
+    }
 }
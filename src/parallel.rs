@@ -0,0 +1,28 @@
+//! Cut-over below which the rayon paths run serially. Spreading a handful of items
+//! over the pool costs more than it saves, e.g. `L = 4` commitment proofs are slower
+//! under rayon than in a loop
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Default number of work items (attributes for commitment proofs, signers for share
+/// collection) from which the `parallel` feature uses rayon
+pub const PARALLEL_THRESHOLD: usize = 8;
+
+static THRESHOLD: AtomicUsize = AtomicUsize::new(PARALLEL_THRESHOLD);
+
+/// Current cut-over, `PARALLEL_THRESHOLD` unless changed with `set_parallel_threshold`
+pub fn parallel_threshold() -> usize {
+    THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Set the process-wide cut-over. `0` always uses rayon, `usize::MAX` never does.
+/// Outputs don't depend on it, only on the caller's RNG
+pub fn set_parallel_threshold(items: usize) {
+    THRESHOLD.store(items, Ordering::Relaxed);
+}
+
+/// Whether `items` units of work go to rayon's pool
+#[cfg(feature = "parallel")]
+pub(crate) fn use_rayon(items: usize) -> bool {
+    items >= parallel_threshold()
+}
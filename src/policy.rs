@@ -0,0 +1,321 @@
+//! Declarative presentation requirements.
+//!
+//! A verifier states once what it needs from a credential in a `PresentationPolicy`:
+//! the domain and its own context, a fresh nonce, the attributes to disclose, the
+//! attributes that must lie in a signed set and an expiry attribute. The policy is
+//! serialized and sent to the holder, who answers with `Credential::present`, and
+//! `PresentationPolicy::verify` checks every requirement in one call.
+//!
+//! The whole serialized policy is absorbed into the presentation's transcript, so a
+//! presentation only verifies under the policy it was made for: another nonce, context
+//! or set gives another challenge. A disclosed attribute gets a zero blinding in the
+//! opening proof, its response is then `c m` and the verifier checks it against the
+//! disclosed value. Each set requirement adds an `AttributeSignatureProof` answering the
+//! same challenge. Range predicates and nullifiers are not part of a policy, nullifiers
+//! need the holder's VRF key and stay with `show_with_nullifier`.
+use crate::commitment::CommitmentProof;
+use crate::errors::{PolicyError, SignatureError};
+use crate::keygen::VerificationKey;
+use crate::presentation::Presentation;
+use crate::revocation::AttributeSignatureProof;
+use crate::set_membership::SetParams;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::{DomainSeparator, Transcript};
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, Rng};
+
+/// What a verifier requires of a presentation
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PresentationPolicy<E: Pairing> {
+    /// Domain the holder's credential makes its proofs under
+    pub domain: DomainSeparator,
+    /// Verifier context, e.g. the name of the service asking
+    pub context: Vec<u8>,
+    /// Freshness value, checking that it wasn't used before is up to the verifier
+    pub nonce: [u8; 32],
+    /// Indices of the attributes to disclose
+    pub disclose: Vec<usize>,
+    /// Attributes that must be among the values signed in the paired set, without
+    /// being disclosed
+    pub membership: Vec<(usize, SetParams<E>)>,
+    /// Index of an attribute holding an expiry time, disclosed and required to be at
+    /// least the verifier's `now`
+    pub expiry: Option<usize>,
+}
+
+/// A presentation made for a `PresentationPolicy`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PolicyPresentation<E: Pairing> {
+    pub presentation: Presentation<E>,
+    /// Disclosed `(index, attribute)` pairs, strictly increasing by index
+    pub disclosed: Vec<(usize, E::ScalarField)>,
+    /// One proof per set requirement, in the policy's order
+    pub membership: Vec<AttributeSignatureProof<E>>,
+}
+
+impl<E: Pairing> PresentationPolicy<E> {
+    /// A policy with nothing required beyond a valid credential bound to `nonce`
+    pub fn new(domain: DomainSeparator, nonce: [u8; 32]) -> Self {
+        Self {
+            domain,
+            context: Vec::new(),
+            nonce,
+            disclose: Vec::new(),
+            membership: Vec::new(),
+            expiry: None,
+        }
+    }
+
+    pub fn with_context(mut self, context: impl Into<Vec<u8>>) -> Self {
+        self.context = context.into();
+        self
+    }
+
+    pub fn with_disclosure(mut self, index: usize) -> Self {
+        self.disclose.push(index);
+        self
+    }
+
+    pub fn with_membership(mut self, index: usize, params: SetParams<E>) -> Self {
+        self.membership.push((index, params));
+        self
+    }
+
+    pub fn with_expiry(mut self, index: usize) -> Self {
+        self.expiry = Some(index);
+        self
+    }
+
+    /// Every index the holder discloses, the expiry attribute included, strictly
+    /// increasing
+    pub fn disclosed_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self.disclose.iter().copied().chain(self.expiry).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Check every requirement of the policy on `presentation`, with `now` in the unit
+    /// of the expiry attribute. The error names the first requirement that failed
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        presentation: &PolicyPresentation<E>,
+        now: u64,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(), PolicyError> {
+        let indices = self.disclosed_indices();
+        for (position, index) in indices.iter().enumerate() {
+            if presentation.disclosed.get(position).map(|(i, _)| i) != Some(index) {
+                return Err(PolicyError::MissingDisclosure(*index));
+            }
+        }
+        if presentation.disclosed.len() != indices.len()
+            || presentation.membership.len() != self.membership.len()
+        {
+            return Err(PolicyError::Malformed);
+        }
+        if let Some(index) = self.expiry {
+            let (_, expiry) = presentation
+                .disclosed
+                .iter()
+                .find(|(i, _)| *i == index)
+                .ok_or(PolicyError::MissingDisclosure(index))?;
+            if expiry.into_bigint() < E::ScalarField::from(now).into_bigint() {
+                return Err(PolicyError::Expired);
+            }
+        }
+
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&presentation.presentation.proof[..])
+                .map_err(SignatureError::from)?;
+        let challenge = policy_show_challenge(
+            self,
+            &presentation.presentation,
+            &presentation.disclosed,
+            &presentation.membership,
+            &proof.schnorr_commitment,
+        );
+        if proof.challenge != challenge {
+            return Err(PolicyError::NotBound);
+        }
+        let response = |index: usize| {
+            proof
+                .responses
+                .get(ck.attribute_position(index))
+                .filter(|_| index < ck.num_attributes())
+                .ok_or(PolicyError::Malformed)
+        };
+        // A zero blinding leaves the response at c m for the disclosed m
+        for (index, value) in &presentation.disclosed {
+            if *response(*index)? != challenge * value {
+                return Err(PolicyError::MissingDisclosure(*index));
+            }
+        }
+        for ((index, params), membership) in self.membership.iter().zip(&presentation.membership) {
+            if !membership.verify(
+                &params.g_tilde,
+                &params.x_tilde,
+                &params.base,
+                response(*index)?,
+                &challenge,
+                rng,
+            ) {
+                return Err(PolicyError::NotInSet(*index));
+            }
+        }
+
+        presentation.presentation.verify(ck, vk, rng)?;
+        Ok(())
+    }
+}
+
+/// Joint challenge over the policy, the randomized credential, the disclosed
+/// attributes and the announcements of every proof
+pub(crate) fn policy_show_challenge<E: Pairing>(
+    policy: &PresentationPolicy<E>,
+    presentation: &Presentation<E>,
+    disclosed: &[(usize, E::ScalarField)],
+    membership: &[AttributeSignatureProof<E>],
+    schnorr_commitment: &E::G1Affine,
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(&policy.domain, b"t-siris show for policy");
+    transcript.append(b"policy", policy);
+    transcript.append(b"signature", &presentation.signature);
+    transcript.append(b"cm", &presentation.commitment);
+    transcript.append(b"cm_tilde", &presentation.commitment_tilde);
+    for (index, value) in disclosed {
+        transcript.append(b"disclosed_index", &(*index as u64));
+        transcript.append(b"disclosed", value);
+    }
+    for proof in membership {
+        transcript.append(b"witness", &proof.witness);
+        transcript.append(b"witness_bar", &proof.witness_bar);
+        transcript.append(b"announcement", &proof.announcement);
+    }
+    transcript.append(b"schnorr_commitment", schnorr_commitment);
+    transcript.challenge_scalar(b"challenge")
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::errors::{CredentialError, SetMembershipError};
+    use crate::protocol::{UserProtocol, VerifierProtocol};
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_policy_requirements() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(4)
+            .build(&mut rng)
+            .unwrap();
+        let user = system.user();
+        // Name, expiry time, role and a hidden secret
+        let issue = |role: u64, rng: &mut _| {
+            let attributes = [
+                Fr::from(42u64),
+                Fr::from(1_000u64),
+                Fr::from(role),
+                Fr::rand(rng),
+            ];
+            let (mut credential, request) =
+                user.request_credential(Some(&attributes), rng).unwrap();
+            let shares = user.collect_signature_shares(&request, rng).unwrap();
+            user.complete_credential(&mut credential, &request, &shares, rng)
+                .unwrap();
+            credential
+        };
+        let credential = issue(2, &mut rng);
+        let roles =
+            SetParams::<Bls12_381>::new(&[Fr::from(1u64), Fr::from(2u64)], &mut rng).unwrap();
+        let policy = PresentationPolicy::new(credential.domain().clone(), [5u8; 32])
+            .with_context(b"verifier".as_slice())
+            .with_disclosure(0)
+            .with_expiry(1)
+            .with_membership(2, roles.clone());
+
+        // The verifier sends the serialized policy
+        let mut bytes = Vec::new();
+        policy.serialize_compressed(&mut bytes).unwrap();
+        let received = PresentationPolicy::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        let shown = UserProtocol::present(&credential, &received, &mut rng).unwrap();
+        assert_eq!(
+            shown.disclosed,
+            vec![(0, Fr::from(42u64)), (1, Fr::from(1_000u64))]
+        );
+        let verify = |policy: &PresentationPolicy<Bls12_381>,
+                      shown: &PolicyPresentation<Bls12_381>,
+                      now: u64,
+                      rng: &mut _| {
+            VerifierProtocol::verify_policy(&system.ck, &system.vk, policy, shown, now, rng)
+        };
+        assert!(verify(&policy, &shown, 999, &mut rng).is_ok());
+
+        // Each requirement violated on its own
+        // A changed disclosed value is absorbed into the challenge
+        let mut altered = shown.clone();
+        altered.disclosed[0].1 = Fr::from(43u64);
+        assert!(matches!(
+            verify(&policy, &altered, 999, &mut rng),
+            Err(PolicyError::NotBound)
+        ));
+        let undisclosed = policy.clone().with_disclosure(3);
+        assert!(matches!(
+            verify(&undisclosed, &shown, 999, &mut rng),
+            Err(PolicyError::MissingDisclosure(3))
+        ));
+        assert!(matches!(
+            verify(&policy, &shown, 1_001, &mut rng),
+            Err(PolicyError::Expired)
+        ));
+        let mut fresh = policy.clone();
+        fresh.nonce = [6u8; 32];
+        assert!(matches!(
+            verify(&fresh, &shown, 999, &mut rng),
+            Err(PolicyError::NotBound)
+        ));
+        let other_context = policy.clone().with_context(b"other".as_slice());
+        assert!(matches!(
+            verify(&other_context, &shown, 999, &mut rng),
+            Err(PolicyError::NotBound)
+        ));
+        let mut forged = shown.clone();
+        forged.membership[0].response += Fr::from(1u64);
+        assert!(matches!(
+            verify(&policy, &forged, 999, &mut rng),
+            Err(PolicyError::NotInSet(2))
+        ));
+        let outsider = issue(3, &mut rng);
+        assert!(matches!(
+            UserProtocol::present(&outsider, &policy, &mut rng),
+            Err(CredentialError::SetMembership(SetMembershipError::NotInSet))
+        ));
+        let other_issuer = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(4)
+            .build(&mut rng)
+            .unwrap();
+        assert!(matches!(
+            VerifierProtocol::verify_policy(
+                &system.ck,
+                &other_issuer.vk,
+                &policy,
+                &shown,
+                999,
+                &mut rng
+            ),
+            Err(PolicyError::Signature(_))
+        ));
+    }
+}
@@ -0,0 +1,1095 @@
+use crate::commitment::{Commitment, CommitmentProof};
+use crate::errors::{CommitmentError, MessageError, SignatureError};
+use crate::keygen::{PreparedVerificationKey, VerificationKey};
+use crate::nullifier::{DYPFPrivPublicKey, DYPFPrivVRF, DYPFPrivVRFOutput, DYPFPrivVRFProof};
+use crate::pairing::PairingCheck;
+use crate::schnorr::SchnorrProtocol;
+use crate::signature::{pair_refs, ThresholdSignature};
+use crate::symmetric_commitment::{g1_commit, g2_commit, SymmetricCommitmentKey};
+use crate::transcript::{DomainSeparator, Transcript};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::UniformRand;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::ops::{Add, Mul};
+use ark_std::rand::{CryptoRng, Rng};
+use ark_std::{One, Zero};
+
+/// A credential presentation produced by `show`: the randomized signature, the
+/// randomized commitment in G1 and G2 and the serialized opening proof for `commitment`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Presentation<E: Pairing> {
+    pub signature: ThresholdSignature<E>,
+    pub commitment: E::G1Affine,
+    pub commitment_tilde: E::G2Affine,
+    pub proof: Vec<u8>,
+    /// Verifier issued nonce the opening proof's challenge is derived from, set by
+    /// `show_with_nonce`. `None` for a `show`, whose challenge is random
+    pub nonce: Option<[u8; 32]>,
+}
+
+/// A presentation in either of the forms a verifier accepts: the arkworks struct or,
+/// under `serde`, a `PresentationDocument` decoded against the verifier's key
+pub enum PresentationForm<'a, E: Pairing> {
+    Binary(&'a Presentation<E>),
+    #[cfg(feature = "serde")]
+    Document(&'a crate::document::PresentationDocument),
+}
+
+impl<'a, E: Pairing> From<&'a Presentation<E>> for PresentationForm<'a, E> {
+    fn from(presentation: &'a Presentation<E>) -> Self {
+        Self::Binary(presentation)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a, E: Pairing> From<&'a crate::document::PresentationDocument> for PresentationForm<'a, E> {
+    fn from(document: &'a crate::document::PresentationDocument) -> Self {
+        Self::Document(document)
+    }
+}
+
+impl<E: Pairing> Presentation<E> {
+    /// Length of the compressed encoding in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+
+    /// Verify the presentation against the issuer's keys
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        ThresholdSignature::verify(
+            ck,
+            vk,
+            &self.commitment,
+            &self.commitment_tilde,
+            &self.signature,
+            &self.proof,
+            rng,
+        )
+    }
+
+    /// `verify` against a key prepared once with `PreparedVerificationKey::new`
+    pub fn verify_prepared(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        pvk: &PreparedVerificationKey<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        ThresholdSignature::verify_prepared(
+            ck,
+            pvk,
+            &self.commitment,
+            &self.commitment_tilde,
+            &self.signature,
+            &self.proof,
+            rng,
+        )
+    }
+
+    /// `verify`, and that the opening proof answers the challenge for `nonce` under the
+    /// holder's `domain`. A presentation shown for another nonce, or without one, is
+    /// rejected, so a captured presentation can't be replayed to a verifier issuing
+    /// fresh nonces
+    pub fn verify_with_nonce(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        domain: &DomainSeparator,
+        nonce: &[u8; 32],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        self.verify_disclosed(ck, vk, domain, nonce, &[], rng)
+    }
+
+    /// `verify_with_nonce` for a presentation from `show_disclosing`, and that each
+    /// `(index, attribute)` of `disclosed`, strictly increasing, is the committed one:
+    /// with a zero blinding its response is `c m`
+    pub fn verify_disclosed(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        domain: &DomainSeparator,
+        nonce: &[u8; 32],
+        disclosed: &[(usize, E::ScalarField)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        if self.nonce.as_ref() != Some(nonce) {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        if disclosed.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(MessageError::UnorderedDisclosure.into());
+        }
+        let attributes = ck.num_attributes();
+        if let Some((index, _)) = disclosed.iter().find(|(index, _)| *index >= attributes) {
+            return Err(MessageError::DisclosedIndexOutOfRange {
+                index: *index,
+                attributes,
+            }
+            .into());
+        }
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&self.proof[..])?;
+        let challenge =
+            nonce_show_challenge(domain, self, nonce, disclosed, &proof.schnorr_commitment);
+        if proof.challenge != challenge {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        for (index, value) in disclosed {
+            if proof.responses.get(ck.attribute_position(*index)) != Some(&(challenge * value)) {
+                return Err(CommitmentError::ProofVerificationFailed.into());
+            }
+        }
+        self.verify(ck, vk, rng)
+    }
+}
+
+/// Challenge of a presentation shown for a verifier's nonce, over the randomized
+/// credential, the nonce, the disclosed attributes and the announcement of the opening
+/// proof
+pub(crate) fn nonce_show_challenge<E: Pairing>(
+    domain: &DomainSeparator,
+    presentation: &Presentation<E>,
+    nonce: &[u8; 32],
+    disclosed: &[(usize, E::ScalarField)],
+    schnorr_commitment: &E::G1Affine,
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris show with nonce");
+    transcript.append(b"signature", &presentation.signature);
+    transcript.append(b"cm", &presentation.commitment);
+    transcript.append(b"cm_tilde", &presentation.commitment_tilde);
+    transcript.append_message(b"nonce", nonce);
+    for (index, value) in disclosed {
+        transcript.append(b"disclosed_index", &(*index as u64));
+        transcript.append(b"disclosed", value);
+    }
+    transcript.append(b"schnorr_commitment", schnorr_commitment);
+    transcript.challenge_scalar(b"challenge")
+}
+
+/// A credential opened in full by `open_all`, for uses that disclose every attribute:
+/// the randomized signature and commitments with the attributes and blinding they open
+/// to. Nothing is hidden, the verifier recomputes the commitments instead of checking
+/// an opening proof
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct OpenedCredential<E: Pairing> {
+    pub signature: ThresholdSignature<E>,
+    pub messages: Vec<E::ScalarField>,
+    pub blinding: E::ScalarField,
+    pub commitment: E::G1Affine,
+    pub commitment_tilde: E::G2Affine,
+}
+
+impl<E: Pairing> OpenedCredential<E> {
+    /// Check that `commitment` and `commitment_tilde` commit to `messages` under
+    /// `blinding` over `vk.show_key(ck)` and that the signature verifies on them, with
+    /// the signature equation alone: two pairings and no proof
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+    ) -> Result<bool, SignatureError> {
+        let bases = ck.num_attributes();
+        if self.messages.len() != bases {
+            return Err(CommitmentError::TooManyMessages {
+                messages: self.messages.len(),
+                bases,
+            }
+            .into());
+        }
+        let show_key = vk.show_key(ck);
+        if g1_commit(&show_key, &self.messages, &self.blinding) != self.commitment
+            || g2_commit(&show_key, &self.messages, &self.blinding) != self.commitment_tilde
+        {
+            return Err(CommitmentError::InvalidCommitment.into());
+        }
+        let [signature_pairs, _] = ThresholdSignature::pairing_equations(
+            ck,
+            vk,
+            &self.commitment,
+            &self.commitment_tilde,
+            &self.signature,
+        )?;
+        if !PairingCheck::<E>::from_pairs(&pair_refs(&signature_pairs), &E::TargetField::one())
+            .verify()
+        {
+            return Err(SignatureError::SignatureVerificationFailed);
+        }
+        Ok(true)
+    }
+}
+
+impl<E: Pairing> Presentation<E> {
+    /// Verify many presentations against the same keys with one final exponentiation.
+    /// Each opening proof is still checked on its own, the pairing equations are
+    /// collected and only randomized and combined at the end. Fails with
+    /// `SignatureVerificationFailed` if any presentation is invalid, without saying
+    /// which; `verify` them one by one to find out
+    pub fn verify_batch(
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        presentations: &[Presentation<E>],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let mut checks: Vec<PairingCheck<E>> = Vec::with_capacity(2 * presentations.len());
+        for presentation in presentations {
+            let equations = ThresholdSignature::check_and_pair(
+                ck,
+                vk,
+                &presentation.commitment,
+                &presentation.commitment_tilde,
+                &presentation.signature,
+                &presentation.proof,
+            )?;
+            for pairs in &equations {
+                checks.push(PairingCheck::from_pairs(
+                    &pair_refs(pairs),
+                    &E::TargetField::one(),
+                ));
+            }
+        }
+        if !PairingCheck::combine_all(&checks, rng).verify() {
+            return Err(SignatureError::SignatureVerificationFailed);
+        }
+        Ok(true)
+    }
+}
+
+/// Several credentials shown together. Each randomized signature and commitment is
+/// sent as in a `Presentation`, the opening proofs share one Fiat-Shamir challenge and
+/// leave out the bases, the commitments and the challenge, which the verifier has
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MultiPresentation<E: Pairing> {
+    pub signatures: Vec<ThresholdSignature<E>>,
+    pub commitments: Vec<E::G1Affine>,
+    pub commitments_tilde: Vec<E::G2Affine>,
+    /// One Schnorr announcement per commitment
+    pub announcements: Vec<E::G1Affine>,
+    /// `z_{i,1}, ..., z_{i,L}, z_{i,r}` for commitment `i`
+    pub responses: Vec<Vec<E::ScalarField>>,
+}
+
+/// Challenge shared by the opening proofs of a `MultiPresentation`, over every
+/// credential's bases, randomized signature and commitments and the announcements
+pub(crate) fn multi_show_challenge<E: Pairing>(
+    domain: &DomainSeparator,
+    bases: &[Vec<E::G1Affine>],
+    signatures: &[ThresholdSignature<E>],
+    commitments: &[E::G1Affine],
+    commitments_tilde: &[E::G2Affine],
+    announcements: &[E::G1Affine],
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris show many");
+    for bases in bases {
+        transcript.append(b"bases", bases);
+    }
+    transcript.append(b"signatures", signatures);
+    transcript.append(b"commitments", commitments);
+    transcript.append(b"commitments_tilde", commitments_tilde);
+    transcript.append(b"announcements", announcements);
+    transcript.challenge_scalar(b"challenge")
+}
+
+impl<E: Pairing> MultiPresentation<E> {
+    /// Length of the compressed encoding in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+
+    /// Verify every credential against its `(ck, vk)` in `keys`, in the order they were
+    /// shown. The opening proofs are checked with one multi-scalar multiplication and
+    /// the pairing equations with one final exponentiation, so a failure doesn't say
+    /// which credential is invalid
+    pub fn verify(
+        &self,
+        keys: &[(&SymmetricCommitmentKey<E>, &VerificationKey<E>)],
+        domain: &DomainSeparator,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let n = keys.len();
+        if n == 0
+            || self.signatures.len() != n
+            || self.commitments.len() != n
+            || self.commitments_tilde.len() != n
+            || self.announcements.len() != n
+            || self.responses.len() != n
+        {
+            return Err(CommitmentError::InvalidProof.into());
+        }
+
+        let bases: Vec<_> = keys
+            .iter()
+            .map(|(ck, vk)| vk.show_key(ck).get_bases().0)
+            .collect();
+        let challenge = multi_show_challenge(
+            domain,
+            &bases,
+            &self.signatures,
+            &self.commitments,
+            &self.commitments_tilde,
+            &self.announcements,
+        );
+
+        // sum_i w_i (sum_j z_ij B_ij - T_i - c cm_i) = 0
+        let mut msm_bases = Vec::new();
+        let mut msm_scalars = Vec::new();
+        for (i, bases) in bases.iter().enumerate() {
+            if !SchnorrProtocol::is_well_formed(
+                bases,
+                &self.commitments[i],
+                &self.announcements[i],
+                &self.responses[i],
+                &challenge,
+            ) {
+                return Err(CommitmentError::InvalidProof.into());
+            }
+            let weight = E::ScalarField::rand(rng);
+            for (base, z) in bases.iter().zip(&self.responses[i]) {
+                msm_bases.push(*base);
+                msm_scalars.push(weight * z);
+            }
+            msm_bases.push(self.announcements[i]);
+            msm_scalars.push(-weight);
+            msm_bases.push(self.commitments[i]);
+            msm_scalars.push(-(weight * challenge));
+        }
+        if !E::G1::msm_unchecked(&msm_bases, &msm_scalars).is_zero() {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+
+        let mut checks: Vec<PairingCheck<E>> = Vec::with_capacity(2 * n);
+        for (i, (ck, vk)) in keys.iter().enumerate() {
+            let equations = ThresholdSignature::pairing_equations(
+                ck,
+                vk,
+                &self.commitments[i],
+                &self.commitments_tilde[i],
+                &self.signatures[i],
+            )?;
+            for pairs in &equations {
+                checks.push(PairingCheck::from_pairs(
+                    &pair_refs(pairs),
+                    &E::TargetField::one(),
+                ));
+            }
+        }
+        if !PairingCheck::combine_all(&checks, rng).verify() {
+            return Err(SignatureError::SignatureVerificationFailed);
+        }
+        Ok(true)
+    }
+}
+
+/// A presentation bound to the nullifier `y = g^(1/(sk + context))`. The opening proof
+/// and the VRF proof answer one challenge derived from a shared transcript, so neither
+/// proof can be lifted into another presentation
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct NullifierPresentation<E: Pairing> {
+    pub presentation: Presentation<E>,
+    pub nullifier: E::G1Affine,
+    /// VRF commitment to the holder's nullifier key
+    pub cm_sk: E::G1Affine,
+    /// VRF commitment to the credential context
+    pub cm_x: E::G1Affine,
+    pub vrf_proof: DYPFPrivVRFProof<E::G1Affine>,
+}
+
+/// Joint challenge over the randomized credential, the nullifier statement and the
+/// announcements of both sigma protocols
+pub(crate) fn nullifier_show_challenge<E: Pairing>(
+    domain: &DomainSeparator,
+    presentation: &Presentation<E>,
+    nullifier: &E::G1Affine,
+    cm_sk: &E::G1Affine,
+    cm_x: &E::G1Affine,
+    schnorr_commitment: &E::G1Affine,
+    vrf_announcements: [&E::G1Affine; 3],
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris show with nullifier");
+    transcript.append(b"signature", &presentation.signature);
+    transcript.append(b"cm", &presentation.commitment);
+    transcript.append(b"cm_tilde", &presentation.commitment_tilde);
+    transcript.append(b"nullifier", nullifier);
+    transcript.append(b"cm_sk", cm_sk);
+    transcript.append(b"cm_x", cm_x);
+    transcript.append(b"schnorr_commitment", schnorr_commitment);
+    for announcement in vrf_announcements {
+        transcript.append(b"vrf_announcement", announcement);
+    }
+    transcript.challenge_scalar(b"challenge")
+}
+
+impl<E: Pairing> NullifierPresentation<E> {
+    /// Verify the credential, the nullifier and that both proofs share the transcript
+    /// challenge, derived under `domain`
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        domain: &DomainSeparator,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&self.presentation.proof[..])?;
+        let challenge = nullifier_show_challenge(
+            domain,
+            &self.presentation,
+            &self.nullifier,
+            &self.cm_sk,
+            &self.cm_x,
+            &proof.schnorr_commitment,
+            [&self.vrf_proof.t1, &self.vrf_proof.t2, &self.vrf_proof.ty],
+        );
+        if proof.challenge != challenge {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+
+        let vrf_pk = DYPFPrivPublicKey {
+            cm_sk: self.cm_sk,
+            cm_x: self.cm_x,
+        };
+        let output = DYPFPrivVRFOutput { y: self.nullifier };
+        if !vrf.verify(&vrf_pk, &output, &self.vrf_proof, &challenge) {
+            return Err(SignatureError::ProofError(
+                "nullifier proof verification failed".to_string(),
+            ));
+        }
+
+        self.presentation.verify(ck, vk, rng)
+    }
+
+    /// `verify`, and that the nullifier is `g^(1/(m + x))` for the hidden attribute `m`
+    /// at `key_index` of the shown credential and `x = nullifier_input(context_input)`.
+    /// `cm_x` has to be `g2^x` and the VRF proof's response for `sk` has to equal the
+    /// opening proof's response for the attribute, both answer the same challenge
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_binding(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        domain: &DomainSeparator,
+        key_index: usize,
+        context_input: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let x = nullifier_input::<E>(domain, context_input);
+        if self.cm_x != vrf.pp.g2.mul(x).into_affine() {
+            return Ok(false);
+        }
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&self.presentation.proof[..])?;
+        if key_index >= ck.num_attributes()
+            || !SchnorrProtocol::verify_shared_responses(
+                &proof.responses,
+                &[self.vrf_proof.z_sk],
+                &[(ck.attribute_position(key_index), 0)],
+            )
+        {
+            return Ok(false);
+        }
+        self.verify(ck, vk, vrf, domain, rng)
+    }
+}
+
+/// The VRF input of a nullifier bound to the public `context_input`, a hash of it into
+/// the scalar field. `show_with_bound_nullifier` commits to it with a zero blinding, so
+/// the verifier recomputes `cm_x = g2^x` itself
+pub fn nullifier_input<E: Pairing>(
+    domain: &DomainSeparator,
+    context_input: &[u8],
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris nullifier input");
+    transcript.append_message(b"context_input", context_input);
+    transcript.challenge_scalar(b"input")
+}
+
+/// A presentation that doubles as a signature of knowledge on a message. The opening
+/// proof answers a challenge over the presentation and `message_hash`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SignedPresentation<E: Pairing> {
+    pub presentation: Presentation<E>,
+    /// Hash of the signed message under the holder's domain
+    pub message_hash: [u8; 32],
+}
+
+/// Hash of a message signed with `show_and_sign`
+pub(crate) fn signed_message_hash(domain: &DomainSeparator, message: &[u8]) -> [u8; 32] {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris signed message");
+    transcript.append_message(b"message", message);
+    transcript.digest(b"message hash")
+}
+
+/// Challenge of a signed presentation, over the randomized credential, the message
+/// hash and the announcement of the opening proof
+pub(crate) fn signed_show_challenge<E: Pairing>(
+    domain: &DomainSeparator,
+    presentation: &Presentation<E>,
+    message_hash: &[u8; 32],
+    schnorr_commitment: &E::G1Affine,
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris show and sign");
+    transcript.append(b"signature", &presentation.signature);
+    transcript.append(b"cm", &presentation.commitment);
+    transcript.append(b"cm_tilde", &presentation.commitment_tilde);
+    transcript.append_message(b"message_hash", message_hash);
+    transcript.append(b"schnorr_commitment", schnorr_commitment);
+    transcript.challenge_scalar(b"challenge")
+}
+
+impl<E: Pairing> SignedPresentation<E> {
+    /// Verify the credential and that the opening proof answers the challenge for
+    /// `message`, derived under `domain`
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        domain: &DomainSeparator,
+        message: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        if signed_message_hash(domain, message) != self.message_hash {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&self.presentation.proof[..])?;
+        let challenge = signed_show_challenge(
+            domain,
+            &self.presentation,
+            &self.message_hash,
+            &proof.schnorr_commitment,
+        );
+        if proof.challenge != challenge {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        self.presentation.verify(ck, vk, rng)
+    }
+}
+
+/// Proof that the holder has a valid signature on the committed attributes, without
+/// revealing the randomized `sigma`. Alongside the opening proof for `commitment`, it is
+/// a Schnorr proof over `S -> e(S, g_tilde)` for the statement
+/// `e(sigma, g_tilde) = e(h, g_tilde_x + cm_tilde)`, with both proofs answering one
+/// challenge over the caller's `challenge_bytes`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PossessionProof<E: Pairing> {
+    /// The randomized `h`
+    pub h: E::G1Affine,
+    pub commitment: E::G1Affine,
+    pub commitment_tilde: E::G2Affine,
+    /// Serialized opening proof for `commitment`
+    pub proof: Vec<u8>,
+    /// `e(T, g_tilde)` for a random `T` in G1
+    pub announcement: E::TargetField,
+    /// `T + c sigma`
+    pub response: E::G1Affine,
+}
+
+/// Challenge of a possession proof, over the caller's bytes, the statement and the
+/// announcements of both sigma protocols
+pub(crate) fn possession_challenge<E: Pairing>(
+    domain: &DomainSeparator,
+    challenge_bytes: &[u8],
+    h: &E::G1Affine,
+    commitment: &E::G1Affine,
+    commitment_tilde: &E::G2Affine,
+    schnorr_commitment: &E::G1Affine,
+    announcement: &E::TargetField,
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris proof of possession");
+    transcript.append_message(b"challenge_bytes", challenge_bytes);
+    transcript.append(b"h", h);
+    transcript.append(b"cm", commitment);
+    transcript.append(b"cm_tilde", commitment_tilde);
+    transcript.append(b"schnorr_commitment", schnorr_commitment);
+    transcript.append(b"announcement", announcement);
+    transcript.challenge_scalar(b"challenge")
+}
+
+impl<E: Pairing> PossessionProof<E> {
+    /// Verify the proof for `challenge_bytes` against the issuer's keys. Checks the
+    /// opening proof, `e(cm, g_tilde) = e(g, cm_tilde)` and
+    /// `e(z, g_tilde) = A e(h, g_tilde_x + cm_tilde)^c`
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        domain: &DomainSeparator,
+        challenge_bytes: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        if self.h.is_zero() {
+            return Err(SignatureError::IdentitySignature);
+        }
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&self.proof[..])?;
+        let challenge = possession_challenge::<E>(
+            domain,
+            challenge_bytes,
+            &self.h,
+            &self.commitment,
+            &self.commitment_tilde,
+            &proof.schnorr_commitment,
+            &self.announcement,
+        );
+        if proof.challenge != challenge
+            || !Commitment::<E>::verify_against(
+                &self.proof,
+                &vk.show_key(ck).get_bases().0,
+                &self.commitment,
+            )?
+        {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+
+        let vk_plus_cm_tilde = vk.g_tilde_x.add(self.commitment_tilde).into_affine();
+        let neg_c_h = self.h.mul(-challenge).into_affine();
+        let mut check = PairingCheck::<E>::rand(
+            rng,
+            &[(&self.response, &ck.g_tilde), (&neg_c_h, &vk_plus_cm_tilde)],
+            &self.announcement,
+        );
+        check.merge(&PairingCheck::<E>::rand(
+            rng,
+            &[
+                (&self.commitment, &ck.g_tilde),
+                (ck.neg_g(), &self.commitment_tilde),
+            ],
+            &E::TargetField::one(),
+        ));
+        if !check.verify() {
+            return Err(SignatureError::SignatureVerificationFailed);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::errors::CredentialError;
+    use crate::keygen::keygen;
+    use crate::protocol::{UserProtocol, VerifierProtocol};
+    use crate::signer::Signer;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+    use ark_std::UniformRand;
+
+    fn presentation_fixture() -> (
+        SymmetricCommitmentKey<Bls12_381>,
+        VerificationKey<Bls12_381>,
+        Presentation<Bls12_381>,
+    ) {
+        let mut rng = test_rng();
+        let threshold = 2;
+        let (ck, vk, ts_keys) = keygen::<Bls12_381>(threshold, 3, 3, &mut rng);
+        let (mut credential, request) =
+            UserProtocol::request_credential(ck.clone(), None, &mut rng).unwrap();
+
+        let signers: Vec<_> = ts_keys
+            .sk_shares
+            .iter()
+            .zip(ts_keys.vk_shares.iter())
+            .map(|(sk, vk)| Signer::new(&ck, sk, vk))
+            .collect();
+        let shares =
+            UserProtocol::collect_signature_shares(&signers, &request, threshold, &mut rng)
+                .unwrap();
+        let sig = UserProtocol::aggregate_shares(
+            &vk,
+            &shares,
+            credential.get_blinding_factors(),
+            threshold,
+            &request.h,
+        )
+        .unwrap();
+        credential.attach_signature(sig, &vk);
+
+        let presentation = UserProtocol::show(&credential, &mut rng).unwrap();
+        (ck, vk, presentation)
+    }
+
+    #[test]
+    fn test_presentation_serialization_round_trip() {
+        let (ck, vk, presentation) = presentation_fixture();
+
+        let mut bytes = Vec::new();
+        presentation.serialize_compressed(&mut bytes).unwrap();
+        let decoded = Presentation::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+
+        assert_eq!(decoded.signature.h, presentation.signature.h);
+        assert_eq!(decoded.signature.sigma, presentation.signature.sigma);
+        assert_eq!(decoded.commitment, presentation.commitment);
+        assert_eq!(decoded.commitment_tilde, presentation.commitment_tilde);
+        assert_eq!(decoded.proof, presentation.proof);
+        assert!(VerifierProtocol::verify(&ck, &vk, &decoded, &mut test_rng()).unwrap());
+    }
+
+    #[test]
+    fn test_presentation_verify() {
+        let (ck, vk, presentation) = presentation_fixture();
+        let mut rng = test_rng();
+        assert!(presentation.verify(&ck, &vk, &mut rng).unwrap());
+
+        // The opening proof must be for the presented commitment
+        let mut swapped = presentation.clone();
+        swapped.commitment = G1Affine::rand(&mut rng);
+        assert!(matches!(
+            VerifierProtocol::verify(&ck, &vk, &swapped, &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::ProofVerificationFailed
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let mut rng = test_rng();
+        let system = crate::system::System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let presentations: Vec<_> = (0..4)
+            .map(|_| UserProtocol::show(&credential, &mut rng).unwrap())
+            .collect();
+        assert!(
+            VerifierProtocol::verify_batch(&system.ck, &system.vk, &presentations, &mut rng)
+                .unwrap()
+        );
+
+        // Another session's sigma keeps every opening proof valid, only the combined
+        // pairing check can catch it
+        let mut tampered = presentations.clone();
+        tampered[2].signature.sigma = presentations[1].signature.sigma;
+        assert!(matches!(
+            Presentation::verify_batch(&system.ck, &system.vk, &tampered, &mut rng),
+            Err(SignatureError::SignatureVerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn test_show_many() {
+        let mut rng = test_rng();
+        let mut committee = |attributes| {
+            crate::system::System::<Bls12_381>::builder()
+                .threshold(2)
+                .signers(3)
+                .attributes(attributes)
+                .build(&mut rng)
+                .unwrap()
+        };
+        let (first, second) = (committee(3), committee(5));
+        let credentials = [
+            first.user().obtain_credential(None, &mut rng).unwrap(),
+            second.user().obtain_credential(None, &mut rng).unwrap(),
+            first.user().obtain_credential(None, &mut rng).unwrap(),
+        ];
+        let keys = [
+            (&first.ck, &first.vk),
+            (&second.ck, &second.vk),
+            (&first.ck, &first.vk),
+        ];
+        let domain = credentials[0].domain().clone();
+
+        let shown =
+            UserProtocol::show_many(&credentials.iter().collect::<Vec<_>>(), &mut rng).unwrap();
+        assert!(VerifierProtocol::verify_many(&keys, &domain, &shown, &mut rng).unwrap());
+        let separate: usize = credentials
+            .iter()
+            .map(|credential| credential.show(&mut rng).unwrap().serialized_size())
+            .sum();
+        assert!(shown.serialized_size() < separate);
+
+        // One corrupted response fails the combined opening check
+        let mut corrupted = shown.clone();
+        corrupted.responses[1][2] += Fr::from(1u64);
+        assert!(matches!(
+            corrupted.verify(&keys, &domain, &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::ProofVerificationFailed
+            ))
+        ));
+
+        // So does another credential's signature, in the pairing check
+        let mut swapped = shown.clone();
+        swapped.signatures.swap(0, 2);
+        assert!(swapped.verify(&keys, &domain, &mut rng).is_err());
+        assert!(shown.verify(&keys[..2], &domain, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_show_and_sign_binds_message() {
+        let mut rng = test_rng();
+        let system = crate::system::System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let domain = credential.domain().clone();
+        let message = [7u8; 32];
+
+        let signed = UserProtocol::show_and_sign(&credential, &message, &mut rng).unwrap();
+        let mut bytes = Vec::new();
+        signed.serialize_compressed(&mut bytes).unwrap();
+        let signed = SignedPresentation::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(VerifierProtocol::verify_signed_presentation(
+            &system.ck, &system.vk, &domain, &signed, &message, &mut rng
+        )
+        .unwrap());
+
+        // One flipped bit of the message
+        let mut flipped = message;
+        flipped[31] ^= 1;
+        assert!(signed
+            .verify(&system.ck, &system.vk, &domain, &flipped, &mut rng)
+            .is_err());
+
+        // Substituting the bound hash for the other message's breaks the challenge
+        let mut substituted = signed.clone();
+        substituted.message_hash = signed_message_hash(&domain, &flipped);
+        assert!(matches!(
+            substituted.verify(&system.ck, &system.vk, &domain, &flipped, &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::ProofVerificationFailed
+            ))
+        ));
+
+        // And the plain presentation inside is still a valid show
+        assert!(signed
+            .presentation
+            .verify(&system.ck, &system.vk, &mut rng)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_replay_with_wrong_nonce_rejected() {
+        let mut rng = test_rng();
+        let system = crate::system::System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let domain = credential.domain().clone();
+        let nonce = [3u8; 32];
+
+        let shown = UserProtocol::show_with_nonce(&credential, nonce, &mut rng).unwrap();
+        let mut bytes = Vec::new();
+        shown.serialize_compressed(&mut bytes).unwrap();
+        let shown = Presentation::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(shown.nonce, Some(nonce));
+        assert!(VerifierProtocol::verify_with_nonce(
+            &system.ck, &system.vk, &domain, &shown, &nonce, &mut rng
+        )
+        .unwrap());
+
+        // Replayed to a verifier that issued a fresh nonce
+        let fresh = [4u8; 32];
+        assert!(matches!(
+            shown.verify_with_nonce(&system.ck, &system.vk, &domain, &fresh, &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::ProofVerificationFailed
+            ))
+        ));
+
+        // Relabelled with the fresh nonce, the proof no longer answers its challenge
+        let mut relabelled = shown.clone();
+        relabelled.nonce = Some(fresh);
+        assert!(relabelled
+            .verify_with_nonce(&system.ck, &system.vk, &domain, &fresh, &mut rng)
+            .is_err());
+
+        // A plain show carries no nonce and its random challenge isn't bound to one
+        let plain = credential.show(&mut rng).unwrap();
+        assert_eq!(plain.nonce, None);
+        assert!(plain
+            .verify_with_nonce(&system.ck, &system.vk, &domain, &nonce, &mut rng)
+            .is_err());
+        let mut labelled = plain.clone();
+        labelled.nonce = Some(nonce);
+        assert!(labelled
+            .verify_with_nonce(&system.ck, &system.vk, &domain, &nonce, &mut rng)
+            .is_err());
+
+        // It's still a valid presentation for verifiers that don't check freshness
+        assert!(shown.verify(&system.ck, &system.vk, &mut rng).unwrap());
+    }
+
+    #[test]
+    fn test_show_disclosing_binds_disclosed_values() {
+        let mut rng = test_rng();
+        let system = crate::system::System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let attributes = [Fr::from(5u64), Fr::from(6u64), Fr::from(7u64)];
+        let credential = system
+            .user()
+            .obtain_credential(Some(&attributes), &mut rng)
+            .unwrap();
+        let domain = credential.domain().clone();
+        let nonce = [9u8; 32];
+
+        let (shown, disclosed) =
+            UserProtocol::show_disclosing(&credential, nonce, &[0, 2], &mut rng).unwrap();
+        assert_eq!(disclosed, vec![(0, attributes[0]), (2, attributes[2])]);
+        assert!(VerifierProtocol::verify_disclosed(
+            &system.ck, &system.vk, &domain, &shown, &nonce, &disclosed, &mut rng
+        )
+        .unwrap());
+
+        // A forged value, a dropped disclosure and an extra one all fail
+        let forged = vec![(0, attributes[0]), (2, Fr::from(8u64))];
+        assert!(shown
+            .verify_disclosed(&system.ck, &system.vk, &domain, &nonce, &forged, &mut rng)
+            .is_err());
+        assert!(shown
+            .verify_disclosed(
+                &system.ck,
+                &system.vk,
+                &domain,
+                &nonce,
+                &disclosed[..1],
+                &mut rng
+            )
+            .is_err());
+        let extra = vec![(0, attributes[0]), (1, attributes[1]), (2, attributes[2])];
+        assert!(shown
+            .verify_disclosed(&system.ck, &system.vk, &domain, &nonce, &extra, &mut rng)
+            .is_err());
+        assert!(matches!(
+            shown.verify_disclosed(
+                &system.ck,
+                &system.vk,
+                &domain,
+                &nonce,
+                &[(3, Fr::from(1u64))],
+                &mut rng
+            ),
+            Err(SignatureError::InvalidMessage(
+                MessageError::DisclosedIndexOutOfRange { .. }
+            ))
+        ));
+        assert!(matches!(
+            credential.show_disclosing(nonce, &[2, 0], &mut rng),
+            Err(CredentialError::InvalidAttributes(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_all_verifies_and_detects_tampering() {
+        let mut rng = test_rng();
+        let system = crate::system::System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+
+        let opened = UserProtocol::open_all(&credential, &mut rng).unwrap();
+        assert_eq!(&opened.messages, credential.get_messages());
+        assert!(VerifierProtocol::verify_opened(&system.ck, &system.vk, &opened).unwrap());
+
+        // A tampered attribute no longer matches the commitments
+        let mut tampered = opened.clone();
+        tampered.messages[1] += Fr::from(1u64);
+        assert!(matches!(
+            VerifierProtocol::verify_opened(&system.ck, &system.vk, &tampered),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::InvalidCommitment
+            ))
+        ));
+
+        // Nor do commitments recomputed for it, which the signature isn't on
+        let show_key = system.vk.show_key(&system.ck);
+        tampered.commitment = g1_commit(&show_key, &tampered.messages, &tampered.blinding);
+        tampered.commitment_tilde = g2_commit(&show_key, &tampered.messages, &tampered.blinding);
+        assert!(matches!(
+            tampered.verify(&system.ck, &system.vk),
+            Err(SignatureError::SignatureVerificationFailed)
+        ));
+
+        let mut short = opened.clone();
+        short.messages.pop();
+        assert!(short.verify(&system.ck, &system.vk).is_err());
+
+        // Each opening is randomized afresh
+        let again = credential.open_all(&mut rng).unwrap();
+        assert_ne!(again.commitment, opened.commitment);
+        assert_ne!(again.signature.h, opened.signature.h);
+        assert!(again.verify(&system.ck, &system.vk).unwrap());
+    }
+
+    #[test]
+    fn test_nullifier_presentation_rejects_swapped_vrf_proof() {
+        let mut rng = test_rng();
+        let system = crate::system::System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let vrf = DYPFPrivVRF::<G1Affine>::new(&mut rng);
+        let (sk, _) = vrf.generate_keys(&mut rng);
+
+        let shown = UserProtocol::show_with_nullifier(&credential, &vrf, &sk, &mut rng).unwrap();
+        assert!(VerifierProtocol::verify_with_nullifier(
+            &system.ck,
+            &system.vk,
+            &vrf,
+            credential.domain(),
+            &shown,
+            &mut rng
+        )
+        .unwrap());
+
+        // The joint challenge depends on the domain
+        assert!(shown
+            .verify(
+                &system.ck,
+                &system.vk,
+                &vrf,
+                &DomainSeparator::new(b"myapp-v1".as_slice()),
+                &mut rng
+            )
+            .is_err());
+
+        // Same key and context give the same nullifier in every session
+        let other_session =
+            UserProtocol::show_with_nullifier(&credential, &vrf, &sk, &mut rng).unwrap();
+        assert_eq!(other_session.nullifier, shown.nullifier);
+
+        let mut swapped = shown.clone();
+        swapped.vrf_proof = other_session.vrf_proof.clone();
+        swapped.cm_x = other_session.cm_x;
+        assert!(matches!(
+            swapped.verify(&system.ck, &system.vk, &vrf, credential.domain(), &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::ProofVerificationFailed
+            ))
+        ));
+
+        // The opening proof alone still carries the joint challenge, it can't be
+        // paired with another session's nullifier proof either
+        let mut mixed = other_session;
+        mixed.presentation = shown.presentation;
+        assert!(mixed
+            .verify(&system.ck, &system.vk, &vrf, credential.domain(), &mut rng)
+            .is_err());
+    }
+}
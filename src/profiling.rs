@@ -0,0 +1,179 @@
+//! Timing breakdown of signature verification.
+//!
+//! `ThresholdSignature::verify_profiled` runs the checks of `verify` one stage at a time
+//! and reports how long each took: deserializing the opening proof, the Schnorr check,
+//! the Miller loops of the signature and commitment equations and the final
+//! exponentiation. `verify` runs both equations in one multi Miller loop, here each
+//! gets its own so it can be timed, which makes the profiled total slightly higher.
+use crate::commitment::CommitmentProof;
+use crate::errors::{CommitmentError, SignatureError};
+use crate::keygen::VerificationKey;
+use crate::pairing::PairingCheck;
+use crate::presentation::Presentation;
+use crate::signature::{pair_refs, ThresholdSignature};
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::CanonicalDeserialize;
+use ark_std::rand::{CryptoRng, Rng};
+use ark_std::One;
+use std::time::Instant;
+
+/// Nanoseconds spent in each stage of `verify_profiled`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerifyTimings {
+    pub deserialization_ns: u64,
+    pub schnorr_ns: u64,
+    /// Miller loop of `e(sigma, g_tilde) e(-h, g_tilde^x cm_tilde) = 1`
+    pub signature_pairing_ns: u64,
+    /// Coefficient and Miller loop of `e(cm, g_tilde) e(-g, cm_tilde) = 1`
+    pub commitment_pairing_ns: u64,
+    pub final_exponentiation_ns: u64,
+}
+
+impl VerifyTimings {
+    pub fn total_ns(&self) -> u64 {
+        self.deserialization_ns
+            + self.schnorr_ns
+            + self.signature_pairing_ns
+            + self.commitment_pairing_ns
+            + self.final_exponentiation_ns
+    }
+}
+
+/// Nanoseconds since `start`, saturating at `u64::MAX`
+fn elapsed_ns(start: Instant) -> u64 {
+    u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX)
+}
+
+impl<E: Pairing> ThresholdSignature<E> {
+    /// `verify` with the time spent in each stage. Fails the same way `verify` does,
+    /// except that failing pairing equations give `Ok((false, timings))` instead of
+    /// `SignatureVerificationFailed`, so a slow rejection can be profiled too
+    pub fn verify_profiled(
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        cm: &E::G1Affine,
+        cm_tilde: &E::G2Affine,
+        sig: &ThresholdSignature<E>,
+        serialized_proof: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(bool, VerifyTimings), SignatureError> {
+        let mut timings = VerifyTimings::default();
+        if sig.h.is_zero() || sig.sigma.is_zero() {
+            return Err(SignatureError::IdentitySignature);
+        }
+
+        let start = Instant::now();
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(serialized_proof)?;
+        timings.deserialization_ns = elapsed_ns(start);
+
+        let bases = vk.show_key(ck).get_bases().0;
+        let start = Instant::now();
+        let opened = proof.verify_against(&bases, cm)?;
+        timings.schnorr_ns = elapsed_ns(start);
+        if !opened {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+
+        // As in `rand_equations`, only the second equation needs a coefficient
+        let [signature_pairs, commitment_pairs] =
+            Self::pairing_equations(ck, vk, cm, cm_tilde, sig)?;
+        let one = E::TargetField::one();
+        let start = Instant::now();
+        let mut check = PairingCheck::<E>::from_pairs(&pair_refs(&signature_pairs), &one);
+        timings.signature_pairing_ns = elapsed_ns(start);
+
+        let start = Instant::now();
+        check.merge(&PairingCheck::<E>::rand(
+            rng,
+            &pair_refs(&commitment_pairs),
+            &one,
+        ));
+        timings.commitment_pairing_ns = elapsed_ns(start);
+
+        let start = Instant::now();
+        let is_valid = check.verify();
+        timings.final_exponentiation_ns = elapsed_ns(start);
+
+        Ok((is_valid, timings))
+    }
+}
+
+impl<E: Pairing> Presentation<E> {
+    /// `verify` with the time spent in each stage, see
+    /// `ThresholdSignature::verify_profiled`
+    pub fn verify_profiled(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(bool, VerifyTimings), SignatureError> {
+        ThresholdSignature::verify_profiled(
+            ck,
+            vk,
+            &self.commitment,
+            &self.commitment_tilde,
+            &self.signature,
+            &self.proof,
+            rng,
+        )
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::Bls12_381;
+
+    #[test]
+    fn test_profiled_verify_matches_verify() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(4)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        assert!(presentation
+            .verify(&system.ck, &system.vk, &mut rng)
+            .unwrap());
+
+        let start = Instant::now();
+        let (valid, timings) = presentation
+            .verify_profiled(&system.ck, &system.vk, &mut rng)
+            .unwrap();
+        let whole = elapsed_ns(start);
+        assert!(valid);
+        // The stages cover the call, up to building the bases and the pairs
+        assert!(timings.total_ns() <= whole);
+        assert!(timings.total_ns() >= whole / 2);
+        assert!(timings.signature_pairing_ns > 0 && timings.final_exponentiation_ns > 0);
+
+        // A signature over other attributes fails both ways
+        let other = system.user().obtain_credential(None, &mut rng).unwrap();
+        let mut forged = presentation.clone();
+        forged.signature = system.user().show(&other, &mut rng).unwrap().signature;
+        assert!(matches!(
+            forged.verify(&system.ck, &system.vk, &mut rng),
+            Err(SignatureError::SignatureVerificationFailed)
+        ));
+        let (valid, _) = forged
+            .verify_profiled(&system.ck, &system.vk, &mut rng)
+            .unwrap();
+        assert!(!valid);
+        let mut bad_proof = presentation.clone();
+        bad_proof.proof.truncate(8);
+        assert_eq!(
+            bad_proof
+                .verify_profiled(&system.ck, &system.vk, &mut rng)
+                .is_err(),
+            bad_proof.verify(&system.ck, &system.vk, &mut rng).is_err()
+        );
+    }
+}
@@ -1,26 +1,58 @@
-use crate::credential::{Credential, CredentialCommitments};
-use crate::errors::{CredentialError, SignatureError};
-use crate::keygen::VerificationKeyShare;
-use crate::keygen::{keygen, ThresholdKeys, VerificationKey};
-use crate::signature::{PartialSignature, ThresholdSignature};
-use crate::signer::Signer;
+use crate::accumulator::{NonMembershipPresentation, UniversalAccumulatorPublicKey};
+#[cfg(feature = "async")]
+use crate::client::SignerClient;
+use crate::errors::{CommitmentError, PolicyError, SignatureError};
+use crate::keygen::{PreparedVerificationKey, VerificationKey};
+use crate::messages::PresentationMessage;
+use crate::nullifier::DYPFPrivVRF;
+use crate::policy::{PolicyPresentation, PresentationPolicy};
+use crate::presentation::{
+    MultiPresentation, NullifierPresentation, OpenedCredential, Presentation, PresentationForm,
+    SignedPresentation,
+};
+use crate::revocation::{AccumulatorPublicKey, RevocationPresentation};
+use crate::set_membership::{SetMembershipPresentation, SetParams};
+use crate::signature::ThresholdSignature;
 use crate::symmetric_commitment::SymmetricCommitmentKey;
-use crate::user::User;
+use crate::transcript::DomainSeparator;
 use ark_ec::pairing::Pairing;
-use ark_std::{rand::Rng, UniformRand};
-use rayon::prelude::*;
+use ark_std::rand::{CryptoRng, Rng};
+#[cfg(feature = "signing")]
+use {
+    crate::accumulator::NonMembershipWitness,
+    crate::attributes::Attributes,
+    crate::commitment::key_attribute,
+    crate::credential::{private_indices, Credential, CredentialCommitments, ReissueRequest},
+    crate::errors::CredentialError,
+    crate::errors::MessageError,
+    crate::keygen::{keygen, ThresholdKeys, VerificationKeyShare},
+    crate::messages::{
+        validate_commitment_points, validate_share_points, IssuanceRequest, IssuanceResponse,
+    },
+    crate::nullifier::DYPFPrivSecretKey,
+    crate::revocation::MembershipWitness,
+    crate::signature::PartialSignature,
+    crate::signer::Signer,
+    crate::user::{CollectedShares, SignerFailure, User},
+    ark_ec::CurveGroup,
+    ark_ff::UniformRand,
+    ark_std::{iter, ops::Mul, Zero},
+};
 
+#[cfg(feature = "signing")]
 pub struct IssuerProtocol;
+#[cfg(feature = "signing")]
 pub struct UserProtocol;
 pub struct VerifierProtocol;
 
+#[cfg(feature = "signing")]
 impl IssuerProtocol {
     /// Setup generates the system parameters and keys
     pub fn setup<E: Pairing>(
         threshold: usize,
         num_signers: usize,
         num_attributes: usize,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> (
         SymmetricCommitmentKey<E>,
         VerificationKey<E>,
@@ -35,30 +67,251 @@ impl IssuerProtocol {
         commitments: &[E::G1Affine],
         commitment_proofs: &[Vec<u8>],
         h: &E::G1Affine,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<PartialSignature<E>, SignatureError> {
         signer.sign_share(commitments, commitment_proofs, h, rng)
     }
+
+    /// Issuer signs a request that commits only to the private attributes, folding in
+    /// its own `(index, value)` attributes
+    pub fn issue_share_with_public_attributes<E: Pairing>(
+        signer: &Signer<E>,
+        commitments: &[E::G1Affine],
+        commitment_proofs: &[Vec<u8>],
+        h: &E::G1Affine,
+        public_attributes: &[(usize, E::ScalarField)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<PartialSignature<E>, SignatureError> {
+        signer.sign_share_with_public_attributes(
+            commitments,
+            commitment_proofs,
+            h,
+            public_attributes,
+            rng,
+        )
+    }
+
+    /// Issuer checks a `ReissueRequest` before its signers sign `reissue.request`: the old
+    /// presentation has to verify under the old keys and every attribute that isn't in
+    /// `reissue.changed` has to be carried over unchanged. `new_ck` is the key the new
+    /// credential is issued under
+    pub fn verify_reissue_request<E: Pairing>(
+        old_ck: &SymmetricCommitmentKey<E>,
+        old_vk: &VerificationKey<E>,
+        new_ck: &SymmetricCommitmentKey<E>,
+        domain: &DomainSeparator,
+        reissue: &ReissueRequest<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let request = &reissue.request;
+        validate_commitment_points::<E>(&request.h, &request.commitments, &request.proofs)?;
+        if request.commitments.len() != new_ck.num_attributes() {
+            return Err(CommitmentError::InvalidProof.into());
+        }
+        reissue.presentation.verify(old_ck, old_vk, rng)?;
+        if !reissue.proof.verify(
+            domain,
+            &request.h,
+            &new_ck.g,
+            &request.commitments,
+            &reissue.carried()?,
+            &old_vk.show_key(old_ck),
+            &reissue.presentation.commitment,
+        )? {
+            return Err(SignatureError::CommitmentConsistencyFailed);
+        }
+        Ok(true)
+    }
+
+    /// Issuer answers an `IssuanceRequest` with its signature share
+    pub fn respond_to_issuance<E: Pairing>(
+        signer: &Signer<E>,
+        request: &IssuanceRequest<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<IssuanceResponse<E>, SignatureError> {
+        request.validate()?;
+        let commitments = &request.commitments;
+        let share = signer.sign_share(
+            &commitments.commitments,
+            &commitments.proofs,
+            &commitments.h,
+            rng,
+        )?;
+        Ok(IssuanceResponse {
+            party_index: share.party_index,
+            share,
+        })
+    }
 }
 
+#[cfg(feature = "signing")]
 impl UserProtocol {
     /// User creates a credential request
     pub fn request_credential<E: Pairing>(
         commitment_key: SymmetricCommitmentKey<E>,
         attributes: Option<&[E::ScalarField]>,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Credential<E>, CredentialCommitments<E>), CredentialError> {
+        if attributes.is_some_and(|attributes| attributes.is_empty()) {
+            return Err(CredentialError::NoAttributes);
+        }
+        let attributes = match attributes {
+            Some(values) => Attributes::new(values.to_vec(), &commitment_key)
+                .map_err(CredentialError::InvalidAttributes)?,
+            None => Attributes::random(&commitment_key, rng),
+        };
+        Self::request_credential_with_attributes(commitment_key, attributes, rng)
+    }
+
+    /// `request_credential` for attributes already checked against `commitment_key`
+    pub fn request_credential_with_attributes<E: Pairing>(
+        commitment_key: SymmetricCommitmentKey<E>,
+        attributes: Attributes<E>,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<(Credential<E>, CredentialCommitments<E>), CredentialError> {
-        let mut credential = Credential::new(commitment_key, attributes, rng);
+        if attributes.is_empty() {
+            return Err(CredentialError::NoAttributes);
+        }
+        let mut credential = Credential::from_attributes(commitment_key, attributes, rng);
         let commitments = credential.compute_commitments_per_m(rng)?;
         Ok((credential, commitments))
     }
 
+    /// User creates a credential request whose attribute 0 is `key_attribute(pk)` for
+    /// their key `pk = g^sk`, with the `KeyBindingProof` signers check under
+    /// `require_key_binding`. `attributes` are the remaining attributes, random ones are
+    /// drawn if `None`
+    pub fn request_credential_bound_to_key<E: Pairing>(
+        commitment_key: SymmetricCommitmentKey<E>,
+        sk: &E::ScalarField,
+        attributes: Option<&[E::ScalarField]>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Credential<E>, CredentialCommitments<E>), CredentialError> {
+        let pk = commitment_key.g.mul(sk).into_affine();
+        let key = key_attribute::<E>(&DomainSeparator::default(), &pk);
+        let rest = match attributes {
+            Some(values) => values.to_vec(),
+            None => (1..commitment_key.num_attributes())
+                .map(|_| E::ScalarField::rand(rng))
+                .collect(),
+        };
+        let attributes: Vec<_> = iter::once(key).chain(rest).collect();
+        let mut credential = Credential::new(commitment_key, Some(&attributes), rng)?;
+        let commitments = credential.compute_commitments_with_key_binding(sk, rng)?;
+        Ok((credential, commitments))
+    }
+
+    /// User asks for a fresh credential carrying over the attributes of `old_credential`
+    /// except for the `(index, value)` pairs in `changed`, under the same key. See
+    /// `Credential::request_reissue` for a rotated key
+    pub fn request_reissue<E: Pairing>(
+        old_credential: &Credential<E>,
+        changed: &[(usize, E::ScalarField)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Credential<E>, ReissueRequest<E>), CredentialError> {
+        old_credential.request_reissue(old_credential.ck.clone(), changed, rng)
+    }
+
+    /// Request a credential whose attributes at the indices in `public_attributes` are
+    /// set by the issuer. Only the private attributes, given in index order, are
+    /// committed to, random ones are drawn if `private_attributes` is `None`
+    pub fn request_credential_partial<E: Pairing>(
+        commitment_key: SymmetricCommitmentKey<E>,
+        private_attributes: Option<&[E::ScalarField]>,
+        public_attributes: &[(usize, E::ScalarField)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Credential<E>, CredentialCommitments<E>), CredentialError> {
+        let num_attributes = commitment_key.num_attributes();
+        let private = private_indices(num_attributes, public_attributes)?;
+        let private_values = match private_attributes {
+            Some(values) if values.len() != private.len() => {
+                return Err(CommitmentError::InvalidPublicAttributes(format!(
+                    "expected {} private attributes, got {}",
+                    private.len(),
+                    values.len()
+                ))
+                .into())
+            }
+            Some(values) => values.to_vec(),
+            None => (0..private.len())
+                .map(|_| E::ScalarField::rand(rng))
+                .collect(),
+        };
+
+        let mut attributes = vec![E::ScalarField::zero(); num_attributes];
+        for (index, value) in public_attributes {
+            attributes[*index] = *value;
+        }
+        for (index, value) in private.into_iter().zip(private_values) {
+            attributes[index] = value;
+        }
+
+        let public_indices: Vec<usize> = public_attributes.iter().map(|(k, _)| *k).collect();
+        let mut credential = Credential::new(commitment_key, Some(&attributes), rng)?;
+        let commitments = credential.compute_commitments_partial(&public_indices, rng)?;
+        Ok((credential, commitments))
+    }
+
+    /// `request_credential` producing the request as an `IssuanceRequest` for `key_id`
+    pub fn request_issuance<E: Pairing>(
+        key_id: &[u8],
+        commitment_key: SymmetricCommitmentKey<E>,
+        attributes: Option<&[E::ScalarField]>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Credential<E>, IssuanceRequest<E>), CredentialError> {
+        let (credential, commitments) = Self::request_credential(commitment_key, attributes, rng)?;
+        let request = IssuanceRequest {
+            key_id: key_id.to_vec(),
+            context: credential.context,
+            commitments,
+        };
+        Ok((credential, request))
+    }
+
+    /// Verify the signers' `IssuanceResponse`s, aggregate them and attach the
+    /// resulting signature to `credential`
+    #[allow(clippy::too_many_arguments)]
+    pub fn complete_issuance<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        vk_shares: &[VerificationKeyShare<E>],
+        credential: &mut Credential<E>,
+        request: &IssuanceRequest<E>,
+        responses: &[IssuanceResponse<E>],
+        threshold: usize,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(), SignatureError> {
+        request.validate()?;
+        let shares = responses
+            .iter()
+            .map(|response| response.clone().try_into())
+            .collect::<Result<Vec<(usize, PartialSignature<E>)>, MessageError>>()?;
+
+        let verified = Self::verify_signature_shares(
+            commitment_key,
+            vk_shares,
+            &request.commitments,
+            &shares,
+            threshold,
+            rng,
+        )?;
+        let signature = Self::aggregate_shares(
+            verification_key,
+            &verified,
+            credential.get_blinding_factors(),
+            threshold,
+            &request.commitments.h,
+        )?;
+        credential.attach_signature(signature, verification_key);
+        Ok(())
+    }
+
     // /// User collects signatures from multiple issuers
     // pub fn collect_signature_shares<E: Pairing>(
     //     signers: &[Signer<E>],
     //     credential_request: &CredentialCommitments<E>,
     //     threshold: usize,
-    //     rng: &mut impl Rng,
+    //     rng: &mut (impl Rng + CryptoRng),
     // ) -> Result<Vec<(usize, PartialSignature<E>)>, SignatureError> {
     //     let mut shares = Vec::new();
 
@@ -89,28 +342,63 @@ impl UserProtocol {
     //     Ok(shares)
     // }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(signers = signers.len(), threshold = threshold))
+    )]
     pub fn collect_signature_shares<E: Pairing>(
         signers: &[Signer<E>],
         credential_request: &CredentialCommitments<E>,
         threshold: usize,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<Vec<(usize, PartialSignature<E>)>, SignatureError> {
         let commitments = &credential_request.commitments;
         let proofs = &credential_request.proofs;
         let h = &credential_request.h;
 
+        #[cfg(feature = "parallel")]
+        let shares: Vec<_> = {
+            use ark_std::rand::{rngs::StdRng, SeedableRng};
+            use rayon::prelude::*;
+
+            // Each signer gets an RNG seeded from the caller's RNG up front, so the
+            // shares are the same whichever side of the cut-over this runs on
+            let seeds: Vec<[u8; 32]> = signers.iter().take(threshold).map(|_| rng.gen()).collect();
+            let sign = |(signer, seed): (&Signer<E>, [u8; 32])| {
+                signer
+                    .sign_share(commitments, proofs, h, &mut StdRng::from_seed(seed))
+                    .map(|sig_share| (sig_share.party_index, sig_share))
+            };
+            if crate::parallel::use_rayon(seeds.len()) {
+                signers
+                    .par_iter()
+                    .zip(seeds)
+                    .map(sign)
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                signers
+                    .iter()
+                    .zip(seeds)
+                    .map(sign)
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+
+        // Sequential fallback if parallel feature is not enabled
+        #[cfg(not(feature = "parallel"))]
         let shares: Vec<_> = signers
-            .par_iter()
+            .iter()
             .take(threshold)
             .map(|signer| {
-                // Each thread gets its own RNG
-                let mut thread_rng = rand::thread_rng();
                 signer
-                    .sign_share(commitments, proofs, h, &mut thread_rng)
+                    .sign_share(commitments, proofs, h, rng)
                     .map(|sig_share| (sig_share.party_index, sig_share))
             })
             .collect::<Result<Vec<_>, _>>()?;
 
+        #[cfg(feature = "tracing")]
+        tracing::debug!(collected = shares.len(), "collected signature shares");
+
         if shares.len() < threshold {
             return Err(SignatureError::InsufficientShares {
                 needed: threshold,
@@ -121,14 +409,147 @@ impl UserProtocol {
         Ok(shares)
     }
 
+    /// Ask `signers` in order for shares over `credential_request`, checking each share
+    /// against the signer's verification key share as it arrives. A signer that fails
+    /// or returns an invalid share is replaced by the next unused one, until `threshold`
+    /// valid shares are in, `max_attempts` signers have been asked or the committee is
+    /// exhausted. The report lists every signer that failed
+    pub fn collect_signature_shares_with_fallback<E: Pairing>(
+        signers: &[Signer<E>],
+        credential_request: &CredentialCommitments<E>,
+        threshold: usize,
+        max_attempts: usize,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<CollectedShares<E>, SignatureError> {
+        let mut collected = CollectedShares {
+            shares: Vec::with_capacity(threshold),
+            failures: Vec::new(),
+        };
+        for signer in signers.iter().take(max_attempts) {
+            if collected.shares.len() == threshold {
+                break;
+            }
+            let index = signer.vk_share.index;
+            match signer.sign_request(credential_request, false, rng) {
+                Ok(share) => {
+                    let valid = share.party_index == index
+                        && share.h == credential_request.h
+                        && validate_share_points(&share).is_ok()
+                        && User::verify_signature_share_pairing_only(
+                            signer.ck,
+                            signer.vk_share,
+                            &credential_request.commitments,
+                            &share,
+                        );
+                    if valid {
+                        collected.shares.push((index, share));
+                    } else {
+                        collected
+                            .failures
+                            .push((index, SignerFailure::InvalidShare));
+                    }
+                }
+                Err(e) => collected.failures.push((index, SignerFailure::NoShare(e))),
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            collected = collected.shares.len(),
+            failed = collected.failures.len(),
+            "collected signature shares with fallback"
+        );
+
+        if collected.shares.len() < threshold {
+            return Err(SignatureError::InsufficientShares {
+                needed: threshold,
+                got: collected.shares.len(),
+            });
+        }
+        Ok(collected)
+    }
+
+    /// Request shares from every client concurrently and return as soon as
+    /// `threshold` of them have answered with a valid share. The request's commitment
+    /// proofs are verified once up front, then each share as it arrives against the
+    /// verification key share with its party index. Failing clients and invalid or
+    /// repeated shares are skipped, and outstanding requests are dropped once enough
+    /// shares have arrived
+    #[cfg(feature = "async")]
+    pub async fn collect_signature_shares_async<E: Pairing, C: SignerClient<E>>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        vk_shares: &[VerificationKeyShare<E>],
+        clients: &[C],
+        credential_request: &CredentialCommitments<E>,
+        threshold: usize,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Vec<(usize, PartialSignature<E>)>, SignatureError> {
+        use futures::stream::{FuturesUnordered, StreamExt};
+
+        User::verify_request_proofs::<E>(&credential_request.proofs, rng)?;
+
+        let mut pending: FuturesUnordered<_> = clients
+            .iter()
+            .map(|client| client.sign_share(credential_request))
+            .collect();
+
+        let mut shares: Vec<(usize, PartialSignature<E>)> = Vec::with_capacity(threshold);
+        while let Some(result) = pending.next().await {
+            let Ok(sig_share) = result else { continue };
+            let index = sig_share.party_index;
+            let valid = sig_share.h == credential_request.h
+                && !shares.iter().any(|(i, _)| *i == index)
+                && validate_share_points(&sig_share).is_ok()
+                && vk_shares
+                    .iter()
+                    .find(|vk_share| vk_share.index == index)
+                    .is_some_and(|vk_share| {
+                        User::verify_signature_share_pairing_only(
+                            commitment_key,
+                            vk_share,
+                            &credential_request.commitments,
+                            &sig_share,
+                        )
+                    });
+            if valid {
+                shares.push((index, sig_share));
+                if shares.len() >= threshold {
+                    return Ok(shares);
+                }
+            }
+        }
+
+        Err(SignatureError::InsufficientShares {
+            needed: threshold,
+            got: shares.len(),
+        })
+    }
+
     /// Verify signature shares before aggregation
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(shares = signature_shares.len(), threshold = threshold))
+    )]
     pub fn verify_signature_shares<E: Pairing>(
         commitment_key: &SymmetricCommitmentKey<E>,
         vk_shares: &[VerificationKeyShare<E>],
         credential_request: &CredentialCommitments<E>,
         signature_shares: &[(usize, PartialSignature<E>)],
         threshold: usize,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<Vec<(usize, PartialSignature<E>)>, SignatureError> {
+        for (_, share) in signature_shares {
+            validate_share_points(share)?;
+        }
+
+        // Shares signed over a different h can't be combined with this request
+        if signature_shares
+            .iter()
+            .any(|(_, share)| share.h != credential_request.h)
+        {
+            return Err(SignatureError::CommitmentConsistencyFailed);
+        }
+
         User::process_signature_shares(
             commitment_key,
             vk_shares,
@@ -136,19 +557,66 @@ impl UserProtocol {
             &credential_request.proofs,
             signature_shares,
             threshold,
+            rng,
+        )
+        .map(|verified| verified.valid)
+    }
+
+    /// `verify_signature_shares` for a request from `request_credential_partial`, with
+    /// the issuer-set attributes the shares were signed over
+    pub fn verify_signature_shares_with_public_attributes<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        vk_shares: &[VerificationKeyShare<E>],
+        credential_request: &CredentialCommitments<E>,
+        public_attributes: &[(usize, E::ScalarField)],
+        signature_shares: &[(usize, PartialSignature<E>)],
+        threshold: usize,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Vec<(usize, PartialSignature<E>)>, SignatureError> {
+        let full_request = credential_request
+            .with_public_attributes(public_attributes, commitment_key.num_attributes())?;
+        Self::verify_signature_shares(
+            commitment_key,
+            vk_shares,
+            &full_request,
+            signature_shares,
+            threshold,
+            rng,
         )
     }
 
     /// Aggregate signature shares into a complete threshold signature
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(shares = shares.len(), threshold = threshold))
+    )]
     pub fn aggregate_shares<E: Pairing>(
-        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
         shares: &[(usize, PartialSignature<E>)],
         blindings: &[E::ScalarField],
         threshold: usize,
         h: &E::G1Affine,
     ) -> Result<ThresholdSignature<E>, SignatureError> {
         ThresholdSignature::aggregate_signature_shares(
-            commitment_key,
+            verification_key,
+            shares,
+            blindings,
+            threshold,
+            h,
+        )
+    }
+
+    /// `aggregate_shares`, also returning the party indices combined in the Lagrange
+    /// interpolation
+    pub fn aggregate_shares_detailed<E: Pairing>(
+        verification_key: &VerificationKey<E>,
+        shares: &[(usize, PartialSignature<E>)],
+        blindings: &[E::ScalarField],
+        threshold: usize,
+        h: &E::G1Affine,
+    ) -> Result<(ThresholdSignature<E>, Vec<usize>), SignatureError> {
+        ThresholdSignature::aggregate_signature_shares_detailed(
+            verification_key,
             shares,
             blindings,
             threshold,
@@ -159,21 +627,428 @@ impl UserProtocol {
     /// User shows credential without revealing attributes
     pub fn show<E: Pairing>(
         credential: &Credential<E>,
-        rng: &mut impl Rng,
-    ) -> Result<(ThresholdSignature<E>, E::G1Affine, E::G2Affine, Vec<u8>), CredentialError> {
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Presentation<E>, CredentialError> {
         credential.show(rng)
     }
+
+    /// User shows credential for the verifier's `nonce`, see `Credential::show_with_nonce`
+    pub fn show_with_nonce<E: Pairing>(
+        credential: &Credential<E>,
+        nonce: [u8; 32],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Presentation<E>, CredentialError> {
+        credential.show_with_nonce(nonce, rng)
+    }
+
+    /// User shows credential for the verifier's `nonce`, disclosing the attributes at
+    /// `indices`, see `Credential::show_disclosing`
+    #[allow(clippy::type_complexity)]
+    pub fn show_disclosing<E: Pairing>(
+        credential: &Credential<E>,
+        nonce: [u8; 32],
+        indices: &[usize],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Presentation<E>, Vec<(usize, E::ScalarField)>), CredentialError> {
+        credential.show_disclosing(nonce, indices, rng)
+    }
+
+    /// User opens credential in full, disclosing every attribute
+    pub fn open_all<E: Pairing>(
+        credential: &Credential<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<OpenedCredential<E>, CredentialError> {
+        credential.open_all(rng)
+    }
+
+    /// User shows credential bound to a nullifier for their VRF key
+    pub fn show_with_nullifier<E: Pairing>(
+        credential: &Credential<E>,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        sk: &DYPFPrivSecretKey<E::ScalarField>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<NullifierPresentation<E>, CredentialError> {
+        credential.show_with_nullifier(vrf, sk, rng)
+    }
+
+    /// User shows credential bound to a nullifier for the attribute at `key_index` and
+    /// the public `context_input`
+    pub fn show_with_bound_nullifier<E: Pairing>(
+        credential: &Credential<E>,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        key_index: usize,
+        context_input: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<NullifierPresentation<E>, CredentialError> {
+        credential.show_with_bound_nullifier(vrf, key_index, context_input, rng)
+    }
+
+    /// User shows several credentials with one combined opening proof
+    pub fn show_many<E: Pairing>(
+        credentials: &[&Credential<E>],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<MultiPresentation<E>, CredentialError> {
+        Credential::show_many(credentials, rng)
+    }
+
+    /// User shows credential as a signature of knowledge on `message`
+    pub fn show_and_sign<E: Pairing>(
+        credential: &Credential<E>,
+        message: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<SignedPresentation<E>, CredentialError> {
+        credential.show_and_sign(message, rng)
+    }
+
+    /// User shows credential with a proof that the attribute at `handle_index` is
+    /// not revoked, against the accumulator value in `witness`
+    pub fn show_with_revocation<E: Pairing>(
+        credential: &Credential<E>,
+        handle_index: usize,
+        witness: &MembershipWitness<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<RevocationPresentation<E>, CredentialError> {
+        credential.show_with_revocation(handle_index, witness, rng)
+    }
+
+    /// User shows credential with a proof that the attribute at `handle_index` is not
+    /// accumulated in `witness.value`, the issuer's accumulator over revoked handles
+    pub fn prove_non_membership<E: Pairing>(
+        credential: &Credential<E>,
+        handle_index: usize,
+        accumulator_key: &UniversalAccumulatorPublicKey<E>,
+        witness: &NonMembershipWitness<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<NonMembershipPresentation<E>, CredentialError> {
+        credential.prove_non_membership(handle_index, accumulator_key, witness, rng)
+    }
+
+    /// User shows credential with a proof that the attribute at `attr_index` is one of
+    /// the values signed in `set_params`, without revealing which
+    pub fn show_with_membership<E: Pairing>(
+        credential: &Credential<E>,
+        attr_index: usize,
+        set_params: &SetParams<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<SetMembershipPresentation<E>, CredentialError> {
+        credential.show_with_membership(attr_index, set_params, rng)
+    }
+
+    /// `show` returning the presentation as a tuple
+    #[deprecated(note = "use `show`, which returns a `Presentation`")]
+    #[allow(deprecated)]
+    pub fn show_tuple<E: Pairing>(
+        credential: &Credential<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<crate::credential::ShowOutput<E>, CredentialError> {
+        credential.show_tuple(rng)
+    }
+
+    /// Answer a verifier's `PresentationPolicy`, see `Credential::present`
+    pub fn present<E: Pairing>(
+        credential: &Credential<E>,
+        policy: &PresentationPolicy<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<PolicyPresentation<E>, CredentialError> {
+        credential.present(policy, rng)
+    }
+
+    /// `show_disclosing` wrapped in a `PresentationMessage` for the verifier's `nonce`,
+    /// revealing the attributes at the strictly increasing `disclosed` indices
+    pub fn present_message<E: Pairing>(
+        credential: &Credential<E>,
+        nonce: [u8; 32],
+        disclosed: &[usize],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<PresentationMessage<E>, CredentialError> {
+        let (presentation, disclosed) = credential.show_disclosing(nonce, disclosed, rng)?;
+        Ok(PresentationMessage {
+            presentation,
+            disclosed,
+            nonce,
+        })
+    }
 }
 
 impl VerifierProtocol {
-    /// Verify a credential presentation
-    pub fn verify<E: Pairing>(
+    /// Verify a credential presentation, given as a `Presentation` or, under `serde`, a
+    /// `PresentationDocument`. A document is checked against its envelope's domain,
+    /// nonce and disclosed attributes, see `Presentation::verify_disclosed`
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn verify<'a, E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        presentation: impl Into<PresentationForm<'a, E>>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        match presentation.into() {
+            PresentationForm::Binary(presentation) => {
+                presentation.verify(commitment_key, verification_key, rng)
+            }
+            #[cfg(feature = "serde")]
+            PresentationForm::Document(document) => {
+                let (presentation, envelope) =
+                    Presentation::from_document(document, commitment_key, verification_key)?;
+                presentation.verify_disclosed(
+                    commitment_key,
+                    verification_key,
+                    &envelope.domain(),
+                    &envelope.nonce,
+                    &envelope.disclosed,
+                    rng,
+                )
+            }
+        }
+    }
+
+    /// Verify a presentation against a verification key prepared once for many checks
+    pub fn verify_prepared<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        prepared_key: &PreparedVerificationKey<E>,
+        presentation: &Presentation<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify_prepared(commitment_key, prepared_key, rng)
+    }
+
+    /// Verify a presentation shown for `nonce`, the value this verifier issued, under
+    /// the holder's `domain`. Presentations for any other nonce are rejected
+    pub fn verify_with_nonce<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        domain: &DomainSeparator,
+        presentation: &Presentation<E>,
+        nonce: &[u8; 32],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify_with_nonce(commitment_key, verification_key, domain, nonce, rng)
+    }
+
+    /// Verify a presentation shown for `nonce` that discloses the `(index, attribute)`
+    /// pairs of `disclosed`, under the holder's `domain`
+    pub fn verify_disclosed<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        domain: &DomainSeparator,
+        presentation: &Presentation<E>,
+        nonce: &[u8; 32],
+        disclosed: &[(usize, E::ScalarField)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify_disclosed(
+            commitment_key,
+            verification_key,
+            domain,
+            nonce,
+            disclosed,
+            rng,
+        )
+    }
+
+    /// Verify a credential opened with `open_all` by recomputing its commitments from
+    /// the revealed attributes and blinding
+    pub fn verify_opened<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        opened: &OpenedCredential<E>,
+    ) -> Result<bool, SignatureError> {
+        opened.verify(commitment_key, verification_key)
+    }
+
+    /// Verify many presentations under the same keys, see `Presentation::verify_batch`
+    pub fn verify_batch<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        presentations: &[Presentation<E>],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        Presentation::verify_batch(commitment_key, verification_key, presentations, rng)
+    }
+
+    /// Verify credentials shown together, each against its `(ck, vk)` in `keys`, under
+    /// the holder's `domain`. See `MultiPresentation::verify`
+    pub fn verify_many<E: Pairing>(
+        keys: &[(&SymmetricCommitmentKey<E>, &VerificationKey<E>)],
+        domain: &DomainSeparator,
+        presentation: &MultiPresentation<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify(keys, domain, rng)
+    }
+
+    /// Verify a presentation against each of `verification_keys` in turn, e.g. the old
+    /// and the new committee during a key rotation, and return the index of the first
+    /// key it verifies under, or `None`. The opening proof is over the key's own
+    /// `g^{y_k}`, so a proof that fails is tried under the next key too. Errors that
+    /// don't depend on the key, such as a malformed opening proof, are returned as is
+    pub fn verify_any<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_keys: &[VerificationKey<E>],
+        presentation: &Presentation<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Option<usize>, SignatureError> {
+        for (i, verification_key) in verification_keys.iter().enumerate() {
+            match presentation.verify(commitment_key, verification_key, rng) {
+                Ok(true) => return Ok(Some(i)),
+                Ok(false)
+                | Err(SignatureError::SignatureVerificationFailed)
+                | Err(SignatureError::CommitmentError(CommitmentError::ProofVerificationFailed)) => {
+                    continue
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Verify a presentation bound to a nullifier under the holder's `domain`. Checking
+    /// the nullifier against previously seen ones is up to the caller
+    pub fn verify_with_nullifier<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        domain: &DomainSeparator,
+        presentation: &NullifierPresentation<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify(commitment_key, verification_key, vrf, domain, rng)
+    }
+
+    /// Verify a presentation bound to a nullifier and that the nullifier was derived
+    /// from the shown credential's attribute at `key_index` and from `context_input`
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_nullifier_binding<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        vrf: &DYPFPrivVRF<E::G1Affine>,
+        domain: &DomainSeparator,
+        presentation: &NullifierPresentation<E>,
+        key_index: usize,
+        context_input: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify_binding(
+            commitment_key,
+            verification_key,
+            vrf,
+            domain,
+            key_index,
+            context_input,
+            rng,
+        )
+    }
+
+    /// Verify a presentation signing `message` under the holder's `domain`
+    pub fn verify_signed_presentation<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        domain: &DomainSeparator,
+        presentation: &SignedPresentation<E>,
+        message: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify(commitment_key, verification_key, domain, message, rng)
+    }
+
+    /// Verify a presentation whose handle must be accumulated in `accumulator_value`,
+    /// the latest value published by the issuer, under the holder's `domain`
+    pub fn verify_not_revoked<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        accumulator_key: &AccumulatorPublicKey<E>,
+        domain: &DomainSeparator,
+        presentation: &RevocationPresentation<E>,
+        accumulator_value: &E::G1Affine,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify(
+            commitment_key,
+            verification_key,
+            accumulator_key,
+            accumulator_value,
+            domain,
+            rng,
+        )
+    }
+
+    /// Verify a presentation whose handle must not be accumulated in `accumulator_value`,
+    /// the latest value of the issuer's accumulator over revoked handles, under the
+    /// holder's `domain`
+    pub fn verify_non_membership<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        accumulator_key: &UniversalAccumulatorPublicKey<E>,
+        domain: &DomainSeparator,
+        presentation: &NonMembershipPresentation<E>,
+        accumulator_value: &E::G1Affine,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify(
+            commitment_key,
+            verification_key,
+            accumulator_key,
+            accumulator_value,
+            domain,
+            rng,
+        )
+    }
+
+    /// Verify a presentation whose attribute must be in the set of `set_params`, under
+    /// the holder's `domain`
+    pub fn verify_membership<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        set_params: &SetParams<E>,
+        domain: &DomainSeparator,
+        presentation: &SetMembershipPresentation<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        presentation.verify(commitment_key, verification_key, set_params, domain, rng)
+    }
+
+    /// Check every requirement of `policy` on `presentation` at time `now`
+    pub fn verify_policy<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        policy: &PresentationPolicy<E>,
+        presentation: &PolicyPresentation<E>,
+        now: u64,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(), PolicyError> {
+        policy.verify(commitment_key, verification_key, presentation, now, rng)
+    }
+
+    /// Verify a `PresentationMessage` shown in `domain`: the proof must be bound to the
+    /// message's nonce and disclosed attributes, see `Presentation::verify_disclosed`.
+    /// Checking that the nonce is the one this verifier issued is up to the caller
+    pub fn verify_message<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        verification_key: &VerificationKey<E>,
+        domain: &DomainSeparator,
+        message: &PresentationMessage<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        message.validate()?;
+        message.presentation.verify_disclosed(
+            commitment_key,
+            verification_key,
+            domain,
+            &message.nonce,
+            &message.disclosed,
+            rng,
+        )
+    }
+
+    /// Verify a presentation given as its separate parts
+    #[deprecated(note = "use `verify` with a `Presentation`")]
+    pub fn verify_tuple<E: Pairing>(
         commitment_key: &SymmetricCommitmentKey<E>,
         verification_key: &VerificationKey<E>,
         commitment: &E::G1Affine,
         commitment_tilde: &E::G2Affine,
         signature: &ThresholdSignature<E>,
-        proof: &Vec<u8>,
+        proof: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<bool, SignatureError> {
         ThresholdSignature::<E>::verify(
             commitment_key,
@@ -182,6 +1057,7 @@ impl VerifierProtocol {
             commitment_tilde,
             signature,
             proof,
+            rng,
         )
     }
 }
@@ -0,0 +1,134 @@
+//! Compact receipts for verified presentations.
+//!
+//! A relying party that accepted a presentation can keep a `Receipt` instead of the
+//! presentation itself: the digest of the presentation, the time of verification and
+//! optionally its own signature over both. The signature is opaque to this crate, the
+//! verifier signs `Receipt::signed_message` with whatever scheme it already uses.
+use crate::presentation::Presentation;
+use crate::transcript::Transcript;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Canonical hash of every field of a presentation. Two presentations get the same
+/// digest exactly when their compressed encodings are equal
+pub fn presentation_digest<E: Pairing>(presentation: &Presentation<E>) -> [u8; 32] {
+    let mut transcript = Transcript::new(b"t-siris presentation digest");
+    transcript.append(b"signature", &presentation.signature);
+    transcript.append(b"cm", &presentation.commitment);
+    transcript.append(b"cm_tilde", &presentation.commitment_tilde);
+    transcript.append_message(b"proof", &presentation.proof);
+    transcript.digest(b"digest")
+}
+
+/// "I verified the presentation with this digest at `timestamp`"
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Receipt {
+    pub digest: [u8; 32],
+    /// Time of verification, in the caller's clock (e.g. Unix seconds)
+    pub timestamp: u64,
+    /// The verifier's signature over `signed_message`
+    pub verifier_signature: Option<Vec<u8>>,
+}
+
+impl Receipt {
+    /// An unsigned receipt for `presentation`
+    pub fn new<E: Pairing>(presentation: &Presentation<E>, timestamp: u64) -> Self {
+        Self {
+            digest: presentation_digest(presentation),
+            timestamp,
+            verifier_signature: None,
+        }
+    }
+
+    /// Attach the verifier's signature over `signed_message`
+    pub fn with_signature(mut self, signature: Vec<u8>) -> Self {
+        self.verifier_signature = Some(signature);
+        self
+    }
+
+    /// The bytes the verifier signs, binding the digest and the timestamp
+    pub fn signed_message(&self) -> Vec<u8> {
+        let mut message = b"t-siris receipt".to_vec();
+        message.extend_from_slice(&self.digest);
+        message.extend_from_slice(&self.timestamp.to_le_bytes());
+        message
+    }
+
+    /// Whether the receipt was issued for `presentation`
+    pub fn matches<E: Pairing>(&self, presentation: &Presentation<E>) -> bool {
+        self.digest == presentation_digest(presentation)
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, G1Affine};
+    use ark_ec::{AffineRepr, CurveGroup};
+
+    fn shown_presentation() -> Presentation<Bls12_381> {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(2)
+            .build(&mut rng)
+            .unwrap();
+        let user = system.user();
+        let (mut credential, request) = user.request_credential(None, &mut rng).unwrap();
+        let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+        user.complete_credential(&mut credential, &request, &shares, &mut rng)
+            .unwrap();
+        user.show(&credential, &mut rng).unwrap()
+    }
+
+    #[test]
+    fn test_presentation_digest() {
+        let presentation = shown_presentation();
+
+        // A byte-identical copy, decoded from the wire
+        let mut bytes = Vec::new();
+        presentation.serialize_compressed(&mut bytes).unwrap();
+        let decoded = Presentation::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(
+            presentation_digest(&presentation),
+            presentation_digest(&decoded)
+        );
+
+        let mut modified = presentation.clone();
+        modified.commitment = (modified.commitment + G1Affine::generator()).into_affine();
+        assert_ne!(
+            presentation_digest(&presentation),
+            presentation_digest(&modified)
+        );
+
+        let mut modified = presentation.clone();
+        *modified.proof.last_mut().unwrap() ^= 1;
+        assert_ne!(
+            presentation_digest(&presentation),
+            presentation_digest(&modified)
+        );
+    }
+
+    #[test]
+    fn test_receipt_binds_digest_and_timestamp() {
+        let presentation = shown_presentation();
+        let receipt = Receipt::new(&presentation, 1_700_000_000).with_signature(vec![7; 64]);
+        assert!(receipt.matches(&presentation));
+
+        let later = Receipt::new(&presentation, 1_700_000_001);
+        assert_ne!(receipt.signed_message(), later.signed_message());
+
+        let mut bytes = Vec::new();
+        receipt.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(
+            Receipt::deserialize_compressed(&bytes[..]).unwrap(),
+            receipt
+        );
+    }
+}
@@ -0,0 +1,430 @@
+//! Revocation through a pairing-based dynamic accumulator.
+//!
+//! Every credential designates one attribute as its revocation handle `h`. The issuer
+//! keeps the accumulator `V = P^{prod (alpha + h)}` over the handles that are *not*
+//! revoked and publishes `V` along with `alpha_tilde = g_tilde^alpha`. A holder's
+//! membership witness is `W = V^{1/(alpha + h)}`, so `e(W, alpha_tilde g_tilde^h) = e(V, g_tilde)`.
+//! Revoking `h` divides it out of `V`, after which no witness for `h` exists.
+//!
+//! Membership rather than non-membership keeps the proof to a randomized witness and
+//! one two-base Schnorr proof. On show the holder sends `W' = W^rho` and
+//! `V_bar = W'^{-h} V^rho = W'^alpha`, checked by `e(W', alpha_tilde) = e(V_bar, g_tilde)`,
+//! and proves knowledge of `(h, rho)` in `V_bar`. The response for `h` is the opening
+//! proof's response for the handle attribute, which ties the handle to the credential.
+//!
+//! Witnesses go stale whenever `V` changes. The holder applies every published change
+//! in order with `update_on_revocation` and `update_on_addition`.
+use crate::commitment::CommitmentProof;
+use crate::errors::{CommitmentError, RevocationError, SignatureError};
+use crate::keygen::VerificationKey;
+use crate::pairing::PairingCheck;
+use crate::presentation::Presentation;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::{DomainSeparator, Transcript};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::ops::Mul;
+use ark_std::rand::{CryptoRng, Rng};
+use ark_std::One;
+#[cfg(feature = "signing")]
+use {
+    crate::ct::{ct_inverse, rand_nonzero},
+    ark_ff::UniformRand,
+    ark_std::collections::BTreeSet,
+    ark_std::Zero,
+};
+
+/// The accumulator's public key `(g_tilde, g_tilde^alpha)`
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AccumulatorPublicKey<E: Pairing> {
+    pub g_tilde: E::G2Affine,
+    pub alpha_tilde: E::G2Affine,
+}
+
+/// The issuer's accumulator over the unrevoked handles
+#[cfg(feature = "signing")]
+pub struct Accumulator<E: Pairing> {
+    alpha: E::ScalarField,
+    public_key: AccumulatorPublicKey<E>,
+    value: E::G1Affine,
+    members: BTreeSet<E::ScalarField>,
+}
+
+#[cfg(feature = "signing")]
+impl<E: Pairing> Accumulator<E> {
+    /// An empty accumulator with a fresh secret `alpha` and a random starting value
+    pub fn new(rng: &mut (impl Rng + CryptoRng)) -> Self {
+        let alpha = rand_nonzero(rng);
+        let g_tilde = E::G2Affine::generator();
+        Self {
+            alpha,
+            public_key: AccumulatorPublicKey {
+                g_tilde,
+                alpha_tilde: g_tilde.mul(alpha).into_affine(),
+            },
+            value: E::G1::rand(rng).into_affine(),
+            members: BTreeSet::new(),
+        }
+    }
+
+    pub fn public_key(&self) -> &AccumulatorPublicKey<E> {
+        &self.public_key
+    }
+
+    /// The current value `V`, to be published after every change
+    pub fn value(&self) -> E::G1Affine {
+        self.value
+    }
+
+    pub fn is_member(&self, handle: &E::ScalarField) -> bool {
+        self.members.contains(handle)
+    }
+
+    /// `1/(alpha + handle)`, failing for the one handle that has no inverse
+    fn inverse(&self, handle: &E::ScalarField) -> Result<E::ScalarField, RevocationError> {
+        let (inverse, is_zero) = ct_inverse(&(self.alpha + handle));
+        if bool::from(is_zero) {
+            return Err(RevocationError::InvalidHandle);
+        }
+        Ok(inverse)
+    }
+
+    /// Accumulate `handle` and return the new value. Holders of earlier witnesses
+    /// update them with `MembershipWitness::update_on_addition`
+    pub fn add(&mut self, handle: E::ScalarField) -> Result<E::G1Affine, RevocationError> {
+        if self.is_member(&handle) {
+            return Err(RevocationError::AlreadyMember);
+        }
+        self.inverse(&handle)?;
+        self.value = self.value.mul(self.alpha + handle).into_affine();
+        self.members.insert(handle);
+        Ok(self.value)
+    }
+
+    /// The witness for `handle` against the current value
+    pub fn witness(
+        &self,
+        handle: &E::ScalarField,
+    ) -> Result<MembershipWitness<E>, RevocationError> {
+        if !self.is_member(handle) {
+            return Err(RevocationError::NotMember);
+        }
+        Ok(MembershipWitness {
+            witness: self.value.mul(self.inverse(handle)?).into_affine(),
+            value: self.value,
+        })
+    }
+
+    /// Remove `handle` and return the new value. Holders of the other handles update
+    /// their witnesses with `MembershipWitness::update_on_revocation`
+    pub fn revoke(&mut self, handle: &E::ScalarField) -> Result<E::G1Affine, RevocationError> {
+        if !self.is_member(handle) {
+            return Err(RevocationError::NotMember);
+        }
+        self.value = self.value.mul(self.inverse(handle)?).into_affine();
+        self.members.remove(handle);
+        Ok(self.value)
+    }
+}
+
+/// A holder's witness `W = V^{1/(alpha + h)}` and the accumulator value `V` it is for
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MembershipWitness<E: Pairing> {
+    pub witness: E::G1Affine,
+    pub value: E::G1Affine,
+}
+
+impl<E: Pairing> MembershipWitness<E> {
+    /// Whether `handle` is accumulated in `self.value`
+    pub fn verify(&self, public_key: &AccumulatorPublicKey<E>, handle: &E::ScalarField) -> bool {
+        if self.witness.is_zero() {
+            return false;
+        }
+        let shifted = (public_key.alpha_tilde + public_key.g_tilde.mul(*handle)).into_affine();
+        E::pairing(self.witness, shifted) == E::pairing(self.value, public_key.g_tilde)
+    }
+
+    /// Move the witness for `handle` to `new_value` after `revoked` was removed:
+    /// `W' = (W / V')^{1/(revoked - handle)}`
+    pub fn update_on_revocation(
+        &mut self,
+        handle: &E::ScalarField,
+        revoked: &E::ScalarField,
+        new_value: E::G1Affine,
+    ) -> Result<(), RevocationError> {
+        let inverse = (*revoked - handle)
+            .inverse()
+            .ok_or(RevocationError::Revoked)?;
+        self.witness = (self.witness.into_group() - new_value)
+            .mul(inverse)
+            .into_affine();
+        self.value = new_value;
+        Ok(())
+    }
+
+    /// Move the witness for `handle` to `new_value` after `added` was accumulated:
+    /// `W' = V W^{added - handle}`
+    pub fn update_on_addition(
+        &mut self,
+        handle: &E::ScalarField,
+        added: &E::ScalarField,
+        new_value: E::G1Affine,
+    ) {
+        self.witness = (self.witness.mul(*added - handle) + self.value).into_affine();
+        self.value = new_value;
+    }
+}
+
+/// Proof that a hidden attribute `m` carries a weak BB signature `S = P^{1/(x + m)}` on
+/// a base `P` under the key `(g_tilde, x_tilde = g_tilde^x)`, for some `S` that is never
+/// revealed. A membership witness is such a signature on the accumulator value, a set
+/// membership signature is one on the set's base
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AttributeSignatureProof<E: Pairing> {
+    /// The randomized signature `S' = S^rho`
+    pub witness: E::G1Affine,
+    /// `S'^{-m} P^rho`
+    pub witness_bar: E::G1Affine,
+    /// `S'^{-t_m} P^{t_rho}`, with `t_m` the opening proof's blinding for the attribute
+    pub announcement: E::G1Affine,
+    /// `t_rho + c rho`
+    pub response: E::ScalarField,
+}
+
+impl<E: Pairing> AttributeSignatureProof<E> {
+    /// Randomize `signature` and commit with the opening proof's `blinding` for the
+    /// attribute `m`. Returns `rho` and `t_rho` as well, the caller sets `response` once
+    /// the challenge is known
+    #[cfg(feature = "signing")]
+    pub(crate) fn commit(
+        signature: &E::G1Affine,
+        base: &E::G1Affine,
+        m: &E::ScalarField,
+        blinding: &E::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> (Self, E::ScalarField, E::ScalarField) {
+        let rho = rand_nonzero::<E::ScalarField, _>(rng);
+        let t_rho = E::ScalarField::rand(rng);
+        let witness = signature.mul(rho).into_affine();
+        let witness_bar = (witness.mul(-*m) + base.mul(rho)).into_affine();
+        let announcement = (witness.mul(-*blinding) + base.mul(t_rho)).into_affine();
+        let proof = Self {
+            witness,
+            witness_bar,
+            announcement,
+            response: E::ScalarField::zero(),
+        };
+        (proof, rho, t_rho)
+    }
+
+    /// Check the proof against `attribute_response`, the opening proof's response for
+    /// the attribute, under the shared `challenge`
+    pub(crate) fn verify(
+        &self,
+        g_tilde: &E::G2Affine,
+        x_tilde: &E::G2Affine,
+        base: &E::G1Affine,
+        attribute_response: &E::ScalarField,
+        challenge: &E::ScalarField,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> bool {
+        // S'^{-z_m} P^{z_rho} = T S_bar^c, then e(S', x_tilde) = e(S_bar, g_tilde)
+        let lhs = self.witness.mul(-*attribute_response) + base.mul(self.response);
+        let rhs = self.witness_bar.mul(*challenge) + self.announcement;
+        let neg_witness_bar = (-self.witness_bar.into_group()).into_affine();
+        let check = PairingCheck::<E>::rand(
+            rng,
+            &[(&self.witness, x_tilde), (&neg_witness_bar, g_tilde)],
+            &E::TargetField::one(),
+        );
+        !self.witness.is_zero() && lhs == rhs && check.verify()
+    }
+}
+
+/// A presentation with a proof that the attribute at `handle_index` is accumulated,
+/// i.e. not revoked. See the module documentation for the statement
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RevocationPresentation<E: Pairing> {
+    pub presentation: Presentation<E>,
+    pub handle_index: usize,
+    /// The membership witness proof, on base `V`
+    pub proof: AttributeSignatureProof<E>,
+}
+
+/// Joint challenge over the randomized credential, the accumulator value, the
+/// randomized witness and the announcements of both proofs
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn revocation_show_challenge<E: Pairing>(
+    domain: &DomainSeparator,
+    presentation: &Presentation<E>,
+    value: &E::G1Affine,
+    handle_index: usize,
+    witness: &E::G1Affine,
+    witness_bar: &E::G1Affine,
+    schnorr_commitment: &E::G1Affine,
+    announcement: &E::G1Affine,
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris show with revocation");
+    transcript.append(b"signature", &presentation.signature);
+    transcript.append(b"cm", &presentation.commitment);
+    transcript.append(b"cm_tilde", &presentation.commitment_tilde);
+    transcript.append(b"accumulator", value);
+    transcript.append(b"handle_index", &(handle_index as u64));
+    transcript.append(b"witness", witness);
+    transcript.append(b"witness_bar", witness_bar);
+    transcript.append(b"schnorr_commitment", schnorr_commitment);
+    transcript.append(b"announcement", announcement);
+    transcript.challenge_scalar(b"challenge")
+}
+
+impl<E: Pairing> RevocationPresentation<E> {
+    /// Verify the credential and that its handle is accumulated in `value`, the
+    /// accumulator value the verifier currently trusts
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        public_key: &AccumulatorPublicKey<E>,
+        value: &E::G1Affine,
+        domain: &DomainSeparator,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&self.presentation.proof[..])?;
+        let challenge = revocation_show_challenge(
+            domain,
+            &self.presentation,
+            value,
+            self.handle_index,
+            &self.proof.witness,
+            &self.proof.witness_bar,
+            &proof.schnorr_commitment,
+            &self.proof.announcement,
+        );
+        if proof.challenge != challenge || self.handle_index >= ck.num_attributes() {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        let handle_response = proof
+            .responses
+            .get(ck.attribute_position(self.handle_index))
+            .ok_or(CommitmentError::InvalidProof)?;
+        if !self.proof.verify(
+            &public_key.g_tilde,
+            &public_key.alpha_tilde,
+            value,
+            handle_response,
+            &challenge,
+            rng,
+        ) {
+            return Err(SignatureError::ProofError(
+                "non-revocation proof verification failed".to_string(),
+            ));
+        }
+
+        self.presentation.verify(ck, vk, rng)
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::protocol::{UserProtocol, VerifierProtocol};
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr};
+
+    #[test]
+    fn test_show_with_revocation() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let mut accumulator = Accumulator::<Bls12_381>::new(&mut rng);
+        let handles: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        for handle in &handles {
+            let value = accumulator.add(*handle).unwrap();
+            assert_eq!(value, accumulator.value());
+        }
+        assert!(matches!(
+            accumulator.add(handles[0]),
+            Err(RevocationError::AlreadyMember)
+        ));
+        let mut witnesses: Vec<_> = handles
+            .iter()
+            .map(|handle| accumulator.witness(handle).unwrap())
+            .collect();
+
+        // Two credentials carrying their handle as attribute 1
+        let credentials: Vec<_> = handles[..2]
+            .iter()
+            .map(|handle| {
+                let attributes = [Fr::rand(&mut rng), *handle, Fr::rand(&mut rng)];
+                let user = system.user();
+                let (mut credential, request) = user
+                    .request_credential(Some(&attributes), &mut rng)
+                    .unwrap();
+                let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+                user.complete_credential(&mut credential, &request, &shares, &mut rng)
+                    .unwrap();
+                credential
+            })
+            .collect();
+        let domain = credentials[0].domain().clone();
+        let public_key = accumulator.public_key().clone();
+        let verify = |presentation: &RevocationPresentation<Bls12_381>, value: &_, rng: &mut _| {
+            VerifierProtocol::verify_not_revoked(
+                &system.ck,
+                &system.vk,
+                &public_key,
+                &domain,
+                presentation,
+                value,
+                rng,
+            )
+        };
+
+        let shown = UserProtocol::show_with_revocation(&credentials[0], 1, &witnesses[0], &mut rng)
+            .unwrap();
+        assert!(verify(&shown, &accumulator.value(), &mut rng).unwrap());
+        // Another attribute isn't the accumulated handle
+        let wrong_index =
+            UserProtocol::show_with_revocation(&credentials[0], 0, &witnesses[0], &mut rng)
+                .unwrap();
+        assert!(verify(&wrong_index, &accumulator.value(), &mut rng).is_err());
+
+        // Revoke the first handle, the second holder updates and still shows
+        let value = accumulator.revoke(&handles[0]).unwrap();
+        assert!(verify(&shown, &value, &mut rng).is_err());
+        let stale = UserProtocol::show_with_revocation(&credentials[0], 1, &witnesses[0], &mut rng)
+            .unwrap();
+        assert!(verify(&stale, &value, &mut rng).is_err());
+        assert!(matches!(
+            witnesses[0].update_on_revocation(&handles[0], &handles[0], value),
+            Err(RevocationError::Revoked)
+        ));
+
+        witnesses[1]
+            .update_on_revocation(&handles[1], &handles[0], value)
+            .unwrap();
+        assert!(witnesses[1].verify(&public_key, &handles[1]));
+        let shown = UserProtocol::show_with_revocation(&credentials[1], 1, &witnesses[1], &mut rng)
+            .unwrap();
+        assert!(verify(&shown, &value, &mut rng).unwrap());
+
+        // Witnesses follow additions too, applied in publication order
+        let added = Fr::rand(&mut rng);
+        let added_value = accumulator.add(added).unwrap();
+        witnesses[2]
+            .update_on_revocation(&handles[2], &handles[0], value)
+            .unwrap();
+        witnesses[2].update_on_addition(&handles[2], &added, added_value);
+        assert!(witnesses[2].verify(&public_key, &handles[2]));
+        assert_eq!(witnesses[2], accumulator.witness(&handles[2]).unwrap());
+    }
+}
@@ -2,84 +2,142 @@
 // TODO let proofs = SchnorrProtocol::new(ck, messages, commitment) this is what it should be!
 use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{rand::Rng, vec::Vec, UniformRand};
+use ark_std::{
+    rand::{CryptoRng, Rng},
+    vec::Vec,
+    UniformRand, Zero,
+};
 
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
-pub struct SchnorrCommitment<G: AffineRepr> {
-    pub random_blindings: Vec<G::ScalarField>,
-    pub commited_blindings: G,
+/// The prover's announcement `T = g_1^{\rho_1} ... g_L^{\rho_L}`, the public half of `commit`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SchnorrAnnouncement<G: AffineRepr>(pub G);
+
+/// The secret nonces `\rho_i` behind an announcement. Anyone holding them and the
+/// responses can solve for the witnesses, so the state is deliberately not serializable,
+/// not `Clone` and consumed by `prove`, which also rules out reusing the nonces.
+///
+/// ```compile_fail
+/// fn assert_serializable<T: ark_serialize::CanonicalSerialize>() {}
+/// assert_serializable::<t_siris::schnorr::SchnorrProverState<ark_bls12_381::G1Affine>>();
+/// ```
+pub struct SchnorrProverState<G: AffineRepr> {
+    pub(crate) random_blindings: Vec<G::ScalarField>,
+}
+
+#[cfg(feature = "zeroize")]
+impl<G: AffineRepr> zeroize::Zeroize for SchnorrProverState<G> {
+    fn zeroize(&mut self) {
+        self.random_blindings.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<G: AffineRepr> Drop for SchnorrProverState<G> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
 }
 
+#[cfg(feature = "zeroize")]
+impl<G: AffineRepr> zeroize::ZeroizeOnDrop for SchnorrProverState<G> {}
+
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SchnorrResponses<G: AffineRepr>(pub Vec<G::ScalarField>);
 
 pub struct SchnorrProtocol;
 
 impl SchnorrProtocol {
-    /// returns a commitment to random blindings, the commitment T = g_1^{\rho_1},...,g_L^{\rho_L} from random blindings and bases
-    pub fn commit<G: AffineRepr, R: Rng>(
+    /// returns the prover state holding random blindings and the announcement T = g_1^{\rho_1},...,g_L^{\rho_L} from those blindings and bases
+    pub fn commit<G: AffineRepr, R: Rng + CryptoRng>(
         public_generators: &[G],
         rng: &mut R,
-    ) -> SchnorrCommitment<G> {
+    ) -> (SchnorrProverState<G>, SchnorrAnnouncement<G>) {
         // random_blindings hide the exponent like a pedersen commitment e.g. g^m h^r
         let random_blindings: Vec<G::ScalarField> = (0..public_generators.len())
             .map(|_| G::ScalarField::rand(rng))
             .collect();
-        // Compute t = public_generators[0] * random_blindings[0] + ... + public_generators[i] * random_blindings[i]
-        // multi-scalar multiplication - efficient
-        let commited_blindings: G =
-            G::Group::msm_unchecked(public_generators, &random_blindings).into_affine();
-        SchnorrCommitment {
-            random_blindings,
-            commited_blindings,
-        }
+        Self::commit_with_prepared_blindings(public_generators, &random_blindings)
     }
 
-    /// returns a commitment to random blindings, the commitment T = g_1^{\rho_1},...,g_L^{\rho_L} from random blindings and bases
+    /// returns the prover state and the announcement T = g_1^{\rho_1},...,g_L^{\rho_L} for the given blindings
     pub fn commit_with_prepared_blindings<G: AffineRepr>(
         public_generators: &[G],
         random_blindings: &[G::ScalarField],
-    ) -> SchnorrCommitment<G> {
-        let commited_blindings: G =
-            G::Group::msm_unchecked(public_generators, &random_blindings).into_affine();
-        SchnorrCommitment {
-            random_blindings: random_blindings.to_vec(),
-            commited_blindings,
-        }
+    ) -> (SchnorrProverState<G>, SchnorrAnnouncement<G>) {
+        // Compute t = public_generators[0] * random_blindings[0] + ... + public_generators[i] * random_blindings[i]
+        // multi-scalar multiplication - efficient
+        let announcement =
+            G::Group::msm_unchecked(public_generators, random_blindings).into_affine();
+        (
+            SchnorrProverState {
+                random_blindings: random_blindings.to_vec(),
+            },
+            SchnorrAnnouncement(announcement),
+        )
     }
 
-    // commit takes in public_generators and exponents
-    pub fn commit_equality<G: AffineRepr, R: Rng>(
+    /// Like `commit`, with `equal_blindness` at position `index`. Proofs answering the
+    /// same challenge then share the response at that position exactly when they share
+    /// the witness
+    pub fn commit_equality<G: AffineRepr, R: Rng + CryptoRng>(
         public_generators: &[G],
         rng: &mut R,
         equal_blindness: &G::ScalarField,
-        index: usize, //hard code index to 0
-    ) -> SchnorrCommitment<G> {
-        // random_blindings hide the exponent like a pedersen commitment e.g. g^m h^r
+        index: usize,
+    ) -> (SchnorrProverState<G>, SchnorrAnnouncement<G>) {
+        Self::commit_with_shared(public_generators, &[(index, *equal_blindness)], rng)
+    }
 
-        let mut random_blindings: Vec<G::ScalarField> = (1..public_generators.len())
+    /// Like `commit`, with the given `(position, blinding)` pairs in place of fresh
+    /// blindings at those positions
+    ///
+    /// # Panics
+    /// If a position is out of range for `public_generators`
+    pub fn commit_with_shared<G: AffineRepr, R: Rng + CryptoRng>(
+        public_generators: &[G],
+        shared: &[(usize, G::ScalarField)],
+        rng: &mut R,
+    ) -> (SchnorrProverState<G>, SchnorrAnnouncement<G>) {
+        // random_blindings hide the exponent like a pedersen commitment e.g. g^m h^r
+        let mut random_blindings: Vec<G::ScalarField> = (0..public_generators.len())
             .map(|_| G::ScalarField::rand(rng))
             .collect();
-
-        // Insert equal_blindness at the front of the vector
-        random_blindings.insert(0, *equal_blindness);
-        // Compute t = public_generators[0] * random_blindings[0] + ... + public_generators[i] * random_blindings[i]
-        // multi-scalar multiplication - efficient
-        let commited_blindings: G =
-            G::Group::msm_unchecked(public_generators, &random_blindings).into_affine();
-        SchnorrCommitment {
-            random_blindings,
-            commited_blindings,
+        for (position, blinding) in shared {
+            assert!(
+                *position < public_generators.len(),
+                "shared position {position} out of range"
+            );
+            random_blindings[*position] = *blinding;
         }
+        Self::commit_with_prepared_blindings(public_generators, &random_blindings)
     }
 
+    /// Companion to `commit_with_shared`: for two proofs answering the same challenge,
+    /// check that each `(i, j)` in `positions` has `responses_a[i] == responses_b[j]`,
+    /// i.e. that witness `i` of the first statement equals witness `j` of the second.
+    /// Each proof still has to be verified on its own
+    pub fn verify_shared_responses<F: PartialEq>(
+        responses_a: &[F],
+        responses_b: &[F],
+        positions: &[(usize, usize)],
+    ) -> bool {
+        !positions.is_empty()
+            && positions
+                .iter()
+                .all(|(i, j)| match (responses_a.get(*i), responses_b.get(*j)) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => false,
+                })
+    }
+
+    /// Consumes the prover state, so each set of nonces answers a single challenge
     pub fn prove<G: AffineRepr>(
-        commitment: &SchnorrCommitment<G>, //schnorr commitment
+        state: SchnorrProverState<G>,
         witnesses: &[G::ScalarField],
         challenge: &G::ScalarField,
     ) -> SchnorrResponses<G> {
         // z_i = t_i + e * m_i
-        let schnorr_responsess: Vec<G::ScalarField> = commitment
+        let schnorr_responsess: Vec<G::ScalarField> = state
             .random_blindings
             .iter()
             .zip(witnesses.iter())
@@ -88,20 +146,38 @@ impl SchnorrProtocol {
         SchnorrResponses(schnorr_responsess)
     }
 
-    // this shouldn't be used, taking in SchnorrResponses is silly because it has randomness
+    /// Structural checks a proof must pass before the verification equation means anything:
+    /// one response per generator (`msm_unchecked` silently truncates to the shorter
+    /// slice), a nonzero challenge (with e = 0 the blindings alone are valid responses)
+    /// and an announcement that is not the identity unless the statement is
+    pub fn is_well_formed<G: AffineRepr>(
+        public_generators: &[G],
+        statement: &G,
+        schnorr_commitment: &G,
+        schnorr_responses: &[G::ScalarField],
+        challenge: &G::ScalarField,
+    ) -> bool {
+        !public_generators.is_empty()
+            && schnorr_responses.len() == public_generators.len()
+            && !challenge.is_zero()
+            && (!schnorr_commitment.is_zero() || statement.is_zero())
+    }
+
+    /// `verify_schnorr` over the typed announcement and responses
     pub fn verify<G: AffineRepr>(
         public_generators: &[G],
         statement: &G,
-        blinding_commitment: &SchnorrCommitment<G>,
+        announcement: &SchnorrAnnouncement<G>,
         schnorr_responses: &SchnorrResponses<G>,
         challenge: &G::ScalarField,
     ) -> bool {
-        //e.g.  LHS = g1^(t1 + e*m1) * g2^(t2 + e*m2) * h^(t3 + e*r)
-        let lhs = G::Group::msm_unchecked(public_generators, &schnorr_responses.0).into_affine();
-        // com^e + com
-        let rhs =
-            (blinding_commitment.commited_blindings + statement.mul(*challenge)).into_affine();
-        lhs == rhs
+        Self::verify_schnorr(
+            public_generators,
+            statement,
+            &announcement.0,
+            &schnorr_responses.0,
+            challenge,
+        )
     }
 
     /// takes input Generators, Statement, Schnorr Commitment \in G, Schnorr responses, challenge
@@ -112,8 +188,17 @@ impl SchnorrProtocol {
         schnorr_responses: &[G::ScalarField],
         challenge: &G::ScalarField,
     ) -> bool {
+        if !Self::is_well_formed(
+            public_generators,
+            statement,
+            schnorr_commitment,
+            schnorr_responses,
+            challenge,
+        ) {
+            return false;
+        }
         //e.g.  LHS = g1^(t1 + e*m1) * g2^(t2 + e*m2) * h^(t3 + e*r)
-        let lhs = G::Group::msm_unchecked(public_generators, &schnorr_responses).into_affine();
+        let lhs = G::Group::msm_unchecked(public_generators, schnorr_responses).into_affine();
         // com^e + com
         let rhs = (schnorr_commitment.into_group() + statement.mul(*challenge)).into_affine();
         lhs == rhs
@@ -123,30 +208,29 @@ impl SchnorrProtocol {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_rng;
     use ark_bls12_381::{Fr, G1Affine, G1Projective, G2Affine};
-    use ark_std::test_rng;
     // use blake2::Blake2b512;
 
     #[test]
     fn test_schnorr_single() {
         let mut rng = test_rng();
 
-        fn check<G: AffineRepr>(rng: &mut impl Rng) {
+        fn check<G: AffineRepr>(rng: &mut (impl Rng + CryptoRng)) {
             let base = G::Group::rand(rng).into_affine();
             let witness = G::ScalarField::rand(rng);
             let statement = base.mul(witness).into_affine();
 
-            let schnorr_commitment = SchnorrProtocol::commit(&[base], rng);
+            let (state, announcement) = SchnorrProtocol::commit(&[base], rng);
 
             let challenge = G::ScalarField::rand(rng);
 
-            let schnorr_responses =
-                SchnorrProtocol::prove(&schnorr_commitment, &[witness], &challenge);
+            let schnorr_responses = SchnorrProtocol::prove(state, &[witness], &challenge);
 
             assert!(SchnorrProtocol::verify(
                 &[base],
                 &statement,
-                &schnorr_commitment,
+                &announcement,
                 &schnorr_responses,
                 &challenge
             ));
@@ -160,23 +244,23 @@ mod tests {
     fn test_schnorr_double() {
         let mut rng = test_rng();
 
-        fn check<G: AffineRepr>(rng: &mut impl Rng) {
+        fn check<G: AffineRepr>(rng: &mut (impl Rng + CryptoRng)) {
             let base1 = G::Group::rand(rng).into_affine();
             let witness1 = G::ScalarField::rand(rng);
             let base2 = G::Group::rand(rng).into_affine();
             let witness2 = G::ScalarField::rand(rng);
             let public_statement = (base1.mul(witness1) + base2.mul(witness2)).into_affine();
 
-            let commitment = SchnorrProtocol::commit(&[base1, base2], rng);
+            let (state, announcement) = SchnorrProtocol::commit(&[base1, base2], rng);
             let challenge = G::ScalarField::rand(rng);
 
             let schnorr_responses =
-                SchnorrProtocol::prove(&commitment, &[witness1, witness2], &challenge);
+                SchnorrProtocol::prove(state, &[witness1, witness2], &challenge);
 
             assert!(SchnorrProtocol::verify(
                 &[base1, base2],
                 &public_statement,
-                &commitment,
+                &announcement,
                 &schnorr_responses,
                 &challenge
             ));
@@ -202,19 +286,146 @@ mod tests {
             G1Projective::msm_unchecked(&public_generators, &witnesses).into_affine();
 
         // Prover's side
-        let commitment = SchnorrProtocol::commit(&public_generators, &mut rng);
+        let (state, announcement) = SchnorrProtocol::commit(&public_generators, &mut rng);
         let challenge = Fr::rand(&mut rng); // In practice, this should be derived from a hash
-        let schnorr_responses = SchnorrProtocol::prove(&commitment, &witnesses, &challenge);
+        let schnorr_responses = SchnorrProtocol::prove(state, &witnesses, &challenge);
 
         // Verifier's side
         let is_valid = SchnorrProtocol::verify(
             &public_generators,
             &public_statement,
-            &commitment,
+            &announcement,
             &schnorr_responses,
             &challenge,
         );
 
         assert!(is_valid, "Schnorr proof verification failed");
     }
+
+    #[test]
+    fn test_rejects_malformed_proofs() {
+        let mut rng = test_rng();
+        let bases: Vec<G1Affine> = (0..2).map(|_| G1Affine::rand(&mut rng)).collect();
+
+        // Only a witness for the first base; a truncated response vector used to make
+        // the MSM skip the second base entirely
+        let w = Fr::rand(&mut rng);
+        let statement = (bases[0] * w).into_affine();
+        let (state, announcement) = SchnorrProtocol::commit(&bases[..1], &mut rng);
+        let challenge = Fr::rand(&mut rng);
+        let responses = SchnorrProtocol::prove(state, &[w], &challenge);
+        let t = announcement.0;
+        assert!(SchnorrProtocol::verify_schnorr(
+            &bases[..1],
+            &statement,
+            &t,
+            &responses.0,
+            &challenge
+        ));
+        assert!(!SchnorrProtocol::verify_schnorr(
+            &bases,
+            &statement,
+            &t,
+            &responses.0,
+            &challenge
+        ));
+        let mut long = responses.0.clone();
+        long.push(Fr::rand(&mut rng));
+        assert!(!SchnorrProtocol::verify_schnorr(
+            &bases[..1],
+            &statement,
+            &t,
+            &long,
+            &challenge
+        ));
+        assert!(!SchnorrProtocol::verify_schnorr(
+            &[],
+            &G1Affine::zero(),
+            &G1Affine::zero(),
+            &[],
+            &challenge
+        ));
+
+        // With a zero challenge the blindings answer for any statement
+        let any_statement = G1Affine::rand(&mut rng);
+        let (state, announcement) = SchnorrProtocol::commit(&bases, &mut rng);
+        let zero = Fr::zero();
+        assert!(!SchnorrProtocol::verify_schnorr(
+            &bases,
+            &any_statement,
+            &announcement.0,
+            &state.random_blindings,
+            &zero
+        ));
+
+        // Identity announcement for a non-identity statement: z = e * w
+        let responses: Vec<Fr> = [w, Fr::zero()].iter().map(|m| *m * challenge).collect();
+        assert!(!SchnorrProtocol::verify_schnorr(
+            &bases,
+            &statement,
+            &G1Affine::zero(),
+            &responses,
+            &challenge
+        ));
+    }
+
+    #[test]
+    fn test_shared_positions() {
+        let mut rng = test_rng();
+        let challenge = Fr::rand(&mut rng);
+        let shared_witness = Fr::rand(&mut rng);
+        let shared_blinding = Fr::rand(&mut rng);
+
+        // Proves knowledge of `witnesses` for `bases`, with the shared witness at `position`
+        let mut prove_with_shared = |arity: usize, position: usize| {
+            let bases: Vec<G1Affine> = (0..arity).map(|_| G1Affine::rand(&mut rng)).collect();
+            let mut witnesses: Vec<Fr> = (0..arity).map(|_| Fr::rand(&mut rng)).collect();
+            witnesses[position] = shared_witness;
+            let statement = G1Projective::msm_unchecked(&bases, &witnesses).into_affine();
+            let (state, announcement) =
+                SchnorrProtocol::commit_equality(&bases, &mut rng, &shared_blinding, position);
+            let responses = SchnorrProtocol::prove(state, &witnesses, &challenge);
+            assert!(SchnorrProtocol::verify(
+                &bases,
+                &statement,
+                &announcement,
+                &responses,
+                &challenge
+            ));
+            responses.0
+        };
+
+        // First, middle and last position, across proofs of arity 3 and 5
+        let first = prove_with_shared(3, 0);
+        let middle = prove_with_shared(5, 2);
+        let last = prove_with_shared(5, 4);
+        for (a, b, positions) in [
+            (&first, &middle, [(0, 2)]),
+            (&middle, &last, [(2, 4)]),
+            (&last, &first, [(4, 0)]),
+        ] {
+            assert!(SchnorrProtocol::verify_shared_responses(a, b, &positions));
+        }
+        assert!(!SchnorrProtocol::verify_shared_responses(
+            &first,
+            &middle,
+            &[(1, 2)]
+        ));
+        assert!(!SchnorrProtocol::verify_shared_responses(
+            &first,
+            &middle,
+            &[(0, 5)]
+        ));
+
+        // Several shared positions in one commitment
+        let bases: Vec<G1Affine> = (0..4).map(|_| G1Affine::rand(&mut rng)).collect();
+        let blindings = [Fr::rand(&mut rng), Fr::rand(&mut rng)];
+        let (state, _) = SchnorrProtocol::commit_with_shared(
+            &bases,
+            &[(1, blindings[0]), (3, blindings[1])],
+            &mut rng,
+        );
+        assert_eq!(state.random_blindings[1], blindings[0]);
+        assert_eq!(state.random_blindings[3], blindings[1]);
+    }
 }
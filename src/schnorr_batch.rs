@@ -5,14 +5,14 @@ use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
 use ark_ff::UniformRand;
 use ark_serialize::CanonicalDeserialize;
 use ark_std::ops::Mul;
-use ark_std::rand::Rng;
+use ark_std::rand::{CryptoRng, Rng};
 use ark_std::Zero;
 
 /// Efficient batch verification of multiple Schnorr proofs
 /// Returns true only if ALL proofs are valid
 pub fn batch_verify<E: Pairing>(
     serialized_proofs: &[Vec<u8>],
-    rng: &mut impl Rng,
+    rng: &mut (impl Rng + CryptoRng),
 ) -> Result<bool, CommitmentError> {
     if serialized_proofs.is_empty() {
         return Ok(true); // No proofs to verify
@@ -23,12 +23,53 @@ pub fn batch_verify<E: Pairing>(
 
     for proof_bytes in serialized_proofs {
         match CommitmentProof::<E>::deserialize_compressed(&proof_bytes[..]) {
+            // A short response vector would otherwise index out of bounds below
+            Ok(proof) if !proof.is_well_formed() => return Err(CommitmentError::InvalidProof),
             Ok(proof) => deserialized_proofs.push(proof),
             Err(e) => return Err(CommitmentError::SerializationError(e)),
         }
     }
 
     // Step 2: Perform batch verification using random linear combination
+    Ok(verify_deserialized(&deserialized_proofs, rng))
+}
+
+/// Outcome of `batch_verify_reporting`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchReport {
+    /// Positions of the proofs that failed to deserialize or are malformed, left out of
+    /// the batch
+    pub malformed: Vec<usize>,
+    /// Whether every other proof verifies, true if there are none
+    pub verified: bool,
+}
+
+/// `batch_verify` that sets malformed proofs aside instead of failing on them. The
+/// report names them apart from the verdict on the rest, so a caller can drop them
+/// and tell a bad encoding from a proof that doesn't verify
+pub fn batch_verify_reporting<E: Pairing>(
+    serialized_proofs: &[Vec<u8>],
+    rng: &mut (impl Rng + CryptoRng),
+) -> BatchReport {
+    let mut malformed = Vec::new();
+    let mut deserialized_proofs = Vec::with_capacity(serialized_proofs.len());
+    for (i, proof_bytes) in serialized_proofs.iter().enumerate() {
+        match CommitmentProof::<E>::deserialize_compressed(&proof_bytes[..]) {
+            Ok(proof) if proof.is_well_formed() => deserialized_proofs.push(proof),
+            _ => malformed.push(i),
+        }
+    }
+    BatchReport {
+        malformed,
+        verified: deserialized_proofs.is_empty() || verify_deserialized(&deserialized_proofs, rng),
+    }
+}
+
+/// The random linear combination check over well-formed proofs
+fn verify_deserialized<E: Pairing>(
+    deserialized_proofs: &[CommitmentProof<E>],
+    rng: &mut (impl Rng + CryptoRng),
+) -> bool {
     // Generate a random scalar for each proof
     let random_scalars: Vec<E::ScalarField> = (0..deserialized_proofs.len())
         .map(|_| E::ScalarField::rand(rng))
@@ -69,8 +110,9 @@ pub fn batch_verify<E: Pairing>(
     let rhs = E::G1::msm_unchecked(&rhs_bases, &rhs_scalars).into_affine();
 
     // Check if LHS == RHS
-    Ok(lhs == rhs)
+    lhs == rhs
 }
+
 /// Efficient batch verification of multiple Schnorr proofs
 /// Returns true only if ALL proofs are valid
 ///
@@ -78,7 +120,7 @@ pub fn batch_verify<E: Pairing>(
 ///
 pub fn batch_verify_old<E: Pairing>(
     serialized_proofs: &[Vec<u8>],
-    rng: &mut impl Rng,
+    rng: &mut (impl Rng + CryptoRng),
 ) -> Result<bool, CommitmentError> {
     if serialized_proofs.is_empty() {
         return Ok(true); // No proofs to verify
@@ -89,6 +131,8 @@ pub fn batch_verify_old<E: Pairing>(
 
     for proof_bytes in serialized_proofs {
         match CommitmentProof::<E>::deserialize_compressed(&proof_bytes[..]) {
+            // A short response vector would otherwise index out of bounds below
+            Ok(proof) if !proof.is_well_formed() => return Err(CommitmentError::InvalidProof),
             Ok(proof) => deserialized_proofs.push(proof),
             Err(e) => return Err(CommitmentError::SerializationError(e)),
         }
@@ -126,7 +170,7 @@ pub fn batch_verify_old<E: Pairing>(
             + proof.commitment.into_group().mul(proof.challenge);
 
         // Add to combined RHS with scaling
-        rhs = rhs + rhs_i.mul(random_scalars[i]);
+        rhs += rhs_i.mul(random_scalars[i]);
     }
     let rhs = rhs.into_affine();
 
@@ -174,3 +218,56 @@ pub fn batch_verify_old<E: Pairing>(
 //         Ok(verification_results)
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment::Commitment;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+
+    #[test]
+    fn test_reporting_separates_malformed_proofs() {
+        let mut rng = test_rng();
+        let h = G1Affine::rand(&mut rng);
+        let g = G1Affine::rand(&mut rng);
+        let mut proofs: Vec<Vec<u8>> = (0..4)
+            .map(|_| {
+                Commitment::<Bls12_381>::new(&h, &g, &Fr::rand(&mut rng), None, &mut rng)
+                    .prove(&mut rng)
+                    .unwrap()
+            })
+            .collect();
+        proofs[2].truncate(10);
+
+        // The plain batch fails as a whole without saying which proof
+        assert!(matches!(
+            batch_verify::<Bls12_381>(&proofs, &mut rng),
+            Err(CommitmentError::SerializationError(_))
+        ));
+        let report = batch_verify_reporting::<Bls12_381>(&proofs, &mut rng);
+        assert_eq!(
+            report,
+            BatchReport {
+                malformed: vec![2],
+                verified: true
+            }
+        );
+
+        // Dropping the malformed entry and retrying passes
+        proofs.remove(2);
+        assert!(batch_verify::<Bls12_381>(&proofs, &mut rng).unwrap());
+
+        // A well-formed proof that doesn't verify fails the verdict, not the encoding
+        let mut other = proofs.clone();
+        other[1].truncate(0);
+        let mut wrong: CommitmentProof<Bls12_381> =
+            CanonicalDeserialize::deserialize_compressed(&proofs[2][..]).unwrap();
+        wrong.challenge += Fr::from(1u64);
+        other[2].clear();
+        ark_serialize::CanonicalSerialize::serialize_compressed(&wrong, &mut other[2]).unwrap();
+        let report = batch_verify_reporting::<Bls12_381>(&other, &mut rng);
+        assert_eq!(report.malformed, vec![1]);
+        assert!(!report.verified);
+    }
+}
@@ -0,0 +1,78 @@
+//! Secret material scrubbed from memory when dropped.
+//!
+//! With the `zeroize` feature the signer's `SecretKeyShare`, the holder's nullifier key
+//! `DYPFPrivSecretKey`, the Schnorr nonces in a `SchnorrProverState` and the attributes,
+//! blindings and commitment opening held by a `Credential`, and the copy of the blindings in an
+//! `IssuanceSession`, are zeroized on drop. The dealer's master secrets and polynomial
+//! coefficients are zeroized inside `keygen` and `generate_shares` before they return.
+//! Public values like verification keys, commitments and signatures are not covered.
+use crate::nullifier::DYPFPrivSecretKey;
+use crate::schnorr::SchnorrProverState;
+use ark_ec::AffineRepr;
+use ark_ff::Field;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+#[cfg(feature = "signing")]
+use {
+    crate::credential::Credential, crate::keygen::SecretKeyShare, crate::session::IssuanceSession,
+    ark_ec::pairing::Pairing,
+};
+
+/// Marks the types whose secrets are zeroized on drop
+pub trait SecretMaterial: Zeroize + ZeroizeOnDrop {}
+
+#[cfg(feature = "signing")]
+impl<E: Pairing> SecretMaterial for SecretKeyShare<E> {}
+#[cfg(feature = "signing")]
+impl<E: Pairing> SecretMaterial for Credential<E> {}
+#[cfg(feature = "signing")]
+impl<E: Pairing> SecretMaterial for IssuanceSession<E> {}
+impl<F: Field> SecretMaterial for DYPFPrivSecretKey<F> {}
+impl<G: AffineRepr> SecretMaterial for SchnorrProverState<G> {}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::keygen::keygen;
+    use crate::nullifier::DYPFPrivVRF;
+    use crate::schnorr::SchnorrProtocol;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+    use ark_std::{UniformRand, Zero};
+
+    fn assert_secret<T: SecretMaterial>(_: &T) {}
+
+    #[test]
+    fn test_zeroize_clears_secrets() {
+        let mut rng = test_rng();
+        let (ck, _, keys) = keygen::<Bls12_381>(2, 3, 4, &mut rng);
+
+        let mut share = keys.sk_shares[0].clone();
+        assert_secret(&share);
+        share.zeroize();
+        assert!(share.x_share.is_zero());
+        assert!(share.y_shares.is_empty());
+
+        let vrf = DYPFPrivVRF::<G1Affine>::new(&mut rng);
+        let (mut sk, _) = vrf.generate_keys(&mut rng);
+        assert_secret(&sk);
+        sk.zeroize();
+        assert!(sk.sk.is_zero() && sk.r_sk.is_zero());
+
+        let mut credential = Credential::<Bls12_381>::new(ck, None, &mut rng).unwrap();
+        credential.compute_commitments_per_m(&mut rng).unwrap();
+        credential.set_symmetric_commitment();
+        assert_secret(&credential);
+        credential.zeroize();
+        assert!(credential.get_messages().is_empty());
+        assert!(credential.get_blinding_factors().is_empty());
+        let cm = credential.cm.as_ref().unwrap();
+        assert!(cm.messages.is_empty());
+        assert_eq!(cm.r, Fr::zero());
+
+        let bases = [G1Affine::rand(&mut rng)];
+        let (mut state, _) = SchnorrProtocol::commit(&bases, &mut rng);
+        assert_secret(&state);
+        state.zeroize();
+        assert!(state.random_blindings.is_empty());
+    }
+}
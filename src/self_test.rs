@@ -0,0 +1,57 @@
+//! A startup check that the build works on a curve.
+//!
+//! `self_test` runs the whole flow once at the smallest useful size, a 2-of-3
+//! committee with 2 attributes: keygen, issuance, show and verification, with the
+//! system and the presentation passed through their serialization on the way. It
+//! catches a broken curve feature or a serialization regression before real
+//! credentials depend on it. The randomness is seeded, nothing it generates is kept.
+use crate::errors::SelfTestError;
+use crate::presentation::Presentation;
+use crate::system::System;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+/// Issue, show and verify one credential over `E`, and check that a tampered
+/// presentation is rejected
+pub fn self_test<E: Pairing>() -> Result<(), SelfTestError> {
+    let mut rng = StdRng::seed_from_u64(0x7_5115);
+    let system = System::<E>::builder()
+        .threshold(2)
+        .signers(3)
+        .attributes(2)
+        .build(&mut rng)?;
+    let system = System::<E>::from_bytes(&system.to_bytes()?)?;
+
+    let credential = system.user().obtain_credential(None, &mut rng)?;
+    let presentation = system.user().show(&credential, &mut rng)?;
+    let mut bytes = Vec::new();
+    presentation.serialize_compressed(&mut bytes)?;
+    let mut presentation = Presentation::<E>::deserialize_compressed(&bytes[..])?;
+    if !matches!(system.verifier().verify(&presentation, &mut rng), Ok(true)) {
+        return Err(SelfTestError::Rejected);
+    }
+
+    presentation.signature.sigma = presentation.signature.h;
+    if matches!(system.verifier().verify(&presentation, &mut rng), Ok(true)) {
+        return Err(SelfTestError::Accepted);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Bls12_381;
+
+    #[test]
+    fn test_self_test_passes() {
+        assert!(self_test::<Bls12_381>().is_ok());
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn test_self_test_passes_on_bn254() {
+        assert!(self_test::<ark_bn254::Bn254>().is_ok());
+    }
+}
@@ -0,0 +1,197 @@
+//! A holder's record of one issuance.
+//!
+//! An `IssuanceSession` keeps the request, the blindings needed to unblind the
+//! aggregate and every verified share that came back, not only the `threshold` that
+//! were combined. Any other subset of the retained shares can then be aggregated later,
+//! e.g. to audit the issuance or to replace a signature whose shares were corrupted.
+//! Every subset gives the same signature.
+use crate::credential::{Credential, CredentialCommitments};
+use crate::errors::SignatureError;
+use crate::keygen::{VerificationKey, VerificationKeyShare};
+use crate::protocol::UserProtocol;
+use crate::signature::{select_subset, PartialSignature, SubsetPolicy, ThresholdSignature};
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use ark_ec::pairing::Pairing;
+use ark_std::collections::BTreeMap;
+use ark_std::rand::{CryptoRng, Rng};
+
+/// The request of one issuance and every verified share received for it
+pub struct IssuanceSession<E: Pairing> {
+    request: CredentialCommitments<E>,
+    blindings: Vec<E::ScalarField>,
+    threshold: usize,
+    shares: BTreeMap<usize, PartialSignature<E>>,
+}
+
+/// Clears the copy of the credential's blindings
+#[cfg(feature = "zeroize")]
+impl<E: Pairing> zeroize::Zeroize for IssuanceSession<E> {
+    fn zeroize(&mut self) {
+        self.blindings.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: Pairing> Drop for IssuanceSession<E> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: Pairing> zeroize::ZeroizeOnDrop for IssuanceSession<E> {}
+
+impl<E: Pairing> IssuanceSession<E> {
+    /// Start a session for `request`, made by `credential`
+    pub fn new(
+        credential: &Credential<E>,
+        request: CredentialCommitments<E>,
+        threshold: usize,
+    ) -> Self {
+        Self {
+            request,
+            blindings: credential.get_blinding_factors().clone(),
+            threshold,
+            shares: BTreeMap::new(),
+        }
+    }
+
+    pub fn request(&self) -> &CredentialCommitments<E> {
+        &self.request
+    }
+
+    /// Verify `shares` against the request and keep the valid ones. Returns how many
+    /// were kept. A different share for an index that is already held is rejected
+    pub fn add_shares(
+        &mut self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk_shares: &[VerificationKeyShare<E>],
+        shares: &[(usize, PartialSignature<E>)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<usize, SignatureError> {
+        let verified =
+            UserProtocol::verify_signature_shares(ck, vk_shares, &self.request, shares, 0, rng)?;
+        for (i, share) in &verified {
+            if self
+                .shares
+                .get(i)
+                .is_some_and(|held| held.sigma != share.sigma)
+            {
+                return Err(SignatureError::DuplicateShare(*i));
+            }
+        }
+        let kept = verified.len();
+        self.shares.extend(verified);
+        Ok(kept)
+    }
+
+    /// Party indices of the retained shares, in increasing order
+    pub fn indices(&self) -> Vec<usize> {
+        self.shares.keys().copied().collect()
+    }
+
+    /// The retained shares, by party index
+    pub fn shares(&self) -> Vec<(usize, PartialSignature<E>)> {
+        self.shares
+            .iter()
+            .map(|(i, share)| (*i, share.clone()))
+            .collect()
+    }
+
+    /// Aggregate the `threshold` retained shares picked by `policy`, unblinding with the
+    /// committee key `vk`
+    pub fn finalize(
+        &self,
+        vk: &VerificationKey<E>,
+        policy: &SubsetPolicy,
+    ) -> Result<ThresholdSignature<E>, SignatureError> {
+        let selected = select_subset(&self.shares(), self.threshold, policy)?;
+        ThresholdSignature::aggregate_signature_shares(
+            vk,
+            &selected,
+            &self.blindings,
+            self.threshold,
+            &self.request.h,
+        )
+    }
+
+    /// Aggregate the retained shares of exactly the parties in `indices`. Can be called
+    /// again with other indices, every subset gives the same signature
+    pub fn finalize_with_indices(
+        &self,
+        vk: &VerificationKey<E>,
+        indices: &[usize],
+    ) -> Result<ThresholdSignature<E>, SignatureError> {
+        self.finalize(vk, &SubsetPolicy::Explicit(indices.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::Bls12_381;
+
+    #[test]
+    fn test_finalize_from_different_subsets() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(3)
+            .signers(5)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let user = system.user();
+        let (mut credential, request) = user.request_credential(None, &mut rng).unwrap();
+        let mut session = IssuanceSession::new(&credential, request.clone(), 3);
+
+        let shares: Vec<_> = system
+            .signers()
+            .iter()
+            .map(|signer| {
+                let share = signer.sign_request(&request, false, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        // Shares arriving in two batches, one of them again
+        assert_eq!(
+            session
+                .add_shares(&system.ck, system.vk_shares(), &shares[..3], &mut rng)
+                .unwrap(),
+            3
+        );
+        session
+            .add_shares(&system.ck, system.vk_shares(), &shares[2..], &mut rng)
+            .unwrap();
+        assert_eq!(session.indices(), vec![1, 2, 3, 4, 5]);
+
+        let first = session
+            .finalize_with_indices(&system.vk, &[1, 2, 3])
+            .unwrap();
+        let second = session
+            .finalize_with_indices(&system.vk, &[2, 4, 5])
+            .unwrap();
+        assert_eq!(first.sigma, second.sigma);
+        for signature in [first, second] {
+            credential.attach_signature(signature, &system.vk);
+            let presentation = user.show(&credential, &mut rng).unwrap();
+            assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+        }
+
+        assert!(matches!(
+            session.finalize_with_indices(&system.vk, &[1, 2]),
+            Err(SignatureError::InsufficientShares { .. })
+        ));
+        let mut corrupted = shares[0].clone();
+        corrupted.1.sigma = shares[1].1.sigma;
+        // A corrupted share fails verification and leaves the held one in place
+        assert_eq!(
+            session
+                .add_shares(&system.ck, system.vk_shares(), &[corrupted], &mut rng)
+                .unwrap(),
+            0
+        );
+        assert_eq!(session.shares()[0].1.sigma, shares[0].1.sigma);
+    }
+}
@@ -0,0 +1,283 @@
+//! Proofs that a hidden attribute lies in a public set.
+//!
+//! The verifier picks a secret `x`, publishes `x_tilde = g_tilde^x` and a weak BB
+//! signature `S_v = P^{1/(x + v)}` on every allowed value `v`, then discards `x`. The
+//! resulting `SetParams` are public and serve any number of presentations.
+//!
+//! On show the holder takes the signature on its attribute `m` and proves it with an
+//! `AttributeSignatureProof`, the same statement as a revocation witness with the set's
+//! base `P` in place of the accumulator value. The response for `m` is the opening
+//! proof's response for the attribute, so the signed value is the credential's own.
+//! Only the randomized signature is sent, which looks the same for every member.
+use crate::commitment::CommitmentProof;
+use crate::ct::{ct_inverse, rand_nonzero};
+use crate::errors::{CommitmentError, SetMembershipError, SignatureError};
+use crate::keygen::VerificationKey;
+use crate::pairing::PairingCheck;
+use crate::presentation::Presentation;
+use crate::revocation::AttributeSignatureProof;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::{DomainSeparator, Transcript};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::ops::Mul;
+use ark_std::rand::{CryptoRng, Rng};
+use ark_std::{One, UniformRand};
+
+/// The public key `(g_tilde, x_tilde)`, the base `P` and the signature on every value
+/// of the set, in the order they were given
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SetParams<E: Pairing> {
+    pub g_tilde: E::G2Affine,
+    pub x_tilde: E::G2Affine,
+    pub base: E::G1Affine,
+    pub signatures: Vec<(E::ScalarField, E::G1Affine)>,
+}
+
+impl<E: Pairing> SetParams<E> {
+    /// Sign every value of `values` under a fresh key whose secret is dropped on return
+    pub fn new(
+        values: &[E::ScalarField],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Self, SetMembershipError> {
+        let x = rand_nonzero::<E::ScalarField, _>(rng);
+        let g_tilde = E::G2Affine::generator();
+        let base = E::G1::rand(rng).into_affine();
+        let mut signatures = Vec::with_capacity(values.len());
+        for (i, value) in values.iter().enumerate() {
+            if values[..i].contains(value) {
+                return Err(SetMembershipError::DuplicateValue);
+            }
+            let (inverse, is_zero) = ct_inverse(&(x + value));
+            if bool::from(is_zero) {
+                return Err(SetMembershipError::InvalidValue);
+            }
+            signatures.push((*value, base.mul(inverse).into_affine()));
+        }
+        Ok(Self {
+            g_tilde,
+            x_tilde: g_tilde.mul(x).into_affine(),
+            base,
+            signatures,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    pub fn contains(&self, value: &E::ScalarField) -> bool {
+        self.signature(value).is_some()
+    }
+
+    /// The signature on `value`, if it is in the set
+    pub fn signature(&self, value: &E::ScalarField) -> Option<&E::G1Affine> {
+        self.signatures
+            .iter()
+            .find(|(v, _)| v == value)
+            .map(|(_, signature)| signature)
+    }
+
+    /// Check every signature, `e(S_v, x_tilde g_tilde^v) = e(P, g_tilde)`, in one
+    /// randomized pairing check. Holders run this once on parameters they receive
+    pub fn validate(&self, rng: &mut (impl Rng + CryptoRng)) -> bool {
+        let neg_base = (-self.base.into_group()).into_affine();
+        let mut check = PairingCheck::<E>::new();
+        for (value, signature) in &self.signatures {
+            let key = (self.x_tilde.into_group() + self.g_tilde.mul(*value)).into_affine();
+            check.merge(&PairingCheck::<E>::rand(
+                rng,
+                &[(signature, &key), (&neg_base, &self.g_tilde)],
+                &E::TargetField::one(),
+            ));
+        }
+        !self.base.is_zero() && check.verify()
+    }
+}
+
+/// A presentation with a proof that the attribute at `attribute_index` is one of the
+/// values signed in a `SetParams`, without revealing which
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SetMembershipPresentation<E: Pairing> {
+    pub presentation: Presentation<E>,
+    pub attribute_index: usize,
+    /// The set signature proof, on the set's base
+    pub proof: AttributeSignatureProof<E>,
+}
+
+/// Joint challenge over the randomized credential, the set's key and base, the
+/// randomized signature and the announcements of both proofs
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn set_membership_show_challenge<E: Pairing>(
+    domain: &DomainSeparator,
+    presentation: &Presentation<E>,
+    params: &SetParams<E>,
+    attribute_index: usize,
+    witness: &E::G1Affine,
+    witness_bar: &E::G1Affine,
+    schnorr_commitment: &E::G1Affine,
+    announcement: &E::G1Affine,
+) -> E::ScalarField {
+    let mut transcript = Transcript::with_domain(domain, b"t-siris show with set membership");
+    transcript.append(b"signature", &presentation.signature);
+    transcript.append(b"cm", &presentation.commitment);
+    transcript.append(b"cm_tilde", &presentation.commitment_tilde);
+    transcript.append(b"x_tilde", &params.x_tilde);
+    transcript.append(b"base", &params.base);
+    transcript.append(b"attribute_index", &(attribute_index as u64));
+    transcript.append(b"witness", witness);
+    transcript.append(b"witness_bar", witness_bar);
+    transcript.append(b"schnorr_commitment", schnorr_commitment);
+    transcript.append(b"announcement", announcement);
+    transcript.challenge_scalar(b"challenge")
+}
+
+impl<E: Pairing> SetMembershipPresentation<E> {
+    /// Verify the credential and that its attribute at `attribute_index` is in the set
+    /// of `params`
+    pub fn verify(
+        &self,
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        params: &SetParams<E>,
+        domain: &DomainSeparator,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let proof: CommitmentProof<E> =
+            CanonicalDeserialize::deserialize_compressed(&self.presentation.proof[..])?;
+        let challenge = set_membership_show_challenge(
+            domain,
+            &self.presentation,
+            params,
+            self.attribute_index,
+            &self.proof.witness,
+            &self.proof.witness_bar,
+            &proof.schnorr_commitment,
+            &self.proof.announcement,
+        );
+        if proof.challenge != challenge || self.attribute_index >= ck.num_attributes() {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        let attribute_response = proof
+            .responses
+            .get(ck.attribute_position(self.attribute_index))
+            .ok_or(CommitmentError::InvalidProof)?;
+        if !self.proof.verify(
+            &params.g_tilde,
+            &params.x_tilde,
+            &params.base,
+            attribute_response,
+            &challenge,
+            rng,
+        ) {
+            return Err(SignatureError::ProofError(
+                "set membership proof verification failed".to_string(),
+            ));
+        }
+
+        self.presentation.verify(ck, vk, rng)
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::protocol::{UserProtocol, VerifierProtocol};
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr};
+
+    #[test]
+    fn test_show_with_membership() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let values: Vec<Fr> = (1..=5u64).map(Fr::from).collect();
+        let params = SetParams::<Bls12_381>::new(&values, &mut rng).unwrap();
+        assert!(params.validate(&mut rng));
+        assert!(matches!(
+            SetParams::<Bls12_381>::new(&[values[0], values[0]], &mut rng),
+            Err(SetMembershipError::DuplicateValue)
+        ));
+        // Parameters survive a round trip and are reused as is
+        let mut bytes = Vec::new();
+        params.serialize_compressed(&mut bytes).unwrap();
+        let params = SetParams::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+
+        // Holders of 2, 4 and 9, carried as attribute 2
+        let credentials: Vec<_> = [Fr::from(2u64), Fr::from(4u64), Fr::from(9u64)]
+            .iter()
+            .map(|value| {
+                let attributes = [Fr::rand(&mut rng), Fr::rand(&mut rng), *value];
+                let user = system.user();
+                let (mut credential, request) = user
+                    .request_credential(Some(&attributes), &mut rng)
+                    .unwrap();
+                let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+                user.complete_credential(&mut credential, &request, &shares, &mut rng)
+                    .unwrap();
+                credential
+            })
+            .collect();
+        let verify = |credential: &crate::credential::Credential<Bls12_381>,
+                      shown: &SetMembershipPresentation<Bls12_381>,
+                      rng: &mut _| {
+            VerifierProtocol::verify_membership(
+                &system.ck,
+                &system.vk,
+                &params,
+                credential.domain(),
+                shown,
+                rng,
+            )
+        };
+
+        let first =
+            UserProtocol::show_with_membership(&credentials[0], 2, &params, &mut rng).unwrap();
+        let second =
+            UserProtocol::show_with_membership(&credentials[1], 2, &params, &mut rng).unwrap();
+        assert!(verify(&credentials[0], &first, &mut rng).unwrap());
+        assert!(verify(&credentials[1], &second, &mut rng).unwrap());
+
+        // Different members give presentations of one shape, none of them carrying a
+        // signature from the set
+        assert_eq!(first.compressed_size(), second.compressed_size());
+        for shown in [&first, &second] {
+            assert!(params
+                .signatures
+                .iter()
+                .all(|(_, signature)| *signature != shown.proof.witness));
+        }
+
+        // A value outside the set has no signature to prove with
+        assert!(matches!(
+            UserProtocol::show_with_membership(&credentials[2], 2, &params, &mut rng),
+            Err(crate::errors::CredentialError::SetMembership(
+                SetMembershipError::NotInSet
+            ))
+        ));
+        // Proving another attribute against the set fails to verify
+        let mut forged = second.clone();
+        forged.attribute_index = 1;
+        assert!(verify(&credentials[1], &forged, &mut rng).is_err());
+        let other = SetParams::<Bls12_381>::new(&values, &mut rng).unwrap();
+        assert!(VerifierProtocol::verify_membership(
+            &system.ck,
+            &system.vk,
+            &other,
+            credentials[0].domain(),
+            &first,
+            &mut rng
+        )
+        .is_err());
+    }
+}
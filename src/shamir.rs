@@ -1,14 +1,48 @@
-use ark_ec::{AffineRepr, CurveGroup};
-use ark_ff::{Field, PrimeField, UniformRand};
-use ark_std::{rand::Rng, vec::Vec};
-use std::ops::Mul;
+use crate::errors::ShamirError;
+use ark_ec::CurveGroup;
+use ark_ff::Field;
+use ark_std::{
+    rand::{CryptoRng, Rng},
+    vec::Vec,
+};
 
 /// Generates shares for a secret using Shamir's Secret Sharing scheme
-pub fn generate_shares<F: Field, R: Rng>(
+pub fn generate_shares<F: Field, R: Rng + CryptoRng>(
     secret: &F,
     threshold: usize,
     num_shares: usize,
     rng: &mut R,
+) -> Vec<(usize, F)> {
+    share_polynomial(secret, threshold, num_shares, rng, |_| ())
+}
+
+/// `generate_shares`, also returning the Feldman commitments `base^{a_j}` to the
+/// coefficients, `a_0` first. Share `i` can be checked against them as
+/// `base^{f(i)} = \prod_j C_j^{i^j}`
+#[allow(clippy::type_complexity)]
+pub fn generate_shares_with_commitments<G: CurveGroup, R: Rng + CryptoRng>(
+    secret: &G::ScalarField,
+    threshold: usize,
+    num_shares: usize,
+    base: &G::Affine,
+    rng: &mut R,
+) -> (Vec<(usize, G::ScalarField)>, Vec<G::Affine>) {
+    let mut commitments = Vec::new();
+    let shares = share_polynomial(secret, threshold, num_shares, rng, |coefficients| {
+        let points: Vec<G> = coefficients.iter().map(|a_j| *base * a_j).collect();
+        commitments = G::normalize_batch(&points);
+    });
+    (shares, commitments)
+}
+
+/// Sample a polynomial of degree `threshold - 1` with constant term `secret`, pass its
+/// coefficients to `inspect` and evaluate it at 1, ..., n
+fn share_polynomial<F: Field, R: Rng + CryptoRng>(
+    secret: &F,
+    threshold: usize,
+    num_shares: usize,
+    rng: &mut R,
+    inspect: impl FnOnce(&[F]),
 ) -> Vec<(usize, F)> {
     // Ensure parameters are valid
     assert!(threshold > 0, "Threshold must be positive");
@@ -24,6 +58,7 @@ pub fn generate_shares<F: Field, R: Rng>(
     for _ in 1..threshold {
         coefficients.push(F::rand(rng));
     }
+    inspect(&coefficients);
 
     // Evaluate the polynomial at points 1, 2, ..., n
     let mut shares = Vec::with_capacity(num_shares);
@@ -40,6 +75,10 @@ pub fn generate_shares<F: Field, R: Rng>(
         shares.push((i, y));
     }
 
+    // a_0 is the secret and the other coefficients reveal it given t - 1 shares
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut coefficients);
+
     shares
 }
 
@@ -50,19 +89,57 @@ pub fn reconstruct_secret<F: Field>(shares: &[(usize, F)], threshold: usize) ->
         "Not enough shares for reconstruction"
     );
 
-    let shares = &shares[0..threshold]; // Only use t shares
+    // Only use t shares, the secret is f(0)
+    interpolate_at(&shares[0..threshold], F::zero())
+}
 
-    // Compute the secret (f(0)) using Lagrange interpolation
-    let mut secret = F::zero();
+/// `reconstruct_secret` that checks the shares past the first `threshold` instead of
+/// ignoring them: each has to lie on the polynomial through the first `threshold`,
+/// which holds exactly when every `threshold`-subset reconstructs the same secret.
+/// Also rejects party index 0, which would be the secret itself, and repeated indices
+pub fn reconstruct_secret_checked<F: Field>(
+    shares: &[(usize, F)],
+    threshold: usize,
+) -> Result<F, ShamirError> {
+    if threshold == 0 {
+        return Err(ShamirError::ZeroThreshold);
+    }
+    if shares.len() < threshold {
+        return Err(ShamirError::NotEnoughShares {
+            shares: shares.len(),
+            threshold,
+        });
+    }
+    for (i, (x_i, _)) in shares.iter().enumerate() {
+        if *x_i == 0 {
+            return Err(ShamirError::ZeroIndex);
+        }
+        if shares[..i].iter().any(|(x_j, _)| x_j == x_i) {
+            return Err(ShamirError::DuplicateIndex(*x_i));
+        }
+    }
+
+    let (base, extra) = shares.split_at(threshold);
+    for (x, y) in extra {
+        if interpolate_at(base, F::from(*x as u64)) != *y {
+            return Err(ShamirError::Inconsistent(*x));
+        }
+    }
+    Ok(interpolate_at(base, F::zero()))
+}
+
+/// `f(x)` for the polynomial of degree below `shares.len()` through `shares`
+fn interpolate_at<F: Field>(shares: &[(usize, F)], x: F) -> F {
+    let mut value = F::zero();
 
     for (i, (x_i, y_i)) in shares.iter().enumerate() {
         let mut lagrange_coef = F::one();
 
-        // Calculate the Lagrange basis polynomial evaluated at 0
+        // Calculate the Lagrange basis polynomial evaluated at x
         for (j, (x_j, _)) in shares.iter().enumerate() {
             if i != j {
-                // (0 - x_j) / (x_i - x_j)
-                let numerator = F::zero() - F::from(*x_j as u64);
+                // (x - x_j) / (x_i - x_j)
+                let numerator = x - F::from(*x_j as u64);
                 let denominator = F::from(*x_i as u64) - F::from(*x_j as u64);
                 // Multiply by the inverse since we're in a field
                 lagrange_coef = lagrange_coef * numerator * denominator.inverse().unwrap();
@@ -70,18 +147,18 @@ pub fn reconstruct_secret<F: Field>(shares: &[(usize, F)], threshold: usize) ->
         }
 
         // Multiply by y_i and add to result
-        secret = secret + (*y_i * lagrange_coef);
+        value += *y_i * lagrange_coef;
     }
 
-    secret
+    value
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_rng;
     use ark_bls12_381::Fr;
-    use ark_ff::One;
-    use ark_std::test_rng;
+    use ark_ff::UniformRand;
 
     #[test]
     fn test_shamir_secret_sharing_basic() {
@@ -98,4 +175,61 @@ mod tests {
         let reconstructed_secret = reconstruct_secret(&shares[0..threshold], threshold);
         assert_eq!(reconstructed_secret, secret);
     }
+
+    // The coefficients are zeroized only after every share has been evaluated
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_shares_correct_with_zeroized_coefficients() {
+        let mut rng = test_rng();
+        let secret = Fr::rand(&mut rng);
+        let (threshold, num_shares) = (3, 6);
+        let shares = generate_shares(&secret, threshold, num_shares, &mut rng);
+
+        for window in shares.windows(threshold) {
+            assert_eq!(reconstruct_secret(window, threshold), secret);
+        }
+    }
+
+    #[test]
+    fn test_checked_reconstruction_detects_inconsistent_share() {
+        let mut rng = test_rng();
+        let secret = Fr::rand(&mut rng);
+        let threshold = 3;
+        let shares = generate_shares(&secret, threshold, threshold + 2, &mut rng);
+        assert_eq!(
+            reconstruct_secret_checked(&shares, threshold).unwrap(),
+            secret
+        );
+
+        // A corrupted extra share is ignored by `reconstruct_secret` and named here
+        let mut corrupted = shares.clone();
+        corrupted[threshold + 1].1 += Fr::from(1u64);
+        assert_eq!(reconstruct_secret(&corrupted, threshold), secret);
+        assert!(matches!(
+            reconstruct_secret_checked(&corrupted, threshold),
+            Err(ShamirError::Inconsistent(5))
+        ));
+        // One among the first threshold throws off the polynomial the extras are
+        // checked against
+        let mut corrupted = shares.clone();
+        corrupted[0].1 += Fr::from(1u64);
+        assert!(matches!(
+            reconstruct_secret_checked(&corrupted, threshold),
+            Err(ShamirError::Inconsistent(4))
+        ));
+
+        let mut repeated = shares.clone();
+        repeated[4] = repeated[1];
+        assert!(matches!(
+            reconstruct_secret_checked(&repeated, threshold),
+            Err(ShamirError::DuplicateIndex(2))
+        ));
+        assert!(matches!(
+            reconstruct_secret_checked(&shares[..2], threshold),
+            Err(ShamirError::NotEnoughShares {
+                shares: 2,
+                threshold: 3
+            })
+        ));
+    }
 }
@@ -1,18 +1,26 @@
-use crate::errors::SignatureError;
-use crate::keygen::{VerificationKey, VerificationKeyShare};
-use crate::pairing::{verify_pairing_equation, PairingCheck};
+use crate::commitment::Commitment;
+use crate::ct::{ct_is_zero, rand_nonzero};
+use crate::display::HexPrefix;
+use crate::errors::{CommitmentError, SignatureError};
+use crate::keygen::{PreparedVerificationKey, VerificationKey};
+use crate::pairing::PairingCheck;
 use crate::symmetric_commitment::SymmetricCommitmentKey;
 use ark_ec::pairing::Pairing;
-use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
-use ark_ff::{Field, UniformRand};
+use ark_ec::{AffineRepr, CurveGroup};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::rand::Rng;
+use ark_std::rand::{CryptoRng, Rng};
 use ark_std::{
+    fmt,
     ops::{Add, Mul, Neg},
     One, Zero,
 };
+#[cfg(feature = "signing")]
+use {
+    crate::keygen::VerificationKeyShare, crate::pairing::verify_pairing_equation,
+    ark_ec::VariableBaseMSM, ark_ff::Field,
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct PartialSignature<E: Pairing> {
     pub party_index: usize,
     pub h: E::G1Affine,
@@ -25,9 +33,108 @@ pub struct ThresholdSignature<E: Pairing> {
     pub sigma: E::G1Affine,
 }
 
+/// `PartialSignature(party=2, h=0x…, sigma=0x…)`
+impl<E: Pairing> fmt::Display for PartialSignature<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PartialSignature(party={}, h={}, sigma={})",
+            self.party_index,
+            HexPrefix(&self.h),
+            HexPrefix(&self.sigma)
+        )
+    }
+}
+
+/// `ThresholdSignature(h=0x…, sigma=0x…)`
+impl<E: Pairing> fmt::Display for ThresholdSignature<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ThresholdSignature(h={}, sigma={})",
+            HexPrefix(&self.h),
+            HexPrefix(&self.sigma)
+        )
+    }
+}
+
+impl<E: Pairing> PartialSignature<E> {
+    /// Length of the compressed encoding in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+}
+
+/// The committee's aggregate key as a standalone Pointcheval–Sanders public key
+/// `(g_tilde, X_tilde = g_tilde^x, Y_tilde_k = g_tilde^{y_k})`, for verifiers that
+/// check plain PS signatures
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregatePublicKey<E: Pairing> {
+    pub g_tilde: E::G2Affine,
+    pub x_tilde: E::G2Affine,
+    pub y_tilde: Vec<E::G2Affine>,
+}
+
+impl<E: Pairing> AggregatePublicKey<E> {
+    /// `g_tilde` comes from `ck`, `X_tilde` and `Y_tilde_k` from `vk`
+    pub fn new(ck: &SymmetricCommitmentKey<E>, vk: &VerificationKey<E>) -> Self {
+        Self {
+            g_tilde: ck.g_tilde,
+            x_tilde: vk.g_tilde_x,
+            y_tilde: vk.g_tilde_y.clone(),
+        }
+    }
+}
+
 impl<E: Pairing> ThresholdSignature<E> {
+    /// Length of the compressed encoding in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+
+    /// `(sigma_1, sigma_2) = (h, sigma)`, the signature in the usual PS order. An
+    /// aggregated signature is `h^{x + sum_k y_k m_k}` over the plaintext attributes, a
+    /// plain PS signature under `AggregatePublicKey`
+    pub fn to_ps_parts(&self) -> (E::G1Affine, E::G1Affine) {
+        (self.h, self.sigma)
+    }
+
+    /// Reference PS verification over the plaintext `messages`:
+    /// `sigma_1 != 1` and `e(sigma_1, X_tilde prod_k Y_tilde_k^{m_k}) = e(sigma_2, g_tilde)`
+    pub fn verify_as_ps(
+        &self,
+        pk: &AggregatePublicKey<E>,
+        messages: &[E::ScalarField],
+    ) -> Result<bool, SignatureError> {
+        if messages.len() != pk.y_tilde.len() {
+            return Err(CommitmentError::TooManyMessages {
+                messages: messages.len(),
+                bases: pk.y_tilde.len(),
+            }
+            .into());
+        }
+        let (sigma_1, sigma_2) = self.to_ps_parts();
+        if sigma_1.is_zero() {
+            return Err(SignatureError::IdentitySignature);
+        }
+        let exponentiated = pk
+            .y_tilde
+            .iter()
+            .zip(messages)
+            .fold(pk.x_tilde.into_group(), |acc, (y_k, m_k)| {
+                acc + y_k.mul(m_k)
+            })
+            .into_affine();
+        let pairs = [
+            (sigma_1, exponentiated),
+            (sigma_2.into_group().neg().into_affine(), pk.g_tilde),
+        ];
+        Ok(PairingCheck::<E>::from_pairs(&pair_refs(&pairs), &E::TargetField::one()).verify())
+    }
+
     /// Verify a signature share from a specific signer
     /// Following RS.ShareVer from the protocol
+    #[cfg(feature = "signing")]
     pub fn verify_share(
         ck: &SymmetricCommitmentKey<E>,
         vk_share: &VerificationKeyShare<E>,
@@ -41,9 +148,8 @@ impl<E: Pairing> ThresholdSignature<E> {
 
         let mut pairs = Vec::new();
 
-        // e(-sigma_i, g̃) = lhs
-        let neg_sigma_i = sig_share.sigma.into_group().neg().into_affine();
-        pairs.push((&neg_sigma_i, &ck.g_tilde));
+        // e(sigma_i, -g̃) = lhs
+        pairs.push((&sig_share.sigma, ck.neg_g_tilde()));
 
         // Add e(h, g̃^[x]_i)
         let g_tilde_x_share = vk_share.g_tilde_x_share;
@@ -60,125 +166,464 @@ impl<E: Pairing> ThresholdSignature<E> {
         verify_pairing_equation::<E>(&pairs, None)
     }
     /// Aggregate signature shares into a complete threshold signature
-    /// A user would do this
+    /// A user would do this. Only the first `threshold` shares are combined, pick them
+    /// with `select_subset` so the choice doesn't follow arrival order. The blindings
+    /// are divided out with the signing committee's `vk.g_y`
+    #[cfg(feature = "signing")]
     pub fn aggregate_signature_shares(
-        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
         signature_shares: &[(usize, PartialSignature<E>)],
         blindings: &[E::ScalarField],
         threshold: usize,
         h: &E::G1Affine,
     ) -> Result<ThresholdSignature<E>, SignatureError> {
-        // Check that we have enough signature shares
-        if signature_shares.len() < threshold {
-            return Err(SignatureError::InsufficientShares {
-                needed: threshold,
-                got: signature_shares.len(),
-            });
+        Self::aggregate_signature_shares_detailed(vk, signature_shares, blindings, threshold, h)
+            .map(|(signature, _)| signature)
+    }
+
+    /// Aggregate the shares of weighted signers, every signer with one share per point it
+    /// holds. `threshold` counts points, so a signer of weight 2 counts twice
+    #[cfg(feature = "signing")]
+    pub fn aggregate_weighted_shares(
+        vk: &VerificationKey<E>,
+        signer_shares: &[(usize, Vec<PartialSignature<E>>)],
+        blindings: &[E::ScalarField],
+        threshold: usize,
+        h: &E::G1Affine,
+    ) -> Result<ThresholdSignature<E>, SignatureError> {
+        let shares: Vec<_> = signer_shares
+            .iter()
+            .flat_map(|(_, shares)| shares)
+            .map(|share| (share.party_index, share.clone()))
+            .collect();
+        Self::aggregate_signature_shares(vk, &shares, blindings, threshold, h)
+    }
+
+    /// `aggregate_signature_shares`, also returning the party indices combined. These
+    /// are the first `threshold` distinct indices, a repeated index is skipped
+    #[cfg(feature = "signing")]
+    pub fn aggregate_signature_shares_detailed(
+        vk: &VerificationKey<E>,
+        signature_shares: &[(usize, PartialSignature<E>)],
+        blindings: &[E::ScalarField],
+        threshold: usize,
+        h: &E::G1Affine,
+    ) -> Result<(ThresholdSignature<E>, Vec<usize>), SignatureError> {
+        // Every share must have been computed against the same h as the request
+        if signature_shares.iter().any(|(_, share)| share.h != *h) {
+            return Err(SignatureError::CommitmentConsistencyFailed);
         }
 
         // Extract indices and signature components
-        let mut indices = Vec::with_capacity(signature_shares.len());
-        let mut sigma_2_components = Vec::with_capacity(signature_shares.len());
+        let mut indices = Vec::with_capacity(threshold);
+        let mut sigma_2_components = Vec::with_capacity(threshold);
 
+        // Only the first `threshold` distinct shares are combined, so interpolate over
+        // exactly those
         for (_, share) in signature_shares {
-            indices.push(share.party_index);
-            sigma_2_components.push((share.party_index, share.sigma));
+            if indices.len() == threshold {
+                break;
+            }
+            if !indices.contains(&share.party_index) {
+                indices.push(share.party_index);
+                sigma_2_components.push((share.party_index, share.sigma));
+            }
         }
 
-        // Compute Lagrange coefficients for each party
-        let mut sigma_2 = E::G1::zero();
-
-        for (idx, (i, sigma_i_2)) in sigma_2_components.iter().enumerate().take(threshold) {
-            // Compute Lagrange coefficient for party i
-            let lagrange_i = compute_lagrange_coefficient::<E::ScalarField>(&indices, *i);
-
-            // Add contribution: sigma_i,2^{L_i}
-            sigma_2 = sigma_2 + sigma_i_2.mul(lagrange_i);
+        // Check that we have enough signature shares
+        if indices.len() < threshold {
+            return Err(SignatureError::InsufficientShares {
+                needed: threshold,
+                got: indices.len(),
+            });
         }
 
-        // Compute g_k^{r_k}
-        let g_k_r_k = E::G1::msm_unchecked(&ck.ck, blindings).neg();
-        let final_sigma = (sigma_2 + g_k_r_k).into_affine();
+        // \prod_i sigma_i^{L_i} \prod_k (g^{y_k})^{-r_k} in one MSM, the Lagrange
+        // coefficients on the shares followed by the negated blindings on the bases
+        let mut bases = Vec::with_capacity(threshold + blindings.len());
+        let mut scalars = Vec::with_capacity(threshold + blindings.len());
+        for (i, sigma_i) in sigma_2_components.iter() {
+            bases.push(*sigma_i);
+            scalars.push(compute_lagrange_coefficient::<E::ScalarField>(
+                &indices, *i,
+            )?);
+        }
+        for (g_k, r_k) in vk.g_y.iter().zip(blindings) {
+            bases.push(*g_k);
+            scalars.push(-*r_k);
+        }
+        let final_sigma = E::G1::msm_unchecked(&bases, &scalars).into_affine();
 
         // Construct the final signature
-        Ok(ThresholdSignature {
+        let signature = ThresholdSignature {
             h: *h,
             sigma: final_sigma,
-        })
+        };
+        Ok((signature, indices))
+    }
+
+    pub fn randomize(
+        &self,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(ThresholdSignature<E>, E::ScalarField), SignatureError> {
+        let u_delta = rand_nonzero::<E::ScalarField, _>(rng);
+        let r_delta = rand_nonzero::<E::ScalarField, _>(rng);
+        Ok((self.randomize_with_factors(&u_delta, &r_delta)?, r_delta))
     }
 
-    pub fn randomize(&self, rng: &mut impl Rng) -> (ThresholdSignature<E>, E::ScalarField) {
-        let u_delta = E::ScalarField::rand(rng);
-        let r_delta: <E as Pairing>::ScalarField = E::ScalarField::rand(rng);
-        (self.randomize_with_factors(&u_delta, &r_delta), r_delta)
+    /// `randomize` for many signatures at once, e.g. a mixer re-showing the credentials
+    /// it holds. All the randomizers are drawn up front and the randomized points are
+    /// normalized to affine with a single batch inversion. Returns every randomized
+    /// signature with its `r_delta`, in the order of `sigs`
+    pub fn randomize_batch(
+        sigs: &[ThresholdSignature<E>],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Vec<(ThresholdSignature<E>, E::ScalarField)> {
+        let factors: Vec<(E::ScalarField, E::ScalarField)> = sigs
+            .iter()
+            .map(|_| (rand_nonzero(rng), rand_nonzero(rng)))
+            .collect();
+        let points: Vec<E::G1> = sigs
+            .iter()
+            .zip(&factors)
+            .flat_map(|(sig, (u_delta, r_delta))| sig.randomized_points(u_delta, r_delta))
+            .collect();
+        E::G1::normalize_batch(&points)
+            .chunks_exact(2)
+            .zip(factors)
+            .map(|(pair, (_, r_delta))| {
+                let signature = ThresholdSignature {
+                    h: pair[0],
+                    sigma: pair[1],
+                };
+                (signature, r_delta)
+            })
+            .collect()
     }
 
-    /// u_delta randomizes sigma1 (h)
+    /// u_delta randomizes sigma1 (h), r_delta the commitment. A zero u_delta sends both
+    /// to the identity and a zero r_delta leaves the shown commitment unrandomized, so
+    /// either is rejected with `ZeroRandomizer`. `randomize` draws both nonzero
     pub fn randomize_with_factors(
         &self,
         u_delta: &E::ScalarField,
         r_delta: &E::ScalarField,
-    ) -> ThresholdSignature<E> {
-        let h_prime = self.h.mul(u_delta).into_affine();
-
-        // let r_times_u = u_delta.mul(r_delta);
-        // let scalars = vec![r_times_u, *u_delta];
-        // let points = vec![self.h, self.sigma];
-        let temp = self.h.mul(r_delta);
-        let sigma_prime = (temp + self.sigma).mul(u_delta).into_affine();
+    ) -> Result<ThresholdSignature<E>, SignatureError> {
+        let randomized = E::G1::normalize_batch(&self.randomized_points(u_delta, r_delta));
 
-        ThresholdSignature {
-            h: h_prime,
-            sigma: sigma_prime,
+        // Only the public failure branches, after the randomized signature is computed
+        if bool::from(ct_is_zero(u_delta) | ct_is_zero(r_delta)) {
+            return Err(SignatureError::ZeroRandomizer);
         }
+
+        Ok(ThresholdSignature {
+            h: randomized[0],
+            sigma: randomized[1],
+        })
     }
 
-    /// Verify a threshold signature using commitments
-    /// Following RS.Ver from the protocol
-    pub fn verify(
+    /// `h' = h^{u_delta}` and `sigma' = (h^{r_delta} sigma)^{u_delta}`, left projective
+    /// for a batch normalization. A 2-term MSM over `[h, sigma]` with `[u r, u]` measured
+    /// no faster, it gives up the GLV endomorphism single multiplications use
+    fn randomized_points(&self, u_delta: &E::ScalarField, r_delta: &E::ScalarField) -> [E::G1; 2] {
+        [
+            self.h.mul(u_delta),
+            (self.h.mul(r_delta) + self.sigma).mul(u_delta),
+        ]
+    }
+
+    /// The non-pairing checks of `verify`, then the pairs of its two equations, each
+    /// of which must multiply out to one
+    pub(crate) fn check_and_pair(
         ck: &SymmetricCommitmentKey<E>,
         vk: &VerificationKey<E>,
         cm: &E::G1Affine,
         cm_tilde: &E::G2Affine,
         sig: &ThresholdSignature<E>,
         serialized_proof: &[u8],
-    ) -> Result<bool, SignatureError> {
-        let mut rng = ark_std::test_rng();
-        let mr = std::sync::Mutex::new(rng);
+    ) -> Result<[PairingEquation<E>; 2], SignatureError> {
+        Self::check_opening(&vk.show_key(ck).get_bases().0, cm, sig, serialized_proof)?;
+        Self::pairing_equations(ck, vk, cm, cm_tilde, sig)
+    }
+
+    /// Reject identity signatures and check the opening proof of `cm` over `bases`, those
+    /// of the key the credential is shown under
+    fn check_opening(
+        bases: &[E::G1Affine],
+        cm: &E::G1Affine,
+        sig: &ThresholdSignature<E>,
+        serialized_proof: &[u8],
+    ) -> Result<(), SignatureError> {
+        // An identity h passes the pairing equation for any key and commitment
+        if sig.h.is_zero() || sig.sigma.is_zero() {
+            return Err(SignatureError::IdentitySignature);
+        }
+
+        // The opening proof must be for cm and over the issuer's own g^{y_k}
+        if !Commitment::<E>::verify_against(serialized_proof, bases, cm)? {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        Ok(())
+    }
+
+    /// The pairs of the signature and commitment equations, for a caller that checks
+    /// the opening of `cm` itself. Rejects identity signatures like `check_and_pair`
+    pub(crate) fn pairing_equations(
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        cm: &E::G1Affine,
+        cm_tilde: &E::G2Affine,
+        sig: &ThresholdSignature<E>,
+    ) -> Result<[PairingEquation<E>; 2], SignatureError> {
+        if sig.h.is_zero() || sig.sigma.is_zero() {
+            return Err(SignatureError::IdentitySignature);
+        }
+
         // Optimized check: e(sigma2, g2) * e(sigma1, vk + cmg2)^-1 = 1
         let vk_plus_cm_tilde = vk.g_tilde_x.add(cm_tilde).into_affine();
-        let check1 = PairingCheck::<E>::rand(
-            &mr,
-            &[
-                (&sig.sigma, &ck.g_tilde),
-                (&sig.h.into_group().neg().into_affine(), &vk_plus_cm_tilde),
-            ],
-            &E::TargetField::one(),
-        );
+        let signature_pairs = vec![
+            (sig.sigma, ck.g_tilde),
+            (sig.h.into_group().neg().into_affine(), vk_plus_cm_tilde),
+        ];
 
         // Optimized check: e(cmg1, g2) * e(g1, cmg2)^-1 = 1
-        let check2 = PairingCheck::<E>::rand(
-            &mr,
-            &[
-                (cm, &ck.g_tilde),
-                (&ck.g.into_group().neg().into_affine(), cm_tilde),
-            ],
-            &E::TargetField::one(),
-        );
+        let commitment_pairs = vec![(*cm, ck.g_tilde), (*ck.neg_g(), *cm_tilde)];
+        Ok([signature_pairs, commitment_pairs])
+    }
 
-        let mut final_check = PairingCheck::<E>::new();
-        final_check.merge(&check1);
-        final_check.merge(&check2);
-        let is_valid = final_check.verify();
+    /// Verify a threshold signature using commitments
+    /// Following RS.Ver from the protocol. `cm` and `cm_tilde` commit over
+    /// `vk.show_key(ck)`, the committee's own `g^{y_k}` and `g_tilde^{y_k}`
+    pub fn verify(
+        ck: &SymmetricCommitmentKey<E>,
+        vk: &VerificationKey<E>,
+        cm: &E::G1Affine,
+        cm_tilde: &E::G2Affine,
+        sig: &ThresholdSignature<E>,
+        serialized_proof: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        let [signature_pairs, commitment_pairs] =
+            Self::check_and_pair(ck, vk, cm, cm_tilde, sig, serialized_proof)?;
+        // Both equations in one multi Miller loop over four pairs, one final exponentiation
+        let is_valid = PairingCheck::<E>::rand_equations(
+            rng,
+            &[&pair_refs(&signature_pairs), &pair_refs(&commitment_pairs)],
+        )
+        .verify();
         if !is_valid {
+            // Only pay for checking the two equations apart when the batch fails
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                signature_check = PairingCheck::<E>::from_pairs(
+                    &pair_refs(&signature_pairs),
+                    &E::TargetField::one()
+                )
+                .verify(),
+                commitment_check = PairingCheck::<E>::from_pairs(
+                    &pair_refs(&commitment_pairs),
+                    &E::TargetField::one()
+                )
+                .verify(),
+                "pairing check failed"
+            );
             return Err(SignatureError::SignatureVerificationFailed);
         }
+        #[cfg(feature = "tracing")]
+        tracing::debug!("signature and commitment pairing checks passed");
 
         Ok(is_valid)
     }
+
+    /// `verify` with the fixed G2 points taken from `pvk`. With a random nonzero `r`
+    /// the two equations merge into
+    /// `e(sigma + r cm, g_tilde) e(-h, g_tilde^x) e(-h - r g, cm_tilde) = 1`,
+    /// three Miller loops of which two run on prepared lines
+    pub fn verify_prepared(
+        ck: &SymmetricCommitmentKey<E>,
+        pvk: &PreparedVerificationKey<E>,
+        cm: &E::G1Affine,
+        cm_tilde: &E::G2Affine,
+        sig: &ThresholdSignature<E>,
+        serialized_proof: &[u8],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        Self::check_opening(&pvk.bases, cm, sig, serialized_proof)?;
+
+        let r = rand_nonzero::<E::ScalarField, _>(rng);
+        let g1 = E::G1::normalize_batch(&[
+            sig.sigma.into_group() + cm.mul(r),
+            sig.h.into_group().neg(),
+            ck.neg_g().mul(r) - sig.h,
+        ]);
+        let g2: [E::G2Prepared; 3] = [
+            pvk.g_tilde.clone(),
+            pvk.g_tilde_x.clone(),
+            (*cm_tilde).into(),
+        ];
+        let is_valid =
+            E::final_exponentiation(E::multi_miller_loop(g1, g2)).is_some_and(|out| out.is_zero());
+        if !is_valid {
+            return Err(SignatureError::SignatureVerificationFailed);
+        }
+        Ok(true)
+    }
+}
+
+/// The pairs of one pairing equation that multiplies out to one
+pub(crate) type PairingEquation<E> = Vec<(<E as Pairing>::G1Affine, <E as Pairing>::G2Affine)>;
+
+/// Borrow owned pairs in the form `PairingCheck` takes them
+pub(crate) fn pair_refs<G1, G2>(pairs: &[(G1, G2)]) -> Vec<(&G1, &G2)> {
+    pairs.iter().map(|(a, b)| (a, b)).collect()
+}
+
+/// How to pick the `threshold` shares to aggregate when more have arrived
+#[cfg(feature = "signing")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubsetPolicy {
+    /// The shares of the lowest party indices
+    LowestIndices,
+    /// A uniformly random subset drawn from a `StdRng` with this seed
+    Random([u8; 32]),
+    /// Exactly the shares of these party indices
+    Explicit(Vec<usize>),
+}
+
+/// Pick `threshold` shares according to `policy`, independently of the order in which
+/// they arrived. The result is sorted by party index, so the same shares, policy and
+/// seed always give the same subset
+#[cfg(feature = "signing")]
+pub fn select_subset<E: Pairing>(
+    shares: &[(usize, PartialSignature<E>)],
+    threshold: usize,
+    policy: &SubsetPolicy,
+) -> Result<Vec<(usize, PartialSignature<E>)>, SignatureError> {
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    let mut sorted = shares.to_vec();
+    sorted.sort_by_key(|(i, _)| *i);
+    if let Some(pair) = sorted.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(SignatureError::DuplicateShare(pair[0].0));
+    }
+    if sorted.len() < threshold {
+        return Err(SignatureError::InsufficientShares {
+            needed: threshold,
+            got: sorted.len(),
+        });
+    }
+
+    let mut selected = match policy {
+        SubsetPolicy::LowestIndices => {
+            sorted.truncate(threshold);
+            sorted
+        }
+        SubsetPolicy::Random(seed) => {
+            // Partial Fisher-Yates shuffle over the sorted shares
+            let mut rng = StdRng::from_seed(*seed);
+            for i in 0..threshold {
+                let j = rng.gen_range(i..sorted.len());
+                sorted.swap(i, j);
+            }
+            sorted.truncate(threshold);
+            sorted
+        }
+        SubsetPolicy::Explicit(indices) => {
+            if indices.len() != threshold {
+                return Err(SignatureError::InsufficientShares {
+                    needed: threshold,
+                    got: indices.len(),
+                });
+            }
+            let mut selected = Vec::with_capacity(threshold);
+            for index in indices {
+                if selected.iter().any(|(i, _)| i == index) {
+                    return Err(SignatureError::DuplicateShare(*index));
+                }
+                let share = sorted
+                    .iter()
+                    .find(|(i, _)| i == index)
+                    .ok_or(SignatureError::InvalidShare(*index))?;
+                selected.push(share.clone());
+            }
+            selected
+        }
+    };
+    selected.sort_by_key(|(i, _)| *i);
+    Ok(selected)
 }
 
-pub fn compute_lagrange_coefficient<F: Field>(indices: &[usize], j: usize) -> F {
+/// Test oracle: `(h, h^x · ∏_k cm_k^{y_k})` signed with the full key instead of shares
+#[cfg(all(test, feature = "signing"))]
+pub(crate) fn sign_centralized<E: Pairing>(
+    ck: &SymmetricCommitmentKey<E>,
+    x: &E::ScalarField,
+    y: &[E::ScalarField],
+    commitments: &[E::G1Affine],
+    h: &E::G1Affine,
+) -> ThresholdSignature<E> {
+    assert_eq!(y.len(), ck.num_attributes());
+    assert_eq!(commitments.len(), ck.num_attributes());
+    let sigma = commitments
+        .iter()
+        .zip(y)
+        .fold(h.mul(x), |acc, (cm_k, y_k)| acc + cm_k.mul(y_k));
+    ThresholdSignature {
+        h: *h,
+        sigma: sigma.into_affine(),
+    }
+}
+
+/// Test oracle: the `sigma` that aggregating shares over the request commitments
+/// `h^{m_k} g^{r_k}` and unblinding should give. Signs the commitments with the full
+/// key, `h^x · ∏_k (h^{m_k} g^{r_k})^{y_k}`, and divides out `∏_k g^{y_k r_k}`, leaving
+/// `h^{x + Σ_k y_k m_k}`
+#[cfg(all(test, feature = "signing"))]
+pub(crate) fn expected_aggregate_sigma<E: Pairing>(
+    ck: &SymmetricCommitmentKey<E>,
+    x: &E::ScalarField,
+    y_values: &[E::ScalarField],
+    messages: &[E::ScalarField],
+    blindings: &[E::ScalarField],
+    h: &E::G1Affine,
+) -> E::G1Affine {
+    assert_eq!(messages.len(), y_values.len());
+    assert_eq!(blindings.len(), y_values.len());
+    let commitments: Vec<E::G1Affine> = messages
+        .iter()
+        .zip(blindings)
+        .map(|(m_k, r_k)| (h.mul(m_k) + ck.g.mul(r_k)).into_affine())
+        .collect();
+    let blinded = commitments
+        .iter()
+        .zip(y_values)
+        .fold(h.mul(x), |acc, (cm_k, y_k)| acc + cm_k.mul(y_k));
+    let blinding = blindings
+        .iter()
+        .zip(y_values)
+        .fold(E::G1::zero(), |acc, (r_k, y_k)| acc + ck.g.mul(*y_k * r_k));
+    (blinded - blinding).into_affine()
+}
+
+/// Lagrange coefficient at 0 of party `j` over the party indices `indices`. Parties
+/// are numbered from 1, index 0 would be the secret's own point; both it and a repeated
+/// index are rejected
+#[cfg(feature = "signing")]
+pub fn compute_lagrange_coefficient<F: Field>(
+    indices: &[usize],
+    j: usize,
+) -> Result<F, SignatureError> {
+    for (position, &i) in indices.iter().enumerate() {
+        if i == 0 {
+            return Err(SignatureError::ZeroPartyIndex);
+        }
+        if indices[..position].contains(&i) {
+            return Err(SignatureError::DuplicateShare(i));
+        }
+    }
+    if j == 0 {
+        return Err(SignatureError::ZeroPartyIndex);
+    }
     let j_field = F::from(j as u64);
 
     let mut result = F::one();
@@ -192,7 +637,429 @@ pub fn compute_lagrange_coefficient<F: Field>(indices: &[usize], j: usize) -> F
         let denominator = j_field - i_field; // (x_j - x_i)
 
         // Compute (0 - x_i)/(x_j - x_i)
-        result *= numerator * denominator.inverse().expect("indices should be distinct");
+        // Distinct indices below the field's characteristic, never zero
+        result *= numerator
+            * denominator
+                .inverse()
+                .ok_or(SignatureError::DuplicateShare(i))?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric_commitment::SymmetricCommitment;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+    use ark_std::{One, UniformRand, Zero};
+
+    // Only uses the verification surface, so it also runs under
+    // `--no-default-features --features verify`
+    #[test]
+    fn test_verify_only_presentation() {
+        let mut rng = test_rng();
+        let l = 3;
+
+        // Sign centrally with x, y_1..y_l instead of going through the threshold dealer
+        let x = Fr::rand(&mut rng);
+        let y_values: Vec<Fr> = (0..l).map(|_| Fr::rand(&mut rng)).collect();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&y_values, &mut rng);
+        let vk = VerificationKey::<Bls12_381> {
+            g_tilde_x: ck.g_tilde.mul(x).into_affine(),
+            g_y: ck.ck.clone(),
+            g_tilde_y: ck.ck_tilde.clone(),
+            params: None,
+        };
+
+        let messages: Vec<Fr> = (0..l).map(|_| Fr::rand(&mut rng)).collect();
+        let r = Fr::rand(&mut rng);
+        let cm = SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &r);
+
+        // sigma = h^{x + sum y_k m_k + r}
+        let exponent = y_values
+            .iter()
+            .zip(messages.iter())
+            .fold(x + r, |acc, (y, m)| acc + *y * m);
+        let h = G1Affine::rand(&mut rng);
+        let sig = ThresholdSignature::<Bls12_381> {
+            h,
+            sigma: h.mul(exponent).into_affine(),
+        };
+        let proof = cm.clone().prove(&mut rng).unwrap();
+
+        let valid =
+            ThresholdSignature::verify(&ck, &vk, &cm.cm, &cm.cm_tilde(), &sig, &proof, &mut rng)
+                .unwrap();
+        assert!(valid);
+
+        let wrong_vk = VerificationKey::<Bls12_381> {
+            g_tilde_x: ck.g_tilde.mul(x + Fr::from(1u64)).into_affine(),
+            g_y: ck.ck.clone(),
+            g_tilde_y: ck.ck_tilde.clone(),
+            params: None,
+        };
+        assert!(matches!(
+            ThresholdSignature::verify(
+                &ck,
+                &wrong_vk,
+                &cm.cm,
+                &cm.cm_tilde(),
+                &sig,
+                &proof,
+                &mut rng
+            ),
+            Err(SignatureError::SignatureVerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn test_randomize_batch_signatures_verify() {
+        let mut rng = test_rng();
+        let l = 3;
+        let x = Fr::rand(&mut rng);
+        let y_values: Vec<Fr> = (0..l).map(|_| Fr::rand(&mut rng)).collect();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&y_values, &mut rng);
+        let vk = VerificationKey::<Bls12_381> {
+            g_tilde_x: ck.g_tilde.mul(x).into_affine(),
+            g_y: ck.ck.clone(),
+            g_tilde_y: ck.ck_tilde.clone(),
+            params: None,
+        };
+
+        // Signatures on the zero-blinded commitments of several credentials
+        let signed: Vec<_> = (0..5)
+            .map(|_| {
+                let messages: Vec<Fr> = (0..l).map(|_| Fr::rand(&mut rng)).collect();
+                let cm = SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &Fr::zero());
+                let exponent = y_values
+                    .iter()
+                    .zip(&messages)
+                    .fold(x, |acc, (y, m)| acc + *y * m);
+                let h = G1Affine::rand(&mut rng);
+                let sig = ThresholdSignature::<Bls12_381> {
+                    h,
+                    sigma: h.mul(exponent).into_affine(),
+                };
+                (cm, sig)
+            })
+            .collect();
+        let sigs: Vec<_> = signed.iter().map(|(_, sig)| sig.clone()).collect();
+
+        let randomized = ThresholdSignature::randomize_batch(&sigs, &mut rng);
+        assert_eq!(randomized.len(), sigs.len());
+        for ((cm, sig), (randomized_sig, r_delta)) in signed.iter().zip(&randomized) {
+            assert_ne!(randomized_sig.h, sig.h);
+            let cm = cm.randomize(r_delta).unwrap();
+            let proof = cm.clone().prove(&mut rng).unwrap();
+            assert!(ThresholdSignature::verify(
+                &ck,
+                &vk,
+                &cm.cm,
+                &cm.cm_tilde(),
+                randomized_sig,
+                &proof,
+                &mut rng
+            )
+            .unwrap());
+        }
+        assert!(ThresholdSignature::<Bls12_381>::randomize_batch(&[], &mut rng).is_empty());
+    }
+
+    #[test]
+    fn test_randomize_with_factors_matches_reference() {
+        let mut rng = test_rng();
+        for _ in 0..16 {
+            let sig = ThresholdSignature::<Bls12_381> {
+                h: G1Affine::rand(&mut rng),
+                sigma: G1Affine::rand(&mut rng),
+            };
+            let (u_delta, r_delta) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+            let randomized = sig.randomize_with_factors(&u_delta, &r_delta).unwrap();
+            // Each point normalized on its own, as before the batch normalization
+            assert_eq!(randomized.h, sig.h.mul(u_delta).into_affine());
+            assert_eq!(
+                randomized.sigma,
+                (sig.h.mul(r_delta) + sig.sigma).mul(u_delta).into_affine()
+            );
+            // and the expanded form h^{u r} sigma^u
+            assert_eq!(
+                randomized.sigma,
+                (sig.h.mul(u_delta * r_delta) + sig.sigma.mul(u_delta)).into_affine()
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_check_verify_matches_merged_checks() {
+        let mut rng = test_rng();
+        let x = Fr::rand(&mut rng);
+        let y_values: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&y_values, &mut rng);
+        let vk = VerificationKey::<Bls12_381> {
+            g_tilde_x: ck.g_tilde.mul(x).into_affine(),
+            g_y: ck.ck.clone(),
+            g_tilde_y: ck.ck_tilde.clone(),
+            params: None,
+        };
+        let messages: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+        let cm = SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &Fr::rand(&mut rng));
+        let proof = cm.clone().prove(&mut rng).unwrap();
+        let h = G1Affine::rand(&mut rng);
+        let exponent = y_values
+            .iter()
+            .zip(&messages)
+            .fold(x + cm.r, |acc, (y, m)| acc + *y * m);
+        let sig = ThresholdSignature::<Bls12_381> {
+            h,
+            sigma: h.mul(exponent).into_affine(),
+        };
+        let point = G1Affine::rand(&mut rng);
+        let other_cm_tilde =
+            SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &Fr::rand(&mut rng)).cm_tilde();
+
+        // The two equations checked apart, each under its own coefficient, then merged
+        let merged = |cm_tilde: &_, sig: &ThresholdSignature<Bls12_381>, rng: &mut _| {
+            let [signature_pairs, commitment_pairs] =
+                ThresholdSignature::pairing_equations(&ck, &vk, &cm.cm, cm_tilde, sig).unwrap();
+            let mut check = PairingCheck::<Bls12_381>::new();
+            for pairs in [signature_pairs, commitment_pairs] {
+                check.merge(&PairingCheck::rand(
+                    rng,
+                    &pair_refs(&pairs),
+                    &<Bls12_381 as Pairing>::TargetField::one(),
+                ));
+            }
+            check.verify()
+        };
+        let forged = ThresholdSignature { h, sigma: point };
+        for (cm_tilde, sig, expected) in [
+            (&cm.cm_tilde(), &sig, true),
+            (&cm.cm_tilde(), &forged, false),
+            (&other_cm_tilde, &sig, false),
+            (&other_cm_tilde, &forged, false),
+        ] {
+            assert_eq!(merged(cm_tilde, sig, &mut rng), expected);
+            let single =
+                ThresholdSignature::verify(&ck, &vk, &cm.cm, cm_tilde, sig, &proof, &mut rng);
+            assert_eq!(single.is_ok(), expected);
+        }
+
+        // Alone, the unscaled first equation of rand_equations counts as non randomized
+        let [signature_pairs, commitment_pairs] =
+            ThresholdSignature::pairing_equations(&ck, &vk, &cm.cm, &cm.cm_tilde(), &sig).unwrap();
+        let check = PairingCheck::<Bls12_381>::rand_equations(
+            &mut rng,
+            &[&pair_refs(&signature_pairs), &pair_refs(&commitment_pairs)],
+        );
+        let mut twice = check;
+        twice.merge(&check);
+        assert!(check.verify() && !twice.verify());
+        assert!(!PairingCheck::<Bls12_381>::rand_equations(
+            &mut rng,
+            &[&pair_refs(&signature_pairs), &[]]
+        )
+        .verify());
+    }
+
+    #[test]
+    fn test_prepared_verify_matches_standard() {
+        let mut rng = test_rng();
+        let x = Fr::rand(&mut rng);
+        let y_values: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&y_values, &mut rng);
+        let vk = VerificationKey::<Bls12_381> {
+            g_tilde_x: ck.g_tilde.mul(x).into_affine(),
+            g_y: ck.ck.clone(),
+            g_tilde_y: ck.ck_tilde.clone(),
+            params: None,
+        };
+        let wrong_vk = VerificationKey::<Bls12_381> {
+            g_tilde_x: ck.g_tilde.mul(x + Fr::from(1u64)).into_affine(),
+            ..vk.clone()
+        };
+        let messages: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let cm = SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &Fr::rand(&mut rng));
+        let other = SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &Fr::rand(&mut rng));
+        let proof = cm.clone().prove(&mut rng).unwrap();
+        let h = G1Affine::rand(&mut rng);
+        let exponent = y_values
+            .iter()
+            .zip(&messages)
+            .fold(x + cm.r, |acc, (y, m)| acc + *y * m);
+        let sig = ThresholdSignature::<Bls12_381> {
+            h,
+            sigma: h.mul(exponent).into_affine(),
+        };
+        let forged = ThresholdSignature::<Bls12_381> {
+            h,
+            sigma: G1Affine::rand(&mut rng),
+        };
+
+        for (vk, cm_tilde, sig, expected) in [
+            (&vk, &cm.cm_tilde(), &sig, true),
+            (&vk, &cm.cm_tilde(), &forged, false),
+            (&vk, &other.cm_tilde(), &sig, false),
+            (&wrong_vk, &cm.cm_tilde(), &sig, false),
+        ] {
+            let pvk = PreparedVerificationKey::new(&ck, vk);
+            let standard =
+                ThresholdSignature::verify(&ck, vk, &cm.cm, cm_tilde, sig, &proof, &mut rng);
+            let prepared = ThresholdSignature::verify_prepared(
+                &ck, &pvk, &cm.cm, cm_tilde, sig, &proof, &mut rng,
+            );
+            assert_eq!(standard.is_ok(), expected);
+            assert_eq!(prepared.is_ok(), expected);
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_lagrange_rejects_zero_and_duplicate_indices() {
+        let coefficients: Vec<Fr> = [1, 3, 4]
+            .iter()
+            .map(|&j| compute_lagrange_coefficient(&[1, 3, 4], j).unwrap())
+            .collect();
+        // Interpolating the constant polynomial 1 gives 1
+        assert_eq!(coefficients.iter().sum::<Fr>(), Fr::one());
+
+        assert!(matches!(
+            compute_lagrange_coefficient::<Fr>(&[0, 1, 2], 1),
+            Err(SignatureError::ZeroPartyIndex)
+        ));
+        assert!(matches!(
+            compute_lagrange_coefficient::<Fr>(&[1, 2], 0),
+            Err(SignatureError::ZeroPartyIndex)
+        ));
+        assert!(matches!(
+            compute_lagrange_coefficient::<Fr>(&[1, 2, 2], 1),
+            Err(SignatureError::DuplicateShare(2))
+        ));
+
+        // A 0-based share reaches aggregation as an error, not a wrong signature
+        let mut rng = test_rng();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&[Fr::rand(&mut rng)], &mut rng);
+        let vk = VerificationKey::<Bls12_381> {
+            g_tilde_x: ck.g_tilde,
+            g_y: ck.ck.clone(),
+            g_tilde_y: ck.ck_tilde.clone(),
+            params: None,
+        };
+        let h = G1Affine::rand(&mut rng);
+        let shares: Vec<_> = (0..2)
+            .map(|i| {
+                let share = PartialSignature::<Bls12_381> {
+                    party_index: i,
+                    h,
+                    sigma: G1Affine::rand(&mut rng),
+                };
+                (i, share)
+            })
+            .collect();
+        assert!(matches!(
+            ThresholdSignature::aggregate_signature_shares(
+                &vk,
+                &shares,
+                &[Fr::rand(&mut rng)],
+                2,
+                &h
+            ),
+            Err(SignatureError::ZeroPartyIndex)
+        ));
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_msm_aggregation_matches_share_by_share() {
+        use crate::system::System;
+
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(4)
+            .signers(7)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let (credential, request) = system.user().request_credential(None, &mut rng).unwrap();
+        let shares: Vec<_> = system.signers()[2..6]
+            .iter()
+            .map(|signer| {
+                let share = signer.sign_request(&request, false, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        let blindings = credential.get_blinding_factors();
+
+        // One scalar multiplication per share, then the blinding MSM subtracted
+        let indices: Vec<usize> = shares.iter().map(|(i, _)| *i).collect();
+        let mut expected = <Bls12_381 as Pairing>::G1::zero();
+        for (i, share) in &shares {
+            expected += share.sigma * compute_lagrange_coefficient::<Fr>(&indices, *i).unwrap();
+        }
+        expected -= <Bls12_381 as Pairing>::G1::msm_unchecked(&system.vk.g_y, blindings);
+
+        let signature = ThresholdSignature::aggregate_signature_shares(
+            &system.vk, &shares, blindings, 4, &request.h,
+        )
+        .unwrap();
+        assert_eq!(signature.sigma, expected.into_affine());
+        assert_eq!(signature.h, request.h);
+    }
+
+    #[test]
+    fn test_degenerate_signatures_rejected() {
+        let mut rng = test_rng();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&[Fr::rand(&mut rng)], &mut rng);
+        let vk = VerificationKey::<Bls12_381> {
+            g_tilde_x: ck.g_tilde.mul(Fr::rand(&mut rng)).into_affine(),
+            g_y: ck.ck.clone(),
+            g_tilde_y: ck.ck_tilde.clone(),
+            params: None,
+        };
+        let cm =
+            SymmetricCommitment::<Bls12_381>::new(&ck, &[Fr::rand(&mut rng)], &Fr::rand(&mut rng));
+        let proof = cm.clone().prove(&mut rng).unwrap();
+        let point = G1Affine::rand(&mut rng);
+
+        for sig in [
+            ThresholdSignature::<Bls12_381> {
+                h: G1Affine::zero(),
+                sigma: G1Affine::zero(),
+            },
+            ThresholdSignature::<Bls12_381> {
+                h: G1Affine::zero(),
+                sigma: point,
+            },
+            ThresholdSignature::<Bls12_381> {
+                h: point,
+                sigma: G1Affine::zero(),
+            },
+        ] {
+            assert!(matches!(
+                ThresholdSignature::verify(
+                    &ck,
+                    &vk,
+                    &cm.cm,
+                    &cm.cm_tilde(),
+                    &sig,
+                    &proof,
+                    &mut rng
+                ),
+                Err(SignatureError::IdentitySignature)
+            ));
+        }
+
+        let sig = ThresholdSignature::<Bls12_381> {
+            h: point,
+            sigma: point,
+        };
+        assert!(matches!(
+            sig.randomize_with_factors(&Fr::zero(), &Fr::rand(&mut rng)),
+            Err(SignatureError::ZeroRandomizer)
+        ));
+        assert!(matches!(
+            sig.randomize_with_factors(&Fr::rand(&mut rng), &Fr::zero()),
+            Err(SignatureError::ZeroRandomizer)
+        ));
     }
-    result
 }
@@ -1,18 +1,55 @@
-use crate::commitment::batch_verify;
+use crate::commitment::{ConsistencyProof, KeyBindingProof};
+use crate::credential::{
+    expand_commitments, AggregatedCredentialCommitments, CredentialCommitments,
+};
 use crate::errors::{CommitmentError, SignatureError};
-use crate::keygen::{SecretKeyShare, VerificationKeyShare};
+use crate::keygen::{SecretKeyShare, VerificationKeyShare, WeightedThresholdKeys};
+use crate::messages::{validate_commitment_points, validate_point};
 use crate::signature::PartialSignature;
 use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::DomainSeparator;
+use crate::user::User;
 use ark_ec::pairing::Pairing;
-use ark_ec::CurveGroup;
+use ark_ec::{AffineRepr, CurveGroup};
 use ark_std::ops::Mul;
-use ark_std::rand::Rng;
+use ark_std::rand::{CryptoRng, Rng};
+
+/// Reject an identity `h` and malformed points before they reach the unchecked MSMs
+fn check_request_points<E: Pairing>(
+    commitments: &[E::G1Affine],
+    commitment_proofs: &[Vec<u8>],
+    h: &E::G1Affine,
+) -> Result<(), SignatureError> {
+    // With h the identity the share would only depend on the commitments
+    if h.is_zero() {
+        return Err(SignatureError::IdentityH);
+    }
+    validate_commitment_points::<E>(h, commitments, commitment_proofs)?;
+    Ok(())
+}
+
+/// Verify the opening proofs of `request` once, for a coordinator that then calls every
+/// signer with `Signer::sign_request(request, true, rng)`. Fails on a malformed request
+/// and with `BatchVerifyError` on proofs that don't verify, like the signers themselves
+pub fn verify_request_proofs<E: Pairing>(
+    request: &CredentialCommitments<E>,
+    rng: &mut (impl Rng + CryptoRng),
+) -> Result<(), SignatureError> {
+    check_request_points::<E>(&request.commitments, &request.proofs, &request.h)?;
+    User::verify_request_proofs::<E>(&request.proofs, rng)
+}
 
 /// A signer in the threshold signature scheme with lifetime parameters
 pub struct Signer<'a, E: Pairing> {
     pub ck: &'a SymmetricCommitmentKey<E>,
     pub sk_share: &'a SecretKeyShare<E>,
     pub vk_share: &'a VerificationKeyShare<E>,
+    /// Only sign requests whose `ConsistencyProof` verifies, see `require_consistency`
+    pub require_consistency: bool,
+    /// Only sign requests whose `KeyBindingProof` verifies, see `require_key_binding`
+    pub require_key_binding: bool,
+    /// Domain the Fiat-Shamir proofs in requests are checked under
+    pub domain: DomainSeparator,
 }
 
 impl<'a, E: Pairing> Signer<'a, E> {
@@ -26,23 +63,104 @@ impl<'a, E: Pairing> Signer<'a, E> {
             ck,
             sk_share,
             vk_share,
+            require_consistency: false,
+            require_key_binding: false,
+            domain: DomainSeparator::default(),
         }
     }
 
+    /// Check the Fiat-Shamir proofs in requests under `domain` instead of the default
+    pub fn with_domain(mut self, domain: DomainSeparator) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// Require a `ConsistencyProof` binding the commitments to the holder's symmetric
+    /// commitment. Only `sign_request` can carry one, the other signing paths then fail
+    /// with `MissingConsistencyProof`
+    pub fn require_consistency(mut self, required: bool) -> Self {
+        self.require_consistency = required;
+        self
+    }
+
+    /// Require a `KeyBindingProof` that attribute 0 is `key_attribute(pk)` for a key
+    /// `pk` the holder controls. Like the consistency proof, only `sign_request` can
+    /// carry one, the other signing paths then fail with `MissingKeyBinding`
+    pub fn require_key_binding(mut self, required: bool) -> Self {
+        self.require_key_binding = required;
+        self
+    }
+
     /// sign a share of the threshold signature
     pub fn sign_share(
         &self,
         commitments: &[E::G1Affine],
         commitment_proofs: &[Vec<u8>],
         h: &E::G1Affine,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<PartialSignature<E>, SignatureError> {
-        // Verify all commitment proofs
+        self.check_request(commitments, commitment_proofs, h, None, None, false, rng)?;
+        Ok(self.compute_share(commitments, h))
+    }
+
+    /// Sign a share over `request`. With `proofs_pre_verified` the opening proofs are
+    /// taken as already checked by `verify_request_proofs`, so `threshold` signers don't
+    /// each verify the same proofs. Unlike `sign_share_no_zkp_verify`, `h`, the points
+    /// and the pairing of each proof with its commitment are still checked
+    pub fn sign_request(
+        &self,
+        request: &CredentialCommitments<E>,
+        proofs_pre_verified: bool,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<PartialSignature<E>, SignatureError> {
+        self.check_request(
+            &request.commitments,
+            &request.proofs,
+            &request.h,
+            request.consistency.as_ref(),
+            request.key_binding.as_ref(),
+            proofs_pre_verified,
+            rng,
+        )?;
+        Ok(self.compute_share(&request.commitments, &request.h))
+    }
+
+    /// Check `h`, the commitments and that `commitment_proofs[k]` is a valid proof for
+    /// `commitments[k]`, skipping only the Schnorr verification if `proofs_pre_verified`.
+    /// With `require_consistency` set, `consistency` has to be present and valid too,
+    /// and the same for `key_binding` with `require_key_binding`
+    #[allow(clippy::too_many_arguments)]
+    fn check_request(
+        &self,
+        commitments: &[E::G1Affine],
+        commitment_proofs: &[Vec<u8>],
+        h: &E::G1Affine,
+        consistency: Option<&ConsistencyProof<E>>,
+        key_binding: Option<&KeyBindingProof<E>>,
+        proofs_pre_verified: bool,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(), SignatureError> {
+        check_request_points::<E>(commitments, commitment_proofs, h)?;
+
+        if self.require_consistency {
+            let proof = consistency.ok_or(SignatureError::MissingConsistencyProof)?;
+            validate_point(&proof.cm, "cm")?;
+            if !proof.verify(&self.domain, h, self.ck, commitments)? {
+                return Err(SignatureError::CommitmentConsistencyFailed);
+            }
+        }
+
+        if self.require_key_binding {
+            let proof = key_binding.ok_or(SignatureError::MissingKeyBinding)?;
+            validate_point(&proof.pk, "pk")?;
+            if !proof.verify(&self.domain, h, &self.ck.g, commitments)? {
+                return Err(SignatureError::KeyBindingFailed);
+            }
+        }
 
         // from 45% to 50% improvement in schnorr verification time
-        let valid = batch_verify::<E>(commitment_proofs, rng)?;
-        if !valid {
-            return Err(CommitmentError::BatchVerifyError.into());
+        if !proofs_pre_verified {
+            User::verify_request_proofs::<E>(commitment_proofs, rng)?;
         }
 
         // for (_, proof) in commitments.iter().zip(commitment_proofs.iter()) {
@@ -51,7 +169,45 @@ impl<'a, E: Pairing> Signer<'a, E> {
         //         return Err(SignatureError::InvalidShare(self.sk_share.index).into());
         //     }
         // }
+        Ok(())
+    }
 
+    /// Sign a share over a request carrying one `AggregateCommitmentProof` for all of
+    /// its commitments, verified in place of `batch_verify`
+    pub fn sign_share_aggregated(
+        &self,
+        request: &AggregatedCredentialCommitments<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<PartialSignature<E>, SignatureError> {
+        if self.require_consistency {
+            return Err(SignatureError::MissingConsistencyProof);
+        }
+        if self.require_key_binding {
+            return Err(SignatureError::MissingKeyBinding);
+        }
+        let h = &request.h;
+        if h.is_zero() {
+            return Err(SignatureError::IdentityH);
+        }
+        validate_point(h, "h")?;
+        for commitment in &request.commitments {
+            validate_point(commitment, "commitment")?;
+        }
+        for announcement in &request.proof.announcements {
+            validate_point(announcement, "announcement")?;
+        }
+
+        if !request
+            .proof
+            .verify(&self.domain, h, &self.ck.g, &request.commitments, rng)?
+        {
+            return Err(CommitmentError::ProofVerificationFailed.into());
+        }
+        Ok(self.compute_share(&request.commitments, h))
+    }
+
+    /// σ_i = (h, h^[x]_i · ∏_{k∈[ℓ]} cm_k^[y_k]_i) over already checked commitments
+    fn compute_share(&self, commitments: &[E::G1Affine], h: &E::G1Affine) -> PartialSignature<E> {
         // Extract the index and secret key shares
         let i = self.sk_share.index;
         let x_i = self.sk_share.x_share;
@@ -62,25 +218,44 @@ impl<'a, E: Pairing> Signer<'a, E> {
         // Add the commitment terms
         for (k, commitment) in commitments.iter().enumerate() {
             if k < self.sk_share.y_shares.len() {
-                sigma = sigma + commitment.mul(self.sk_share.y_shares[k]);
+                sigma += commitment.mul(self.sk_share.y_shares[k]);
             }
         }
 
-        Ok(PartialSignature {
+        PartialSignature {
             party_index: i,
-            h: h.clone(),
+            h: *h,
             sigma: sigma.into_affine(),
-        })
+        }
+    }
+
+    /// Sign a share over the private attribute commitments in `commitments` and the
+    /// issuer-set `(index, value)` pairs in `public_attributes`. The commitments are
+    /// for the remaining indices, in increasing order
+    pub fn sign_share_with_public_attributes(
+        &self,
+        commitments: &[E::G1Affine],
+        commitment_proofs: &[Vec<u8>],
+        h: &E::G1Affine,
+        public_attributes: &[(usize, E::ScalarField)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<PartialSignature<E>, SignatureError> {
+        // The proofs are for the private commitments only, the public slots are h^m
+        self.check_request(commitments, commitment_proofs, h, None, None, false, rng)?;
+        let commitments =
+            expand_commitments::<E>(h, commitments, public_attributes, self.ck.num_attributes())?;
+        Ok(self.compute_share(&commitments, h))
     }
 
     /// sign a share of the threshold signature
-    // for testing and comparison purposes - no zkp verify.
+    // for testing and comparison purposes - no zkp verify. `sign_request` with
+    // `proofs_pre_verified` is the checked way to skip the proofs
     pub fn sign_share_no_zkp_verify(
         &self,
         commitments: &[E::G1Affine],
-        commitment_proofs: &[Vec<u8>],
+        _commitment_proofs: &[Vec<u8>],
         h: &E::G1Affine,
-        rng: &mut impl Rng,
+        _rng: &mut (impl Rng + CryptoRng),
     ) -> Result<PartialSignature<E>, SignatureError> {
         // Verify all commitment proofs
 
@@ -107,14 +282,53 @@ impl<'a, E: Pairing> Signer<'a, E> {
         // Add the commitment terms
         for (k, commitment) in commitments.iter().enumerate() {
             if k < self.sk_share.y_shares.len() {
-                sigma = sigma + commitment.mul(self.sk_share.y_shares[k]);
+                sigma += commitment.mul(self.sk_share.y_shares[k]);
             }
         }
 
         Ok(PartialSignature {
             party_index: i,
-            h: h.clone(),
+            h: *h,
             sigma: sigma.into_affine(),
         })
     }
 }
+
+/// A signer of a weighted committee, with a `Signer` for every point it holds
+pub struct WeightedSigner<'a, E: Pairing> {
+    /// The signer, counting from 0
+    pub signer: usize,
+    pub points: Vec<Signer<'a, E>>,
+}
+
+impl<'a, E: Pairing> WeightedSigner<'a, E> {
+    /// Signer `s` of `keys`, if there is one
+    pub fn new(
+        ck: &'a SymmetricCommitmentKey<E>,
+        keys: &'a WeightedThresholdKeys<E>,
+        s: usize,
+    ) -> Option<Self> {
+        let points = keys.points(s)?;
+        let points = keys.keys.sk_shares[points.clone()]
+            .iter()
+            .zip(&keys.keys.vk_shares[points])
+            .map(|(sk_share, vk_share)| Signer::new(ck, sk_share, vk_share))
+            .collect();
+        Some(Self { signer: s, points })
+    }
+
+    /// One share per point over `request`. The opening proofs are verified once, by
+    /// the first point, unless `proofs_pre_verified`
+    pub fn sign_request(
+        &self,
+        request: &CredentialCommitments<E>,
+        proofs_pre_verified: bool,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Vec<PartialSignature<E>>, SignatureError> {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(j, point)| point.sign_request(request, proofs_pre_verified || j > 0, rng))
+            .collect()
+    }
+}
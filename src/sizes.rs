@@ -0,0 +1,173 @@
+//! Expected compressed sizes of the protocol objects for a given attribute count.
+//!
+//! The sizes follow the `CanonicalSerialize` layouts: vectors carry an 8-byte length
+//! prefix and party indices are encoded as 8 bytes. A presentation carries a constant
+//! part (randomized signature and commitments) and an opening proof with one base
+//! and one response per attribute, plus one for the blinding.
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::CanonicalSerialize;
+use ark_std::Zero;
+
+/// Length prefix of a serialized `Vec`
+const LENGTH_PREFIX: usize = 8;
+
+/// Tag byte of a serialized `Option`
+const OPTION_TAG: usize = 1;
+
+/// Sizes in bytes for credentials with `attributes` attributes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeReport {
+    pub attributes: usize,
+    /// One serialized opening proof in a credential request
+    pub request_proof: usize,
+    /// `CredentialCommitments`, the holder's request to every signer
+    pub credential_request: usize,
+    /// `AggregatedCredentialCommitments`, the same request with one aggregated proof
+    pub aggregated_credential_request: usize,
+    pub partial_signature: usize,
+    pub threshold_signature: usize,
+    /// The part of a presentation that doesn't depend on the attribute count
+    pub presentation_fixed: usize,
+    /// The serialized opening proof inside a presentation
+    pub presentation_proof: usize,
+    /// `Presentation`, the sum of the fixed part and the proof with its length prefix
+    pub presentation: usize,
+    /// `DYPFPrivVRFProof` over G1
+    pub nullifier_proof: usize,
+    /// `NullifierPresentation`
+    pub nullifier_presentation: usize,
+}
+
+/// Compute the sizes for `l_attributes` attributes over the pairing `E`
+pub fn report<E: Pairing>(l_attributes: usize) -> SizeReport {
+    let g1 = E::G1Affine::zero().compressed_size();
+    let g2 = E::G2Affine::zero().compressed_size();
+    let scalar = E::ScalarField::zero().compressed_size();
+
+    // commitment, schnorr commitment, bases, challenge, responses
+    let commitment_proof = |bases: usize| {
+        2 * g1 + LENGTH_PREFIX + bases * g1 + scalar + LENGTH_PREFIX + bases * scalar
+    };
+
+    let request_proof = commitment_proof(2);
+    // h, commitments, proofs and the tags of the absent consistency and key binding proofs
+    let credential_request = g1
+        + LENGTH_PREFIX
+        + l_attributes * g1
+        + LENGTH_PREFIX
+        + l_attributes * (LENGTH_PREFIX + request_proof)
+        + 2 * OPTION_TAG;
+    // h, commitments, then announcements and both response vectors
+    let aggregated_credential_request = g1
+        + LENGTH_PREFIX
+        + l_attributes * g1
+        + LENGTH_PREFIX
+        + l_attributes * g1
+        + 2 * (LENGTH_PREFIX + l_attributes * scalar);
+    let threshold_signature = 2 * g1;
+    // signature, commitments and the tag of the absent nonce
+    let presentation_fixed = threshold_signature + g1 + g2 + OPTION_TAG;
+    let presentation_proof = commitment_proof(l_attributes + 1);
+    let presentation = presentation_fixed + LENGTH_PREFIX + presentation_proof;
+    let nullifier_proof = 3 * g1 + 5 * scalar;
+
+    SizeReport {
+        attributes: l_attributes,
+        request_proof,
+        credential_request,
+        aggregated_credential_request,
+        partial_signature: LENGTH_PREFIX + 2 * g1,
+        threshold_signature,
+        presentation_fixed,
+        presentation_proof,
+        presentation,
+        nullifier_proof,
+        // nullifier, cm_sk and cm_x
+        nullifier_presentation: presentation + 3 * g1 + nullifier_proof,
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::credential::Credential;
+    use crate::nullifier::DYPFPrivVRF;
+    use crate::protocol::UserProtocol;
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, G1Affine};
+
+    #[test]
+    fn test_report_matches_serialized_sizes() {
+        let mut rng = test_rng();
+        for l in [1, 3, 8] {
+            let system = System::<Bls12_381>::builder()
+                .threshold(2)
+                .signers(3)
+                .attributes(l)
+                .build(&mut rng)
+                .unwrap();
+            let user = system.user();
+            let (mut credential, request) = user.request_credential(None, &mut rng).unwrap();
+            let aggregated = Credential::new(system.ck.clone(), None, &mut rng)
+                .unwrap()
+                .prove_all(&mut rng)
+                .unwrap();
+            let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+            user.complete_credential(&mut credential, &request, &shares, &mut rng)
+                .unwrap();
+            let presentation = user.show(&credential, &mut rng).unwrap();
+            let vrf = DYPFPrivVRF::<G1Affine>::new(&mut rng);
+            let (sk, _) = vrf.generate_keys(&mut rng);
+            let shown =
+                UserProtocol::show_with_nullifier(&credential, &vrf, &sk, &mut rng).unwrap();
+
+            let expected = report::<Bls12_381>(l);
+            assert_eq!(request.serialized_size(), expected.credential_request);
+            assert_eq!(
+                aggregated.serialized_size(),
+                expected.aggregated_credential_request
+            );
+            assert_eq!(request.proofs[0].len(), expected.request_proof);
+            assert_eq!(shares[0].1.serialized_size(), expected.partial_signature);
+            assert_eq!(
+                presentation.signature.serialized_size(),
+                expected.threshold_signature
+            );
+            assert_eq!(presentation.proof.len(), expected.presentation_proof);
+            assert_eq!(presentation.serialized_size(), expected.presentation);
+            assert_eq!(shown.vrf_proof.serialized_size(), expected.nullifier_proof);
+            assert_eq!(shown.compressed_size(), expected.nullifier_presentation);
+        }
+    }
+
+    #[test]
+    fn test_size_growth_in_attributes() {
+        let base = report::<Bls12_381>(1);
+        let g1 = 48;
+        let scalar = 32;
+        for l in [2, 16, 128] {
+            let sizes = report::<Bls12_381>(l);
+            // Signature and randomized commitments don't grow with the attributes
+            assert_eq!(sizes.presentation_fixed, base.presentation_fixed);
+            assert_eq!(sizes.threshold_signature, base.threshold_signature);
+            // The opening proof adds one base and one response per attribute
+            assert_eq!(
+                sizes.presentation - base.presentation,
+                (l - 1) * (g1 + scalar)
+            );
+            // Every attribute adds its commitment and a fixed size proof to the request
+            assert_eq!(
+                sizes.credential_request - base.credential_request,
+                (l - 1) * (g1 + LENGTH_PREFIX + base.request_proof)
+            );
+            // With one aggregated proof it only adds an announcement and two responses
+            assert_eq!(
+                sizes.aggregated_credential_request - base.aggregated_credential_request,
+                (l - 1) * (2 * g1 + 2 * scalar)
+            );
+            assert!(sizes.aggregated_credential_request < sizes.credential_request);
+        }
+    }
+}
@@ -1,37 +1,86 @@
 use crate::commitment::CommitmentProof;
-use crate::errors::CommitmentError;
+use crate::errors::{CommitmentError, MessageError};
+use crate::messages::validate_point;
 use crate::schnorr::SchnorrProtocol;
 use ark_ec::pairing::Pairing;
-use ark_ec::{CurveGroup, VariableBaseMSM};
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
 use ark_ff::UniformRand;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
 use ark_std::ops::{Add, Mul};
-use ark_std::rand::Rng;
+use ark_std::rand::{CryptoRng, Rng};
+use ark_std::Zero;
+use std::sync::OnceLock;
 
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+/// Serialized as `ck, messages, r, cm, cm_tilde`; a commitment made with
+/// `new_g1_only` computes `cm_tilde` on first access and keeps it
+#[derive(Clone, Debug)]
 pub struct SymmetricCommitment<E: Pairing> {
     pub ck: SymmetricCommitmentKey<E>,
     pub messages: Vec<E::ScalarField>,
     pub r: E::ScalarField,
     pub cm: E::G1Affine,
-    pub cm_tilde: E::G2Affine,
+    cm_tilde: OnceLock<E::G2Affine>,
 }
 
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+/// Where the blinding base `g` sits in `get_bases`, and so where `r` sits among the
+/// exponents and the responses of opening proofs. The commitments themselves don't
+/// depend on it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BaseOrder {
+    /// `ck_1, ..., ck_L, g`
+    #[default]
+    BlindingLast,
+    /// `g, ck_1, ..., ck_L`, for verifiers that expect the blinding first
+    BlindingFirst,
+}
+
+/// Serialized as `g, ck, g_tilde, ck_tilde`; the negated generators are cached on
+/// construction and recomputed on deserialization. The base order isn't serialized,
+/// a deserialized key is `BlindingLast` until `with_base_order` says otherwise
+#[derive(Clone, Debug)]
 pub struct SymmetricCommitmentKey<E: Pairing> {
     pub g: E::G1Affine,
-    pub ck: Vec<E::G1Affine>,
+    pub(crate) ck: Vec<E::G1Affine>,
     pub g_tilde: E::G2Affine,
-    pub ck_tilde: Vec<E::G2Affine>,
+    pub(crate) ck_tilde: Vec<E::G2Affine>,
+    neg_g: E::G1Affine,
+    neg_g_tilde: E::G2Affine,
+    base_order: BaseOrder,
 }
 
 impl<E: Pairing> SymmetricCommitmentKey<E> {
     /// Create a new symmetric commitment key
-    pub fn new(y_values: &[E::ScalarField], rng: &mut impl Rng) -> Self {
+    pub fn new(y_values: &[E::ScalarField], rng: &mut (impl Rng + CryptoRng)) -> Self {
         // Generate random base points
         let g = E::G1Affine::rand(rng);
         let g_tilde = E::G2Affine::rand(rng);
 
+        Self::derive(g, g_tilde, y_values)
+    }
+
+    /// A key over the given generators, e.g. a standardized pair shared with another
+    /// implementation. `ck` and `ck_tilde` are derived from them as in `new`, so the same
+    /// generators and `y_values` always give the same key. Fails for an identity or
+    /// off-subgroup generator
+    pub fn new_with_generators(
+        g: E::G1Affine,
+        g_tilde: E::G2Affine,
+        y_values: &[E::ScalarField],
+    ) -> Result<Self, MessageError> {
+        validate_point(&g, "g")?;
+        validate_point(&g_tilde, "g_tilde")?;
+        Ok(Self::derive(g, g_tilde, y_values))
+    }
+
+    /// `g^{y_k}` and `g_tilde^{y_k}` for every `y_k`
+    pub(crate) fn derive(
+        g: E::G1Affine,
+        g_tilde: E::G2Affine,
+        y_values: &[E::ScalarField],
+    ) -> Self {
         // Compute commitment bases in G1
         let ck = y_values
             .iter()
@@ -44,60 +93,295 @@ impl<E: Pairing> SymmetricCommitmentKey<E> {
             .map(|y_k| g_tilde.mul(y_k).into_affine())
             .collect();
 
+        Self::from_bases(g, ck, g_tilde, ck_tilde)
+    }
+
+    /// A key over existing bases, caching the negated generators
+    pub fn from_bases(
+        g: E::G1Affine,
+        ck: Vec<E::G1Affine>,
+        g_tilde: E::G2Affine,
+        ck_tilde: Vec<E::G2Affine>,
+    ) -> Self {
         Self {
             g,
             ck,
             g_tilde,
             ck_tilde,
+            neg_g: (-g.into_group()).into_affine(),
+            neg_g_tilde: (-g_tilde.into_group()).into_affine(),
+            base_order: BaseOrder::default(),
+        }
+    }
+
+    /// The key with its bases, exponents and proof responses laid out in `order`.
+    /// Provers and verifiers have to agree on it, proofs don't verify across orders
+    pub fn with_base_order(mut self, order: BaseOrder) -> Self {
+        self.base_order = order;
+        self
+    }
+
+    pub fn base_order(&self) -> BaseOrder {
+        self.base_order
+    }
+
+    /// Index of attribute `k`'s base in `get_bases`, and of its response in an
+    /// opening proof
+    pub fn attribute_position(&self, k: usize) -> usize {
+        match self.base_order {
+            BaseOrder::BlindingLast => k,
+            BaseOrder::BlindingFirst => k + 1,
+        }
+    }
+
+    /// `messages` and `r` in the order of `get_bases`
+    pub fn order_exponents(
+        &self,
+        messages: &[E::ScalarField],
+        r: &E::ScalarField,
+    ) -> Vec<E::ScalarField> {
+        let mut exponents = Vec::with_capacity(messages.len() + 1);
+        if self.base_order == BaseOrder::BlindingFirst {
+            exponents.push(*r);
         }
+        exponents.extend_from_slice(messages);
+        if self.base_order == BaseOrder::BlindingLast {
+            exponents.push(*r);
+        }
+        exponents
+    }
+
+    /// `L`, the number of attributes the key commits to
+    pub fn num_attributes(&self) -> usize {
+        self.ck.len()
+    }
+
+    /// `-g`, for moving a pairing with `g` to the other side of an equation
+    pub fn neg_g(&self) -> &E::G1Affine {
+        &self.neg_g
     }
 
-    /// Get all bases for proving
+    /// `-g_tilde`
+    pub fn neg_g_tilde(&self) -> &E::G2Affine {
+        &self.neg_g_tilde
+    }
+
+    /// Get all bases for proving, in the key's `BaseOrder`
     pub fn get_bases(&self) -> (Vec<E::G1Affine>, Vec<E::G2Affine>) {
-        let mut bases = self.ck.clone();
-        bases.push(self.g);
+        match self.base_order {
+            BaseOrder::BlindingLast => {
+                let mut bases = self.ck.clone();
+                bases.push(self.g);
+
+                let mut bases_tilde = self.ck_tilde.clone();
+                bases_tilde.push(self.g_tilde);
+
+                (bases, bases_tilde)
+            }
+            BaseOrder::BlindingFirst => (
+                [&[self.g], &self.ck[..]].concat(),
+                [&[self.g_tilde], &self.ck_tilde[..]].concat(),
+            ),
+        }
+    }
+}
 
-        let mut bases_tilde = self.ck_tilde.clone();
-        bases_tilde.push(self.g_tilde);
+impl<E: Pairing> CanonicalSerialize for SymmetricCommitmentKey<E> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.g.serialize_with_mode(&mut writer, compress)?;
+        self.ck.serialize_with_mode(&mut writer, compress)?;
+        self.g_tilde.serialize_with_mode(&mut writer, compress)?;
+        self.ck_tilde.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.g.serialized_size(compress)
+            + self.ck.serialized_size(compress)
+            + self.g_tilde.serialized_size(compress)
+            + self.ck_tilde.serialized_size(compress)
+    }
+}
 
-        (bases, bases_tilde)
+impl<E: Pairing> Valid for SymmetricCommitmentKey<E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.g.check()?;
+        self.ck.check()?;
+        self.g_tilde.check()?;
+        self.ck_tilde.check()
+    }
+}
+
+impl<E: Pairing> CanonicalDeserialize for SymmetricCommitmentKey<E> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let g = E::G1Affine::deserialize_with_mode(&mut reader, compress, validate)?;
+        let ck = Vec::deserialize_with_mode(&mut reader, compress, validate)?;
+        let g_tilde = E::G2Affine::deserialize_with_mode(&mut reader, compress, validate)?;
+        let ck_tilde = Vec::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(Self::from_bases(g, ck, g_tilde, ck_tilde))
+    }
+}
+
+impl<E: Pairing> CanonicalSerialize for SymmetricCommitment<E> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.ck.serialize_with_mode(&mut writer, compress)?;
+        self.messages.serialize_with_mode(&mut writer, compress)?;
+        self.r.serialize_with_mode(&mut writer, compress)?;
+        self.cm.serialize_with_mode(&mut writer, compress)?;
+        self.cm_tilde().serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.ck.serialized_size(compress)
+            + self.messages.serialized_size(compress)
+            + self.r.serialized_size(compress)
+            + self.cm.serialized_size(compress)
+            + E::G2Affine::zero().serialized_size(compress)
+    }
+}
+
+impl<E: Pairing> Valid for SymmetricCommitment<E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.ck.check()?;
+        self.messages.check()?;
+        self.r.check()?;
+        self.cm.check()?;
+        match self.cm_tilde.get() {
+            Some(cm_tilde) => cm_tilde.check(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<E: Pairing> CanonicalDeserialize for SymmetricCommitment<E> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let ck = SymmetricCommitmentKey::deserialize_with_mode(&mut reader, compress, validate)?;
+        let messages = Vec::deserialize_with_mode(&mut reader, compress, validate)?;
+        let r = E::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?;
+        let cm = E::G1Affine::deserialize_with_mode(&mut reader, compress, validate)?;
+        let cm_tilde = E::G2Affine::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(Self {
+            ck,
+            messages,
+            r,
+            cm,
+            cm_tilde: OnceLock::from(cm_tilde),
+        })
     }
 }
 
 // takes in pp, messages, r. creates cm, cm_tilde by 1. exponentiate each pp.ckg1 with mi and pp.g1 with r, msm together
 impl<E: Pairing> SymmetricCommitment<E> {
+    /// `new`, failing instead of panicking when there are more messages than bases
+    pub fn try_new(
+        ck: &SymmetricCommitmentKey<E>,
+        messages: &[E::ScalarField],
+        r: &E::ScalarField,
+    ) -> Result<Self, CommitmentError> {
+        check_fits(ck, messages)?;
+        Ok(Self::new(ck, messages, r))
+    }
+
+    /// `new_g1_only`, failing instead of panicking when there are more messages than
+    /// bases
+    pub fn try_new_g1_only(
+        ck: &SymmetricCommitmentKey<E>,
+        messages: &[E::ScalarField],
+        r: &E::ScalarField,
+    ) -> Result<Self, CommitmentError> {
+        check_fits(ck, messages)?;
+        Ok(Self::new_g1_only(ck, messages, r))
+    }
+
+    /// Commit to `messages` under `r` in both groups. Panics if there are more
+    /// messages than bases in `ck`, use `try_new` for lengths that aren't known to fit
     pub fn new(
         ck: &SymmetricCommitmentKey<E>,
-        messages: &Vec<E::ScalarField>,
+        messages: &[E::ScalarField],
         r: &E::ScalarField,
     ) -> Self {
-        // Compute commitment in G1
-        let cm = g1_commit::<E>(ck, messages, r);
-
-        // Compute commitment in G2
-        let cm_tilde = g2_commit::<E>(ck, messages, r);
+        // The G2 MSM dominates, under `parallel` the G1 one runs beside it
+        let (cm, cm_tilde) = join(
+            || g1_commit::<E>(ck, messages, r),
+            || g2_commit::<E>(ck, messages, r),
+        );
 
         Self {
             ck: ck.clone(),
             messages: messages.to_vec(),
             r: *r,
             cm,
-            cm_tilde,
+            cm_tilde: OnceLock::from(cm_tilde),
+        }
+    }
+
+    /// Commit to `messages` under `r` in G1 only. `cm_tilde` is computed the first
+    /// time it is needed, G2 arithmetic costs several times as much and holders only
+    /// need it to show
+    pub fn new_g1_only(
+        ck: &SymmetricCommitmentKey<E>,
+        messages: &[E::ScalarField],
+        r: &E::ScalarField,
+    ) -> Self {
+        Self {
+            ck: ck.clone(),
+            messages: messages.to_vec(),
+            r: *r,
+            cm: g1_commit::<E>(ck, messages, r),
+            cm_tilde: OnceLock::new(),
         }
     }
 
-    pub fn randomize(&self, r_delta: &E::ScalarField) -> Self {
+    /// The commitment in G2, computed and kept on first access
+    pub fn cm_tilde(&self) -> E::G2Affine {
+        *self
+            .cm_tilde
+            .get_or_init(|| g2_commit::<E>(&self.ck, &self.messages, &self.r))
+    }
+
+    /// Shift the blinding by `r_delta` in both groups. A zero `r_delta` would show the
+    /// commitment unchanged, so it is rejected
+    pub fn randomize(&self, r_delta: &E::ScalarField) -> Result<Self, CommitmentError> {
+        let (cm_delta, cm_tilde_delta) = self.randomized_points(r_delta)?;
         let new_r = self.r + r_delta;
-        let cm_delta = (self.cm + self.ck.g.mul(r_delta)).into_affine();
-        let cm_tilde_delta = (self.cm_tilde + self.ck.g_tilde.mul(r_delta)).into_affine();
 
-        Self {
+        Ok(Self {
             ck: self.ck.clone(),
             messages: self.messages.clone(),
             r: new_r,
             cm: cm_delta,
-            cm_tilde: cm_tilde_delta,
+            cm_tilde: OnceLock::from(cm_tilde_delta),
+        })
+    }
+
+    /// `cm` and `cm_tilde` of `randomize(r_delta)`, without copying the key and the
+    /// messages
+    pub(crate) fn randomized_points(
+        &self,
+        r_delta: &E::ScalarField,
+    ) -> Result<(E::G1Affine, E::G2Affine), CommitmentError> {
+        if r_delta.is_zero() {
+            return Err(CommitmentError::ZeroRandomizer);
         }
+        Ok(join(
+            || (self.cm + self.ck.g.mul(r_delta)).into_affine(),
+            || (self.cm_tilde() + self.ck.g_tilde.mul(r_delta)).into_affine(),
+        ))
     }
 
     pub fn randomize_just_g1(&self, r_delta: &E::ScalarField) -> Self {
@@ -109,27 +393,37 @@ impl<E: Pairing> SymmetricCommitment<E> {
             messages: self.messages.clone(),
             r: new_r,
             cm: cm_delta,
-            cm_tilde: self.cm_tilde,
+            cm_tilde: OnceLock::from(self.cm_tilde()),
+        }
+    }
+
+    pub fn randomize_just_g2(&self, r_delta: &E::ScalarField) -> Self {
+        let new_r = self.r + r_delta;
+        let cm_tilde_delta = (self.cm_tilde() + self.ck.g_tilde.mul(r_delta)).into_affine();
+
+        Self {
+            ck: self.ck.clone(),
+            messages: self.messages.clone(),
+            r: new_r,
+            cm: self.cm,
+            cm_tilde: OnceLock::from(cm_tilde_delta),
         }
     }
 
-    // get all exponents of the commitment, C([m_1,...,m_n],r)
+    // get all exponents of the commitment, C([m_1,...,m_n],r), in the key's base order
     pub fn get_exponents(&self) -> Vec<E::ScalarField> {
-        let mut exponents: Vec<E::ScalarField> = self.messages.clone();
-        exponents.push(self.r.clone());
-        exponents
+        self.ck.order_exponents(&self.messages, &self.r)
     }
 
-    pub fn prove(self, rng: &mut impl Rng) -> Result<Vec<u8>, CommitmentError> {
+    pub fn prove(self, rng: &mut (impl Rng + CryptoRng)) -> Result<Vec<u8>, CommitmentError> {
         let bases = self.ck.get_bases().0;
-        let schnorr_commitment = SchnorrProtocol::commit(&bases, rng);
+        let (state, announcement) = SchnorrProtocol::commit(&bases, rng);
         let challenge = E::ScalarField::rand(rng);
-        let responses =
-            SchnorrProtocol::prove(&schnorr_commitment, &self.get_exponents(), &challenge);
+        let responses = SchnorrProtocol::prove(state, &self.get_exponents(), &challenge);
         let proof: CommitmentProof<E> = CommitmentProof {
             commitment: self.cm,
-            schnorr_commitment: schnorr_commitment.commited_blindings,
-            bases: bases,
+            schnorr_commitment: announcement.0,
+            bases,
             challenge,
             responses: responses.0,
         };
@@ -160,6 +454,30 @@ impl<E: Pairing> SymmetricCommitment<E> {
     // pub fun verify
 }
 
+/// Run `a` and `b` on rayon's pool under `parallel`, one after the other without it
+fn join<A: Send, B: Send>(a: impl FnOnce() -> A + Send, b: impl FnOnce() -> B + Send) -> (A, B) {
+    #[cfg(feature = "parallel")]
+    let both = rayon::join(a, b);
+    #[cfg(not(feature = "parallel"))]
+    let both = (a(), b());
+    both
+}
+
+/// Reject more messages than `ck` has bases in either group
+pub(crate) fn check_fits<E: Pairing>(
+    ck: &SymmetricCommitmentKey<E>,
+    messages: &[E::ScalarField],
+) -> Result<(), CommitmentError> {
+    let bases = ck.ck.len().min(ck.ck_tilde.len());
+    if messages.len() > bases {
+        return Err(CommitmentError::TooManyMessages {
+            messages: messages.len(),
+            bases,
+        });
+    }
+    Ok(())
+}
+
 pub fn g1_commit<E: Pairing>(
     ck: &SymmetricCommitmentKey<E>,
     messages: &[E::ScalarField],
@@ -189,7 +507,7 @@ pub fn g2_commit<E: Pairing>(
     temp.add(g2_r).into_affine()
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "signing"))]
 mod tests {
     use super::*;
     use crate::shamir::generate_shares;
@@ -197,12 +515,12 @@ mod tests {
 
     #[test]
     fn test_randomized_commitment() {
-        let mut rng = ark_std::test_rng();
+        let mut rng = crate::test_rng();
         let x = Fr::rand(&mut rng);
         let t = 3;
         let n = 5;
         let l = 4;
-        let x_shares = generate_shares(&x, t, n, &mut rng);
+        let _x_shares = generate_shares(&x, t, n, &mut rng);
 
         // generate y values [y1,..,yL]
         let mut y_values = Vec::with_capacity(l);
@@ -228,18 +546,242 @@ mod tests {
 
         // Let's test opening proof
         let (bases, _) = ck.get_bases();
-        let schnorr_commitment = SchnorrProtocol::commit(&bases, &mut rng);
-        let responses =
-            SchnorrProtocol::prove(&schnorr_commitment, &commitment.get_exponents(), &challenge);
+        let (state, announcement) = SchnorrProtocol::commit(&bases, &mut rng);
+        let responses = SchnorrProtocol::prove(state, &commitment.get_exponents(), &challenge);
 
         let is_valid = SchnorrProtocol::verify(
             &bases,
             &commitment.cm,
-            &schnorr_commitment,
+            &announcement,
             &responses,
             &challenge,
         );
 
         assert!(is_valid);
     }
+
+    #[test]
+    fn test_randomize_just_g2() {
+        let mut rng = crate::test_rng();
+        let l = 4;
+        let y_values: Vec<Fr> = (0..l).map(|_| Fr::rand(&mut rng)).collect();
+        let ck: SymmetricCommitmentKey<Bls12_381> =
+            SymmetricCommitmentKey::new(&y_values, &mut rng);
+
+        let messages: Vec<Fr> = (0..l).map(|_| Fr::rand(&mut rng)).collect();
+        let r = Fr::rand(&mut rng);
+        let commitment = SymmetricCommitment::new(&ck, &messages, &r);
+
+        let r_delta = Fr::rand(&mut rng);
+        let randomized = commitment.randomize_just_g2(&r_delta);
+
+        // G1 side is untouched, G2 side commits to the same messages under r + r_delta
+        assert_eq!(randomized.cm, commitment.cm);
+        assert_eq!(randomized.r, r + r_delta);
+        assert_eq!(
+            randomized.cm_tilde(),
+            g2_commit::<Bls12_381>(&ck, &messages, &(r + r_delta))
+        );
+        assert_ne!(randomized.cm_tilde(), commitment.cm_tilde());
+    }
+
+    #[test]
+    fn test_randomize_rejects_zero_delta() {
+        let mut rng = crate::test_rng();
+        let y_values: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+        let ck: SymmetricCommitmentKey<Bls12_381> =
+            SymmetricCommitmentKey::new(&y_values, &mut rng);
+        let messages: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+        let commitment = SymmetricCommitment::new(&ck, &messages, &Fr::rand(&mut rng));
+
+        assert!(matches!(
+            commitment.randomize(&Fr::from(0u64)),
+            Err(CommitmentError::ZeroRandomizer)
+        ));
+        let randomized = commitment.randomize(&Fr::from(1u64)).unwrap();
+        assert_ne!(randomized.cm, commitment.cm);
+        assert_ne!(randomized.cm_tilde(), commitment.cm_tilde());
+    }
+
+    #[test]
+    fn test_try_new_rejects_too_many_messages() {
+        let mut rng = crate::test_rng();
+        let y_values: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&y_values, &mut rng);
+        let messages: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let r = Fr::rand(&mut rng);
+
+        assert!(matches!(
+            SymmetricCommitment::try_new(&ck, &messages, &r),
+            Err(CommitmentError::TooManyMessages {
+                messages: 3,
+                bases: 2
+            })
+        ));
+        assert!(matches!(
+            crate::credential::Credential::new(ck.clone(), Some(&messages), &mut rng),
+            Err(crate::errors::CredentialError::InvalidAttributes(
+                CommitmentError::TooManyMessages { .. }
+            ))
+        ));
+
+        let fits = SymmetricCommitment::try_new(&ck, &messages[..2], &r).unwrap();
+        assert_eq!(
+            fits.cm,
+            SymmetricCommitment::new(&ck, &messages[..2], &r).cm
+        );
+    }
+
+    #[test]
+    fn test_g1_only_commitment_fills_cm_tilde_lazily() {
+        let mut rng = crate::test_rng();
+        let y_values: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&y_values, &mut rng);
+        let messages: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let r = Fr::rand(&mut rng);
+        let eager = SymmetricCommitment::new(&ck, &messages, &r);
+        let lazy = SymmetricCommitment::new_g1_only(&ck, &messages, &r);
+
+        assert_eq!(lazy.cm, eager.cm);
+        assert!(lazy.cm_tilde.get().is_none());
+        // Serializing computes the G2 half, the bytes are those of the eager commitment
+        let encode = |cm: &SymmetricCommitment<Bls12_381>| {
+            let mut bytes = Vec::new();
+            cm.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        };
+        assert_eq!(encode(&lazy), encode(&eager));
+        assert_eq!(lazy.cm_tilde(), eager.cm_tilde());
+
+        // Credentials only compute cm_tilde once they are shown
+        let system = crate::system::System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let cm = credential.cm.as_ref().unwrap();
+        assert!(cm.cm_tilde.get().is_none());
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+        assert_eq!(
+            cm.cm_tilde.get(),
+            Some(&g2_commit::<Bls12_381>(
+                &system.vk.show_key(&system.ck),
+                &cm.messages,
+                &cm.r
+            ))
+        );
+    }
+
+    #[test]
+    fn test_key_from_supplied_generators() {
+        use ark_bls12_381::{Fq2, G1Affine, G2Affine};
+
+        let mut rng = crate::test_rng();
+        let y_values: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let g = G1Affine::generator();
+        let g_tilde = G2Affine::generator();
+        let encode = |ck: &SymmetricCommitmentKey<Bls12_381>| {
+            let mut bytes = Vec::new();
+            ck.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        };
+
+        // Two independent keygens over the same generators agree
+        let first = SymmetricCommitmentKey::<Bls12_381>::new_with_generators(g, g_tilde, &y_values)
+            .unwrap();
+        let second =
+            SymmetricCommitmentKey::<Bls12_381>::new_with_generators(g, g_tilde, &y_values)
+                .unwrap();
+        assert_eq!(encode(&first), encode(&second));
+        assert_eq!(first.ck[1], g.mul(y_values[1]).into_affine());
+        assert_eq!(first.neg_g(), second.neg_g());
+
+        assert!(matches!(
+            SymmetricCommitmentKey::<Bls12_381>::new_with_generators(
+                G1Affine::zero(),
+                g_tilde,
+                &y_values
+            ),
+            Err(MessageError::IdentityElement("g"))
+        ));
+        let off_subgroup = (1u64..)
+            .find_map(|x| {
+                G2Affine::get_point_from_x_unchecked(Fq2::from(x), false)
+                    .filter(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+            })
+            .unwrap();
+        assert!(matches!(
+            SymmetricCommitmentKey::<Bls12_381>::new_with_generators(g, off_subgroup, &y_values),
+            Err(MessageError::InvalidPoint("g_tilde"))
+        ));
+    }
+
+    #[test]
+    fn test_cached_negations() {
+        let mut rng = crate::test_rng();
+        let system = crate::system::System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(3)
+            .build(&mut rng)
+            .unwrap();
+        let ck = &system.ck;
+        assert_eq!(*ck.neg_g(), (-ck.g.into_group()).into_affine());
+        assert_eq!(*ck.neg_g_tilde(), (-ck.g_tilde.into_group()).into_affine());
+
+        // The cache isn't serialized, it is rebuilt on load
+        let mut bytes = Vec::new();
+        ck.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(
+            bytes.len(),
+            ck.g.compressed_size()
+                + ck.ck.compressed_size()
+                + ck.g_tilde.compressed_size()
+                + ck.ck_tilde.compressed_size()
+        );
+        let decoded =
+            SymmetricCommitmentKey::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(decoded.neg_g(), ck.neg_g());
+        assert_eq!(decoded.neg_g_tilde(), ck.neg_g_tilde());
+
+        // Issuance and verification go through the cached values
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        assert!(presentation.verify(&decoded, &system.vk, &mut rng).unwrap());
+    }
+
+    #[test]
+    fn test_joined_commitment_matches_serial() {
+        let mut rng = crate::test_rng();
+        let y_values: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+        let ck = SymmetricCommitmentKey::<Bls12_381>::new(&y_values, &mut rng);
+        let messages: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+        let r = Fr::rand(&mut rng);
+
+        // `new` computes both halves at once, the serial reference one at a time
+        let joined = SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &r);
+        assert_eq!(joined.cm, g1_commit::<Bls12_381>(&ck, &messages, &r));
+        assert_eq!(
+            joined.cm_tilde(),
+            g2_commit::<Bls12_381>(&ck, &messages, &r)
+        );
+        let again = SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &r);
+        assert_eq!((again.cm, again.cm_tilde()), (joined.cm, joined.cm_tilde()));
+
+        let r_delta = Fr::rand(&mut rng);
+        let randomized = joined.randomize(&r_delta).unwrap();
+        let reference = SymmetricCommitment::<Bls12_381>::new(&ck, &messages, &(r + r_delta));
+        assert_eq!(randomized.r, reference.r);
+        assert_eq!(randomized.cm, reference.cm);
+        assert_eq!(randomized.cm_tilde(), reference.cm_tilde());
+        // A lazy G2 half is filled in on the other side of the join
+        let lazy = SymmetricCommitment::<Bls12_381>::new_g1_only(&ck, &messages, &r);
+        assert_eq!(
+            lazy.randomize(&r_delta).unwrap().cm_tilde(),
+            reference.cm_tilde()
+        );
+    }
 }
@@ -0,0 +1,363 @@
+//! A threshold credential system owning its keys, with handles for each role.
+//!
+//! ```ignore
+//! let system = System::<Bls12_381>::builder()
+//!     .threshold(3)
+//!     .signers(5)
+//!     .attributes(16)
+//!     .build(&mut rng)?;
+//! let credential = system.user().obtain_credential(None, &mut rng)?;
+//! let presentation = system.user().show(&credential, &mut rng)?;
+//! assert!(system.verifier().verify(&presentation, &mut rng)?);
+//! ```
+use crate::attributes::Attributes;
+use crate::credential::{Credential, CredentialCommitments};
+use crate::errors::{CredentialError, ProtocolError, SignatureError};
+use crate::keygen::{
+    keygen, ThresholdKeys, ThresholdParams, VerificationKey, VerificationKeyShare,
+};
+use crate::presentation::Presentation;
+use crate::protocol::{UserProtocol, VerifierProtocol};
+use crate::signature::PartialSignature;
+use crate::signer::Signer;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::marker::PhantomData;
+use ark_std::rand::{CryptoRng, Rng};
+
+/// Parameters for a new `System`, created with `System::builder`
+#[derive(Clone, Debug)]
+pub struct SystemBuilder<E: Pairing> {
+    threshold: usize,
+    signers: usize,
+    attributes: usize,
+    _pairing: PhantomData<E>,
+}
+
+impl<E: Pairing> SystemBuilder<E> {
+    /// Number of signature shares needed to issue a credential
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Number of signers holding a key share
+    pub fn signers(mut self, signers: usize) -> Self {
+        self.signers = signers;
+        self
+    }
+
+    /// Number of attributes in each credential
+    pub fn attributes(mut self, attributes: usize) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Run the dealer keygen and return the resulting system
+    pub fn build(self, rng: &mut (impl Rng + CryptoRng)) -> Result<System<E>, ProtocolError> {
+        check_parameters(self.threshold, self.signers, self.attributes)?;
+        let (ck, vk, keys) = keygen(self.threshold, self.signers, self.attributes, rng);
+        Ok(System { ck, vk, keys })
+    }
+}
+
+/// Public parameters and the dealer's key shares of one threshold credential system
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct System<E: Pairing> {
+    pub ck: SymmetricCommitmentKey<E>,
+    pub vk: VerificationKey<E>,
+    pub keys: ThresholdKeys<E>,
+}
+
+impl<E: Pairing> System<E> {
+    pub fn builder() -> SystemBuilder<E> {
+        SystemBuilder {
+            threshold: 0,
+            signers: 0,
+            attributes: 0,
+            _pairing: PhantomData,
+        }
+    }
+
+    /// Assemble a system from existing key material, checking that the shares fit together
+    pub fn from_key_material(
+        ck: SymmetricCommitmentKey<E>,
+        vk: VerificationKey<E>,
+        keys: ThresholdKeys<E>,
+    ) -> Result<Self, ProtocolError> {
+        check_parameters(keys.t, keys.n, keys.l)?;
+        let consistent = keys.sk_shares.len() == keys.n
+            && keys.vk_shares.len() == keys.n
+            && ck.num_attributes() == keys.l
+            && ck.ck_tilde.len() == keys.l
+            && vk.g_y.len() == keys.l
+            && vk.g_tilde_y.len() == keys.l
+            && vk.params.is_none_or(|params| {
+                params
+                    == ThresholdParams {
+                        t: keys.t,
+                        n: keys.n,
+                    }
+            })
+            && keys
+                .sk_shares
+                .iter()
+                .zip(keys.vk_shares.iter())
+                .all(|(sk, vk)| {
+                    sk.index == vk.index
+                        && sk.index != 0
+                        && sk.y_shares.len() == keys.l
+                        && vk.g_tilde_y_shares.len() == keys.l
+                });
+        if !consistent {
+            return Err(ProtocolError::InvalidParameters(
+                "key shares don't match the system parameters".to_string(),
+            ));
+        }
+        Ok(Self { ck, vk, keys })
+    }
+
+    /// Load a system serialized with `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let System { ck, vk, keys } = Self::deserialize_compressed(bytes)?;
+        Self::from_key_material(ck, vk, keys)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProtocolError> {
+        let mut bytes = Vec::with_capacity(self.compressed_size());
+        self.serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.keys.threshold()
+    }
+
+    pub fn vk_shares(&self) -> &[VerificationKeyShare<E>] {
+        &self.keys.vk_shares
+    }
+
+    /// The signer holding the `i`-th key share, counting from 0
+    pub fn signer(&self, i: usize) -> Option<Signer<'_, E>> {
+        let sk_share = self.keys.sk_shares.get(i)?;
+        let vk_share = self.keys.vk_shares.get(i)?;
+        Some(Signer::new(&self.ck, sk_share, vk_share))
+    }
+
+    /// All signers, in key share order
+    pub fn signers(&self) -> Vec<Signer<'_, E>> {
+        (0..self.keys.participants())
+            .filter_map(|i| self.signer(i))
+            .collect()
+    }
+
+    pub fn user(&self) -> UserHandle<'_, E> {
+        UserHandle { system: self }
+    }
+
+    pub fn verifier(&self) -> VerifierHandle<'_, E> {
+        VerifierHandle { system: self }
+    }
+}
+
+fn check_parameters(
+    threshold: usize,
+    signers: usize,
+    attributes: usize,
+) -> Result<(), ProtocolError> {
+    if threshold == 0 || threshold > signers {
+        return Err(ProtocolError::InvalidParameters(format!(
+            "threshold must be between 1 and {signers}, got {threshold}"
+        )));
+    }
+    if attributes == 0 {
+        return Err(ProtocolError::InvalidParameters(
+            "credentials need at least one attribute".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Runs the holder's side of the protocol against a `System`
+pub struct UserHandle<'a, E: Pairing> {
+    system: &'a System<E>,
+}
+
+impl<'a, E: Pairing> UserHandle<'a, E> {
+    pub fn request_credential(
+        &self,
+        attributes: Option<&[E::ScalarField]>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Credential<E>, CredentialCommitments<E>), CredentialError> {
+        UserProtocol::request_credential(self.system.ck.clone(), attributes, rng)
+    }
+
+    /// `request_credential` for attributes built with `Attributes::new(values, &system.ck)`
+    pub fn request_credential_with_attributes(
+        &self,
+        attributes: Attributes<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(Credential<E>, CredentialCommitments<E>), CredentialError> {
+        UserProtocol::request_credential_with_attributes(self.system.ck.clone(), attributes, rng)
+    }
+
+    /// Collect `threshold` shares from the system's signers
+    pub fn collect_signature_shares(
+        &self,
+        request: &CredentialCommitments<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Vec<(usize, PartialSignature<E>)>, SignatureError> {
+        UserProtocol::collect_signature_shares(
+            &self.system.signers(),
+            request,
+            self.system.threshold(),
+            rng,
+        )
+    }
+
+    /// Verify `shares`, aggregate them and attach the signature to `credential`
+    pub fn complete_credential(
+        &self,
+        credential: &mut Credential<E>,
+        request: &CredentialCommitments<E>,
+        shares: &[(usize, PartialSignature<E>)],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(), SignatureError> {
+        let system = self.system;
+        let verified = UserProtocol::verify_signature_shares(
+            &system.ck,
+            system.vk_shares(),
+            request,
+            shares,
+            system.threshold(),
+            rng,
+        )?;
+        let signature = UserProtocol::aggregate_shares(
+            &system.vk,
+            &verified,
+            credential.get_blinding_factors(),
+            system.threshold(),
+            &request.h,
+        )?;
+        credential.attach_signature(signature, &system.vk);
+        Ok(())
+    }
+
+    /// Request, collect, verify and aggregate in one go
+    pub fn obtain_credential(
+        &self,
+        attributes: Option<&[E::ScalarField]>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Credential<E>, ProtocolError> {
+        let (mut credential, request) = self.request_credential(attributes, rng)?;
+        let shares = self.collect_signature_shares(&request, rng)?;
+        self.complete_credential(&mut credential, &request, &shares, rng)?;
+        Ok(credential)
+    }
+
+    pub fn show(
+        &self,
+        credential: &Credential<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Presentation<E>, CredentialError> {
+        UserProtocol::show(credential, rng)
+    }
+}
+
+/// Verifies presentations against a `System`'s public keys
+pub struct VerifierHandle<'a, E: Pairing> {
+    system: &'a System<E>,
+}
+
+impl<'a, E: Pairing> VerifierHandle<'a, E> {
+    pub fn verify(
+        &self,
+        presentation: &Presentation<E>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<bool, SignatureError> {
+        VerifierProtocol::verify(&self.system.ck, &self.system.vk, presentation, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng;
+    use ark_bls12_381::Bls12_381;
+
+    #[test]
+    fn test_builder_rejects_bad_parameters() {
+        let mut rng = test_rng();
+        let too_high = System::<Bls12_381>::builder()
+            .threshold(4)
+            .signers(3)
+            .attributes(2)
+            .build(&mut rng);
+        assert!(matches!(too_high, Err(ProtocolError::InvalidParameters(_))));
+
+        let no_attributes = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .build(&mut rng);
+        assert!(matches!(
+            no_attributes,
+            Err(ProtocolError::InvalidParameters(_))
+        ));
+    }
+
+    #[test]
+    fn test_system_serialization_round_trip() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(2)
+            .build(&mut rng)
+            .unwrap();
+
+        let loaded = System::<Bls12_381>::from_bytes(&system.to_bytes().unwrap()).unwrap();
+        let credential = loaded.user().obtain_credential(None, &mut rng).unwrap();
+        let presentation = loaded.user().show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // Dropping a signer's share no longer matches n
+        let mut keys = system.keys.clone();
+        keys.sk_shares.pop();
+        assert!(matches!(
+            System::from_key_material(system.ck.clone(), system.vk.clone(), keys),
+            Err(ProtocolError::InvalidParameters(_))
+        ));
+    }
+
+    #[test]
+    fn test_key_parameters() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(2)
+            .signers(3)
+            .attributes(4)
+            .build(&mut rng)
+            .unwrap();
+        assert_eq!(system.ck.num_attributes(), 4);
+        assert_eq!(system.vk.num_attributes(), 4);
+        assert!(system.vk.has_params(ThresholdParams { t: 2, n: 3 }));
+
+        // A verifier expecting another committee notices
+        assert!(!system.vk.has_params(ThresholdParams { t: 3, n: 5 }));
+        let mut unlabelled = system.vk.clone();
+        unlabelled.params = None;
+        assert!(!unlabelled.has_params(ThresholdParams { t: 2, n: 3 }));
+
+        // And a key claiming other parameters than its shares is rejected
+        let mut vk = system.vk.clone();
+        vk.params = Some(ThresholdParams { t: 3, n: 3 });
+        assert!(matches!(
+            System::from_key_material(system.ck.clone(), vk, system.keys.clone()),
+            Err(ProtocolError::InvalidParameters(_))
+        ));
+        assert!(
+            System::from_key_material(system.ck.clone(), unlabelled, system.keys.clone()).is_ok()
+        );
+    }
+}
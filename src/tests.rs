@@ -1,110 +1,1274 @@
+use crate::test_rng;
 use crate::{
-    commitment::Commitment,
+    attributes::Attributes,
+    commitment::{key_attribute, CommitmentProof, ConsistencyProof, KeyBindingProof},
     credential::Credential,
-    credential::CredentialCommitments,
-    errors::SignatureError,
-    keygen::keygen,
-    keygen::{SecretKeyShare, ThresholdKeys, VerificationKey, VerificationKeyShare},
+    errors::{CommitmentError, CredentialError, MessageError, SignatureError},
+    keygen::{keygen, keygen_under_crs, keygen_weighted, reconstruct_full_key, setup_crs},
     protocol::{IssuerProtocol, UserProtocol, VerifierProtocol},
-    shamir::reconstruct_secret,
-    signature::{PartialSignature, ThresholdSignature},
-    signer::Signer,
-    symmetric_commitment::SymmetricCommitmentKey,
+    signature::{
+        expected_aggregate_sigma, select_subset, sign_centralized, AggregatePublicKey,
+        SubsetPolicy, ThresholdSignature,
+    },
+    signer::{verify_request_proofs, Signer, WeightedSigner},
+    symmetric_commitment::SymmetricCommitment,
+    system::System,
+    transcript::DomainSeparator,
+    user::{SignerFailure, User},
 };
-use ark_bls12_381::{Bls12_381, Fr};
-use ark_ec::pairing::Pairing;
-use ark_ec::CurveGroup;
+use ark_bls12_381::{Bls12_381, Fr, G1Affine};
+use ark_ec::{AffineRepr, CurveGroup};
 use ark_ff::UniformRand;
-use ark_std::rand::Rng;
-use ark_std::test_rng;
-use std::ops::{Add, Mul, Neg};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::Zero;
 
 // Constants for tests
 const THRESHOLD: usize = 2;
 const N_PARTICIPANTS: usize = 5;
 const L_ATTRIBUTES: usize = 3;
 
-#[cfg(test)]
-mod tests {
+fn test_system() -> System<Bls12_381> {
+    System::builder()
+        .threshold(THRESHOLD)
+        .signers(N_PARTICIPANTS)
+        .attributes(L_ATTRIBUTES)
+        .build(&mut test_rng())
+        .expect("Failed to set up system")
+}
+
+mod flow_tests {
     use super::*;
-    use crate::symmetric_commitment;
 
     #[test]
     fn test_complete_credential_flow() {
         let mut rng = test_rng();
 
-        // 1. SETUP: Generate system parameters and keys
-        let (ck, vk, ts_keys) =
-            keygen::<Bls12_381>(THRESHOLD, N_PARTICIPANTS, L_ATTRIBUTES, &mut rng);
-
-        // Create signers from key shares
-        // Create signers
-        let signers: Vec<_> = ts_keys
-            .sk_shares
-            .iter()
-            .zip(ts_keys.vk_shares.iter())
-            .map(|(sk_share, vk_share)| Signer::new(&ck, sk_share, vk_share))
-            .collect();
+        // 1. SETUP: Generate system parameters, keys and signers
+        let system = test_system();
+        let user = system.user();
 
         // 2. USER: Create credential with random attributes
-        let attributes: Vec<Fr> = (0..L_ATTRIBUTES).map(|_| Fr::rand(&mut rng)).collect();
-        let mut credential = Credential::new(ck.clone(), Some(&attributes), &mut rng);
+        let attributes = Attributes::random(&system.ck, &mut rng);
 
         // Generate commitments for each attribute
-        let (mut credential, credential_request) =
-            UserProtocol::request_credential(ck.clone(), Some(&attributes), &mut rng)
-                .expect("Failed to create credential request");
+        let (mut credential, credential_request) = user
+            .request_credential_with_attributes(attributes, &mut rng)
+            .expect("Failed to create credential request");
 
         // 3. ISSUERS: Each issuer signs the credential request
-        let signature_shares = UserProtocol::collect_signature_shares(
-            &signers,
+        let signature_shares = user
+            .collect_signature_shares(&credential_request, &mut rng)
+            .expect("Failed to collect signature shares");
+
+        // 4-6. USER: Verify the signature shares, aggregate them and attach the signature
+        user.complete_credential(
+            &mut credential,
             &credential_request,
-            THRESHOLD,
+            &signature_shares,
             &mut rng,
         )
-        .expect("Failed to collect signature shares");
+        .expect("Failed to complete credential");
 
-        // 4. USER: Verify the signature shares before aggregation
-        let verified_shares = UserProtocol::verify_signature_shares(
-            &ck,
-            &ts_keys.vk_shares,
+        // 7. USER: Generate a credential presentation (zero-knowledge proof)
+        let presentation = user
+            .show(&credential, &mut rng)
+            .expect("Failed to generate credential presentation");
+
+        // 8. VERIFIER: Verify the credential presentation
+        let is_valid = system
+            .verifier()
+            .verify(&presentation, &mut rng)
+            .expect("Verification failed");
+
+        assert!(is_valid, "Credential verification should succeed");
+    }
+
+    #[test]
+    fn test_share_with_different_h_rejected() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let user = system.user();
+
+        let (credential, credential_request) = user
+            .request_credential(None, &mut rng)
+            .expect("Failed to create credential request");
+        let mut signature_shares = user
+            .collect_signature_shares(&credential_request, &mut rng)
+            .expect("Failed to collect signature shares");
+
+        // One signer signs against a different h
+        let other_h = G1Affine::rand(&mut rng);
+        let bad_share = system
+            .signer(1)
+            .unwrap()
+            .sign_share(
+                &credential_request.commitments,
+                &credential_request.proofs,
+                &other_h,
+                &mut rng,
+            )
+            .expect("Signing should succeed");
+        signature_shares[1] = (bad_share.party_index, bad_share);
+
+        let verify_result = UserProtocol::verify_signature_shares(
+            &system.ck,
+            system.vk_shares(),
             &credential_request,
             &signature_shares,
             THRESHOLD,
-        )
-        .expect("Failed to verify signature shares");
+            &mut rng,
+        );
+        assert!(matches!(
+            verify_result,
+            Err(SignatureError::CommitmentConsistencyFailed)
+        ));
 
-        // 5. USER: Aggregate verified signature shares
-        let blindings = credential.get_blinding_factors();
-        let threshold_signature = UserProtocol::aggregate_shares(
-            &ck,
-            &verified_shares,
-            &blindings,
+        let aggregate_result = UserProtocol::aggregate_shares(
+            &system.vk,
+            &signature_shares,
+            credential.get_blinding_factors(),
             THRESHOLD,
             &credential_request.h,
+        );
+        assert!(matches!(
+            aggregate_result,
+            Err(SignatureError::CommitmentConsistencyFailed)
+        ));
+    }
+
+    #[test]
+    fn test_sign_share_rejects_identity_h() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let (_, credential_request) = system
+            .user()
+            .request_credential(None, &mut rng)
+            .expect("Failed to create credential request");
+
+        let result = system.signer(1).unwrap().sign_share(
+            &credential_request.commitments,
+            &credential_request.proofs,
+            &G1Affine::zero(),
+            &mut rng,
+        );
+        assert!(matches!(result, Err(SignatureError::IdentityH)));
+    }
+
+    #[test]
+    fn test_sign_share_rejects_reordered_proofs() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let (_, mut request) = system
+            .user()
+            .request_credential(None, &mut rng)
+            .expect("Failed to create credential request");
+
+        // Every proof still verifies, but proofs[0] is now for commitments[1]
+        request.proofs.swap(0, 1);
+        let result = system.signer(1).unwrap().sign_share(
+            &request.commitments,
+            &request.proofs,
+            &request.h,
+            &mut rng,
+        );
+        assert!(matches!(
+            result,
+            Err(SignatureError::InvalidMessage(
+                MessageError::ProofCommitmentMismatch(0)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_sign_with_pre_verified_proofs() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let user = system.user();
+        let (mut credential, request) = user.request_credential(None, &mut rng).unwrap();
+
+        // Verified once by the coordinator, then every signer skips the proofs
+        verify_request_proofs(&request, &mut rng).unwrap();
+        let shares: Vec<_> = system
+            .signers()
+            .iter()
+            .map(|signer| {
+                let share = signer.sign_request(&request, true, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        user.complete_credential(&mut credential, &request, &shares, &mut rng)
+            .unwrap();
+        let presentation = user.show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // Skipping the proofs still checks that each one is for its commitment
+        let mut reordered = request.clone();
+        reordered.proofs.swap(0, 1);
+        assert!(verify_request_proofs(&reordered, &mut rng).is_err());
+        // A proof that doesn't verify fails the same way as at the signers
+        let mut proof =
+            CommitmentProof::<Bls12_381>::deserialize_compressed(&request.proofs[1][..]).unwrap();
+        proof.responses[0] += Fr::from(1u64);
+        let mut tampered = request.clone();
+        tampered.proofs[1].clear();
+        proof.serialize_compressed(&mut tampered.proofs[1]).unwrap();
+        assert!(matches!(
+            verify_request_proofs(&tampered, &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::BatchVerifyError
+            ))
+        ));
+        assert!(matches!(
+            system
+                .signer(1)
+                .unwrap()
+                .sign_request(&reordered, true, &mut rng),
+            Err(SignatureError::InvalidMessage(
+                MessageError::ProofCommitmentMismatch(0)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_identify_cheaters() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let (mut credential, request) = system.user().request_credential(None, &mut rng).unwrap();
+
+        // Signer 2 signs with a corrupted key share and nobody checks the shares
+        let mut bad_sk_share = system.keys.sk_shares[2].clone();
+        bad_sk_share.x_share += Fr::from(1u64);
+        let cheater = Signer::new(&system.ck, &bad_sk_share, &system.keys.vk_shares[2]);
+        let shares: Vec<_> = [system.signer(0).unwrap(), cheater]
+            .iter()
+            .map(|signer| {
+                let share = signer
+                    .sign_share_no_zkp_verify(
+                        &request.commitments,
+                        &request.proofs,
+                        &request.h,
+                        &mut rng,
+                    )
+                    .unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        let signature = UserProtocol::aggregate_shares(
+            &system.vk,
+            &shares,
+            credential.get_blinding_factors(),
+            THRESHOLD,
+            &request.h,
         )
-        .expect("Failed to aggregate signature shares");
+        .unwrap();
+        credential.attach_signature(signature, &system.vk);
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        assert!(!matches!(
+            system.verifier().verify(&presentation, &mut rng),
+            Ok(true)
+        ));
 
-        // 6. USER: Attach signature to credential
-        credential.attach_signature(threshold_signature);
+        let cheaters = User::identify_cheaters(
+            &system.ck,
+            system.vk_shares(),
+            &request.commitments,
+            &shares,
+        );
+        assert_eq!(cheaters, vec![shares[1].0]);
+    }
 
-        // 7. USER: Generate a credential presentation (zero-knowledge proof)
-        let (randomized_sig, commitment, commitment_tilde, proof) =
-            UserProtocol::show(&credential, &mut rng)
-                .expect("Failed to generate credential presentation");
+    #[test]
+    fn test_process_shares_reports_failed_signers() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let (_, request) = system.user().request_credential(None, &mut rng).unwrap();
+        let mut shares: Vec<_> = system
+            .signers()
+            .iter()
+            .map(|signer| {
+                let share = signer.sign_request(&request, false, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        // Signers 2 and 4 return shares that don't verify
+        shares[1].1.sigma = shares[0].1.sigma;
+        shares[3].1.sigma = shares[0].1.sigma;
+        let process = |vk_shares: &[_], shares: &[_], rng: &mut _| {
+            User::process_signature_shares(
+                &system.ck,
+                vk_shares,
+                &request.commitments,
+                &request.proofs,
+                shares,
+                THRESHOLD,
+                rng,
+            )
+        };
 
-        // 8. VERIFIER: Verify the credential presentation
-        let is_valid = VerifierProtocol::verify(
+        let verified = process(system.vk_shares(), &shares, &mut rng).unwrap();
+        assert_eq!(verified.failed(), vec![2, 4]);
+        let valid: Vec<usize> = verified.valid.iter().map(|(i, _)| *i).collect();
+        assert_eq!(valid, vec![1, 3, 5]);
+
+        // The same outcome with key shares derived from the keygen commitments
+        let derived = User::process_signature_shares_with_transcript(
+            &system.ck,
+            &system.keys.transcript,
+            &request.commitments,
+            &request.proofs,
+            &shares,
+            THRESHOLD,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(derived.failed(), vec![2, 4]);
+        assert_eq!(derived.valid.len(), 3);
+
+        // Without the key share of signer 3
+        let mut missing = system.vk_shares().to_vec();
+        missing.remove(2);
+        assert!(matches!(
+            process(&missing, &shares, &mut rng),
+            Err(SignatureError::MissingVerificationKeyShare(3))
+        ));
+        // With the key share of signer 1 twice
+        let mut duplicated = system.vk_shares().to_vec();
+        duplicated.push(duplicated[0].clone());
+        assert!(matches!(
+            process(&duplicated, &shares, &mut rng),
+            Err(SignatureError::DuplicateVerificationKeyShare(1))
+        ));
+        // Signer 1's share listed under index 3 would be checked against signer 3's key
+        // but interpolated at 1
+        let mut relabeled = shares.clone();
+        relabeled[2].1 = shares[0].1.clone();
+        assert!(matches!(
+            process(system.vk_shares(), &relabeled, &mut rng),
+            Err(SignatureError::PartyIndexMismatch {
+                index: 3,
+                party_index: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_collect_with_fallback_signers() {
+        let mut rng = test_rng();
+        let system = System::<Bls12_381>::builder()
+            .threshold(3)
+            .signers(5)
+            .attributes(L_ATTRIBUTES)
+            .build(&mut rng)
+            .unwrap();
+        let (mut credential, request) = system.user().request_credential(None, &mut rng).unwrap();
+
+        // Signer 2 never answers, a request it refuses stands in for the dead link.
+        // Signer 3 signs with a corrupted key share
+        let mut bad_sk_share = system.keys.sk_shares[2].clone();
+        bad_sk_share.x_share += Fr::from(1u64);
+        let mut signers = system.signers();
+        signers[1] = system.signer(1).unwrap().require_key_binding(true);
+        signers[2] = Signer::new(&system.ck, &bad_sk_share, &system.keys.vk_shares[2]);
+
+        let collected = UserProtocol::collect_signature_shares_with_fallback(
+            &signers, &request, 3, 5, &mut rng,
+        )
+        .unwrap();
+        let indices: Vec<usize> = collected.shares.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, vec![1, 4, 5]);
+        assert_eq!(collected.failures.len(), 2);
+        assert!(matches!(
+            collected.failures[0],
+            (2, SignerFailure::NoShare(SignatureError::MissingKeyBinding))
+        ));
+        assert!(matches!(
+            collected.failures[1],
+            (3, SignerFailure::InvalidShare)
+        ));
+
+        system
+            .user()
+            .complete_credential(&mut credential, &request, &collected.shares, &mut rng)
+            .unwrap();
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // Four attempts leave only two valid shares
+        assert!(matches!(
+            UserProtocol::collect_signature_shares_with_fallback(
+                &signers, &request, 3, 4, &mut rng
+            ),
+            Err(SignatureError::InsufficientShares { needed: 3, got: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_aggregate_reports_contributing_signers() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let (mut credential, request) = system.user().request_credential(None, &mut rng).unwrap();
+        let shares: Vec<_> = [3, 3, 1, 4]
+            .iter()
+            .map(|&i| {
+                let share = system
+                    .signer(i)
+                    .unwrap()
+                    .sign_request(&request, false, &mut rng)
+                    .unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+
+        // Signer 4 shows up twice, the second copy is skipped
+        let (signature, indices) = UserProtocol::aggregate_shares_detailed(
+            &system.vk,
+            &shares,
+            credential.get_blinding_factors(),
+            THRESHOLD,
+            &request.h,
+        )
+        .unwrap();
+        assert_eq!(indices, vec![4, 2]);
+        credential.attach_signature(signature, &system.vk);
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        assert!(matches!(
+            UserProtocol::aggregate_shares_detailed(
+                &system.vk,
+                &shares[..2],
+                credential.get_blinding_factors(),
+                THRESHOLD,
+                &request.h,
+            ),
+            Err(SignatureError::InsufficientShares { needed: 2, got: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_weighted_threshold() {
+        let mut rng = test_rng();
+        // Signer 0 carries two points, three points are needed
+        let (ck, vk, keys) = keygen_weighted::<Bls12_381>(&[2, 1, 1], 3, L_ATTRIBUTES, &mut rng);
+        assert_eq!(keys.vk_share(0).unwrap().indices(), vec![1, 2]);
+        assert_eq!(keys.vk_share(2).unwrap().indices(), vec![4]);
+        assert!(keys.vk_share(3).is_none());
+
+        let (mut credential, request) =
+            UserProtocol::request_credential(ck.clone(), None, &mut rng).unwrap();
+        let sign = |s: usize, rng: &mut _| {
+            let signer = WeightedSigner::new(&ck, &keys, s).unwrap();
+            (s, signer.sign_request(&request, false, rng).unwrap())
+        };
+        let heavy_and_light = [sign(0, &mut rng), sign(2, &mut rng)];
+        assert_eq!(heavy_and_light[0].1.len(), 2);
+
+        // Every sub-share verifies under its own point's key share
+        let flattened: Vec<_> = heavy_and_light
+            .iter()
+            .flat_map(|(_, shares)| shares)
+            .map(|share| (share.party_index, share.clone()))
+            .collect();
+        let verified = User::process_signature_shares(
             &ck,
+            &keys.keys.vk_shares,
+            &request.commitments,
+            &request.proofs,
+            &flattened,
+            3,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(verified.failed().is_empty());
+
+        let signature = ThresholdSignature::aggregate_weighted_shares(
             &vk,
-            &commitment,
-            &commitment_tilde,
-            &randomized_sig,
-            &proof,
+            &heavy_and_light,
+            credential.get_blinding_factors(),
+            3,
+            &request.h,
         )
-        .expect("Verification failed");
+        .unwrap();
+        credential.attach_signature(signature, &vk);
+        let presentation = UserProtocol::show(&credential, &mut rng).unwrap();
+        assert!(VerifierProtocol::verify(&ck, &vk, &presentation, &mut rng).unwrap());
 
-        assert!(is_valid, "Credential verification should succeed");
+        // The two light signers hold only two points
+        let light = [sign(1, &mut rng), sign(2, &mut rng)];
+        assert!(matches!(
+            ThresholdSignature::aggregate_weighted_shares(
+                &vk,
+                &light,
+                credential.get_blinding_factors(),
+                3,
+                &request.h,
+            ),
+            Err(SignatureError::InsufficientShares { needed: 3, got: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_consistency_proof() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let mut credential = Credential::new(system.ck.clone(), None, &mut rng).unwrap();
+        let request = credential
+            .compute_commitments_with_consistency(&mut rng)
+            .unwrap();
+        let signers: Vec<_> = system
+            .signers()
+            .into_iter()
+            .map(|signer| signer.require_consistency(true))
+            .collect();
+
+        // Honest request: every signer checks the proof and the credential verifies
+        let shares: Vec<_> = signers
+            .iter()
+            .map(|signer| {
+                let share = signer.sign_request(&request, false, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        let user = system.user();
+        user.complete_credential(&mut credential, &request, &shares, &mut rng)
+            .unwrap();
+        let presentation = user.show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // A symmetric commitment to other messages than the per-attribute commitments
+        let other: Vec<Fr> = (0..L_ATTRIBUTES).map(|_| Fr::rand(&mut rng)).collect();
+        let r = Fr::rand(&mut rng);
+        let other_cm = SymmetricCommitment::new(&system.ck, &other, &r);
+        let mut forged = request.clone();
+        forged.consistency = Some(
+            ConsistencyProof::prove(
+                credential.domain(),
+                &request.h,
+                &system.ck,
+                &request.commitments,
+                &other,
+                &credential.blindings,
+                &other_cm.cm,
+                &r,
+                &mut rng,
+            )
+            .unwrap(),
+        );
+        assert!(matches!(
+            signers[0].sign_request(&forged, false, &mut rng),
+            Err(SignatureError::CommitmentConsistencyFailed)
+        ));
+
+        // The policy also rejects requests without a proof
+        let mut missing = request.clone();
+        missing.consistency = None;
+        assert!(matches!(
+            signers[0].sign_request(&missing, false, &mut rng),
+            Err(SignatureError::MissingConsistencyProof)
+        ));
+        assert!(system
+            .signer(0)
+            .unwrap()
+            .sign_request(&missing, false, &mut rng)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_blinding_first_base_order() {
+        use crate::policy::PresentationPolicy;
+        use crate::symmetric_commitment::BaseOrder;
+
+        let mut rng = test_rng();
+        let mut system = test_system();
+        let blinding_last = system.ck.clone();
+        system.ck = system.ck.clone().with_base_order(BaseOrder::BlindingFirst);
+        let (bases, bases_tilde) = system.ck.get_bases();
+        assert_eq!((bases[0], bases_tilde[0]), (system.ck.g, system.ck.g_tilde));
+        assert_eq!(bases[1..], blinding_last.get_bases().0[..L_ATTRIBUTES]);
+
+        // Issuance with a consistency proof, showing and a policy all use the order
+        let mut credential = Credential::new(system.ck.clone(), None, &mut rng).unwrap();
+        let request = credential
+            .compute_commitments_with_consistency(&mut rng)
+            .unwrap();
+        let signer = system.signer(0).unwrap().require_consistency(true);
+        let shares: Vec<_> = std::iter::once(signer)
+            .chain(system.signers().into_iter().skip(1))
+            .take(THRESHOLD)
+            .map(|signer| {
+                let share = signer.sign_request(&request, false, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        let user = system.user();
+        user.complete_credential(&mut credential, &request, &shares, &mut rng)
+            .unwrap();
+        let presentation = user.show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+        let policy =
+            PresentationPolicy::new(credential.domain().clone(), [7u8; 32]).with_disclosure(1);
+        let answer = credential.present(&policy, &mut rng).unwrap();
+        assert_eq!(answer.disclosed, vec![(1, credential.get_messages()[1])]);
+        policy
+            .verify(&system.ck, &system.vk, &answer, 0, &mut rng)
+            .unwrap();
+
+        // Proofs don't verify under the other order, in either direction
+        assert!(!matches!(
+            presentation.verify(&blinding_last, &system.vk, &mut rng),
+            Ok(true)
+        ));
+        assert!(policy
+            .verify(&blinding_last, &system.vk, &answer, 0, &mut rng)
+            .is_err());
+        let other = test_system();
+        let last_credential = other.user().obtain_credential(None, &mut rng).unwrap();
+        let last_presentation = other.user().show(&last_credential, &mut rng).unwrap();
+        let first_key = other.ck.clone().with_base_order(BaseOrder::BlindingFirst);
+        assert!(!matches!(
+            last_presentation.verify(&first_key, &other.vk, &mut rng),
+            Ok(true)
+        ));
+    }
+
+    #[test]
+    fn test_key_binding() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let signers: Vec<_> = system
+            .signers()
+            .into_iter()
+            .map(|signer| signer.require_key_binding(true))
+            .collect();
+        let sk = Fr::rand(&mut rng);
+
+        // Honest request: attribute 0 is the hash of the holder's key
+        let (mut credential, request) =
+            UserProtocol::request_credential_bound_to_key(system.ck.clone(), &sk, None, &mut rng)
+                .unwrap();
+        let pk = (system.ck.g * sk).into_affine();
+        assert_eq!(
+            credential.get_messages()[0],
+            key_attribute::<Bls12_381>(credential.domain(), &pk)
+        );
+        let shares: Vec<_> = signers
+            .iter()
+            .take(THRESHOLD)
+            .map(|signer| {
+                let share = signer.sign_request(&request, false, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        let user = system.user();
+        user.complete_credential(&mut credential, &request, &shares, &mut rng)
+            .unwrap();
+        let presentation = user.show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // cm_0 commits to another value than the key attribute
+        let mut other = Credential::new(system.ck.clone(), None, &mut rng).unwrap();
+        assert!(other
+            .compute_commitments_with_key_binding(&sk, &mut rng)
+            .is_err());
+        let mut forged = other.compute_commitments_per_m(&mut rng).unwrap();
+        forged.key_binding = Some(
+            KeyBindingProof::prove(
+                other.domain(),
+                &forged.h,
+                &system.ck.g,
+                &forged.commitments,
+                &sk,
+                &other.blindings[0],
+                &mut rng,
+            )
+            .unwrap(),
+        );
+        assert!(matches!(
+            signers[0].sign_request(&forged, false, &mut rng),
+            Err(SignatureError::KeyBindingFailed)
+        ));
+
+        // Without the proof of knowledge of sk the policy rejects the request
+        let mut missing = request.clone();
+        missing.key_binding = None;
+        assert!(matches!(
+            signers[0].sign_request(&missing, false, &mut rng),
+            Err(SignatureError::MissingKeyBinding)
+        ));
+        assert!(matches!(
+            signers[0].sign_share(&request.commitments, &request.proofs, &request.h, &mut rng),
+            Err(SignatureError::MissingKeyBinding)
+        ));
+    }
+
+    #[test]
+    fn test_reissue_preserves_hidden_attributes() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let user = system.user();
+        let old = user.obtain_credential(None, &mut rng).unwrap();
+        let domain = old.domain().clone();
+
+        // Re-issue with attribute 1 changed, the others carried over
+        let value = Fr::rand(&mut rng);
+        let (mut credential, reissue) =
+            UserProtocol::request_reissue(&old, &[(1, value)], &mut rng).unwrap();
+        assert_eq!(reissue.carried().unwrap(), vec![0, 2]);
+        assert!(IssuerProtocol::verify_reissue_request(
+            &system.ck, &system.vk, &system.ck, &domain, &reissue, &mut rng
+        )
+        .unwrap());
+        let shares = user
+            .collect_signature_shares(&reissue.request, &mut rng)
+            .unwrap();
+        user.complete_credential(&mut credential, &reissue.request, &shares, &mut rng)
+            .unwrap();
+        let presentation = user.show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+        let (old_messages, new_messages) = (old.get_messages(), credential.get_messages());
+        assert_eq!(new_messages[1], value);
+        assert_eq!(
+            (new_messages[0], new_messages[2]),
+            (old_messages[0], old_messages[2])
+        );
+
+        // Claiming the changed attribute as carried over is a lie the proof can't back
+        let (_, mut lying) = UserProtocol::request_reissue(&old, &[(1, value)], &mut rng).unwrap();
+        lying.changed.clear();
+        assert!(IssuerProtocol::verify_reissue_request(
+            &system.ck, &system.vk, &system.ck, &domain, &lying, &mut rng
+        )
+        .is_err());
+
+        // So is carrying over into a request for other attributes
+        let (_, honest) = UserProtocol::request_reissue(&old, &[(1, value)], &mut rng).unwrap();
+        let mut swapped = honest.clone();
+        let mut other = Credential::new(system.ck.clone(), None, &mut rng).unwrap();
+        swapped.request = other.compute_commitments_per_m(&mut rng).unwrap();
+        assert!(matches!(
+            IssuerProtocol::verify_reissue_request(
+                &system.ck, &system.vk, &system.ck, &domain, &swapped, &mut rng
+            ),
+            Err(SignatureError::CommitmentConsistencyFailed)
+        ));
+    }
+
+    #[test]
+    fn test_share_verification_checks_proofs_once() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let user = system.user();
+        let (_, request) = user.request_credential(None, &mut rng).unwrap();
+        let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+
+        // Same verdict as checking every share with its own proof verification
+        let verified = UserProtocol::verify_signature_shares(
+            &system.ck,
+            system.vk_shares(),
+            &request,
+            &shares,
+            THRESHOLD,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(verified.len(), shares.len());
+        for (i, share) in &shares {
+            assert!(User::verify_signature_share(
+                &system.ck,
+                &system.vk_shares()[*i - 1],
+                &request.commitments,
+                &request.proofs,
+                share,
+                &mut rng,
+            )
+            .unwrap());
+        }
+
+        // An invalid commitment proof is still caught
+        let mut proof =
+            CommitmentProof::<Bls12_381>::deserialize_compressed(&request.proofs[1][..]).unwrap();
+        proof.responses[0] += Fr::from(1u64);
+        let mut tampered = request.clone();
+        tampered.proofs[1].clear();
+        proof.serialize_compressed(&mut tampered.proofs[1]).unwrap();
+        assert!(matches!(
+            UserProtocol::verify_signature_shares(
+                &system.ck,
+                system.vk_shares(),
+                &tampered,
+                &shares,
+                THRESHOLD,
+                &mut rng,
+            ),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::BatchVerifyError
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_verify_any_during_key_rotation() {
+        let mut rng = test_rng();
+        let mut committee = || {
+            System::<Bls12_381>::builder()
+                .threshold(THRESHOLD)
+                .signers(N_PARTICIPANTS)
+                .attributes(L_ATTRIBUTES)
+                .build(&mut rng)
+                .unwrap()
+        };
+        let (old, new) = (committee(), committee());
+        let vks = [old.vk.clone(), new.vk.clone()];
+
+        let credential = new.user().obtain_credential(None, &mut rng).unwrap();
+        let presentation = new.user().show(&credential, &mut rng).unwrap();
+        assert_eq!(
+            VerifierProtocol::verify_any(&new.ck, &vks, &presentation, &mut rng).unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            VerifierProtocol::verify_any(&new.ck, &vks[..1], &presentation, &mut rng).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_committees_under_one_crs() {
+        let mut rng = test_rng();
+        let crs = setup_crs::<Bls12_381>(L_ATTRIBUTES, &mut rng);
+        let mut committee = || {
+            let (ck, vk, keys) = keygen_under_crs(&crs, THRESHOLD, N_PARTICIPANTS, &mut rng);
+            System::from_key_material(ck, vk, keys).unwrap()
+        };
+        let (first, second) = (committee(), committee());
+        // One commitment key for both, each committee signs with its own g^{y_k}
+        for system in [&first, &second] {
+            assert_eq!(system.ck.get_bases(), crs.commitment_key().get_bases());
+        }
+        assert_ne!(first.vk.g_y, second.vk.g_y);
+        assert_ne!(first.vk.g_tilde_y, second.vk.g_tilde_y);
+        assert!(first.keys.proof.verify(&first.ck, &first.vk).unwrap());
+        assert!(second.keys.proof.verify(&second.ck, &second.vk).unwrap());
+        assert!(!first.keys.proof.verify(&first.ck, &second.vk).unwrap());
+
+        // Each committee's credentials verify under its own key only
+        for (issuer, other) in [(&first, &second), (&second, &first)] {
+            let credential = issuer.user().obtain_credential(None, &mut rng).unwrap();
+            let presentation = issuer.user().show(&credential, &mut rng).unwrap();
+            assert!(issuer.verifier().verify(&presentation, &mut rng).unwrap());
+            assert!(other.verifier().verify(&presentation, &mut rng).is_err());
+            assert!(
+                VerifierProtocol::verify(&issuer.ck, &other.vk, &presentation, &mut rng).is_err()
+            );
+        }
+
+        // A request only uses the shared key, so the other committee can sign it too
+        let (_, request) = first.user().request_credential(None, &mut rng).unwrap();
+        let signer = second.signer(0).unwrap();
+        let share = signer
+            .sign_share(&request.commitments, &request.proofs, &request.h, &mut rng)
+            .unwrap();
+        assert!(User::verify_signature_share_pairing_only(
+            &second.ck,
+            &second.vk_shares()[0],
+            &request.commitments,
+            &share,
+        ));
+    }
+
+    #[test]
+    fn test_aggregated_proof_flow() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let mut credential = Credential::new(system.ck.clone(), None, &mut rng).unwrap();
+        let request = credential.prove_all(&mut rng).unwrap();
+
+        let shares: Vec<_> = system
+            .signers()
+            .iter()
+            .take(THRESHOLD)
+            .map(|signer| {
+                let share = signer.sign_share_aggregated(&request, &mut rng).unwrap();
+                (share.party_index, share)
+            })
+            .collect();
+        let signature = UserProtocol::aggregate_shares(
+            &system.vk,
+            &shares,
+            credential.get_blinding_factors(),
+            THRESHOLD,
+            &request.h,
+        )
+        .unwrap();
+        credential.attach_signature(signature, &system.vk);
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        // A proof for other commitments is rejected
+        let mut forged = request.clone();
+        forged.commitments.swap(0, 1);
+        assert!(matches!(
+            system
+                .signer(1)
+                .unwrap()
+                .sign_share_aggregated(&forged, &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::ProofVerificationFailed
+            ))
+        ));
+
+        // So is the same proof at a signer of another application
+        let other_app = system
+            .signer(1)
+            .unwrap()
+            .with_domain(DomainSeparator::new(b"myapp-v1".as_slice()));
+        assert!(matches!(
+            other_app.sign_share_aggregated(&request, &mut rng),
+            Err(SignatureError::CommitmentError(
+                CommitmentError::ProofVerificationFailed
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_aggregate_sigma_matches_oracle() {
+        let mut rng = test_rng();
+        let (ck, vk, ts_keys) =
+            keygen::<Bls12_381>(THRESHOLD, N_PARTICIPANTS, L_ATTRIBUTES, &mut rng);
+        let (x, y) = reconstruct_full_key(&ts_keys, THRESHOLD);
+        let system = System::from_key_material(ck.clone(), vk, ts_keys).unwrap();
+        let user = system.user();
+
+        let (credential, request) = user.request_credential(None, &mut rng).unwrap();
+        let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+        let signature = UserProtocol::aggregate_shares(
+            &system.vk,
+            &shares,
+            credential.get_blinding_factors(),
+            THRESHOLD,
+            &request.h,
+        )
+        .unwrap();
+
+        let expected = expected_aggregate_sigma(
+            &ck,
+            &x,
+            &y,
+            credential.get_messages(),
+            credential.get_blinding_factors(),
+            &request.h,
+        );
+        assert_eq!(signature.sigma, expected);
+        let exponent = credential
+            .get_messages()
+            .iter()
+            .zip(&y)
+            .fold(x, |acc, (m_k, y_k)| acc + *y_k * m_k);
+        assert_eq!(expected, (request.h * exponent).into_affine());
+    }
+
+    #[test]
+    fn test_threshold_signature_verifies_as_ps() {
+        let mut rng = test_rng();
+        let (ck, vk, ts_keys) =
+            keygen::<Bls12_381>(THRESHOLD, N_PARTICIPANTS, L_ATTRIBUTES, &mut rng);
+        let pk = AggregatePublicKey::new(&ck, &vk);
+        let system = System::from_key_material(ck.clone(), vk, ts_keys).unwrap();
+        let user = system.user();
+
+        let messages =
+            Attributes::new((1..=L_ATTRIBUTES as u64).map(Fr::from).collect(), &ck).unwrap();
+        let (credential, request) = user
+            .request_credential_with_attributes(messages.clone(), &mut rng)
+            .unwrap();
+        let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+        let signature = UserProtocol::aggregate_shares(
+            &system.vk,
+            &shares,
+            credential.get_blinding_factors(),
+            THRESHOLD,
+            &request.h,
+        )
+        .unwrap();
+
+        assert!(signature.verify_as_ps(&pk, &messages).unwrap());
+        let mut tampered = messages.to_vec();
+        tampered[1] += Fr::from(1u64);
+        assert!(!signature.verify_as_ps(&pk, &tampered).unwrap());
+        assert!(signature.verify_as_ps(&pk, &messages[1..]).is_err());
+
+        // Compressed arkworks encodings, hex: sigma_1 then sigma_2, 48 bytes each, and
+        // the key as g_tilde, X_tilde, a u64 length and the Y_tilde_k, 96 bytes per point
+        let hex = |value: &dyn Fn(&mut Vec<u8>)| {
+            let mut bytes = Vec::new();
+            value(&mut bytes);
+            bytes.iter().map(|b| format!("{b:02x}")).collect::<String>()
+        };
+        let (sigma_1, sigma_2) = signature.to_ps_parts();
+        let fixture: serde_json::Value =
+            serde_json::from_str(include_str!("../fixtures/ps_signature.json")).unwrap();
+        assert_eq!(
+            fixture["sigma_1"],
+            hex(&|bytes| sigma_1.serialize_compressed(bytes).unwrap())
+        );
+        assert_eq!(
+            fixture["sigma_2"],
+            hex(&|bytes| sigma_2.serialize_compressed(bytes).unwrap())
+        );
+        assert_eq!(
+            fixture["public_key"],
+            hex(&|bytes| pk.serialize_compressed(bytes).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parallel_threshold_does_not_change_results() {
+        use crate::parallel::{set_parallel_threshold, PARALLEL_THRESHOLD};
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+        // Enough attributes and signers that both sides of the default cut-over differ
+        let system = System::<Bls12_381>::builder()
+            .threshold(PARALLEL_THRESHOLD + 1)
+            .signers(PARALLEL_THRESHOLD + 2)
+            .attributes(PARALLEL_THRESHOLD + 1)
+            .build(&mut test_rng())
+            .unwrap();
+        let user = system.user();
+        let run = |cut_over: usize| {
+            set_parallel_threshold(cut_over);
+            let mut rng = StdRng::seed_from_u64(7);
+            let (_, request) = user.request_credential(None, &mut rng).unwrap();
+            let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+            (request, shares)
+        };
+        let (parallel_request, parallel_shares) = run(0);
+        let (serial_request, serial_shares) = run(usize::MAX);
+        set_parallel_threshold(PARALLEL_THRESHOLD);
+
+        assert_eq!(parallel_request.commitments, serial_request.commitments);
+        assert_eq!(parallel_request.proofs, serial_request.proofs);
+        assert_eq!(parallel_shares.len(), serial_shares.len());
+        for ((i, a), (j, b)) in parallel_shares.iter().zip(&serial_shares) {
+            assert_eq!(i, j);
+            assert_eq!(a.sigma, b.sigma);
+        }
+    }
+
+    #[test]
+    fn test_threshold_signature_matches_centralized_signing() {
+        let mut rng = test_rng();
+        let (ck, vk, ts_keys) =
+            keygen::<Bls12_381>(THRESHOLD, N_PARTICIPANTS, L_ATTRIBUTES, &mut rng);
+        let (x, y) = reconstruct_full_key(&ts_keys, THRESHOLD);
+        let system = System::from_key_material(ck.clone(), vk, ts_keys).unwrap();
+        let user = system.user();
+
+        let (credential, request) = user.request_credential(None, &mut rng).unwrap();
+        let shares = user.collect_signature_shares(&request, &mut rng).unwrap();
+
+        // Without unblinding the aggregate is the signature over the commitments
+        let no_blindings = vec![Fr::zero(); L_ATTRIBUTES];
+        let blinded = UserProtocol::aggregate_shares(
+            &system.vk,
+            &shares,
+            &no_blindings,
+            THRESHOLD,
+            &request.h,
+        )
+        .unwrap();
+        let centralized = sign_centralized(&ck, &x, &y, &request.commitments, &request.h);
+        assert_eq!(
+            (blinded.h, blinded.sigma),
+            (centralized.h, centralized.sigma)
+        );
+
+        // Unblinded, it is the signature over h^{m_k}
+        let unblinded = UserProtocol::aggregate_shares(
+            &system.vk,
+            &shares,
+            credential.get_blinding_factors(),
+            THRESHOLD,
+            &request.h,
+        )
+        .unwrap();
+        let h_m: Vec<G1Affine> = credential
+            .get_messages()
+            .iter()
+            .map(|m| (request.h * m).into_affine())
+            .collect();
+        let centralized = sign_centralized(&ck, &x, &y, &h_m, &request.h);
+        assert_eq!(
+            (unblinded.h, unblinded.sigma),
+            (centralized.h, centralized.sigma)
+        );
+    }
+
+    #[test]
+    fn test_issuer_set_attribute_flow() {
+        let mut rng = test_rng();
+        let system = test_system();
+
+        // The issuer sets attribute 0, e.g. an issuance date, the user blinds 1 and 2
+        let issuance_date = Fr::from(20261016u64);
+        let public_attributes = [(0, issuance_date)];
+        let private_attributes: Vec<Fr> = (1..L_ATTRIBUTES).map(|_| Fr::rand(&mut rng)).collect();
+
+        let (mut credential, request) = UserProtocol::request_credential_partial(
+            system.ck.clone(),
+            Some(&private_attributes),
+            &public_attributes,
+            &mut rng,
+        )
+        .expect("Failed to create partial credential request");
+        assert_eq!(request.commitments.len(), L_ATTRIBUTES - 1);
+        assert_eq!(credential.get_messages()[0], issuance_date);
+        assert_eq!(&credential.get_messages()[1..], &private_attributes[..]);
+
+        let shares: Vec<_> = system
+            .signers()
+            .iter()
+            .take(THRESHOLD)
+            .map(|signer| {
+                let share = IssuerProtocol::issue_share_with_public_attributes(
+                    signer,
+                    &request.commitments,
+                    &request.proofs,
+                    &request.h,
+                    &public_attributes,
+                    &mut rng,
+                )
+                .expect("Failed to sign share");
+                (share.party_index, share)
+            })
+            .collect();
+
+        // Shares don't verify against a different issuer-set value
+        assert!(
+            UserProtocol::verify_signature_shares_with_public_attributes(
+                &system.ck,
+                system.vk_shares(),
+                &request,
+                &[(0, issuance_date + Fr::from(1u64))],
+                &shares,
+                THRESHOLD,
+                &mut rng,
+            )
+            .is_err()
+        );
+
+        let verified = UserProtocol::verify_signature_shares_with_public_attributes(
+            &system.ck,
+            system.vk_shares(),
+            &request,
+            &public_attributes,
+            &shares,
+            THRESHOLD,
+            &mut rng,
+        )
+        .expect("Failed to verify signature shares");
+        let signature = UserProtocol::aggregate_shares(
+            &system.vk,
+            &verified,
+            credential.get_blinding_factors(),
+            THRESHOLD,
+            &request.h,
+        )
+        .expect("Failed to aggregate shares");
+        credential.attach_signature(signature, &system.vk);
+        assert!(credential.check_invariants());
+
+        let presentation = system.user().show(&credential, &mut rng).unwrap();
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+    }
+
+    #[test]
+    fn test_aggregate_with_subset_policies() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let user = system.user();
+        let (mut credential, request) = user
+            .request_credential(None, &mut rng)
+            .expect("Failed to create credential request");
+
+        // Every signer answers, in reverse order of arrival
+        let mut shares: Vec<_> = system
+            .signers()
+            .iter()
+            .map(|signer| {
+                let share = signer
+                    .sign_share(&request.commitments, &request.proofs, &request.h, &mut rng)
+                    .expect("Failed to sign share");
+                (share.party_index, share)
+            })
+            .collect();
+        shares.reverse();
+
+        let policies = [
+            SubsetPolicy::LowestIndices,
+            SubsetPolicy::Random([9u8; 32]),
+            SubsetPolicy::Explicit(vec![5, 3]),
+        ];
+        for policy in &policies {
+            let subset = select_subset(&shares, THRESHOLD, policy).unwrap();
+            let indices: Vec<usize> = subset.iter().map(|(i, _)| *i).collect();
+            match policy {
+                SubsetPolicy::LowestIndices => assert_eq!(indices, vec![1, 2]),
+                SubsetPolicy::Explicit(_) => assert_eq!(indices, vec![3, 5]),
+                SubsetPolicy::Random(_) => {
+                    assert_eq!(indices.len(), THRESHOLD);
+                    // Same seed, same subset whatever the arrival order
+                    let mut arrived = shares.clone();
+                    arrived.rotate_left(2);
+                    let again: Vec<usize> = select_subset(&arrived, THRESHOLD, policy)
+                        .unwrap()
+                        .iter()
+                        .map(|(i, _)| *i)
+                        .collect();
+                    assert_eq!(indices, again);
+                }
+            }
+
+            let signature = UserProtocol::aggregate_shares(
+                &system.vk,
+                &subset,
+                credential.get_blinding_factors(),
+                THRESHOLD,
+                &request.h,
+            )
+            .expect("Failed to aggregate shares");
+            credential.attach_signature(signature, &system.vk);
+            let presentation = user.show(&credential, &mut rng).unwrap();
+            assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+        }
+
+        assert!(matches!(
+            select_subset(&shares, THRESHOLD, &SubsetPolicy::Explicit(vec![1, 7])),
+            Err(SignatureError::InvalidShare(7))
+        ));
+    }
+
+    #[test]
+    fn test_request_without_attributes_rejected() {
+        let mut rng = test_rng();
+        let system = test_system();
+        assert!(matches!(
+            system.user().request_credential(Some(&[]), &mut rng),
+            Err(CredentialError::NoAttributes)
+        ));
+    }
+
+    #[test]
+    fn test_request_with_wrong_attribute_count_rejected() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let short = vec![Fr::from(1u64); L_ATTRIBUTES - 1];
+        assert!(matches!(
+            system.user().request_credential(Some(&short), &mut rng),
+            Err(CredentialError::InvalidAttributes(
+                CommitmentError::TooManyMessages { .. }
+            ))
+        ));
+        assert!(Attributes::new(short, &system.ck).is_err());
     }
 
     // #[test]
@@ -367,3 +1531,366 @@ mod tests {
     //     }
     // }
 }
+
+/// Aggregation over random committee parameters, subsets and arrival orders
+#[cfg(not(target_arch = "wasm32"))]
+mod property_tests {
+    use super::*;
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use proptest::prelude::*;
+
+    /// `(t, n, l)` with `1 <= t <= n <= 6` and `1 <= l <= 4`
+    fn committee_params() -> impl Strategy<Value = (usize, usize, usize)> {
+        (1usize..=6).prop_flat_map(|n| (1..=n, Just(n), 1usize..=4))
+    }
+
+    /// `t` distinct signer positions out of `n`, in a random order
+    fn ordered_subset(t: usize, n: usize) -> impl Strategy<Value = Vec<usize>> {
+        Just((0..n).collect::<Vec<_>>())
+            .prop_shuffle()
+            .prop_map(move |positions| positions[..t].to_vec())
+    }
+
+    /// Committee parameters, up to three ordered subsets and a seed for the keys
+    fn aggregation_case() -> impl Strategy<Value = (usize, usize, usize, Vec<Vec<usize>>, u64)> {
+        committee_params().prop_flat_map(|(t, n, l)| {
+            (
+                Just(t),
+                Just(n),
+                Just(l),
+                prop::collection::vec(ordered_subset(t, n), 1..=3),
+                any::<u64>(),
+            )
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(12))]
+
+        #[test]
+        fn prop_every_subset_aggregates_to_a_valid_signature(
+            (t, n, l, subsets, seed) in aggregation_case()
+        ) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let system = System::<Bls12_381>::builder()
+                .threshold(t)
+                .signers(n)
+                .attributes(l)
+                .build(&mut rng)
+                .unwrap();
+            let user = system.user();
+            let (mut credential, request) = user.request_credential(None, &mut rng).unwrap();
+            let shares: Vec<_> = system
+                .signers()
+                .iter()
+                .map(|signer| {
+                    let share = signer
+                        .sign_share(&request.commitments, &request.proofs, &request.h, &mut rng)
+                        .unwrap();
+                    (share.party_index, share)
+                })
+                .collect();
+
+            for subset in subsets {
+                let chosen: Vec<_> = subset.iter().map(|&i| shares[i].clone()).collect();
+                let signature = UserProtocol::aggregate_shares(
+                    &system.vk,
+                    &chosen,
+                    credential.get_blinding_factors(),
+                    t,
+                    &request.h,
+                )
+                .unwrap();
+                credential.attach_signature(signature, &system.vk);
+                let presentation = user.show(&credential, &mut rng).unwrap();
+                prop_assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+            }
+        }
+    }
+}
+
+/// The same flows run over every supported curve, to catch assumptions that only
+/// hold for BLS12-381
+mod curve_tests {
+    use super::*;
+    use crate::nullifier::DYPFPrivVRF;
+    use crate::presentation::{NullifierPresentation, Presentation};
+    use ark_ec::pairing::Pairing;
+
+    fn curve_system<E: Pairing>() -> System<E> {
+        System::builder()
+            .threshold(THRESHOLD)
+            .signers(N_PARTICIPANTS)
+            .attributes(L_ATTRIBUTES)
+            .build(&mut test_rng())
+            .expect("Failed to set up system")
+    }
+
+    fn round_trip<T: CanonicalSerialize + CanonicalDeserialize>(value: &T) -> T {
+        let mut bytes = Vec::new();
+        value.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), value.compressed_size());
+        T::deserialize_compressed(&bytes[..]).unwrap()
+    }
+
+    fn run_credential_flow<E: Pairing>() {
+        let mut rng = test_rng();
+        let system = curve_system::<E>();
+        let attributes: Vec<E::ScalarField> = (0..L_ATTRIBUTES)
+            .map(|_| E::ScalarField::rand(&mut rng))
+            .collect();
+        let credential = system
+            .user()
+            .obtain_credential(Some(&attributes), &mut rng)
+            .expect("Failed to obtain credential");
+        assert!(credential.check_invariants());
+
+        let presentation: Presentation<E> =
+            round_trip(&system.user().show(&credential, &mut rng).unwrap());
+        assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+
+        let message = round_trip(
+            &UserProtocol::present_message(&credential, [3u8; 32], &[0], &mut rng).unwrap(),
+        );
+        assert_eq!(message.disclosed, vec![(0, attributes[0])]);
+        assert!(VerifierProtocol::verify_message(
+            &system.ck,
+            &system.vk,
+            credential.domain(),
+            &message,
+            &mut rng
+        )
+        .unwrap());
+
+        let loaded = System::<E>::from_bytes(&system.to_bytes().unwrap()).unwrap();
+        assert!(loaded.verifier().verify(&presentation, &mut rng).unwrap());
+    }
+
+    fn run_nullifier_flow<E: Pairing>() {
+        let mut rng = test_rng();
+        let system = curve_system::<E>();
+        let credential = system.user().obtain_credential(None, &mut rng).unwrap();
+        let vrf = DYPFPrivVRF::<E::G1Affine>::new(&mut rng);
+        let (sk, _) = vrf.generate_keys(&mut rng);
+
+        let first: NullifierPresentation<E> = round_trip(
+            &UserProtocol::show_with_nullifier(&credential, &vrf, &sk, &mut rng).unwrap(),
+        );
+        let second = UserProtocol::show_with_nullifier(&credential, &vrf, &sk, &mut rng).unwrap();
+        assert_eq!(first.nullifier, second.nullifier);
+        for shown in [&first, &second] {
+            assert!(VerifierProtocol::verify_with_nullifier(
+                &system.ck,
+                &system.vk,
+                &vrf,
+                credential.domain(),
+                shown,
+                &mut rng
+            )
+            .unwrap());
+        }
+
+        // Nullifiers keyed by the hidden attribute 0 and bound to a public context
+        let bound = |credential: &Credential<E>, context_input: &[u8], rng: &mut _| {
+            UserProtocol::show_with_bound_nullifier(credential, &vrf, 0, context_input, rng)
+                .unwrap()
+        };
+        let verify_binding =
+            |shown: &NullifierPresentation<E>, context_input: &[u8], rng: &mut _| {
+                VerifierProtocol::verify_nullifier_binding(
+                    &system.ck,
+                    &system.vk,
+                    &vrf,
+                    credential.domain(),
+                    shown,
+                    0,
+                    context_input,
+                    rng,
+                )
+            };
+        let shown: NullifierPresentation<E> = round_trip(&bound(&credential, b"poll 7", &mut rng));
+        assert!(verify_binding(&shown, b"poll 7", &mut rng).unwrap());
+        assert_eq!(
+            shown.nullifier,
+            bound(&credential, b"poll 7", &mut rng).nullifier
+        );
+        assert!(!verify_binding(&shown, b"poll 8", &mut rng).unwrap());
+        // A nullifier that isn't keyed by the shown credential's attribute
+        assert!(!verify_binding(&first, b"poll 7", &mut rng).unwrap());
+
+        // The nullifier of another credential for the same context
+        let other = system.user().obtain_credential(None, &mut rng).unwrap();
+        let other_shown = bound(&other, b"poll 7", &mut rng);
+        assert_ne!(other_shown.nullifier, shown.nullifier);
+        let mut spliced = shown.clone();
+        spliced.nullifier = other_shown.nullifier;
+        spliced.cm_sk = other_shown.cm_sk;
+        spliced.vrf_proof = other_shown.vrf_proof.clone();
+        assert!(!matches!(
+            verify_binding(&spliced, b"poll 7", &mut rng),
+            Ok(true)
+        ));
+    }
+
+    #[test]
+    fn test_bls12_381_credential_flow() {
+        run_credential_flow::<Bls12_381>();
+    }
+
+    #[test]
+    fn test_bls12_381_nullifier_flow() {
+        run_nullifier_flow::<Bls12_381>();
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn test_bn254_credential_flow() {
+        run_credential_flow::<ark_bn254::Bn254>();
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn test_bn254_nullifier_flow() {
+        run_nullifier_flow::<ark_bn254::Bn254>();
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod tracing_tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    #[derive(Default)]
+    struct Fields(HashMap<&'static str, String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    struct CapturedSpan {
+        metadata: &'static Metadata<'static>,
+        fields: Fields,
+    }
+
+    /// Keeps every span with its recorded fields, span ids are 1-based positions
+    #[derive(Clone, Default)]
+    struct CapturingSubscriber {
+        spans: Arc<Mutex<Vec<CapturedSpan>>>,
+    }
+
+    impl Subscriber for CapturingSubscriber {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields::default();
+            span.record(&mut fields);
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(CapturedSpan {
+                metadata: span.metadata(),
+                fields,
+            });
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut spans[span.into_u64() as usize - 1].fields);
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_failed_share_verification_span() {
+        let mut rng = test_rng();
+        let system = test_system();
+        let (_, request) = system
+            .user()
+            .request_credential(None, &mut rng)
+            .expect("Failed to create credential request");
+        let mut shares: Vec<_> = system
+            .signers()
+            .iter()
+            .map(|signer| {
+                let share = signer
+                    .sign_share(&request.commitments, &request.proofs, &request.h, &mut rng)
+                    .expect("Failed to sign share");
+                (share.party_index, share)
+            })
+            .collect();
+        // Signer 2 returns a share over garbage
+        shares[1].1.sigma = G1Affine::rand(&mut rng);
+
+        let subscriber = CapturingSubscriber::default();
+        let verified = tracing::subscriber::with_default(subscriber.clone(), || {
+            UserProtocol::verify_signature_shares(
+                &system.ck,
+                system.vk_shares(),
+                &request,
+                &shares,
+                THRESHOLD,
+                &mut rng,
+            )
+        })
+        .expect("The remaining shares meet the threshold");
+        assert_eq!(verified.len(), N_PARTICIPANTS - 1);
+
+        let spans = subscriber.spans.lock().unwrap();
+        let share_spans: Vec<_> = spans
+            .iter()
+            .filter(|span| span.metadata.name() == "verify_signature_share")
+            .map(|span| &span.fields.0)
+            .collect();
+        assert_eq!(share_spans.len(), N_PARTICIPANTS);
+        let failed: Vec<_> = share_spans
+            .iter()
+            .filter(|fields| fields.get("valid").map(String::as_str) == Some("false"))
+            .map(|fields| fields["index"].as_str())
+            .collect();
+        assert_eq!(failed, vec!["2"]);
+    }
+}
+
+/// Holder-side smoke test for `wasm32-unknown-unknown`, run with
+/// `wasm-pack test --node -- --no-default-features --features signing`. Issuance
+/// needs `signing`, and `parallel` is left off since rayon has no threads there
+#[cfg(target_arch = "wasm32")]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn test_wasm_request_credential_to_show() {
+        let mut rng = test_rng();
+        let system = test_system();
+
+        let attributes: Vec<Fr> = (0..L_ATTRIBUTES).map(|_| Fr::rand(&mut rng)).collect();
+        let credential = system
+            .user()
+            .obtain_credential(Some(&attributes), &mut rng)
+            .expect("Failed to obtain credential");
+
+        let presentation = system
+            .user()
+            .show(&credential, &mut rng)
+            .expect("Failed to show credential");
+        assert!(system
+            .verifier()
+            .verify(&presentation, &mut rng)
+            .expect("Verification failed"));
+    }
+}
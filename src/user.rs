@@ -1,14 +1,49 @@
 use crate::commitment::batch_verify;
 use crate::errors::{CommitmentError, SignatureError};
-use crate::keygen::VerificationKeyShare;
+use crate::keygen::{KeygenTranscript, VerificationKeyShare};
+use crate::misbehavior::MisbehaviorReport;
 use crate::pairing::verify_pairing_equation;
 use crate::signature::PartialSignature;
 use crate::symmetric_commitment::SymmetricCommitmentKey;
 use ark_ec::pairing::Pairing;
-use ark_ec::AffineRepr;
-use ark_ec::CurveGroup;
-use ark_std::ops::Neg;
-use ark_std::rand::Rng;
+use ark_std::collections::BTreeMap;
+use ark_std::rand::{CryptoRng, Rng};
+
+/// The outcome of `User::process_signature_shares`
+#[derive(Clone, Debug)]
+pub struct VerifiedShares<E: Pairing> {
+    pub valid: Vec<(usize, PartialSignature<E>)>,
+    /// A report for every share that failed verification, in the order received
+    pub reports: Vec<MisbehaviorReport<E>>,
+}
+
+impl<E: Pairing> VerifiedShares<E> {
+    /// Indices of the signers whose shares failed verification
+    pub fn failed(&self) -> Vec<usize> {
+        self.reports
+            .iter()
+            .map(|report| report.signer_index)
+            .collect()
+    }
+}
+
+/// Why a signer contributed no share to `UserProtocol::collect_signature_shares_with_fallback`
+#[derive(Debug)]
+pub enum SignerFailure {
+    /// The signer returned an error instead of a share, e.g. it is unreachable or
+    /// refused the request
+    NoShare(SignatureError),
+    /// The returned share failed verification
+    InvalidShare,
+}
+
+/// Valid shares collected with fallback and the signers that failed on the way
+#[derive(Debug)]
+pub struct CollectedShares<E: Pairing> {
+    pub shares: Vec<(usize, PartialSignature<E>)>,
+    /// `(party index, failure)` in the order the signers were asked
+    pub failures: Vec<(usize, SignerFailure)>,
+}
 
 pub struct User;
 impl User {
@@ -20,8 +55,24 @@ impl User {
         commitments: &[E::G1Affine],
         commitment_proofs: &[Vec<u8>],
         sig_share: &PartialSignature<E>,
-        rng: &mut impl Rng,
+        rng: &mut (impl Rng + CryptoRng),
     ) -> Result<bool, SignatureError> {
+        Self::verify_request_proofs::<E>(commitment_proofs, rng)?;
+        Ok(Self::verify_signature_share_pairing_only(
+            commitment_key,
+            vk_share,
+            commitments,
+            sig_share,
+        ))
+    }
+
+    /// The commitment proof half of `verify_signature_share`, batch verifying the
+    /// opening proofs of a request. Fails with `BatchVerifyError` if they don't verify,
+    /// `signer::verify_request_proofs` checks the request's points first and calls this
+    pub(crate) fn verify_request_proofs<E: Pairing>(
+        commitment_proofs: &[Vec<u8>],
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(), SignatureError> {
         // 1. First verify the ZKPs for each commitment
         // We can use the optimised version of batch_verify
         // let timer_start = std::time::Instant::now();
@@ -41,15 +92,34 @@ impl User {
         //         return Ok(false);
         //     }
         // }
+        Ok(())
+    }
 
+    /// The pairing half of `verify_signature_share`, for commitments whose proofs have
+    /// already been verified
+    pub fn verify_signature_share_pairing_only<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        vk_share: &VerificationKeyShare<E>,
+        commitments: &[E::G1Affine],
+        sig_share: &PartialSignature<E>,
+    ) -> bool {
         // 2. Verify the signature share using the pairing equation
-        // e([σ*]_i,2, g̃) = e(h, g̃^[x]_i) · ∏_{k∈[ℓ]} e(cm_k, g̃^[y_k]_i)
+        let pairs = Self::share_pairs(commitment_key, vk_share, commitments, sig_share);
+        verify_pairing_equation::<E>(&pairs, None)
+    }
 
+    /// The pairs of the share equation
+    /// e([σ*]_i,2, g̃) = e(h, g̃^[x]_i) · ∏_{k∈[ℓ]} e(cm_k, g̃^[y_k]_i), moved to one side
+    pub(crate) fn share_pairs<'a, E: Pairing>(
+        commitment_key: &'a SymmetricCommitmentKey<E>,
+        vk_share: &'a VerificationKeyShare<E>,
+        commitments: &'a [E::G1Affine],
+        sig_share: &'a PartialSignature<E>,
+    ) -> Vec<(&'a E::G1Affine, &'a E::G2Affine)> {
         let mut pairs = Vec::new();
 
-        // e(-sigma_i, g̃) = e([σ*]_i,2, g̃)^(-1)
-        let neg_sigma_i = sig_share.sigma.into_group().neg().into_affine();
-        pairs.push((&neg_sigma_i, &commitment_key.g_tilde));
+        // e(sigma_i, -g̃) = e([σ*]_i,2, g̃)^(-1)
+        pairs.push((&sig_share.sigma, commitment_key.neg_g_tilde()));
 
         // e(h, g̃^[x]_i)
         pairs.push((&sig_share.h, &vk_share.g_tilde_x_share));
@@ -61,14 +131,13 @@ impl User {
             }
         }
 
-        // Verify the pairing equation
-        let is_valid_signature = verify_pairing_equation::<E>(&pairs, None);
-
-        Ok(is_valid_signature)
+        pairs
     }
 
     /// Process signature shares - verify and collect valid ones
-    /// Returns collected valid shares
+    /// Returns the valid shares and the indices of the signers whose shares failed.
+    /// Every share needs exactly one verification key share with its index, and must be
+    /// listed under its own `party_index`, the index aggregation interpolates at
     pub fn process_signature_shares<E: Pairing>(
         commitment_key: &SymmetricCommitmentKey<E>,
         vk_shares: &[VerificationKeyShare<E>],
@@ -76,43 +145,149 @@ impl User {
         commitment_proofs: &[Vec<u8>],
         signature_shares: &[(usize, PartialSignature<E>)],
         threshold: usize,
-    ) -> Result<Vec<(usize, PartialSignature<E>)>, SignatureError> {
-        let mut valid_shares = Vec::new();
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<VerifiedShares<E>, SignatureError> {
+        let mut vk_by_index = BTreeMap::new();
+        for vk_share in vk_shares {
+            if vk_by_index.insert(vk_share.index, vk_share).is_some() {
+                return Err(SignatureError::DuplicateVerificationKeyShare(
+                    vk_share.index,
+                ));
+            }
+        }
+        for (i, sig_share) in signature_shares {
+            if *i != sig_share.party_index {
+                return Err(SignatureError::PartyIndexMismatch {
+                    index: *i,
+                    party_index: sig_share.party_index,
+                });
+            }
+            if !vk_by_index.contains_key(i) {
+                return Err(SignatureError::MissingVerificationKeyShare(*i));
+            }
+        }
+
+        let mut verified = VerifiedShares {
+            valid: Vec::new(),
+            reports: Vec::new(),
+        };
+        // The proofs are the same for every share, so they are verified once, where the
+        // first share would have verified them
+        if !signature_shares.is_empty() {
+            Self::verify_request_proofs::<E>(commitment_proofs, rng)?;
+        }
 
         for (i, sig_share) in signature_shares {
-            // Find the corresponding verification key share
-            let vk_share =
-                vk_shares
-                    .iter()
-                    .find(|vk| vk.index == *i)
-                    .ok_or(SignatureError::InvalidState(format!(
-                        "No verification key for signer {}",
-                        i
-                    )))?;
+            #[cfg(feature = "tracing")]
+            let span = tracing::debug_span!(
+                "verify_signature_share",
+                index = *i,
+                valid = tracing::field::Empty
+            )
+            .entered();
 
             // Verify this signature share
-            let is_valid = Self::verify_signature_share(
+            let is_valid = Self::verify_signature_share_pairing_only(
                 commitment_key,
-                vk_share,
+                vk_by_index[i],
                 commitments,
-                commitment_proofs,
                 sig_share,
-                &mut ark_std::test_rng(),
-            )?;
+            );
+
+            #[cfg(feature = "tracing")]
+            span.record("valid", is_valid);
 
             if is_valid {
-                valid_shares.push((*i, sig_share.clone()));
+                verified.valid.push((*i, sig_share.clone()));
+            } else {
+                verified.reports.push(MisbehaviorReport {
+                    signer_index: *i,
+                    commitments: commitments.to_vec(),
+                    share: sig_share.clone(),
+                });
             }
         }
 
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            passed = verified.valid.len(),
+            failed = verified.reports.len(),
+            failed_indices = ?verified.failed(),
+            "verified signature shares"
+        );
+
         // Check if we have enough valid shares
-        if valid_shares.len() < threshold {
-            return Err(SignatureError::from(SignatureError::InsufficientShares {
+        if verified.valid.len() < threshold {
+            return Err(SignatureError::InsufficientShares {
                 needed: threshold + 1,
-                got: valid_shares.len(),
-            }));
+                got: verified.valid.len(),
+            });
+        }
+
+        Ok(verified)
+    }
+
+    /// `process_signature_shares` with every signer's key share derived from the
+    /// published keygen `transcript` instead of taken from the dealer. Index 0 is not a
+    /// party, a share claiming it has no key share
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_signature_shares_with_transcript<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        transcript: &KeygenTranscript<E>,
+        commitments: &[E::G1Affine],
+        commitment_proofs: &[Vec<u8>],
+        signature_shares: &[(usize, PartialSignature<E>)],
+        threshold: usize,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<VerifiedShares<E>, SignatureError> {
+        let mut vk_by_index = BTreeMap::new();
+        for (i, _) in signature_shares {
+            if *i == 0 {
+                return Err(SignatureError::MissingVerificationKeyShare(0));
+            }
+            vk_by_index
+                .entry(*i)
+                .or_insert_with(|| VerificationKeyShare::derive_from_commitments(transcript, *i));
         }
+        let vk_shares: Vec<_> = vk_by_index.into_values().collect();
+        Self::process_signature_shares(
+            commitment_key,
+            &vk_shares,
+            commitments,
+            commitment_proofs,
+            signature_shares,
+            threshold,
+            rng,
+        )
+    }
 
-        Ok(valid_shares)
+    /// Find the signers behind an aggregated signature that fails `verify`, e.g. with
+    /// shares from `sign_share_no_zkp_verify` that were never checked. Each share is
+    /// re-verified on its own against the verification key share with its index and
+    /// the indices that fail are returned. A share without a verification key share is
+    /// flagged as well
+    pub fn identify_cheaters<E: Pairing>(
+        commitment_key: &SymmetricCommitmentKey<E>,
+        vk_shares: &[VerificationKeyShare<E>],
+        commitments: &[E::G1Affine],
+        signature_shares: &[(usize, PartialSignature<E>)],
+    ) -> Vec<usize> {
+        let vk_by_index: BTreeMap<_, _> = vk_shares
+            .iter()
+            .map(|vk_share| (vk_share.index, vk_share))
+            .collect();
+        signature_shares
+            .iter()
+            .filter(|(i, sig_share)| match vk_by_index.get(i) {
+                Some(vk_share) => !Self::verify_signature_share_pairing_only(
+                    commitment_key,
+                    vk_share,
+                    commitments,
+                    sig_share,
+                ),
+                None => true,
+            })
+            .map(|(i, _)| *i)
+            .collect()
     }
 }
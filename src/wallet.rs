@@ -0,0 +1,228 @@
+//! A holder's store of credentials.
+//!
+//! Each credential gets a `CredentialId` when it enters the wallet and is filed under
+//! the `KeyId` of its commitment key, a digest of the serialized key. Credentials from
+//! several committees can live in one wallet, the key id tells them apart.
+//!
+//! ```ignore
+//! let mut wallet = Wallet::new();
+//! let (id, request) = wallet.request_and_track(system.ck.clone(), None, &mut rng)?;
+//! let shares = system.user().collect_signature_shares(&request, &mut rng)?;
+//! system.user().complete_credential(wallet.get_mut(id).unwrap(), &request, &shares, &mut rng)?;
+//! let presentation = wallet.show(id, &mut rng)?;
+//! ```
+use crate::credential::{Credential, CredentialCommitments};
+use crate::errors::CredentialError;
+use crate::presentation::Presentation;
+use crate::protocol::UserProtocol;
+use crate::symmetric_commitment::SymmetricCommitmentKey;
+use crate::transcript::Transcript;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::collections::BTreeMap;
+use ark_std::rand::{CryptoRng, Rng};
+
+/// Handle of a credential in a `Wallet`
+pub type CredentialId = u64;
+
+/// Digest identifying a commitment key
+pub type KeyId = [u8; 32];
+
+/// The `KeyId` of `ck`
+pub fn key_id<E: Pairing>(ck: &SymmetricCommitmentKey<E>) -> KeyId {
+    let mut transcript = Transcript::new(b"t-siris commitment key");
+    transcript.append(b"ck", ck);
+    transcript.digest(b"key id")
+}
+
+/// A stored credential and the key it was issued under
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct WalletEntry<E: Pairing> {
+    pub key_id: KeyId,
+    pub credential: Credential<E>,
+}
+
+/// Credentials indexed by `CredentialId`, ids are never reused
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct Wallet<E: Pairing> {
+    entries: BTreeMap<CredentialId, WalletEntry<E>>,
+    next_id: CredentialId,
+}
+
+impl<E: Pairing> Default for Wallet<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Pairing> Wallet<E> {
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Store `credential` under the key id of its commitment key
+    pub fn add(&mut self, credential: Credential<E>) -> CredentialId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let key_id = key_id(&credential.ck);
+        self.entries.insert(id, WalletEntry { key_id, credential });
+        id
+    }
+
+    pub fn get(&self, id: CredentialId) -> Option<&Credential<E>> {
+        self.entries.get(&id).map(|entry| &entry.credential)
+    }
+
+    /// Mutable access, e.g. to attach the signature to a tracked request
+    pub fn get_mut(&mut self, id: CredentialId) -> Option<&mut Credential<E>> {
+        self.entries.get_mut(&id).map(|entry| &mut entry.credential)
+    }
+
+    pub fn remove(&mut self, id: CredentialId) -> Option<Credential<E>> {
+        self.entries.remove(&id).map(|entry| entry.credential)
+    }
+
+    /// The key id `id` is filed under
+    pub fn key_id(&self, id: CredentialId) -> Option<&KeyId> {
+        self.entries.get(&id).map(|entry| &entry.key_id)
+    }
+
+    /// Ids of the credentials for `context`, in insertion order
+    pub fn list_by_context(&self, context: &E::ScalarField) -> Vec<CredentialId> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.credential.context == *context)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Ids of the credentials issued under `key_id`, in insertion order
+    pub fn list_by_key(&self, key_id: &KeyId) -> Vec<CredentialId> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.key_id == *key_id)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Create a credential request under `ck` and keep the pending credential. Once the
+    /// shares are in, complete it through `get_mut`
+    pub fn request_and_track(
+        &mut self,
+        ck: SymmetricCommitmentKey<E>,
+        attributes: Option<&[E::ScalarField]>,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<(CredentialId, CredentialCommitments<E>), CredentialError> {
+        let (credential, request) = UserProtocol::request_credential(ck, attributes, rng)?;
+        Ok((self.add(credential), request))
+    }
+
+    /// Show the credential `id`
+    pub fn show(
+        &self,
+        id: CredentialId,
+        rng: &mut (impl Rng + CryptoRng),
+    ) -> Result<Presentation<E>, CredentialError> {
+        let credential = self.get(id).ok_or(CredentialError::UnknownCredential(id))?;
+        UserProtocol::show(credential, rng)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        let mut bytes = Vec::with_capacity(self.compressed_size());
+        self.serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Load a wallet written with `to_bytes`. Every key id is recomputed from the
+    /// stored key and every credential has to pass `check_invariants`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        let wallet = Self::deserialize_compressed(bytes)?;
+        let consistent = wallet.entries.iter().all(|(id, entry)| {
+            *id < wallet.next_id
+                && entry.key_id == key_id(&entry.credential.ck)
+                && entry.credential.check_invariants()
+        });
+        if !consistent {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(wallet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::System;
+    use crate::test_rng;
+    use ark_bls12_381::{Bls12_381, Fr};
+
+    #[test]
+    fn test_wallet_round_trip() {
+        let mut rng = test_rng();
+        let mut committee = || {
+            System::<Bls12_381>::builder()
+                .threshold(2)
+                .signers(3)
+                .attributes(3)
+                .build(&mut rng)
+                .unwrap()
+        };
+        let (first, second) = (committee(), committee());
+
+        let mut wallet = Wallet::new();
+        let mut ids = Vec::new();
+        for i in 0..10 {
+            let system = if i % 2 == 0 { &first } else { &second };
+            let (id, request) = wallet
+                .request_and_track(system.ck.clone(), None, &mut rng)
+                .unwrap();
+            let shares = system
+                .user()
+                .collect_signature_shares(&request, &mut rng)
+                .unwrap();
+            system
+                .user()
+                .complete_credential(wallet.get_mut(id).unwrap(), &request, &shares, &mut rng)
+                .unwrap();
+            ids.push(id);
+        }
+        assert_eq!(wallet.len(), 10);
+        assert_eq!(wallet.list_by_key(&key_id(&first.ck)).len(), 5);
+
+        let loaded = Wallet::<Bls12_381>::from_bytes(&wallet.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.len(), 10);
+        for &id in &ids[3..6] {
+            let system = if id % 2 == 0 { &first } else { &second };
+            assert_eq!(loaded.key_id(id), Some(&key_id(&system.ck)));
+            let presentation = loaded.show(id, &mut rng).unwrap();
+            assert!(system.verifier().verify(&presentation, &mut rng).unwrap());
+        }
+
+        let context = loaded.get(ids[4]).unwrap().context;
+        assert_eq!(loaded.list_by_context(&context), vec![ids[4]]);
+        assert!(loaded.list_by_context(&Fr::from(7u64)).is_empty());
+
+        let mut loaded = loaded;
+        assert!(loaded.remove(ids[4]).is_some());
+        assert!(matches!(
+            loaded.show(ids[4], &mut rng),
+            Err(CredentialError::UnknownCredential(4))
+        ));
+
+        // A credential filed under another key is rejected on load
+        let mut tampered = Wallet::<Bls12_381>::from_bytes(&wallet.to_bytes().unwrap()).unwrap();
+        tampered.entries.get_mut(&0).unwrap().key_id = key_id(&second.ck);
+        assert!(Wallet::<Bls12_381>::from_bytes(&tampered.to_bytes().unwrap()).is_err());
+    }
+}